@@ -0,0 +1,26 @@
+// Comparison kernels used by `DeviceSlice::device_eq`.
+//
+// The slices are compared as machine words of type `W` (8, 32 or 64 bits), so that one kernel
+// handles every element type of a given alignment. Each thread compares one word, and any thread
+// which finds a difference sets `*differs` to 1.
+
+template <typename W>
+__device__ void compare(const W* a, const W* b, unsigned int* differs, unsigned int total) {
+    unsigned int t = blockIdx.x * blockDim.x + threadIdx.x;
+    if (t >= total) {
+        return;
+    }
+    if (a[t] != b[t]) {
+        *differs = 1;
+    }
+}
+
+#define COMPARE_KERNEL(BITS, W)                                                                 \
+    extern "C" __global__ void compare_b##BITS(const W* a, const W* b, unsigned int* differs,   \
+                                               unsigned int total) {                            \
+        compare(a, b, differs, total);                                                          \
+    }
+
+COMPARE_KERNEL(8, unsigned char)
+COMPARE_KERNEL(32, unsigned int)
+COMPARE_KERNEL(64, unsigned long long)
@@ -0,0 +1,53 @@
+// Gather and scatter kernels used by `DeviceSlice::copy_indexed_from` and `DeviceSlice::scatter_to`.
+//
+// Elements are moved as `words` machine words of type `W` (8, 32 or 64 bits), so that one kernel
+// handles every element type of a given alignment. Each thread moves one word. Indices that are
+// out of bounds (`>= bound`) are skipped.
+
+template <typename W>
+__device__ void gather(W* dst, const W* src, const unsigned int* indices, unsigned int total,
+                       unsigned int words, unsigned int bound) {
+    unsigned int t = blockIdx.x * blockDim.x + threadIdx.x;
+    if (t >= total) {
+        return;
+    }
+    unsigned int elem = t / words;
+    unsigned int word = t - elem * words;
+    unsigned int index = indices[elem];
+    if (index >= bound) {
+        return;
+    }
+    dst[t] = src[(unsigned long long)index * words + word];
+}
+
+template <typename W>
+__device__ void scatter(W* dst, const W* src, const unsigned int* indices, unsigned int total,
+                        unsigned int words, unsigned int bound) {
+    unsigned int t = blockIdx.x * blockDim.x + threadIdx.x;
+    if (t >= total) {
+        return;
+    }
+    unsigned int elem = t / words;
+    unsigned int word = t - elem * words;
+    unsigned int index = indices[elem];
+    if (index >= bound) {
+        return;
+    }
+    dst[(unsigned long long)index * words + word] = src[t];
+}
+
+#define PERMUTE_KERNELS(BITS, W)                                                                  \
+    extern "C" __global__ void gather_b##BITS(W* dst, const W* src, const unsigned int* indices,  \
+                                              unsigned int total, unsigned int words,             \
+                                              unsigned int bound) {                               \
+        gather(dst, src, indices, total, words, bound);                                           \
+    }                                                                                             \
+    extern "C" __global__ void scatter_b##BITS(W* dst, const W* src, const unsigned int* indices, \
+                                               unsigned int total, unsigned int words,            \
+                                               unsigned int bound) {                              \
+        scatter(dst, src, indices, total, words, bound);                                          \
+    }
+
+PERMUTE_KERNELS(8, unsigned char)
+PERMUTE_KERNELS(32, unsigned int)
+PERMUTE_KERNELS(64, unsigned long long)
\ No newline at end of file
@@ -0,0 +1,18 @@
+// Reduction kernels following the contract of `DeviceSlice::reduce_to_host`: each block reduces
+// the elements `blockIdx.x, blockIdx.x + gridDim.x, ...` of `input` and writes the result to
+// `output[blockIdx.x]`.
+//
+// This implementation is deliberately simple (only one thread per block does any work) so that it
+// can serve as a reference in tests. Real kernels should use the dynamic shared memory to perform
+// a tree reduction within each block.
+
+extern "C" __global__ void sum_f32(const float* input, float* output, unsigned int count) {
+    if (threadIdx.x != 0) {
+        return;
+    }
+    float acc = 0.0f;
+    for (unsigned int i = blockIdx.x; i < count; i += gridDim.x) {
+        acc += input[i];
+    }
+    output[blockIdx.x] = acc;
+}
\ No newline at end of file
@@ -0,0 +1,5 @@
+// Legacy texture and surface references, looked up by the `Module::get_texref` and
+// `Module::get_surfref` tests.
+
+texture<float, cudaTextureType2D> legacy_texture;
+surface<void, cudaSurfaceType2D> legacy_surface;
@@ -0,0 +1,4 @@
+// Empty kernel launched by `utils::warmup`. Launching it makes the driver finish any lazy
+// initialization of the context and the launch path.
+
+extern "C" __global__ void warmup() {}
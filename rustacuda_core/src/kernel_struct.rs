@@ -0,0 +1,93 @@
+use crate::memory::{DeviceCopy, DevicePointer, UnifiedPointer};
+
+/// Types which have an equivalent type in CUDA C, so that they can be described in a generated C
+/// header.
+///
+/// This is implemented for the primitive numeric types, `bool`, device and unified pointers to
+/// `CType`s, arrays of `CType`s, and structs which derive
+/// [`KernelStruct`](trait.KernelStruct.html). Arrays of arrays are rejected at compile time.
+pub trait CType {
+    /// The name of the C type, without any pointer or array declarators.
+    const C_NAME: &'static str;
+    /// The number of levels of pointer indirection, e.g. 1 for `float*`.
+    const POINTERS: usize = 0;
+    /// The length of the array, or zero if the type is not an array.
+    const ARRAY_LEN: usize = 0;
+}
+
+macro_rules! impl_c_type {
+    ($($t:ty => $name:expr,)*) => {
+        $(
+            impl CType for $t {
+                const C_NAME: &'static str = $name;
+            }
+        )*
+    };
+}
+impl_c_type! {
+    u8 => "uint8_t",
+    u16 => "uint16_t",
+    u32 => "uint32_t",
+    u64 => "uint64_t",
+    usize => "size_t",
+    i8 => "int8_t",
+    i16 => "int16_t",
+    i32 => "int32_t",
+    i64 => "int64_t",
+    isize => "ptrdiff_t",
+    f32 => "float",
+    f64 => "double",
+    bool => "bool",
+}
+
+impl<T: CType> CType for DevicePointer<T> {
+    const C_NAME: &'static str = T::C_NAME;
+    const POINTERS: usize = T::POINTERS + 1;
+}
+impl<T: CType> CType for UnifiedPointer<T> {
+    const C_NAME: &'static str = T::C_NAME;
+    const POINTERS: usize = T::POINTERS + 1;
+}
+impl<T: CType, const N: usize> CType for [T; N] {
+    const C_NAME: &'static str = T::C_NAME;
+    const POINTERS: usize = T::POINTERS;
+    const ARRAY_LEN: usize = {
+        // Only one array dimension can be described, so reject nested arrays rather than
+        // declaring a field with the inner dimension missing.
+        assert!(
+            T::ARRAY_LEN == 0,
+            "arrays of arrays can't be described as a C type"
+        );
+        N
+    };
+}
+
+/// One field of a [`KernelStruct`](trait.KernelStruct.html).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KernelField {
+    /// The name of the field. Fields of tuple structs are named `_0`, `_1` and so on.
+    pub name: &'static str,
+    /// The name of the field's C type, without any pointer or array declarators.
+    pub c_type: &'static str,
+    /// The number of levels of pointer indirection.
+    pub pointers: usize,
+    /// The length of the array, or zero if the field is not an array.
+    pub array_len: usize,
+    /// The offset of the field in bytes from the start of the struct.
+    pub offset: usize,
+}
+
+/// `#[repr(C)]` structs which are shared with CUDA C kernels, and whose layout can be described in
+/// a generated C header so that the kernel's definition stays in sync with the Rust one.
+///
+/// This should be implemented with `#[derive(KernelStruct)]`, which also implements `DeviceCopy`
+/// and `CType`. See `rustacuda::kernel_header` for how to generate the header.
+///
+/// # Safety
+///
+/// The struct must be `#[repr(C)]` and `FIELDS` must describe every one of its fields, in
+/// declaration order.
+pub unsafe trait KernelStruct: DeviceCopy + CType {
+    /// The fields of the struct, in declaration order.
+    const FIELDS: &'static [KernelField];
+}
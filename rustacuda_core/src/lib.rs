@@ -14,5 +14,7 @@
 )]
 #![allow(unknown_lints)]
 
+mod kernel_struct;
 mod memory;
+pub use crate::kernel_struct::*;
 pub use crate::memory::*;
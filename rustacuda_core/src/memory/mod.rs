@@ -2,6 +2,7 @@ mod pointer;
 pub use self::pointer::*;
 
 use core::marker::PhantomData;
+use core::mem::MaybeUninit;
 use core::num::*;
 
 /// Marker trait for types which can safely be copied to or from a CUDA device.
@@ -86,6 +87,7 @@ unsafe impl<T: DeviceCopy> DeviceCopy for Option<T> {}
 unsafe impl<L: DeviceCopy, R: DeviceCopy> DeviceCopy for Result<L, R> {}
 unsafe impl<T: ?Sized + DeviceCopy> DeviceCopy for PhantomData<T> {}
 unsafe impl<T: DeviceCopy> DeviceCopy for Wrapping<T> {}
+unsafe impl<T: DeviceCopy> DeviceCopy for MaybeUninit<T> {}
 
 macro_rules! impl_device_copy_array {
     ($($n:expr)*) => {
@@ -19,6 +19,88 @@ pub fn derive_device_copy(input: BaseTokenStream) -> BaseTokenStream {
     BaseTokenStream::from(gen)
 }
 
+/// Implements `DeviceCopy`, `CType` and `KernelStruct` for a `#[repr(C)]` struct, so that its
+/// layout can be exported to a C header for the kernels which use it.
+#[proc_macro_derive(KernelStruct)]
+pub fn derive_kernel_struct(input: BaseTokenStream) -> BaseTokenStream {
+    let ast = syn::parse(input).unwrap();
+    let gen = impl_kernel_struct(&ast).unwrap_or_else(|e| e.to_compile_error());
+    BaseTokenStream::from(gen)
+}
+
+fn impl_kernel_struct(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let input_type = &input.ident;
+    let data_struct = match input.data {
+        Data::Struct(ref data_struct) => data_struct,
+        _ => {
+            return Err(syn::Error::new_spanned(
+                input,
+                "KernelStruct can only be derived for structs",
+            ))
+        }
+    };
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input.generics,
+            "KernelStruct can't be derived for generic structs, which have no C equivalent",
+        ));
+    }
+    let is_repr_c = input.attrs.iter().any(|attr| {
+        attr.path.is_ident("repr")
+            && attr
+                .parse_args_with(
+                    syn::punctuated::Punctuated::<Ident, syn::Token![,]>::parse_terminated,
+                )
+                .map(|reprs| reprs.iter().any(|repr| repr == "C"))
+                .unwrap_or(false)
+    });
+    if !is_repr_c {
+        return Err(syn::Error::new_spanned(
+            input_type,
+            "KernelStruct requires #[repr(C)], so that the layout matches the C declaration",
+        ));
+    }
+
+    let fields = data_struct.fields.iter().enumerate().map(|(i, field)| {
+        let field_type = &field.ty;
+        let (name, member) = match field.ident {
+            Some(ref ident) => (ident.to_string(), quote!(#ident)),
+            None => {
+                let index = syn::Index::from(i);
+                (format!("_{}", i), quote!(#index))
+            }
+        };
+        quote! {
+            ::rustacuda_core::KernelField {
+                name: #name,
+                c_type: <#field_type as ::rustacuda_core::CType>::C_NAME,
+                pointers: <#field_type as ::rustacuda_core::CType>::POINTERS,
+                array_len: <#field_type as ::rustacuda_core::CType>::ARRAY_LEN,
+                offset: ::core::mem::offset_of!(#input_type, #member),
+            }
+        }
+    });
+    let c_name = input_type.to_string();
+    let device_copy = impl_device_copy(input);
+
+    Ok(quote! {
+        #device_copy
+
+        impl ::rustacuda_core::CType for #input_type {
+            const C_NAME: &'static str = #c_name;
+        }
+
+        unsafe impl ::rustacuda_core::KernelStruct for #input_type {
+            const FIELDS: &'static [::rustacuda_core::KernelField] = &[#(#fields),*];
+        }
+
+        // Evaluate the field descriptions where the struct is defined, so that fields which can't
+        // be described, such as arrays of arrays, are reported even if `FIELDS` is never used.
+        const _: &'static [::rustacuda_core::KernelField] =
+            <#input_type as ::rustacuda_core::KernelStruct>::FIELDS;
+    })
+}
+
 fn impl_device_copy(input: &DeriveInput) -> TokenStream {
     let input_type = &input.ident;
 
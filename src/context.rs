@@ -113,13 +113,23 @@
 //! ```
 
 use crate::device::Device;
-use crate::error::{CudaResult, DropResult, ToResult};
+use crate::error::{CudaError, CudaResult, DropExpect, DropResult, ToResult};
+use crate::memory::{self, UnifiedAdvice};
+use crate::module;
 use crate::private::Sealed;
+use crate::stream::Stream;
+use crate::sync::lock_ignoring_poison;
+use crate::teardown;
 use crate::CudaApiVersion;
 use cuda_driver_sys::CUcontext;
+use std::cell::Cell;
+use std::marker::PhantomData;
 use std::mem;
 use std::mem::transmute;
+use std::ops::Deref;
 use std::ptr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
 
 /// This enumeration represents configuration settings for devices which share hardware resources
 /// between L1 cache and shared memory.
@@ -150,6 +160,8 @@ pub enum CacheConfig {
 /// This enumeration represents the limited resources which can be accessed through
 /// [CurrentContext::get_resource_limit](struct.CurrentContext.html#method.get_resource_limit) and
 /// [CurrentContext::set_resource_limit](struct.CurrentContext.html#method.set_resource_limit).
+#[doc(alias = "Limit")]
+#[doc(alias = "CUlimit")]
 #[repr(u32)]
 #[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
 pub enum ResourceLimit {
@@ -170,6 +182,11 @@ pub enum ResourceLimit {
     DeviceRuntimePendingLaunchCount = 4,
     /// L2 cache fetch granularity
     MaxL2FetchGranularity = 5,
+    /// The size in bytes of the portion of L2 set aside for persisting accesses, see
+    /// [`Stream::set_access_policy_window`](../stream/struct.Stream.html#method.set_access_policy_window).
+    ///
+    /// Requires a CUDA 11.0 or newer driver and a device of compute capability 8.0 or higher.
+    PersistingL2CacheSize = 6,
 
     #[doc(hidden)]
     __Nonexhaustive,
@@ -229,6 +246,156 @@ bitflags! {
     }
 }
 
+/// Builder for a [`Context`](struct.Context.html), which can also configure the new context's
+/// cache preferences and resource limits.
+///
+/// # Examples
+///
+/// ```
+/// # use rustacuda::*;
+/// # use std::error::Error;
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// use rustacuda::context::{CacheConfig, Context, ResourceLimit};
+/// use rustacuda::device::Device;
+///
+/// init(CudaFlags::empty())?;
+/// let context = Context::builder()
+///     .device(Device::get_device(0)?)
+///     .sched_yield()
+///     .map_host()
+///     .cache_config(CacheConfig::PreferL1)
+///     .limit(ResourceLimit::MallocHeapSize, 64 * 1024 * 1024)
+///     .build()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ContextBuilder {
+    flags: ContextFlags,
+    device: Option<Device>,
+    cache_config: Option<CacheConfig>,
+    shared_memory_config: Option<SharedMemoryConfig>,
+    limits: Vec<(ResourceLimit, usize)>,
+}
+impl ContextBuilder {
+    /// Create a builder for a context on the first device, with `SCHED_AUTO` scheduling.
+    pub fn new() -> Self {
+        ContextBuilder {
+            flags: ContextFlags::SCHED_AUTO,
+            device: None,
+            cache_config: None,
+            shared_memory_config: None,
+            limits: Vec::new(),
+        }
+    }
+
+    /// Set the flags of the context, replacing any flags set previously.
+    pub fn flags(mut self, flags: ContextFlags) -> Self {
+        self.flags = flags;
+        self
+    }
+
+    /// Create the context for `device` instead of the first device.
+    pub fn device(mut self, device: Device) -> Self {
+        self.device = Some(device);
+        self
+    }
+
+    /// Spin while waiting for the GPU. See `ContextFlags::SCHED_SPIN`.
+    pub fn sched_spin(mut self) -> Self {
+        self.flags |= ContextFlags::SCHED_SPIN;
+        self
+    }
+
+    /// Yield the thread while waiting for the GPU. See `ContextFlags::SCHED_YIELD`.
+    pub fn sched_yield(mut self) -> Self {
+        self.flags |= ContextFlags::SCHED_YIELD;
+        self
+    }
+
+    /// Block on a synchronization primitive while waiting for the GPU. See
+    /// `ContextFlags::SCHED_BLOCKING_SYNC`.
+    pub fn sched_blocking_sync(mut self) -> Self {
+        self.flags |= ContextFlags::SCHED_BLOCKING_SYNC;
+        self
+    }
+
+    /// Support mapped page-locked allocations. See `ContextFlags::MAP_HOST`.
+    pub fn map_host(mut self) -> Self {
+        self.flags |= ContextFlags::MAP_HOST;
+        self
+    }
+
+    /// Keep local memory at its largest size after kernels resize it. See
+    /// `ContextFlags::LMEM_RESIZE_TO_MAX`.
+    pub fn lmem_resize_to_max(mut self) -> Self {
+        self.flags |= ContextFlags::LMEM_RESIZE_TO_MAX;
+        self
+    }
+
+    /// Set the preferred cache configuration of the context. See
+    /// [`CurrentContext::set_cache_config`](struct.CurrentContext.html#method.set_cache_config).
+    pub fn cache_config(mut self, config: CacheConfig) -> Self {
+        self.cache_config = Some(config);
+        self
+    }
+
+    /// Set the shared memory bank size of the context. See
+    /// [`CurrentContext::set_shared_memory_config`](struct.CurrentContext.html#method.set_shared_memory_config).
+    pub fn shared_memory_config(mut self, config: SharedMemoryConfig) -> Self {
+        self.shared_memory_config = Some(config);
+        self
+    }
+
+    /// Set a resource limit of the context. See
+    /// [`CurrentContext::set_resource_limit`](struct.CurrentContext.html#method.set_resource_limit).
+    /// Setting the same limit twice keeps the last value.
+    pub fn limit(mut self, resource: ResourceLimit, value: usize) -> Self {
+        self.limits.retain(|&(limit, _)| limit != resource);
+        self.limits.push((resource, value));
+        self
+    }
+
+    /// Create the context, push it onto the context stack and apply the configuration.
+    ///
+    /// [`init`](../fn.init.html) must have been called first.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidValue` if more than one scheduling flag is set. If creating the context or
+    /// applying the configuration fails, the context is destroyed and the CUDA error is returned.
+    pub fn build(self) -> CudaResult<Context> {
+        let sched = self.flags
+            & (ContextFlags::SCHED_SPIN
+                | ContextFlags::SCHED_YIELD
+                | ContextFlags::SCHED_BLOCKING_SYNC);
+        if sched.bits().count_ones() > 1 {
+            return Err(CudaError::InvalidValue);
+        }
+        let device = match self.device {
+            Some(device) => device,
+            None => Device::get_device(0)?,
+        };
+
+        let context = Context::create_and_push(self.flags, device)?;
+        if let Some(config) = self.cache_config {
+            CurrentContext::set_cache_config(config)?;
+        }
+        if let Some(config) = self.shared_memory_config {
+            CurrentContext::set_shared_memory_config(config)?;
+        }
+        for (resource, value) in self.limits {
+            CurrentContext::set_resource_limit(resource, value)?;
+        }
+        Ok(context)
+    }
+}
+impl Default for ContextBuilder {
+    fn default() -> Self {
+        ContextBuilder::new()
+    }
+}
+
 /// Owned handle to a CUDA context.
 ///
 /// The context will be destroyed when this goes out of scope. If this is the current context on
@@ -239,6 +406,11 @@ bitflags! {
 pub struct Context {
     inner: CUcontext,
 }
+// The driver API is thread-safe and a context can be current on several threads at once, so a
+// context can be used and destroyed from any thread. See the module-level documentation for the
+// care needed when destroying a context which other threads are still using.
+unsafe impl Send for Context {}
+unsafe impl Sync for Context {}
 impl Context {
     /// Create a CUDA context for the given device.
     ///
@@ -262,16 +434,25 @@ impl Context {
             // lifetime guarantees so we create-and-push, then pop, then the programmer has to
             // push again.
             let mut ctx: CUcontext = ptr::null_mut();
-            cuda_driver_sys::cuCtxCreate_v2(
+            driver_call!(cuda_driver_sys::cuCtxCreate_v2(
                 &mut ctx as *mut CUcontext,
                 flags.bits(),
                 device.into_inner(),
-            )
-            .to_result()?;
+            ))
+            .to_result()
+            .map_err(|e| device.explain_context_error(e))?;
+            register_context(ctx);
+            cache_current(Some(ctx));
             Ok(Context { inner: ctx })
         }
     }
 
+    /// Returns a [`ContextBuilder`](struct.ContextBuilder.html) for creating a context with more
+    /// configuration than `create_and_push` takes.
+    pub fn builder() -> ContextBuilder {
+        ContextBuilder::new()
+    }
+
     /// Get the API version used to create this context.
     ///
     /// This is not necessarily the latest version supported by the driver.
@@ -294,8 +475,11 @@ impl Context {
     pub fn get_api_version(&self) -> CudaResult<CudaApiVersion> {
         unsafe {
             let mut api_version = 0u32;
-            cuda_driver_sys::cuCtxGetApiVersion(self.inner, &mut api_version as *mut u32)
-                .to_result()?;
+            driver_call!(cuda_driver_sys::cuCtxGetApiVersion(
+                self.inner,
+                &mut api_version as *mut u32
+            ))
+            .to_result()?;
             Ok(CudaApiVersion {
                 version: api_version as i32,
             })
@@ -326,6 +510,59 @@ impl Context {
         UnownedContext { inner: self.inner }
     }
 
+    /// Allow this context to access memory allocated in the `peer` context, which must be on a
+    /// different device.
+    ///
+    /// Once enabled, kernels running in this context can dereference pointers to memory allocated
+    /// in `peer`, and copies between the two contexts' memory go directly between the devices.
+    /// Access is one-way; enable it on `peer` as well to allow access in the other direction. Use
+    /// [`Device::can_access_peer`](../device/struct.Device.html#method.can_access_peer) to check
+    /// whether the devices support it.
+    ///
+    /// This context does not need to be current; it is pushed for the duration of the call.
+    ///
+    /// # Errors
+    ///
+    /// Returns `PeerAccessUnsupported` if the devices can't access each other,
+    /// `PeerAccessAlreadyEnabled` if access is already enabled, and `TooManyPeers` if the device's
+    /// limit of peers has been reached.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rustacuda::device::Device;
+    /// # use rustacuda::context::{Context, ContextFlags};
+    /// # use std::error::Error;
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # rustacuda::init(rustacuda::CudaFlags::empty())?;
+    /// if Device::num_devices()? >= 2 {
+    ///     let (first, second) = (Device::get_device(0)?, Device::get_device(1)?);
+    ///     let a = Context::create_and_push(ContextFlags::MAP_HOST | ContextFlags::SCHED_AUTO, first)?;
+    ///     let b = Context::create_and_push(ContextFlags::MAP_HOST | ContextFlags::SCHED_AUTO, second)?;
+    ///     if first.can_access_peer(second)? {
+    ///         a.enable_peer_access(&b)?;
+    ///         // ... kernels in `a` can now read memory allocated in `b` ...
+    ///         a.disable_peer_access(&b)?;
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn enable_peer_access<C: ContextHandle>(&self, peer: &C) -> CudaResult<()> {
+        with_pushed(self, || enable_peer_access(peer))
+    }
+
+    /// Stop this context from accessing memory allocated in the `peer` context.
+    ///
+    /// # Errors
+    ///
+    /// Returns `PeerAccessNotEnabled` if access to `peer` was not enabled with
+    /// [`enable_peer_access`](#method.enable_peer_access).
+    pub fn disable_peer_access<C: ContextHandle>(&self, peer: &C) -> CudaResult<()> {
+        with_pushed(self, || disable_peer_access(peer))
+    }
+
     /// Destroy a `Context`, returning an error.
     ///
     /// Destroying a context can return errors from previous asynchronous work. This function
@@ -359,8 +596,10 @@ impl Context {
 
         unsafe {
             let inner = mem::replace(&mut ctx.inner, ptr::null_mut());
-            match cuda_driver_sys::cuCtxDestroy_v2(inner).to_result() {
+            match driver_call!(cuda_driver_sys::cuCtxDestroy_v2(inner)).to_result() {
                 Ok(()) => {
+                    unregister_context(inner);
+                    teardown::context_destroyed(inner);
                     mem::forget(ctx);
                     Ok(())
                 }
@@ -378,10 +617,144 @@ impl Drop for Context {
         unsafe {
             let inner = mem::replace(&mut self.inner, ptr::null_mut());
             // No choice but to panic here.
-            cuda_driver_sys::cuCtxDestroy_v2(inner)
+            driver_call!(cuda_driver_sys::cuCtxDestroy_v2(inner))
                 .to_result()
-                .expect("Failed to destroy context");
+                .expect_in_drop("Failed to destroy context");
+            unregister_context(inner);
+            teardown::context_destroyed(inner);
+        }
+    }
+}
+
+/// Returns the handle of the current context, or `InvalidContext` if there is none.
+pub(crate) fn current_handle() -> CudaResult<CUcontext> {
+    let mut ctx: CUcontext = ptr::null_mut();
+    unsafe { driver_call!(cuda_driver_sys::cuCtxGetCurrent(&mut ctx as *mut CUcontext)) }
+        .to_result()?;
+    if ctx.is_null() {
+        return Err(CudaError::InvalidContext);
+    }
+    Ok(ctx)
+}
+
+/// Source of generation numbers for contexts created by RustaCUDA.
+static NEXT_GENERATION: AtomicU64 = AtomicU64::new(1);
+
+/// The contexts created by RustaCUDA that have not yet been destroyed, as (handle, generation)
+/// pairs. The driver may reuse the handle of a destroyed context, so the generation is what
+/// distinguishes a new context from a dead one at the same address.
+static LIVE_CONTEXTS: Mutex<Vec<(usize, u64)>> = Mutex::new(Vec::new());
+
+fn live_contexts() -> std::sync::MutexGuard<'static, Vec<(usize, u64)>> {
+    lock_ignoring_poison(&LIVE_CONTEXTS)
+}
+
+fn register_context(ctx: CUcontext) {
+    let generation = NEXT_GENERATION.fetch_add(1, Ordering::Relaxed);
+    let mut live = live_contexts();
+    live.retain(|&(handle, _)| handle != ctx as usize);
+    live.push((ctx as usize, generation));
+}
+
+fn unregister_context(ctx: CUcontext) {
+    live_contexts().retain(|&(handle, _)| handle != ctx as usize);
+    memory::forget_default_advice(ctx);
+    module::forget_builtin_modules(ctx);
+    let _ = DESTROYED_CONTEXTS.fetch_add(1, Ordering::Relaxed);
+    cache_current(None);
+}
+
+/// Counts the contexts destroyed by RustaCUDA, on any thread. A cached current context is only
+/// trusted while this is unchanged, since the driver may reuse a destroyed context's handle.
+static DESTROYED_CONTEXTS: AtomicU64 = AtomicU64::new(0);
+
+thread_local! {
+    /// The context which RustaCUDA last made current on this thread, if known, along with the
+    /// value of `DESTROYED_CONTEXTS` at the time.
+    static CURRENT_CONTEXT: Cell<Option<(usize, u64)>> = const { Cell::new(None) };
+}
+
+/// Records that `ctx` is now the current context of this thread, or that it is unknown.
+fn cache_current(ctx: Option<CUcontext>) {
+    let entry = ctx.map(|ctx| (ctx as usize, DESTROYED_CONTEXTS.load(Ordering::Relaxed)));
+    CURRENT_CONTEXT.with(|current| current.set(entry));
+}
+
+/// Returns `true` if `ctx` is known to be the current context of this thread.
+fn is_cached_current(ctx: CUcontext) -> bool {
+    let entry = CURRENT_CONTEXT.with(Cell::get);
+    entry == Some((ctx as usize, DESTROYED_CONTEXTS.load(Ordering::Relaxed)))
+}
+
+/// Identifies one specific context created by RustaCUDA, so that objects which live in that
+/// context can detect that it has been destroyed.
+///
+/// Modules, functions, symbols and events record the token of the context they were created in,
+/// and debug builds (or builds with the `context-check` feature) check it before using them.
+/// Contexts which were not created by RustaCUDA (such as primary contexts retained by other
+/// libraries) have no token and are never checked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct ContextToken {
+    handle: usize,
+    generation: u64,
+}
+impl ContextToken {
+    /// Returns the token of the current context, if there is one and it was created by RustaCUDA.
+    /// Always returns `None` in release builds without the `context-check` feature, where tokens
+    /// are not checked.
+    pub(crate) fn current() -> Option<ContextToken> {
+        if !cfg!(any(debug_assertions, feature = "context-check")) {
+            return None;
         }
+        let mut ctx: CUcontext = ptr::null_mut();
+        unsafe { driver_call!(cuda_driver_sys::cuCtxGetCurrent(&mut ctx as *mut CUcontext)) }
+            .to_result()
+            .ok()?;
+        live_contexts()
+            .iter()
+            .find(|&&(handle, _)| handle == ctx as usize)
+            .map(|&(handle, generation)| ContextToken { handle, generation })
+    }
+
+    /// Returns `true` if this token identifies the context `ctx`.
+    pub(crate) fn is_context(self, ctx: CUcontext) -> bool {
+        self.handle == ctx as usize
+    }
+
+    /// Returns `ContextIsDestroyed` if the context identified by `token` has been destroyed.
+    pub(crate) fn check(token: Option<ContextToken>) -> CudaResult<()> {
+        match token {
+            Some(token) if !live_contexts().contains(&(token.handle, token.generation)) => {
+                Err(CudaError::ContextIsDestroyed)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns `ContextMismatch` if `token` identifies a different context than the one `stream`
+    /// belongs to. `what` describes the object `token` belongs to, for the log message.
+    #[cfg_attr(not(feature = "log"), allow(unused_variables))]
+    pub(crate) fn check_stream(
+        token: Option<ContextToken>,
+        stream: &Stream,
+        what: &str,
+    ) -> CudaResult<()> {
+        let token = match token {
+            Some(token) => token,
+            None => return Ok(()),
+        };
+        let ctx = stream.context()?.get_inner();
+        if token.is_context(ctx) {
+            return Ok(());
+        }
+        #[cfg(feature = "log")]
+        log::error!(
+            "{} belongs to context {:#x}, but the stream belongs to context {:p}",
+            what,
+            token.handle,
+            ctx
+        );
+        Err(CudaError::ContextMismatch)
     }
 }
 
@@ -403,6 +776,13 @@ impl ContextHandle for UnownedContext {
     }
 }
 
+impl Sealed for BorrowedContext<'_> {}
+impl ContextHandle for BorrowedContext<'_> {
+    fn get_inner(&self) -> CUcontext {
+        self.context.inner
+    }
+}
+
 /// Non-owning handle to a CUDA context.
 #[derive(Debug, Clone)]
 pub struct UnownedContext {
@@ -411,6 +791,16 @@ pub struct UnownedContext {
 unsafe impl Send for UnownedContext {}
 unsafe impl Sync for UnownedContext {}
 impl UnownedContext {
+    pub(crate) fn from_inner(inner: CUcontext) -> UnownedContext {
+        UnownedContext { inner }
+    }
+
+    /// Returns the raw CUDA context handle, for passing to other CUDA libraries or to driver API
+    /// functions that RustaCUDA does not wrap.
+    pub fn as_raw(&self) -> CUcontext {
+        self.inner
+    }
+
     /// Get the API version used to create this context.
     ///
     /// This is not necessarily the latest version supported by the driver.
@@ -435,13 +825,143 @@ impl UnownedContext {
     pub fn get_api_version(&self) -> CudaResult<CudaApiVersion> {
         unsafe {
             let mut api_version = 0u32;
-            cuda_driver_sys::cuCtxGetApiVersion(self.inner, &mut api_version as *mut u32)
-                .to_result()?;
+            driver_call!(cuda_driver_sys::cuCtxGetApiVersion(
+                self.inner,
+                &mut api_version as *mut u32
+            ))
+            .to_result()?;
             Ok(CudaApiVersion {
                 version: api_version as i32,
             })
         }
     }
+
+    /// Returns the device this context was created for.
+    ///
+    /// This context does not need to be current; it is pushed for the duration of the call.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rustacuda::*;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # let _ctx = quick_init()?;
+    /// use rustacuda::context::CurrentContext;
+    ///
+    /// // A library handed whatever context the application made current.
+    /// let context = CurrentContext::get_current()?;
+    /// println!("Running on {}", context.get_device()?.name()?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_device(&self) -> CudaResult<Device> {
+        with_pushed(self, CurrentContext::get_device)
+    }
+
+    /// Returns the flags this context was created with.
+    ///
+    /// This context does not need to be current; it is pushed for the duration of the call.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rustacuda::*;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # let _ctx = quick_init()?;
+    /// use rustacuda::context::{ContextFlags, CurrentContext};
+    ///
+    /// let context = CurrentContext::get_current()?;
+    /// if !context.get_flags()?.contains(ContextFlags::MAP_HOST) {
+    ///     // Fall back to explicit copies instead of mapped host memory.
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_flags(&self) -> CudaResult<ContextFlags> {
+        with_pushed(self, CurrentContext::get_flags)
+    }
+
+    /// Allow this context to access memory allocated in the `peer` context. See
+    /// [`Context::enable_peer_access`](struct.Context.html#method.enable_peer_access).
+    pub fn enable_peer_access<C: ContextHandle>(&self, peer: &C) -> CudaResult<()> {
+        with_pushed(self, || enable_peer_access(peer))
+    }
+
+    /// Stop this context from accessing memory allocated in the `peer` context. See
+    /// [`Context::disable_peer_access`](struct.Context.html#method.disable_peer_access).
+    pub fn disable_peer_access<C: ContextHandle>(&self, peer: &C) -> CudaResult<()> {
+        with_pushed(self, || disable_peer_access(peer))
+    }
+}
+
+// Run `f` with `ctx` pushed onto the context stack, so that it operates on `ctx` whatever context
+// the caller has made current.
+fn with_pushed<C: ContextHandle, T>(ctx: &C, f: impl FnOnce() -> CudaResult<T>) -> CudaResult<T> {
+    ContextStack::push(ctx)?;
+    let result = f();
+    let _ = ContextStack::pop()?;
+    result
+}
+
+fn enable_peer_access<C: ContextHandle>(peer: &C) -> CudaResult<()> {
+    unsafe { driver_call!(cuda_driver_sys::cuCtxEnablePeerAccess(peer.get_inner(), 0)).to_result() }
+}
+
+fn disable_peer_access<C: ContextHandle>(peer: &C) -> CudaResult<()> {
+    unsafe { driver_call!(cuda_driver_sys::cuCtxDisablePeerAccess(peer.get_inner())).to_result() }
+}
+
+/// A context created outside of RustaCUDA, such as by a framework which RustaCUDA code is embedded
+/// in, borrowed for the lifetime `'a`.
+///
+/// This dereferences to an [`UnownedContext`](struct.UnownedContext.html), and can be pushed onto
+/// the context stack or made current like any other context. Dropping it does nothing; the context
+/// stays owned by whoever created it.
+///
+/// # Examples
+///
+/// ```
+/// # use rustacuda::*;
+/// # use std::error::Error;
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// # let _ctx = quick_init()?;
+/// # let raw_context = context::CurrentContext::get_current()?.as_raw();
+/// use rustacuda::context::{BorrowedContext, ContextStack};
+///
+/// // `raw_context` is a `CUcontext` handed to us by the host application.
+/// let context = unsafe { BorrowedContext::from_raw(raw_context) };
+/// ContextStack::push(&context)?;
+/// // Use CUDA here.
+/// ContextStack::pop()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct BorrowedContext<'a> {
+    context: UnownedContext,
+    _marker: PhantomData<&'a ()>,
+}
+impl<'a> BorrowedContext<'a> {
+    /// Borrows a context from its raw CUDA context handle.
+    ///
+    /// # Safety
+    ///
+    /// `handle` must be a valid CUDA context which stays alive for the lifetime `'a`.
+    pub unsafe fn from_raw(handle: CUcontext) -> Self {
+        BorrowedContext {
+            context: UnownedContext::from_inner(handle),
+            _marker: PhantomData,
+        }
+    }
+}
+impl Deref for BorrowedContext<'_> {
+    type Target = UnownedContext;
+
+    fn deref(&self) -> &UnownedContext {
+        &self.context
+    }
 }
 
 /// Type used to represent the thread-local context stack.
@@ -470,7 +990,12 @@ impl ContextStack {
     pub fn pop() -> CudaResult<UnownedContext> {
         unsafe {
             let mut ctx: CUcontext = ptr::null_mut();
-            cuda_driver_sys::cuCtxPopCurrent_v2(&mut ctx as *mut CUcontext).to_result()?;
+            // Whatever context was below it on the stack is current now.
+            cache_current(None);
+            driver_call!(cuda_driver_sys::cuCtxPopCurrent_v2(
+                &mut ctx as *mut CUcontext
+            ))
+            .to_result()?;
             Ok(UnownedContext { inner: ctx })
         }
     }
@@ -495,7 +1020,8 @@ impl ContextStack {
     /// ```
     pub fn push<C: ContextHandle>(ctx: &C) -> CudaResult<()> {
         unsafe {
-            cuda_driver_sys::cuCtxPushCurrent_v2(ctx.get_inner()).to_result()?;
+            driver_call!(cuda_driver_sys::cuCtxPushCurrent_v2(ctx.get_inner())).to_result()?;
+            cache_current(Some(ctx.get_inner()));
             Ok(())
         }
     }
@@ -512,6 +1038,17 @@ pub struct StreamPriorityRange {
     /// The greatest stream priority
     pub greatest: i32,
 }
+impl StreamPriorityRange {
+    /// Returns `true` if `priority` is within the range, and so won't be clamped.
+    pub fn contains(&self, priority: i32) -> bool {
+        self.greatest <= priority && priority <= self.least
+    }
+
+    /// Returns `priority` clamped to the range, as the driver does when creating a stream.
+    pub fn clamp(&self, priority: i32) -> i32 {
+        priority.max(self.greatest).min(self.least)
+    }
+}
 
 /// Type representing the top context in the thread-local stack.
 #[derive(Debug)]
@@ -542,9 +1079,9 @@ impl CurrentContext {
     pub fn get_cache_config() -> CudaResult<CacheConfig> {
         unsafe {
             let mut config = CacheConfig::PreferNone;
-            cuda_driver_sys::cuCtxGetCacheConfig(
+            driver_call!(cuda_driver_sys::cuCtxGetCacheConfig(
                 &mut config as *mut CacheConfig as *mut cuda_driver_sys::CUfunc_cache,
-            )
+            ))
             .to_result()?;
             Ok(config)
         }
@@ -570,8 +1107,10 @@ impl CurrentContext {
     pub fn get_device() -> CudaResult<Device> {
         unsafe {
             let mut device = Device { device: 0 };
-            cuda_driver_sys::cuCtxGetDevice(&mut device.device as *mut cuda_driver_sys::CUdevice)
-                .to_result()?;
+            driver_call!(cuda_driver_sys::cuCtxGetDevice(
+                &mut device.device as *mut cuda_driver_sys::CUdevice
+            ))
+            .to_result()?;
             Ok(device)
         }
     }
@@ -596,11 +1135,46 @@ impl CurrentContext {
     pub fn get_flags() -> CudaResult<ContextFlags> {
         unsafe {
             let mut flags = 0u32;
-            cuda_driver_sys::cuCtxGetFlags(&mut flags as *mut u32).to_result()?;
+            driver_call!(cuda_driver_sys::cuCtxGetFlags(&mut flags as *mut u32)).to_result()?;
             Ok(ContextFlags::from_bits_truncate(flags))
         }
     }
 
+    /// Returns the free and total device memory, in bytes, of the current context's device.
+    ///
+    /// The free memory is a snapshot; other contexts and processes on the same device can allocate
+    /// or free memory at any time, so an allocation of that size can still fail.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rustacuda::*;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # let _ctx = quick_init()?;
+    /// use rustacuda::context::CurrentContext;
+    /// use rustacuda::memory::DeviceBuffer;
+    ///
+    /// let (free, total) = CurrentContext::get_mem_info()?;
+    /// assert!(free <= total);
+    /// // Use at most half of the free memory for a batch of floats.
+    /// let batch = unsafe { DeviceBuffer::<f32>::uninitialized(free / 2 / 4)? };
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_mem_info() -> CudaResult<(usize, usize)> {
+        unsafe {
+            let mut free = 0usize;
+            let mut total = 0usize;
+            driver_call!(cuda_driver_sys::cuMemGetInfo_v2(
+                &mut free as *mut usize,
+                &mut total as *mut usize
+            ))
+            .to_result()?;
+            Ok((free, total))
+        }
+    }
+
     /// Return resource limits for the current context.
     ///
     /// # Example
@@ -618,11 +1192,16 @@ impl CurrentContext {
     /// # Ok(())
     /// # }
     /// ```
+    #[doc(alias = "get_limit")]
+    #[doc(alias = "cuCtxGetLimit")]
     pub fn get_resource_limit(resource: ResourceLimit) -> CudaResult<usize> {
         unsafe {
             let mut limit: usize = 0;
-            cuda_driver_sys::cuCtxGetLimit(&mut limit as *mut usize, transmute(resource))
-                .to_result()?;
+            driver_call!(cuda_driver_sys::cuCtxGetLimit(
+                &mut limit as *mut usize,
+                transmute::<ResourceLimit, cuda_driver_sys::CUlimit>(resource)
+            ))
+            .to_result()?;
             Ok(limit)
         }
     }
@@ -647,9 +1226,9 @@ impl CurrentContext {
     pub fn get_shared_memory_config() -> CudaResult<SharedMemoryConfig> {
         unsafe {
             let mut cfg = SharedMemoryConfig::DefaultBankSize;
-            cuda_driver_sys::cuCtxGetSharedMemConfig(
+            driver_call!(cuda_driver_sys::cuCtxGetSharedMemConfig(
                 &mut cfg as *mut SharedMemoryConfig as *mut cuda_driver_sys::CUsharedconfig,
-            )
+            ))
             .to_result()?;
             Ok(cfg)
         }
@@ -682,10 +1261,10 @@ impl CurrentContext {
                 least: 0,
                 greatest: 0,
             };
-            cuda_driver_sys::cuCtxGetStreamPriorityRange(
+            driver_call!(cuda_driver_sys::cuCtxGetStreamPriorityRange(
                 &mut range.least as *mut i32,
                 &mut range.greatest as *mut i32,
-            )
+            ))
             .to_result()?;
             Ok(range)
         }
@@ -717,7 +1296,10 @@ impl CurrentContext {
     /// # }
     /// ```
     pub fn set_cache_config(cfg: CacheConfig) -> CudaResult<()> {
-        unsafe { cuda_driver_sys::cuCtxSetCacheConfig(transmute(cfg)).to_result() }
+        unsafe {
+            let cfg = transmute::<CacheConfig, cuda_driver_sys::CUfunc_cache>(cfg);
+            driver_call!(cuda_driver_sys::cuCtxSetCacheConfig(cfg)).to_result()
+        }
     }
 
     /// Sets a requested resource limit for the current context.
@@ -757,16 +1339,42 @@ impl CurrentContext {
     /// # let device = Device::get_device(0)?;
     /// let context = Context::create_and_push(ContextFlags::MAP_HOST | ContextFlags::SCHED_AUTO, device)?;
     /// CurrentContext::set_resource_limit(ResourceLimit::StackSize, 2048)?;
+    ///
+    /// // Make room for kernels which call malloc() and printf() on the device.
+    /// CurrentContext::set_resource_limit(ResourceLimit::MallocHeapSize, 64 * 1024 * 1024)?;
+    /// CurrentContext::set_resource_limit(ResourceLimit::PrintfFifoSize, 4 * 1024 * 1024)?;
+    /// assert!(CurrentContext::get_resource_limit(ResourceLimit::MallocHeapSize)? >= 64 * 1024 * 1024);
     /// # Ok(())
     /// # }
     /// ```
+    #[doc(alias = "set_limit")]
+    #[doc(alias = "cuCtxSetLimit")]
     pub fn set_resource_limit(resource: ResourceLimit, limit: usize) -> CudaResult<()> {
         unsafe {
-            cuda_driver_sys::cuCtxSetLimit(transmute(resource), limit).to_result()?;
+            driver_call!(cuda_driver_sys::cuCtxSetLimit(
+                transmute::<ResourceLimit, cuda_driver_sys::CUlimit>(resource),
+                limit
+            ))
+            .to_result()?;
             Ok(())
         }
     }
 
+    /// Reset all persisting lines in the L2 cache to normal, so that they can be evicted.
+    ///
+    /// Lines keep persisting after the
+    /// [access policy window](../stream/struct.Stream.html#method.set_access_policy_window) which
+    /// brought them into L2 is removed; call this once they are no longer needed.
+    ///
+    /// # Errors
+    ///
+    /// Returns `NotSupported` if the driver is older than CUDA 11.0. If the cache can't be reset,
+    /// returns the CUDA error value.
+    pub fn reset_persisting_l2_cache() -> CudaResult<()> {
+        let reset = reset_persisting_l2_cache_fn().ok_or(CudaError::NotSupported)?;
+        unsafe { reset().to_result() }
+    }
+
     /// Sets the preferred shared memory configuration for the current context.
     ///
     /// On devices with configurable shared memory banks, this function will set the context's
@@ -788,7 +1396,56 @@ impl CurrentContext {
     /// # }
     /// ```
     pub fn set_shared_memory_config(cfg: SharedMemoryConfig) -> CudaResult<()> {
-        unsafe { cuda_driver_sys::cuCtxSetSharedMemConfig(transmute(cfg)).to_result() }
+        unsafe {
+            let cfg = transmute::<SharedMemoryConfig, cuda_driver_sys::CUsharedconfig>(cfg);
+            driver_call!(cuda_driver_sys::cuCtxSetSharedMemConfig(cfg)).to_result()
+        }
+    }
+
+    /// Returns the memory advice applied to every unified allocation made in the current
+    /// context, as set with [`set_unified_advice`](#method.set_unified_advice).
+    ///
+    /// # Errors
+    ///
+    /// If there is no current context, returns `InvalidContext`.
+    pub fn get_unified_advice() -> CudaResult<UnifiedAdvice> {
+        Ok(memory::get_default_advice(current_handle()?))
+    }
+
+    /// Sets memory advice which is applied to every unified allocation made in the current
+    /// context from now on, such as the memory of each `UnifiedBuffer` and `UnifiedBox`. This
+    /// saves repeating the same hints for every buffer, for example to keep all unified memory
+    /// on the device. Allocations which already exist are not affected. Pass
+    /// `UnifiedAdvice::default()` to stop giving advice.
+    ///
+    /// # Errors
+    ///
+    /// If there is no current context, returns `InvalidContext`. Advice which the device can't
+    /// follow is reported by the allocations it is applied to.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rustacuda::*;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # let _ctx = quick_init()?;
+    /// use rustacuda::context::CurrentContext;
+    /// use rustacuda::memory::{UnifiedAdvice, UnifiedBuffer};
+    ///
+    /// let device = CurrentContext::get_device()?;
+    /// CurrentContext::set_unified_advice(UnifiedAdvice {
+    ///     preferred_location: Some(device.into()),
+    ///     ..UnifiedAdvice::default()
+    /// })?;
+    /// // The pages of this buffer stay on the device where possible.
+    /// let buffer = UnifiedBuffer::new(&0.0f32, 1024)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_unified_advice(advice: UnifiedAdvice) -> CudaResult<()> {
+        memory::set_default_advice(current_handle()?, advice);
+        Ok(())
     }
 
     /// Returns a non-owning handle to the current context.
@@ -811,7 +1468,9 @@ impl CurrentContext {
     pub fn get_current() -> CudaResult<UnownedContext> {
         unsafe {
             let mut ctx: CUcontext = ptr::null_mut();
-            cuda_driver_sys::cuCtxGetCurrent(&mut ctx as *mut CUcontext).to_result()?;
+            driver_call!(cuda_driver_sys::cuCtxGetCurrent(&mut ctx as *mut CUcontext))
+                .to_result()?;
+            cache_current(Some(ctx));
             Ok(UnownedContext { inner: ctx })
         }
     }
@@ -839,16 +1498,62 @@ impl CurrentContext {
     /// ```
     pub fn set_current<C: ContextHandle>(c: &C) -> CudaResult<()> {
         unsafe {
-            cuda_driver_sys::cuCtxSetCurrent(c.get_inner()).to_result()?;
+            cache_current(None);
+            driver_call!(cuda_driver_sys::cuCtxSetCurrent(c.get_inner())).to_result()?;
+            cache_current(Some(c.get_inner()));
             Ok(())
         }
     }
 
+    /// Make the given context current for this thread, like [`set_current`](#method.set_current),
+    /// unless it already is.
+    ///
+    /// RustaCUDA remembers which context it last made current on each thread, so when the context
+    /// rarely changes, such as in tasks of a thread pool which each call this before doing their
+    /// work, this usually returns without calling the driver at all. The cache is updated by
+    /// `set_current`, `get_current`, [`ContextStack`](struct.ContextStack.html) and by creating and
+    /// destroying contexts. If other code changes the current context through the driver API
+    /// directly, call `set_current` or `get_current` afterwards so that `ensure` sees the change.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rustacuda::*;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # let context = quick_init()?;
+    /// use rustacuda::context::CurrentContext;
+    ///
+    /// let unowned = context.get_unowned();
+    /// let worker = std::thread::spawn(move || {
+    ///     for _ in 0..100 {
+    ///         // Only the first call makes the context current.
+    ///         CurrentContext::ensure(&unowned)?;
+    ///         // ... submit work ...
+    ///     }
+    ///     Ok::<(), error::CudaError>(())
+    /// });
+    /// worker.join().unwrap()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn ensure<C: ContextHandle>(c: &C) -> CudaResult<()> {
+        let ctx = c.get_inner();
+        if is_cached_current(ctx) || CurrentContext::get_current()?.inner == ctx {
+            return Ok(());
+        }
+        CurrentContext::set_current(c)
+    }
+
     /// Block to wait for a context's tasks to complete.
     pub fn synchronize() -> CudaResult<()> {
         unsafe {
-            cuda_driver_sys::cuCtxSynchronize().to_result()?;
+            driver_call!(cuda_driver_sys::cuCtxSynchronize()).to_result()?;
             Ok(())
         }
     }
 }
+
+type ResetPersistingL2CacheFn = unsafe extern "C" fn() -> cuda_driver_sys::CUresult;
+
+driver_fn!(reset_persisting_l2_cache_fn: ResetPersistingL2CacheFn = b"cuCtxResetPersistingL2Cache\0");
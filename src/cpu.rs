@@ -0,0 +1,427 @@
+//! Host emulation of device buffers, streams and kernel launches, for running tests on machines
+//! without a GPU.
+//!
+//! This module is only available with the `cpu-fallback` feature enabled.
+//!
+//! [`DeviceBuffer`](struct.DeviceBuffer.html) and [`Stream`](struct.Stream.html) mirror the types
+//! of the same names in the [`memory`](../memory/index.html) and [`stream`](../stream/index.html)
+//! modules, but keep their data in host memory and never call the CUDA driver, so they work
+//! without a GPU or a context. A crate can run its unit tests on GPU-less CI machines by choosing
+//! between them with a feature of its own:
+//!
+//! ```ignore
+//! #[cfg(not(feature = "cpu"))]
+//! use rustacuda::{memory::DeviceBuffer, stream::Stream};
+//! #[cfg(feature = "cpu")]
+//! use rustacuda::cpu::{DeviceBuffer, Stream};
+//! ```
+//!
+//! Compiled kernels can't run on the host, so kernels are written as closures instead and launched
+//! with [`Stream::launch`](struct.Stream.html#method.launch). The closure is called once for each
+//! thread of the grid, one thread after another, with the thread's
+//! [`ThreadIndex`](struct.ThreadIndex.html). Kernels which rely on threads running concurrently,
+//! such as those using shared memory and `__syncthreads`, can't be emulated this way.
+//!
+//! All work runs synchronously as soon as it is submitted, so the asynchronous copy functions and
+//! launches have completed when they return, and synchronizing does nothing.
+//!
+//! This crate still links against the CUDA driver library. On machines without a driver, link
+//! against the stub library shipped in the CUDA toolkit (`lib64/stubs/libcuda.so`); nothing in
+//! this module calls into it.
+//!
+//! # Examples
+//!
+//! ```
+//! # use std::error::Error;
+//! # fn main() -> Result<(), Box<dyn Error>> {
+//! use rustacuda::cpu::{DeviceBuffer, Stream};
+//! use rustacuda::memory::CopyDestination;
+//! use rustacuda::stream::StreamFlags;
+//!
+//! let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+//! let x = DeviceBuffer::from_slice(&[1.0f32, 2.0, 3.0])?;
+//! let mut out = DeviceBuffer::from_slice(&[0.0f32; 3])?;
+//!
+//! let (input, output) = (x.as_kernel_slice(), out.as_kernel_mut_slice());
+//! stream.launch(1, 3, |t| {
+//!     let i = t.global_x() as usize;
+//!     output[i] = input[i] * 2.0;
+//! })?;
+//! stream.synchronize()?;
+//!
+//! let mut host = [0.0f32; 3];
+//! out.copy_to(&mut host[..])?;
+//! assert_eq!([2.0, 4.0, 6.0], host);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::{CudaError, CudaResult, DropResult};
+use crate::function::{BlockSize, GridSize};
+use crate::memory::{CopyDestination, DeviceCopy, DevicePointer};
+use crate::stream::StreamFlags;
+use std::mem::MaybeUninit;
+use std::ptr;
+use std::slice;
+
+/// The maximum number of threads in a block, which is the same on every current GPU.
+const MAX_THREADS_PER_BLOCK: u32 = 1024;
+
+/// The x, y and z components of a thread or block index or dimension.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Dim3 {
+    /// The x component
+    pub x: u32,
+    /// The y component
+    pub y: u32,
+    /// The z component
+    pub z: u32,
+}
+
+/// The position of an emulated thread, equivalent to the built-in variables of a CUDA kernel.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ThreadIndex {
+    /// The index of the thread in its block (`threadIdx`)
+    pub thread_idx: Dim3,
+    /// The index of the block in the grid (`blockIdx`)
+    pub block_idx: Dim3,
+    /// The dimensions of each block (`blockDim`)
+    pub block_dim: Dim3,
+    /// The dimensions of the grid (`gridDim`)
+    pub grid_dim: Dim3,
+}
+impl ThreadIndex {
+    /// Returns the x index of the thread in the whole grid,
+    /// `blockIdx.x * blockDim.x + threadIdx.x`.
+    #[inline]
+    pub fn global_x(&self) -> u32 {
+        self.block_idx.x * self.block_dim.x + self.thread_idx.x
+    }
+
+    /// Returns the y index of the thread in the whole grid,
+    /// `blockIdx.y * blockDim.y + threadIdx.y`.
+    #[inline]
+    pub fn global_y(&self) -> u32 {
+        self.block_idx.y * self.block_dim.y + self.thread_idx.y
+    }
+
+    /// Returns the z index of the thread in the whole grid,
+    /// `blockIdx.z * blockDim.z + threadIdx.z`.
+    #[inline]
+    pub fn global_z(&self) -> u32 {
+        self.block_idx.z * self.block_dim.z + self.thread_idx.z
+    }
+}
+
+/// Host emulation of a [`DeviceBuffer`](../memory/struct.DeviceBuffer.html).
+///
+/// See the [module-level documentation](index.html) for more details.
+#[derive(Debug)]
+pub struct DeviceBuffer<T> {
+    // Uninitialized until written, like device memory.
+    buf: Vec<MaybeUninit<T>>,
+}
+impl<T: DeviceCopy> DeviceBuffer<T> {
+    /// Allocate a new buffer of `size` elements without initializing it.
+    ///
+    /// # Errors
+    ///
+    /// Never fails; returns a `CudaResult` to match `memory::DeviceBuffer`.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that the contents of the buffer are initialized before reading from
+    /// the buffer.
+    pub unsafe fn uninitialized(size: usize) -> CudaResult<Self> {
+        let buf = (0..size).map(|_| MaybeUninit::uninit()).collect();
+        Ok(DeviceBuffer { buf })
+    }
+
+    /// Allocate a new buffer of the same size as `slice`, initialized with a copy of the data in
+    /// `slice`.
+    ///
+    /// # Errors
+    ///
+    /// Never fails; returns a `CudaResult` to match `memory::DeviceBuffer`.
+    pub fn from_slice(slice: &[T]) -> CudaResult<Self> {
+        unsafe {
+            let mut buffer = DeviceBuffer::uninitialized(slice.len())?;
+            ptr::copy_nonoverlapping(slice.as_ptr(), buffer.as_mut_ptr(), slice.len());
+            Ok(buffer)
+        }
+    }
+
+    fn as_ptr(&self) -> *const T {
+        self.buf.as_ptr() as *const T
+    }
+
+    fn as_mut_ptr(&mut self) -> *mut T {
+        self.buf.as_mut_ptr() as *mut T
+    }
+
+    /// Returns the number of elements in the buffer.
+    pub fn len(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Returns `true` if the buffer has a length of 0.
+    pub fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+
+    /// Returns a pointer to the buffer. Since the buffer is in host memory, this pointer can be
+    /// dereferenced by emulated kernels (and by the host).
+    pub fn as_device_ptr(&mut self) -> DevicePointer<T> {
+        unsafe { DevicePointer::wrap(self.as_mut_ptr()) }
+    }
+
+    /// Returns the contents of the buffer, for a kernel closure to read.
+    pub fn as_kernel_slice(&self) -> &[T] {
+        unsafe { slice::from_raw_parts(self.as_ptr(), self.len()) }
+    }
+
+    /// Returns the contents of the buffer, for a kernel closure to write.
+    pub fn as_kernel_mut_slice(&mut self) -> &mut [T] {
+        unsafe { slice::from_raw_parts_mut(self.as_mut_ptr(), self.len()) }
+    }
+
+    /// Copy data from `val` "asynchronously". The copy has completed when this returns.
+    ///
+    /// # Errors
+    ///
+    /// Never fails; returns a `CudaResult` to match `AsyncCopyDestination`.
+    ///
+    /// # Safety
+    ///
+    /// This function is safe, but is `unsafe` to match `AsyncCopyDestination`.
+    pub unsafe fn async_copy_from<I: AsRef<[T]> + AsMut<[T]> + ?Sized>(
+        &mut self,
+        val: &I,
+        _stream: &Stream,
+    ) -> CudaResult<()> {
+        self.copy_from(val)
+    }
+
+    /// Copy data to `val` "asynchronously". The copy has completed when this returns.
+    ///
+    /// # Errors
+    ///
+    /// Never fails; returns a `CudaResult` to match `AsyncCopyDestination`.
+    ///
+    /// # Safety
+    ///
+    /// This function is safe, but is `unsafe` to match `AsyncCopyDestination`.
+    pub unsafe fn async_copy_to<I: AsRef<[T]> + AsMut<[T]> + ?Sized>(
+        &self,
+        val: &mut I,
+        _stream: &Stream,
+    ) -> CudaResult<()> {
+        self.copy_to(val)
+    }
+
+    /// Destroy a `DeviceBuffer`, returning an error.
+    ///
+    /// Freeing emulated memory can't fail, so this always returns `Ok`. It exists to match
+    /// `memory::DeviceBuffer`.
+    pub fn drop(buf: DeviceBuffer<T>) -> DropResult<DeviceBuffer<T>> {
+        drop(buf);
+        Ok(())
+    }
+}
+impl<T> crate::private::Sealed for DeviceBuffer<T> {}
+impl<T: DeviceCopy, I: AsRef<[T]> + AsMut<[T]> + ?Sized> CopyDestination<I> for DeviceBuffer<T> {
+    fn copy_from(&mut self, val: &I) -> CudaResult<()> {
+        let val = val.as_ref();
+        assert!(
+            self.len() == val.len(),
+            "destination and source slices have different lengths"
+        );
+        unsafe { ptr::copy_nonoverlapping(val.as_ptr(), self.as_mut_ptr(), val.len()) };
+        Ok(())
+    }
+
+    fn copy_to(&self, val: &mut I) -> CudaResult<()> {
+        let val = val.as_mut();
+        assert!(
+            self.len() == val.len(),
+            "destination and source slices have different lengths"
+        );
+        unsafe { ptr::copy_nonoverlapping(self.as_ptr(), val.as_mut_ptr(), val.len()) };
+        Ok(())
+    }
+}
+impl<T: DeviceCopy> CopyDestination<DeviceBuffer<T>> for DeviceBuffer<T> {
+    fn copy_from(&mut self, val: &DeviceBuffer<T>) -> CudaResult<()> {
+        self.copy_from(val.as_kernel_slice())
+    }
+
+    fn copy_to(&self, val: &mut DeviceBuffer<T>) -> CudaResult<()> {
+        self.copy_to(val.as_kernel_mut_slice())
+    }
+}
+
+/// Host emulation of a [`Stream`](../stream/struct.Stream.html).
+///
+/// Work submitted to an emulated stream runs immediately, on the calling thread. See the
+/// [module-level documentation](index.html) for more details.
+#[derive(Debug)]
+pub struct Stream {
+    flags: StreamFlags,
+    priority: i32,
+}
+impl Stream {
+    /// Create a new emulated stream with the given flags and optional priority.
+    ///
+    /// # Errors
+    ///
+    /// Never fails; returns a `CudaResult` to match `stream::Stream`.
+    pub fn new(flags: StreamFlags, priority: Option<i32>) -> CudaResult<Self> {
+        Ok(Stream {
+            flags,
+            priority: priority.unwrap_or(0),
+        })
+    }
+
+    /// Return the flags which were used to create this stream.
+    ///
+    /// # Errors
+    ///
+    /// Never fails; returns a `CudaResult` to match `stream::Stream`.
+    pub fn get_flags(&self) -> CudaResult<StreamFlags> {
+        Ok(self.flags)
+    }
+
+    /// Return the priority of this stream.
+    ///
+    /// # Errors
+    ///
+    /// Never fails; returns a `CudaResult` to match `stream::Stream`.
+    pub fn get_priority(&self) -> CudaResult<i32> {
+        Ok(self.priority)
+    }
+
+    /// Call `callback` with the status of the stream. Since all work on an emulated stream has
+    /// already completed, the callback is called before this returns.
+    ///
+    /// # Errors
+    ///
+    /// Never fails; returns a `CudaResult` to match `stream::Stream`.
+    pub fn add_callback<T>(&self, callback: Box<T>) -> CudaResult<()>
+    where
+        T: FnOnce(CudaResult<()>) + Send,
+    {
+        callback(Ok(()));
+        Ok(())
+    }
+
+    /// Wait until all work in the stream has completed, which it always has.
+    ///
+    /// # Errors
+    ///
+    /// Never fails; returns a `CudaResult` to match `stream::Stream`.
+    pub fn synchronize(&self) -> CudaResult<()> {
+        Ok(())
+    }
+
+    /// Run `kernel` once for every thread of a grid of `grid_size` blocks of `block_size` threads.
+    ///
+    /// Threads run one at a time, block by block, in order of their x, then y, then z index. If
+    /// `kernel` panics, the panic propagates out of this call.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidValue` if any dimension of the grid or block is zero, or the block has more
+    /// than 1024 threads, as a launch on a GPU would.
+    pub fn launch<G, B, F>(&self, grid_size: G, block_size: B, mut kernel: F) -> CudaResult<()>
+    where
+        G: Into<GridSize>,
+        B: Into<BlockSize>,
+        F: FnMut(ThreadIndex),
+    {
+        let grid: GridSize = grid_size.into();
+        let block: BlockSize = block_size.into();
+        let grid_dim = Dim3 {
+            x: grid.x,
+            y: grid.y,
+            z: grid.z,
+        };
+        let block_dim = Dim3 {
+            x: block.x,
+            y: block.y,
+            z: block.z,
+        };
+        let threads = u64::from(block.x) * u64::from(block.y) * u64::from(block.z);
+        if threads == 0 || threads > u64::from(MAX_THREADS_PER_BLOCK) {
+            return Err(CudaError::InvalidValue);
+        }
+        if grid.x == 0 || grid.y == 0 || grid.z == 0 {
+            return Err(CudaError::InvalidValue);
+        }
+
+        for block_idx in dims(grid_dim) {
+            for thread_idx in dims(block_dim) {
+                kernel(ThreadIndex {
+                    thread_idx,
+                    block_idx,
+                    block_dim,
+                    grid_dim,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Destroy a `Stream`, returning an error.
+    ///
+    /// Destroying an emulated stream can't fail, so this always returns `Ok`. It exists to match
+    /// `stream::Stream`.
+    pub fn drop(stream: Stream) -> DropResult<Stream> {
+        let _ = stream;
+        Ok(())
+    }
+}
+
+// Every index within `dim`, in order of x, then y, then z.
+fn dims(dim: Dim3) -> impl Iterator<Item = Dim3> {
+    (0..dim.z)
+        .flat_map(move |z| (0..dim.y).flat_map(move |y| (0..dim.x).map(move |x| Dim3 { x, y, z })))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_emulated_launch() {
+        let stream = Stream::new(StreamFlags::NON_BLOCKING, Some(-1)).unwrap();
+        assert_eq!(-1, stream.get_priority().unwrap());
+        let mut buffer = DeviceBuffer::from_slice(&[0u32; 24]).unwrap();
+
+        let out = buffer.as_kernel_mut_slice();
+        stream
+            .launch((2, 2), (3, 2), |t| {
+                let width = t.grid_dim.x * t.block_dim.x;
+                out[(t.global_y() * width + t.global_x()) as usize] =
+                    t.block_idx.y * 10 + t.thread_idx.x;
+            })
+            .unwrap();
+        let mut host = [0u32; 24];
+        buffer.copy_to(&mut host[..]).unwrap();
+        assert_eq!([0, 1, 2, 0, 1, 2], host[..6]);
+        assert_eq!([10, 11, 12, 10, 11, 12], host[18..]);
+
+        let mut copy = DeviceBuffer::from_slice(&[0u32; 24]).unwrap();
+        unsafe { copy.async_copy_from(&host[..], &stream).unwrap() };
+        stream.synchronize().unwrap();
+        assert_eq!(&host[..], copy.as_kernel_slice());
+
+        assert_eq!(
+            Err(CudaError::InvalidValue),
+            stream.launch(1, 1025, |_| unreachable!())
+        );
+        assert_eq!(
+            Err(CudaError::InvalidValue),
+            stream.launch((1, 0), 1, |_| unreachable!())
+        );
+    }
+}
@@ -54,3 +54,38 @@
 //!     o: *const i64,
 //! }
 //! ```
+//!
+//! ```compile_fail
+//! #[macro_use]
+//! extern crate rustacuda;
+//! extern crate rustacuda_core;
+//!
+//! #[derive(Clone, Copy, KernelStruct)]
+//! struct ShouldFailNotReprC {
+//!     x: u32,
+//! }
+//! ```
+//!
+//! ```compile_fail
+//! #[macro_use]
+//! extern crate rustacuda;
+//! extern crate rustacuda_core;
+//!
+//! #[derive(Clone, Copy, KernelStruct)]
+//! #[repr(C)]
+//! struct ShouldFailNoCType {
+//!     x: char,
+//! }
+//! ```
+//!
+//! ```compile_fail
+//! #[macro_use]
+//! extern crate rustacuda;
+//! extern crate rustacuda_core;
+//!
+//! #[derive(Clone, Copy, KernelStruct)]
+//! #[repr(C)]
+//! struct ShouldFailNestedArray {
+//!     x: [[u8; 2]; 3],
+//! }
+//! ```
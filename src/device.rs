@@ -1,12 +1,23 @@
 //! Functions and types for enumerating CUDA devices and retrieving information about them.
 
-use crate::error::{CudaResult, ToResult};
+use crate::error::{CudaError, CudaResult, ToResult};
+use crate::function::{BlockSize, GridSize};
+#[cfg(feature = "cuda-11-2")]
+use crate::memory::MemPool;
 use cuda_driver_sys::*;
-use std::ffi::CStr;
+use std::ffi::CString;
+use std::fmt;
 use std::ops::Range;
+use std::str::FromStr;
+use std::sync::OnceLock;
 
 /// All supported device attributes for [Device::get_attribute](struct.Device.html#method.get_attribute)
+///
+/// Attributes added after CUDA 10.2 return `InvalidValue` on drivers which don't know them. Use
+/// [Device::get_attribute_raw](struct.Device.html#method.get_attribute_raw) for attributes which
+/// this enum doesn't have yet.
 #[repr(u32)]
+#[non_exhaustive]
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum DeviceAttribute {
     /// Maximum number of threads per block
@@ -220,9 +231,79 @@ pub enum DeviceAttribute {
     HandleTypeWin32HandleSupported = 104,
     /// Device supports exporting memory to a Win32 KMT handle
     HandleTypeWin32KmtHandleSupported = 105,
+    /// Maximum number of blocks per multiprocessor (CUDA 11.0)
+    MaxBlocksPerMultiprocessor = 106,
+    /// Device supports compression of memory (CUDA 11.0)
+    GenericCompressionSupported = 107,
+    /// Maximum L2 persisting lines capacity in bytes (CUDA 11.0)
+    MaxPersistingL2CacheSize = 108,
+    /// Maximum value of `AccessPolicyWindow::num_bytes` (CUDA 11.0)
+    MaxAccessPolicyWindowSize = 109,
+    /// Device supports GPUDirect RDMA with memory allocated by the virtual memory management APIs
+    /// (CUDA 11.0)
+    GpuDirectRdmaWithCudaVmmSupported = 110,
+    /// Shared memory reserved by the driver per block in bytes (CUDA 11.0)
+    ReservedSharedMemoryPerBlock = 111,
+    /// Device supports sparse CUDA arrays and sparse mipmapped CUDA arrays (CUDA 11.1)
+    SparseCudaArraySupported = 112,
+    /// Device supports registering host memory as read-only to the GPU (CUDA 11.1)
+    ReadOnlyHostRegisterSupported = 113,
+    /// External timeline semaphore interop is supported (CUDA 11.2)
+    TimelineSemaphoreInteropSupported = 114,
+    /// Device supports stream-ordered allocation with memory pools (CUDA 11.2)
+    MemoryPoolsSupported = 115,
+    /// Device supports GPUDirect RDMA APIs (CUDA 11.3)
+    GpuDirectRdmaSupported = 116,
+    /// Bitmask of the flush options supported for GPUDirect RDMA writes (CUDA 11.3)
+    GpuDirectRdmaFlushWritesOptions = 117,
+    /// The ordering of GPUDirect RDMA writes the device guarantees (CUDA 11.3)
+    GpuDirectRdmaWritesOrdering = 118,
+    /// Bitmask of the handle types supported with memory pool based IPC (CUDA 11.3)
+    MempoolSupportedHandleTypes = 119,
+    /// Device supports launching kernels in thread block clusters (CUDA 11.8)
+    ClusterLaunch = 120,
+    /// Device supports deferred mapping of CUDA arrays (CUDA 11.8)
+    DeferredMappingCudaArraySupported = 121,
+}
+
+/// The compute mode of a device, which controls how many contexts and processes can use it at once.
+/// See [Device::compute_mode](struct.Device.html#method.compute_mode).
+///
+/// The compute mode is set by an administrator with `nvidia-smi --compute-mode`.
+#[repr(u32)]
+#[non_exhaustive]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ComputeMode {
+    /// Any number of contexts, from any number of processes, can use the device
+    Default = 0,
+    /// No contexts can be created on the device
+    Prohibited = 2,
+    /// Only one process at a time can have contexts on the device, though it can have several,
+    /// used from any number of threads
+    ExclusiveProcess = 3,
+}
+
+/// Attributes of the link between two devices, for
+/// [Device::p2p_attribute](struct.Device.html#method.p2p_attribute)
+#[repr(u32)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum P2PAttribute {
+    /// A relative value indicating the performance of the link between the devices. Links with a
+    /// lower rank perform better, eg. NVLink ranks below PCIe.
+    PerformanceRank = 1,
+    /// Peer access is supported between the devices
+    AccessSupported = 2,
+    /// Native atomic operations over the link are supported
+    NativeAtomicSupported = 3,
+    /// Accessing CUDA arrays over the link is supported
+    CudaArrayAccessSupported = 4,
+}
 
-    #[doc(hidden)]
-    __NonExhaustive = 106,
+extern "C" {
+    // `cuDeviceGetAttribute`, declared with a plain integer attribute since the bindings' enum only
+    // has the attributes of CUDA 10.2, and other values would be invalid for it.
+    #[link_name = "cuDeviceGetAttribute"]
+    fn cuDeviceGetAttributeRaw(pi: *mut i32, attrib: i32, dev: CUdevice) -> CUresult;
 }
 
 /// Opaque handle to a CUDA device.
@@ -251,7 +332,7 @@ impl Device {
     pub fn num_devices() -> CudaResult<u32> {
         unsafe {
             let mut num_devices = 0i32;
-            cuDeviceGetCount(&mut num_devices as *mut i32).to_result()?;
+            driver_call!(cuDeviceGetCount(&mut num_devices as *mut i32)).to_result()?;
             Ok(num_devices as u32)
         }
     }
@@ -275,7 +356,11 @@ impl Device {
     pub fn get_device(ordinal: u32) -> CudaResult<Device> {
         unsafe {
             let mut device = Device { device: 0 };
-            cuDeviceGet(&mut device.device as *mut CUdevice, ordinal as i32).to_result()?;
+            driver_call!(cuDeviceGet(
+                &mut device.device as *mut CUdevice,
+                ordinal as i32
+            ))
+            .to_result()?;
             Ok(device)
         }
     }
@@ -319,7 +404,8 @@ impl Device {
     pub fn total_memory(self) -> CudaResult<usize> {
         unsafe {
             let mut memory = 0;
-            cuDeviceTotalMem_v2(&mut memory as *mut usize, self.device).to_result()?;
+            driver_call!(cuDeviceTotalMem_v2(&mut memory as *mut usize, self.device))
+                .to_result()?;
             Ok(memory)
         }
     }
@@ -341,24 +427,259 @@ impl Device {
     pub fn name(self) -> CudaResult<String> {
         unsafe {
             let mut name = [0u8; 128]; // Hopefully this is big enough...
-            cuDeviceGetName(
+            driver_call!(cuDeviceGetName(
                 &mut name[0] as *mut u8 as *mut ::std::os::raw::c_char,
                 128,
                 self.device,
-            )
+            ))
             .to_result()?;
-            let nul_index = name
+            // The driver truncates names that don't fit, so fall back to the whole buffer if there
+            // is no nul terminator.
+            let len = name
                 .iter()
-                .cloned()
-                .position(|byte| byte == 0)
-                .expect("Expected device name to fit in 128 bytes and be nul-terminated.");
-            let cstr = CStr::from_bytes_with_nul_unchecked(&name[0..=nul_index]);
-            Ok(cstr.to_string_lossy().into_owned())
+                .position(|&byte| byte == 0)
+                .unwrap_or(name.len());
+            Ok(String::from_utf8_lossy(&name[..len]).into_owned())
+        }
+    }
+
+    /// Returns the compute capability of this device.
+    ///
+    /// # Example
+    /// ```
+    /// # use rustacuda::*;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # init(CudaFlags::empty())?;
+    /// use rustacuda::device::{ComputeCapability, Device};
+    /// let device = Device::get_device(0)?;
+    /// let capability = device.compute_capability()?;
+    /// if capability.supports(ComputeCapability::new(7, 0)) {
+    ///     // Load the kernels which use tensor cores.
+    /// }
+    /// println!("Compute capability: {}", capability);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn compute_capability(self) -> CudaResult<ComputeCapability> {
+        Ok(ComputeCapability::new(
+            self.get_attribute(DeviceAttribute::ComputeCapabilityMajor)? as u32,
+            self.get_attribute(DeviceAttribute::ComputeCapabilityMinor)? as u32,
+        ))
+    }
+
+    /// Returns the PCI bus ID of this device, in the `domain:bus:device.function` format used by
+    /// `nvidia-smi` and `lspci`, such as `0000:3b:00.0`.
+    ///
+    /// # Example
+    /// ```
+    /// # use rustacuda::*;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # init(CudaFlags::empty())?;
+    /// use rustacuda::device::Device;
+    /// let device = Device::get_device(0)?;
+    /// println!("PCI bus ID: {}", device.pci_bus_id()?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn pci_bus_id(self) -> CudaResult<String> {
+        unsafe {
+            // The driver documents 13 bytes as enough for the ID and nul terminator.
+            let mut id = [0u8; 16];
+            driver_call!(cuDeviceGetPCIBusId(
+                id.as_mut_ptr() as *mut ::std::os::raw::c_char,
+                id.len() as i32,
+                self.device,
+            ))
+            .to_result()?;
+            let len = id.iter().position(|&byte| byte == 0).unwrap_or(id.len());
+            Ok(String::from_utf8_lossy(&id[..len]).into_owned())
+        }
+    }
+
+    /// Returns the device with the given PCI bus ID.
+    ///
+    /// The ID may be in any of the formats `domain:bus:device.function`, `domain:bus:device` or
+    /// `bus:device.function`, with hexadecimal numbers, as listed by `nvidia-smi` or `lspci`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidValue` if the ID contains a nul byte, and `InvalidDevice` if no visible
+    /// device has that ID.
+    ///
+    /// # Example
+    /// ```
+    /// # use rustacuda::*;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # init(CudaFlags::empty())?;
+    /// use rustacuda::device::Device;
+    /// let device = Device::get_device(0)?;
+    /// assert_eq!(device, Device::from_pci_bus_id(&device.pci_bus_id()?)?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_pci_bus_id(id: &str) -> CudaResult<Device> {
+        let id = CString::new(id).map_err(|_| CudaError::InvalidValue)?;
+        unsafe {
+            let mut device = Device { device: 0 };
+            driver_call!(cuDeviceGetByPCIBusId(&mut device.device, id.as_ptr())).to_result()?;
+            Ok(device)
         }
     }
 
+    /// Returns the ordinal of this device, as passed to [`get_device`](#method.get_device).
+    ///
+    /// Ordinals count only the devices visible through `CUDA_VISIBLE_DEVICES`, so this is not
+    /// necessarily the index `nvidia-smi` shows for the GPU. Use [`identity`](#method.identity) to
+    /// report which physical GPU a device is.
+    pub fn ordinal(self) -> u32 {
+        self.device as u32
+    }
+
+    /// Returns the ordinal of this device together with the identifiers of the physical GPU
+    /// behind it, which don't depend on `CUDA_VISIBLE_DEVICES`.
+    ///
+    /// # Example
+    /// ```
+    /// # use rustacuda::*;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # init(CudaFlags::empty())?;
+    /// use rustacuda::device::Device;
+    /// for device in Device::devices()? {
+    ///     // eg. "device 0 (GPU-5f9a3c2e-7d41-8b06-e2a1-0c94f3b87d15, PCI 0000:65:00.0)"
+    ///     println!("{}", device?.identity()?);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn identity(self) -> CudaResult<DeviceIdentity> {
+        let mig_uuid = match self.mig_uuid() {
+            Ok(uuid) => uuid,
+            Err(CudaError::NotSupported) => None,
+            Err(e) => return Err(e),
+        };
+        Ok(DeviceIdentity {
+            ordinal: self.ordinal(),
+            uuid: self.uuid()?,
+            mig_uuid,
+            pci_bus_id: self.pci_bus_id()?,
+        })
+    }
+
+    /// Returns the device for a physical GPU, identified the way a user or scheduler would name
+    /// it: by UUID (`GPU-...` or `MIG-...`), by PCI bus ID, or by index.
+    ///
+    /// Ordinals are remapped by `CUDA_VISIBLE_DEVICES`: with `CUDA_VISIBLE_DEVICES=2,3`, GPU 2 is
+    /// `Device::get_device(0)`, and passing the physical index to `get_device` picks the wrong GPU
+    /// or fails. This translates the identifier through the mask instead. An index is interpreted
+    /// as CUDA interprets the indices in `CUDA_VISIBLE_DEVICES`, which follow `CUDA_DEVICE_ORDER`;
+    /// they only match the indices shown by `nvidia-smi` if that is set to `PCI_BUS_ID`. UUIDs and
+    /// PCI bus IDs are unambiguous, so prefer them where possible.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidDevice` if the GPU is hidden by `CUDA_VISIBLE_DEVICES`, or if an index is
+    /// given but the mask names devices by UUID, so that indices can't be translated.
+    ///
+    /// # Example
+    /// ```
+    /// # use rustacuda::*;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # init(CudaFlags::empty())?;
+    /// # let requested = device::Device::get_device(0)?.uuid()?.to_string();
+    /// use rustacuda::device::Device;
+    ///
+    /// // `requested` is a UUID, PCI bus ID or index from the deployment's configuration.
+    /// let device = Device::from_physical_id(&requested)?;
+    /// println!("Using {}", device.identity()?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_physical_id(id: &str) -> CudaResult<Device> {
+        let id = id.trim();
+        if let Ok(index) = id.parse::<u32>() {
+            return match visible_devices() {
+                None => Device::get_device(index),
+                Some(entries) => {
+                    let ordinal = entries
+                        .iter()
+                        .position(|entry| entry.parse::<u32>() == Ok(index))
+                        .ok_or(CudaError::InvalidDevice)?;
+                    Device::get_device(ordinal as u32)
+                }
+            };
+        }
+        if let Ok(uuid) = id.parse::<DeviceUuid>() {
+            return Device::from_uuid(&uuid);
+        }
+        Device::from_pci_bus_id(id)
+    }
+
+    /// Returns the default memory pool of this device, from which stream-ordered allocations are
+    /// made unless another pool is set with [`set_mem_pool`](#method.set_mem_pool).
+    ///
+    /// Only available with the `cuda-11-2` feature.
+    ///
+    /// # Errors
+    ///
+    /// Returns `NotSupported` if the device doesn't support memory pools. See
+    /// [`DeviceAttribute::MemoryPoolsSupported`](enum.DeviceAttribute.html#variant.MemoryPoolsSupported).
+    ///
+    /// # Example
+    /// ```
+    /// # use rustacuda::*;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # init(CudaFlags::empty())?;
+    /// use rustacuda::device::Device;
+    /// let device = Device::get_device(0)?;
+    /// if let Ok(pool) = device.default_mem_pool() {
+    ///     println!("Pool keeps {} bytes", pool.release_threshold()?);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "cuda-11-2")]
+    pub fn default_mem_pool(self) -> CudaResult<MemPool> {
+        crate::memory::device_default_mem_pool(self)
+    }
+
+    /// Returns the memory pool which stream-ordered allocations on this device are currently made
+    /// from.
+    ///
+    /// Only available with the `cuda-11-2` feature.
+    ///
+    /// # Errors
+    ///
+    /// Returns `NotSupported` if the device doesn't support memory pools.
+    #[cfg(feature = "cuda-11-2")]
+    pub fn mem_pool(self) -> CudaResult<MemPool> {
+        crate::memory::device_mem_pool(self)
+    }
+
+    /// Make stream-ordered allocations on this device come from `pool`, which must be a pool of
+    /// this device.
+    ///
+    /// Only available with the `cuda-11-2` feature.
+    ///
+    /// # Errors
+    ///
+    /// Returns `NotSupported` if the device doesn't support memory pools.
+    #[cfg(feature = "cuda-11-2")]
+    pub fn set_mem_pool(self, pool: &MemPool) -> CudaResult<()> {
+        crate::memory::device_set_mem_pool(self, pool)
+    }
+
     /// Returns the UUID of this device.
     ///
+    /// For a MIG instance (see [`is_mig_instance`](#method.is_mig_instance)), this is the UUID of
+    /// the physical GPU which the instance is part of; use [`mig_uuid`](#method.mig_uuid) to tell
+    /// instances apart.
+    ///
     /// # Example
     /// ```
     /// # use rustacuda::*;
@@ -367,16 +688,129 @@ impl Device {
     /// # init(CudaFlags::empty())?;
     /// use rustacuda::device::Device;
     /// let device = Device::get_device(0)?;
-    /// println!("Device UUID: {:?}", device.uuid()?);
+    /// println!("Device UUID: {}", device.uuid()?);
     /// # Ok(())
     /// # }
     /// ```
-    pub fn uuid(self) -> CudaResult<[u8; 16]> {
+    pub fn uuid(self) -> CudaResult<DeviceUuid> {
         unsafe {
             let mut cu_uuid = CUuuid { bytes: [0i8; 16] };
-            cuDeviceGetUuid(&mut cu_uuid, self.device).to_result()?;
+            driver_call!(cuDeviceGetUuid(&mut cu_uuid, self.device)).to_result()?;
             let uuid: [u8; 16] = ::std::mem::transmute(cu_uuid.bytes);
-            Ok(uuid)
+            Ok(DeviceUuid::from_bytes(uuid))
+        }
+    }
+
+    /// Returns the device with the given UUID.
+    ///
+    /// Unlike ordinals, UUIDs don't depend on `CUDA_VISIBLE_DEVICES` or the order in which the
+    /// driver enumerates devices, so they can be used to identify a device across processes.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidDevice` if no visible device has that UUID.
+    ///
+    /// # Example
+    /// ```
+    /// # use rustacuda::*;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # init(CudaFlags::empty())?;
+    /// # let assigned = device::Device::get_device(0)?.uuid()?.to_string();
+    /// use rustacuda::device::{Device, DeviceUuid};
+    ///
+    /// // `assigned` is a string such as "GPU-5f9a3c2e-..." handed out by a scheduler.
+    /// let uuid: DeviceUuid = assigned.parse()?;
+    /// let device = Device::from_uuid(&uuid)?;
+    /// # assert_eq!(device, Device::get_device(0)?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_uuid(uuid: &DeviceUuid) -> CudaResult<Device> {
+        for device in Device::devices()? {
+            let device = device?;
+            let matches = if uuid.is_mig() {
+                device.mig_uuid()? == Some(*uuid)
+            } else {
+                device.uuid()? == *uuid
+            };
+            if matches {
+                return Ok(device);
+            }
+        }
+        Err(CudaError::InvalidDevice)
+    }
+
+    /// Returns true if this device is an instance of a GPU partitioned with Multi-Instance GPU
+    /// (MIG), such as a slice of an A100 or H100.
+    ///
+    /// MIG instances behave differently from whole GPUs in ways which are easy to misread:
+    ///
+    /// - [`total_memory`](#method.total_memory), `DeviceAttribute::MultiprocessorCount` and the
+    ///   other limits describe the instance, not the physical GPU, which `nvidia-smi` reports.
+    /// - A process can only use one MIG instance. Even if several are visible to
+    ///   `CUDA_VISIBLE_DEVICES`, CUDA only enumerates the first, so [`num_devices`](#method.num_devices)
+    ///   is 1.
+    /// - Peer access between instances is not supported, even on the same GPU.
+    /// - [`uuid`](#method.uuid) returns the UUID of the physical GPU. The instance is identified by
+    ///   its [`mig_uuid`](#method.mig_uuid), which is what `CUDA_VISIBLE_DEVICES` expects.
+    ///
+    /// Drivers older than CUDA 11.4 can't report MIG UUIDs, so with those this falls back to
+    /// checking for the `MIG` profile in the device name, eg. `A100-SXM4-40GB MIG 1g.5gb`.
+    ///
+    /// # Example
+    /// ```
+    /// # use rustacuda::*;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # init(CudaFlags::empty())?;
+    /// use rustacuda::device::Device;
+    /// let device = Device::get_device(0)?;
+    /// if device.is_mig_instance()? {
+    ///     println!("Running on a MIG instance with {} bytes", device.total_memory()?);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn is_mig_instance(self) -> CudaResult<bool> {
+        match self.mig_uuid() {
+            Ok(uuid) => Ok(uuid.is_some()),
+            Err(CudaError::NotSupported) => Ok(self.name()?.contains(" MIG ")),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Returns the MIG UUID of this device, such as `MIG-5f9a3c2e-7d41-8b06-e2a1-0c94f3b87d15`,
+    /// or `None` if it is not a MIG instance.
+    ///
+    /// # Errors
+    ///
+    /// Returns `NotSupported` if the driver is older than CUDA 11.4, which can't report MIG
+    /// UUIDs.
+    ///
+    /// # Example
+    /// ```
+    /// # use rustacuda::*;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # init(CudaFlags::empty())?;
+    /// use rustacuda::device::Device;
+    /// let device = Device::get_device(0)?;
+    /// if let Ok(Some(uuid)) = device.mig_uuid() {
+    ///     println!("CUDA_VISIBLE_DEVICES={}", uuid);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn mig_uuid(self) -> CudaResult<Option<DeviceUuid>> {
+        let get_uuid_v2 = get_uuid_v2_fn().ok_or(CudaError::NotSupported)?;
+        let mut cu_uuid = CUuuid { bytes: [0i8; 16] };
+        unsafe { get_uuid_v2(&mut cu_uuid, self.device).to_result()? };
+        let bytes = cu_uuid.bytes.map(|byte| byte as u8);
+        if bytes == self.uuid()?.bytes {
+            Ok(None)
+        } else {
+            Ok(Some(DeviceUuid { bytes, mig: true }))
         }
     }
 
@@ -396,19 +830,282 @@ impl Device {
     /// # }
     /// ```
     pub fn get_attribute(self, attr: DeviceAttribute) -> CudaResult<i32> {
+        self.get_attribute_raw(attr as i32)
+    }
+
+    /// Returns the value of the device attribute with the given `CUdevice_attribute` number.
+    ///
+    /// This reaches attributes of newer CUDA versions before `DeviceAttribute` has a variant for
+    /// them.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidValue` if the driver doesn't know the attribute.
+    ///
+    /// # Example
+    /// ```
+    /// # use rustacuda::*;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # init(CudaFlags::empty())?;
+    /// use rustacuda::device::Device;
+    /// let device = Device::get_device(0)?;
+    /// // CU_DEVICE_ATTRIBUTE_MEMORY_POOLS_SUPPORTED
+    /// let pools = device.get_attribute_raw(115).unwrap_or(0) != 0;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_attribute_raw(self, attr: i32) -> CudaResult<i32> {
+        unsafe {
+            let mut val = 0i32;
+            driver_call!(cuDeviceGetAttributeRaw(
+                &mut val as *mut i32,
+                attr,
+                self.device,
+            ))
+            .to_result()?;
+            Ok(val)
+        }
+    }
+
+    /// Returns true if this device supports allocating unified (managed) memory.
+    ///
+    /// Some platforms, such as certain Windows and WSL configurations, do not support managed
+    /// memory at all. On those platforms the unified allocation functions return
+    /// `CudaError::UnifiedMemoryNotSupported`.
+    ///
+    /// # Example
+    /// ```
+    /// # use rustacuda::*;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # init(CudaFlags::empty())?;
+    /// use rustacuda::device::Device;
+    /// let device = Device::get_device(0)?;
+    /// println!("Supports managed memory: {}", device.supports_managed_memory()?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn supports_managed_memory(self) -> CudaResult<bool> {
+        Ok(self.get_attribute(DeviceAttribute::ManagedMemory)? != 0)
+    }
+
+    /// Returns true if this device can access unified memory concurrently with the host.
+    ///
+    /// Without concurrent managed access, the host must not touch unified memory while any kernel
+    /// is running on the device, even if the kernel does not use that memory.
+    ///
+    /// # Example
+    /// ```
+    /// # use rustacuda::*;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # init(CudaFlags::empty())?;
+    /// use rustacuda::device::Device;
+    /// let device = Device::get_device(0)?;
+    /// println!("Concurrent managed access: {}", device.concurrent_managed_access()?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn concurrent_managed_access(self) -> CudaResult<bool> {
+        Ok(self.get_attribute(DeviceAttribute::ConcurrentManagedAccess)? != 0)
+    }
+
+    /// Returns the compute mode of this device.
+    ///
+    /// Shared servers often put their GPUs in exclusive-process mode, where creating a context
+    /// fails while another process is using the device. RustaCUDA checks the compute mode when
+    /// that happens and returns `ExclusiveDeviceInUse` or `DeviceProhibited` instead of the
+    /// driver's less descriptive error.
+    ///
+    /// # Example
+    /// ```
+    /// # use rustacuda::*;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # init(CudaFlags::empty())?;
+    /// use rustacuda::device::{ComputeMode, Device};
+    /// let device = Device::get_device(0)?;
+    /// if device.compute_mode()? == ComputeMode::ExclusiveProcess {
+    ///     println!("{} can only be used by one process at a time", device.name()?);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn compute_mode(self) -> CudaResult<ComputeMode> {
+        match self.get_attribute(DeviceAttribute::ComputeMode)? {
+            0 => Ok(ComputeMode::Default),
+            2 => Ok(ComputeMode::Prohibited),
+            3 => Ok(ComputeMode::ExclusiveProcess),
+            _ => Err(CudaError::UnknownError),
+        }
+    }
+
+    /// Replaces `error`, from failing to create a context on this device, with a more descriptive
+    /// error if the compute mode of the device explains it. Drivers report this as
+    /// `CUDA_ERROR_INVALID_DEVICE` or, since CUDA 11, `CUDA_ERROR_DEVICE_UNAVAILABLE`, which the
+    /// bindings don't know and so becomes `UnknownError`.
+    pub(crate) fn explain_context_error(self, error: CudaError) -> CudaError {
+        if error != CudaError::InvalidDevice && error != CudaError::UnknownError {
+            return error;
+        }
+        match self.compute_mode() {
+            Ok(ComputeMode::ExclusiveProcess) => CudaError::ExclusiveDeviceInUse,
+            Ok(ComputeMode::Prohibited) => CudaError::DeviceProhibited,
+            _ => error,
+        }
+    }
+
+    /// Returns true if contexts on this device can access memory allocated in contexts on `peer`
+    /// once peer access is enabled with
+    /// [`Context::enable_peer_access`](../context/struct.Context.html#method.enable_peer_access).
+    ///
+    /// A device can't be its own peer, so this returns false if `peer` is this device.
+    ///
+    /// # Example
+    /// ```
+    /// # use rustacuda::*;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # init(CudaFlags::empty())?;
+    /// use rustacuda::device::Device;
+    /// let device = Device::get_device(0)?;
+    /// for peer in Device::devices()? {
+    ///     let peer = peer?;
+    ///     println!("Can access {}: {}", peer.name()?, device.can_access_peer(peer)?);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn can_access_peer(self, peer: Device) -> CudaResult<bool> {
+        unsafe {
+            let mut can_access = 0i32;
+            driver_call!(cuDeviceCanAccessPeer(
+                &mut can_access as *mut i32,
+                self.device,
+                peer.device
+            ))
+            .to_result()?;
+            Ok(can_access != 0)
+        }
+    }
+
+    /// Returns information about the link from this device to `peer`, such as its relative
+    /// performance or whether atomics are supported over it.
+    ///
+    /// This can be used to decide between copying directly between two devices and staging the
+    /// copy through host memory, or to pick the best-connected pair of devices.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidDevice` if `peer` is this device.
+    ///
+    /// # Example
+    /// ```
+    /// # use rustacuda::*;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # init(CudaFlags::empty())?;
+    /// use rustacuda::device::{Device, P2PAttribute};
+    /// let device = Device::get_device(0)?;
+    /// for peer in Device::devices()? {
+    ///     let peer = peer?;
+    ///     if peer != device && device.p2p_attribute(peer, P2PAttribute::AccessSupported)? != 0 {
+    ///         let rank = device.p2p_attribute(peer, P2PAttribute::PerformanceRank)?;
+    ///         println!("{}: performance rank {}", peer.name()?, rank);
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn p2p_attribute(self, peer: Device, attr: P2PAttribute) -> CudaResult<i32> {
         unsafe {
             let mut val = 0i32;
-            cuDeviceGetAttribute(
+            driver_call!(cuDeviceGetP2PAttribute(
                 &mut val as *mut i32,
-                // This should be safe, as the repr and values of DeviceAttribute should match.
-                ::std::mem::transmute(attr),
+                // This should be safe, as the repr and values of P2PAttribute should match.
+                ::std::mem::transmute::<P2PAttribute, CUdevice_P2PAttribute>(attr),
                 self.device,
-            )
+                peer.device
+            ))
             .to_result()?;
             Ok(val)
         }
     }
 
+    /// Returns a snapshot of the commonly-used properties of this device, such as its name, memory
+    /// size, compute capability and launch limits.
+    ///
+    /// This queries every property at once, which is convenient for logging or choosing launch
+    /// parameters. Use [`get_attribute`](#method.get_attribute) for attributes which aren't part of
+    /// [`DeviceProperties`](struct.DeviceProperties.html).
+    ///
+    /// # Example
+    /// ```
+    /// # use rustacuda::*;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # init(CudaFlags::empty())?;
+    /// use rustacuda::device::Device;
+    /// let device = Device::get_device(0)?;
+    /// let properties = device.properties()?;
+    /// println!(
+    ///     "{} (compute {}): {} multiprocessors, {} bytes of memory",
+    ///     properties.name,
+    ///     properties.compute_capability,
+    ///     properties.multiprocessor_count,
+    ///     properties.total_memory
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn properties(self) -> CudaResult<DeviceProperties> {
+        let attribute = |attr| self.get_attribute(attr).map(|val| val as u32);
+        Ok(DeviceProperties {
+            name: self.name()?,
+            pci_bus_id: self.pci_bus_id()?,
+            total_memory: self.total_memory()?,
+            compute_capability: self.compute_capability()?,
+            multiprocessor_count: attribute(DeviceAttribute::MultiprocessorCount)?,
+            clock_rate_khz: attribute(DeviceAttribute::ClockRate)?,
+            memory_clock_rate_khz: attribute(DeviceAttribute::MemoryClockRate)?,
+            memory_bus_width: attribute(DeviceAttribute::GlobalMemoryBusWidth)?,
+            l2_cache_size: attribute(DeviceAttribute::L2CacheSize)? as usize,
+            total_constant_memory: attribute(DeviceAttribute::TotalConstantMemory)? as usize,
+            warp_size: attribute(DeviceAttribute::WarpSize)?,
+            max_threads_per_block: attribute(DeviceAttribute::MaxThreadsPerBlock)?,
+            max_threads_per_multiprocessor: attribute(
+                DeviceAttribute::MaxThreadsPerMultiprocessor,
+            )?,
+            max_block_dim: BlockSize::xyz(
+                attribute(DeviceAttribute::MaxBlockDimX)?,
+                attribute(DeviceAttribute::MaxBlockDimY)?,
+                attribute(DeviceAttribute::MaxBlockDimZ)?,
+            ),
+            max_grid_dim: GridSize::xyz(
+                attribute(DeviceAttribute::MaxGridDimX)?,
+                attribute(DeviceAttribute::MaxGridDimY)?,
+                attribute(DeviceAttribute::MaxGridDimZ)?,
+            ),
+            shared_memory_per_block: attribute(DeviceAttribute::MaxSharedMemoryPerBlock)? as usize,
+            shared_memory_per_block_optin: attribute(DeviceAttribute::MaxSharedMemoryPerBlockOptin)?
+                as usize,
+            shared_memory_per_multiprocessor: attribute(
+                DeviceAttribute::MaxSharedMemoryPerMultiprocessor,
+            )? as usize,
+            registers_per_block: attribute(DeviceAttribute::MaxRegistersPerBlock)?,
+            registers_per_multiprocessor: attribute(
+                DeviceAttribute::MaxRegistersPerMultiprocessor,
+            )?,
+            async_engine_count: attribute(DeviceAttribute::AsyncEngineCount)?,
+            integrated: attribute(DeviceAttribute::Integrated)? != 0,
+            ecc_enabled: attribute(DeviceAttribute::EccEnabled)? != 0,
+            unified_addressing: attribute(DeviceAttribute::UnifiedAddressing)? != 0,
+            concurrent_kernels: attribute(DeviceAttribute::ConcurrentKernels)? != 0,
+            mig_instance: self.is_mig_instance()?,
+        })
+    }
+
     pub(crate) fn into_inner(self) -> CUdevice {
         self.device
     }
@@ -428,6 +1125,279 @@ impl Iterator for Devices {
     }
 }
 
+/// The compute capability of a device, which identifies the features its architecture supports.
+///
+/// Capabilities are ordered by major and then minor version, so that features introduced in some
+/// version can be checked for with a comparison or [`supports`](#method.supports).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ComputeCapability {
+    /// The major version, eg. the 8 in 8.6.
+    pub major: u32,
+    /// The minor version, eg. the 6 in 8.6.
+    pub minor: u32,
+}
+impl ComputeCapability {
+    /// Create a compute capability from its major and minor versions.
+    pub fn new(major: u32, minor: u32) -> Self {
+        ComputeCapability { major, minor }
+    }
+
+    /// Returns true if this capability is at least `required`.
+    ///
+    /// Note that a few features were removed in later architectures, so this is only a test of the
+    /// version number.
+    pub fn supports(self, required: ComputeCapability) -> bool {
+        self >= required
+    }
+}
+impl fmt::Display for ComputeCapability {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+/// The UUID of a CUDA device, as returned by [`Device::uuid`](struct.Device.html#method.uuid), or
+/// of a MIG instance, as returned by [`Device::mig_uuid`](struct.Device.html#method.mig_uuid).
+///
+/// This is displayed in the format used by `nvidia-smi -L` and `CUDA_VISIBLE_DEVICES`, such as
+/// `GPU-5f9a3c2e-7d41-8b06-e2a1-0c94f3b87d15` or `MIG-5f9a3c2e-7d41-8b06-e2a1-0c94f3b87d15`, and can
+/// be parsed from that format. Without a prefix, the UUID is taken to be a GPU UUID.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct DeviceUuid {
+    bytes: [u8; 16],
+    mig: bool,
+}
+impl DeviceUuid {
+    /// Create a GPU UUID from its bytes.
+    pub fn from_bytes(bytes: [u8; 16]) -> Self {
+        DeviceUuid { bytes, mig: false }
+    }
+
+    /// Returns the bytes of the UUID.
+    pub fn as_bytes(&self) -> &[u8; 16] {
+        &self.bytes
+    }
+
+    /// Returns true if this is the UUID of a MIG instance rather than of a GPU.
+    pub fn is_mig(&self) -> bool {
+        self.mig
+    }
+}
+impl fmt::Display for DeviceUuid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(if self.mig { "MIG" } else { "GPU" })?;
+        for (i, byte) in self.bytes.iter().enumerate() {
+            if [0, 4, 6, 8, 10].contains(&i) {
+                f.write_str("-")?;
+            }
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+impl fmt::Debug for DeviceUuid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "DeviceUuid({})", self)
+    }
+}
+impl FromStr for DeviceUuid {
+    type Err = CudaError;
+
+    /// Parses a UUID such as `GPU-5f9a3c2e-7d41-8b06-e2a1-0c94f3b87d15` or
+    /// `MIG-5f9a3c2e-7d41-8b06-e2a1-0c94f3b87d15`. The `GPU-` prefix is optional and hex digits
+    /// may be upper or lower case. Returns `InvalidValue` if the string is not a UUID.
+    fn from_str(s: &str) -> CudaResult<Self> {
+        let s = s.trim();
+        let (s, mig) = match s.strip_prefix("MIG-") {
+            Some(s) => (s, true),
+            None => (s.strip_prefix("GPU-").unwrap_or(s), false),
+        };
+        let groups: Vec<&str> = s.split('-').collect();
+        let lengths: Vec<usize> = groups.iter().map(|group| group.len()).collect();
+        if lengths != [8, 4, 4, 4, 12] {
+            return Err(CudaError::InvalidValue);
+        }
+        let digits: String = groups.concat();
+        let mut bytes = [0u8; 16];
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            let pair = digits
+                .get(2 * i..2 * i + 2)
+                .ok_or(CudaError::InvalidValue)?;
+            *byte = u8::from_str_radix(pair, 16).map_err(|_| CudaError::InvalidValue)?;
+        }
+        Ok(DeviceUuid { bytes, mig })
+    }
+}
+
+/// A snapshot of the commonly-used properties of a device, returned by
+/// [Device::properties](struct.Device.html#method.properties).
+///
+/// Clock rates are in kilohertz and memory sizes in bytes. More fields may be added in future
+/// versions, so this can't be constructed outside of RustaCUDA.
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceProperties {
+    /// The name of the device, eg. "GeForce RTX 2080"
+    pub name: String,
+    /// The PCI bus ID of the device, in the form `domain:bus:device.function`
+    pub pci_bus_id: String,
+    /// Total global memory
+    pub total_memory: usize,
+    /// Compute capability of the device
+    pub compute_capability: ComputeCapability,
+    /// Number of multiprocessors on the device
+    pub multiprocessor_count: u32,
+    /// Typical clock frequency
+    pub clock_rate_khz: u32,
+    /// Peak memory clock frequency
+    pub memory_clock_rate_khz: u32,
+    /// Global memory bus width in bits
+    pub memory_bus_width: u32,
+    /// Size of the L2 cache
+    pub l2_cache_size: usize,
+    /// Memory available for constants
+    pub total_constant_memory: usize,
+    /// Threads per warp
+    pub warp_size: u32,
+    /// Maximum number of threads per block
+    pub max_threads_per_block: u32,
+    /// Maximum number of resident threads per multiprocessor
+    pub max_threads_per_multiprocessor: u32,
+    /// Maximum size of each dimension of a block
+    pub max_block_dim: BlockSize,
+    /// Maximum size of each dimension of a grid
+    pub max_grid_dim: GridSize,
+    /// Maximum shared memory available to a block by default
+    pub shared_memory_per_block: usize,
+    /// Maximum shared memory a block can opt in to, by raising its kernel's
+    /// `CU_FUNC_ATTRIBUTE_MAX_DYNAMIC_SHARED_SIZE_BYTES`
+    pub shared_memory_per_block_optin: usize,
+    /// Shared memory available per multiprocessor
+    pub shared_memory_per_multiprocessor: usize,
+    /// Maximum number of 32-bit registers available to a block
+    pub registers_per_block: u32,
+    /// Number of 32-bit registers available per multiprocessor
+    pub registers_per_multiprocessor: u32,
+    /// Number of copy engines, which can copy memory concurrently with kernel execution
+    pub async_engine_count: u32,
+    /// The device is integrated with the host, sharing its memory
+    pub integrated: bool,
+    /// ECC support is enabled
+    pub ecc_enabled: bool,
+    /// The device shares a unified address space with the host
+    pub unified_addressing: bool,
+    /// The device can run multiple kernels concurrently
+    pub concurrent_kernels: bool,
+    /// The device is a MIG instance, so the sizes and counts above describe the instance rather
+    /// than the whole GPU. See [Device::is_mig_instance](struct.Device.html#method.is_mig_instance).
+    pub mig_instance: bool,
+}
+
+/// The CUDA ordinal of a device and the identifiers of the physical GPU behind it, as returned by
+/// [Device::identity](struct.Device.html#method.identity).
+///
+/// This is displayed as eg. `device 0 (GPU-5f9a3c2e-7d41-8b06-e2a1-0c94f3b87d15, PCI 0000:65:00.0)`,
+/// with the MIG UUID in place of the GPU UUID for MIG instances, which is useful for logging which
+/// GPU a process actually ended up on.
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DeviceIdentity {
+    /// The ordinal of the device among the visible devices
+    pub ordinal: u32,
+    /// The UUID of the physical GPU
+    pub uuid: DeviceUuid,
+    /// The UUID of the MIG instance, if the device is one and the driver can report it
+    pub mig_uuid: Option<DeviceUuid>,
+    /// The PCI bus ID of the GPU
+    pub pci_bus_id: String,
+}
+impl fmt::Display for DeviceIdentity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "device {} ({}, PCI {})",
+            self.ordinal,
+            self.mig_uuid.unwrap_or(self.uuid),
+            self.pci_bus_id
+        )
+    }
+}
+
+/// Returns the entries of `CUDA_VISIBLE_DEVICES`, or `None` if it is not set and every device is
+/// visible.
+///
+/// The entries are indices, GPU UUIDs or MIG UUIDs, in the order of the ordinals they are given.
+/// Like CUDA, this stops at the first empty entry; CUDA also ignores the entries after one which
+/// doesn't name a device, which can't be detected here. See
+/// [Device::from_physical_id](struct.Device.html#method.from_physical_id) to translate an entry
+/// into a device.
+///
+/// # Example
+/// ```
+/// use rustacuda::device;
+/// if let Some(visible) = device::visible_devices() {
+///     println!("Only {} device(s) are visible: {:?}", visible.len(), visible);
+/// }
+/// ```
+pub fn visible_devices() -> Option<Vec<String>> {
+    let mask = std::env::var("CUDA_VISIBLE_DEVICES").ok()?;
+    Some(parse_visible_devices(&mask))
+}
+
+fn parse_visible_devices(mask: &str) -> Vec<String> {
+    mask.split(',')
+        .map(str::trim)
+        .take_while(|entry| !entry.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Returns true if an MPS control daemon appears to be running, in which case this process is
+/// likely a client of the CUDA Multi-Process Service (MPS).
+///
+/// MPS lets several processes share a GPU concurrently by sending their work through one server
+/// process. It is mostly transparent, but MPS clients behave differently in a few ways:
+///
+/// - Some MPS versions don't support stream priorities. There, creating a stream with a
+///   non-default priority fails with the driver's error.
+/// - If `CUDA_MPS_ACTIVE_THREAD_PERCENTAGE` is set, kernels only run on a fraction of the device's
+///   multiprocessors, so `DeviceAttribute::MultiprocessorCount` overstates the parallelism
+///   available.
+/// - On GPUs older than Volta, a fatal error in one client, such as an out-of-bounds access, can
+///   terminate the other clients too.
+///
+/// The driver tries to connect to MPS when it is initialized if the MPS control daemon's pipe
+/// exists in `CUDA_MPS_PIPE_DIRECTORY` (by default `/tmp/nvidia-mps`), so that is what this
+/// checks. The driver doesn't report whether the connection was made, so this is only a hint for
+/// diagnostics; RustaCUDA doesn't change its behavior based on it. The result is determined on
+/// the first call and cached. MPS is only available on Linux, so this always returns false on
+/// other platforms.
+///
+/// # Example
+/// ```
+/// use rustacuda::device;
+/// if device::is_mps_active() {
+///     println!("Sharing the GPU through MPS");
+/// }
+/// ```
+pub fn is_mps_active() -> bool {
+    static MPS_ACTIVE: OnceLock<bool> = OnceLock::new();
+    *MPS_ACTIVE.get_or_init(|| {
+        if !cfg!(target_os = "linux") {
+            return false;
+        }
+        let pipe_dir = std::env::var_os("CUDA_MPS_PIPE_DIRECTORY")
+            .filter(|dir| !dir.is_empty())
+            .unwrap_or_else(|| "/tmp/nvidia-mps".into());
+        std::path::Path::new(&pipe_dir).join("control").exists()
+    })
+}
+
+// `cuDeviceGetUuid_v2`, added in CUDA 11.4, which returns the MIG UUID of MIG instances.
+type GetUuidV2Fn = unsafe extern "C" fn(*mut CUuuid, CUdevice) -> CUresult;
+
+driver_fn!(get_uuid_v2_fn: GetUuidV2Fn = b"cuDeviceGetUuid_v2\0");
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -438,6 +1408,18 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn test_priorities_under_mps() {
+        let _context = crate::quick_init().unwrap();
+        // RustaCUDA doesn't special-case MPS; the range query is answered by the driver either way,
+        // and only some MPS versions reject non-default priorities.
+        assert!(crate::context::CurrentContext::get_stream_priority_range().is_ok());
+        let stream = crate::stream::Stream::new(crate::stream::StreamFlags::DEFAULT, Some(-1));
+        if !is_mps_active() {
+            assert!(stream.is_ok());
+        }
+    }
+
     #[test]
     fn test_num_devices() -> Result<(), Box<dyn Error>> {
         test_init()?;
@@ -473,20 +1455,170 @@ mod test {
         Ok(())
     }
 
-    // Ensure that the two enums always stay aligned.
+    // Ensure that the two enums stay aligned up to the last attribute the bindings know.
     #[test]
     fn test_enums_align() {
         assert_eq!(
-            DeviceAttribute::__NonExhaustive as u32,
+            DeviceAttribute::HandleTypeWin32KmtHandleSupported as u32 + 1,
             CUdevice_attribute_enum::CU_DEVICE_ATTRIBUTE_MAX as u32
         );
+        assert_eq!(
+            CUdevice_attribute_enum::CU_DEVICE_ATTRIBUTE_COOPERATIVE_LAUNCH as u32,
+            DeviceAttribute::CooperativeLaunch as u32
+        );
+    }
+
+    #[test]
+    fn test_p2p_attributes_align() {
+        assert_eq!(
+            CUdevice_P2PAttribute::CU_DEVICE_P2P_ATTRIBUTE_PERFORMANCE_RANK as u32,
+            P2PAttribute::PerformanceRank as u32
+        );
+        assert_eq!(
+            CUdevice_P2PAttribute::CU_DEVICE_P2P_ATTRIBUTE_CUDA_ARRAY_ACCESS_SUPPORTED as u32,
+            P2PAttribute::CudaArrayAccessSupported as u32
+        );
     }
 
     #[test]
     fn test_uuid() -> Result<(), Box<dyn Error>> {
         test_init()?;
-        let uuid = Device::get_device(0)?.uuid()?;
-        println!("{:?}", uuid);
+        let device = Device::get_device(0)?;
+        let uuid = device.uuid()?;
+        println!("{}", uuid);
+        assert_eq!(device, Device::from_uuid(&uuid)?);
+        if let Ok(Some(mig_uuid)) = device.mig_uuid() {
+            assert!(device.is_mig_instance()?);
+            assert_eq!(device, Device::from_uuid(&mig_uuid)?);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_pci_bus_id() -> Result<(), Box<dyn Error>> {
+        test_init()?;
+        let device = Device::get_device(0)?;
+        let id = device.pci_bus_id()?;
+        assert_eq!(device, Device::from_pci_bus_id(&id)?);
+        assert_eq!(
+            Err(CudaError::InvalidValue),
+            Device::from_pci_bus_id("0000:00\0")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_compute_capability() -> Result<(), Box<dyn Error>> {
+        let turing = ComputeCapability::new(7, 5);
+        assert!(turing.supports(ComputeCapability::new(7, 0)));
+        assert!(turing.supports(turing));
+        assert!(!turing.supports(ComputeCapability::new(8, 0)));
+        assert!(ComputeCapability::new(6, 2) < ComputeCapability::new(7, 0));
+        assert_eq!("7.5", turing.to_string());
+
+        test_init()?;
+        let capability = Device::get_device(0)?.compute_capability()?;
+        assert!(capability.supports(ComputeCapability::new(2, 0)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_uuid_format() {
+        let uuid = DeviceUuid::from_bytes([
+            0x5f, 0x9a, 0x3c, 0x2e, 0x7d, 0x41, 0x8b, 0x06, 0xe2, 0xa1, 0x0c, 0x94, 0xf3, 0xb8,
+            0x7d, 0x15,
+        ]);
+        let text = "GPU-5f9a3c2e-7d41-8b06-e2a1-0c94f3b87d15";
+        assert_eq!(text, uuid.to_string());
+        assert_eq!(Ok(uuid), text.parse());
+        assert_eq!(Ok(uuid), text[4..].to_uppercase().parse());
+        assert!(!uuid.is_mig());
+
+        let mig: DeviceUuid = "MIG-5f9a3c2e-7d41-8b06-e2a1-0c94f3b87d15".parse().unwrap();
+        assert!(mig.is_mig());
+        assert_eq!(uuid.as_bytes(), mig.as_bytes());
+        assert_ne!(uuid, mig);
+        assert_eq!("MIG-5f9a3c2e-7d41-8b06-e2a1-0c94f3b87d15", mig.to_string());
+        assert_eq!(
+            Err(CudaError::InvalidValue),
+            "GPU-5f9a3c2e-7d41-8b06".parse::<DeviceUuid>()
+        );
+        assert_eq!(
+            Err(CudaError::InvalidValue),
+            "GPU-5f9a3c2e-7d41-8b06-e2a1-0c94f3b87dzz".parse::<DeviceUuid>()
+        );
+    }
+
+    #[test]
+    fn test_properties() -> Result<(), Box<dyn Error>> {
+        test_init()?;
+        let device = Device::get_device(0)?;
+        let properties = device.properties()?;
+        assert_eq!(device.name()?, properties.name);
+        assert_eq!(device.total_memory()?, properties.total_memory);
+        assert_eq!(32, properties.warp_size);
+        assert!(properties.max_threads_per_block >= properties.max_block_dim.z);
+        assert!(properties.shared_memory_per_block_optin >= properties.shared_memory_per_block);
+        Ok(())
+    }
+
+    #[test]
+    fn test_compute_mode() -> Result<(), Box<dyn Error>> {
+        test_init()?;
+        let device = Device::get_device(0)?;
+        let mode = device.compute_mode()?;
+        assert_eq!(
+            mode as i32,
+            device.get_attribute(DeviceAttribute::ComputeMode)?
+        );
+        // Errors the compute mode can't explain are passed through.
+        assert_eq!(
+            CudaError::OutOfMemory,
+            device.explain_context_error(CudaError::OutOfMemory)
+        );
+        if mode == ComputeMode::Default {
+            assert_eq!(
+                CudaError::InvalidDevice,
+                device.explain_context_error(CudaError::InvalidDevice)
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_visible_devices() {
+        assert_eq!(vec!["2", "3"], parse_visible_devices("2,3"));
+        assert_eq!(
+            vec!["GPU-5f9a3c2e", "1"],
+            parse_visible_devices(" GPU-5f9a3c2e , 1 ")
+        );
+        assert_eq!(vec!["0"], parse_visible_devices("0,,1"));
+        assert!(parse_visible_devices("").is_empty());
+    }
+
+    #[test]
+    fn test_identity() -> Result<(), Box<dyn Error>> {
+        test_init()?;
+        let device = Device::get_device(0)?;
+        let identity = device.identity()?;
+        assert_eq!(0, identity.ordinal);
+        assert_eq!(
+            device,
+            Device::from_physical_id(&identity.uuid.to_string())?
+        );
+        assert_eq!(device, Device::from_physical_id(&identity.pci_bus_id)?);
+        assert!(identity.to_string().starts_with("device 0 ("));
+        Ok(())
+    }
+
+    #[test]
+    fn test_managed_memory_queries() -> Result<(), Box<dyn Error>> {
+        test_init()?;
+        let device = Device::get_device(0)?;
+        // Concurrent access implies that managed memory is supported at all.
+        if device.concurrent_managed_access()? {
+            assert!(device.supports_managed_memory()?);
+        }
         Ok(())
     }
 }
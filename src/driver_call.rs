@@ -0,0 +1,130 @@
+//! Wrapper macro for calls into the CUDA driver API.
+//!
+//! Every `cu*` function that RustaCUDA calls goes through the `driver_call!` macro. Normally the
+//! macro expands to the plain function call. When the `driver-call-log` feature is enabled, it
+//! also times the call and reports the function name, arguments, result and duration through the
+//! `log` crate at trace level.
+//!
+//! Driver functions newer than the CUDA version the bindings were generated from are looked up
+//! at runtime with the `driver_fn!` macro instead, so that RustaCUDA still runs on older drivers
+//! which don't export them. The `cuda-11-*` features raise the minimum driver version, which
+//! `init` checks, and enable the APIs built on the functions that version introduced, such as
+//! memory pools (`cuda-11-2`) and graph memory nodes (`cuda-11-4`).
+
+/// Call a CUDA driver API function, eg. `driver_call!(cuInit(0))`.
+#[cfg(not(feature = "driver-call-log"))]
+macro_rules! driver_call {
+    ($($f:ident)::+ ( $($arg:expr),* $(,)? )) => {
+        $($f)::+($($arg),*)
+    };
+}
+
+/// Call a CUDA driver API function, eg. `driver_call!(cuInit(0))`, and log it at trace level.
+#[cfg(feature = "driver-call-log")]
+macro_rules! driver_call {
+    ($($f:ident)::+ ( $($arg:expr),* $(,)? )) => {
+        driver_call!(@bind [$($f)::+] [] $($arg,)*)
+    };
+    // Evaluate each argument exactly once, in order, so that it can be both passed to the driver
+    // and logged afterwards.
+    (@bind [$($f:ident)::+] [$($bound:ident)*] $arg:expr, $($rest:expr,)*) => {{
+        let arg = $arg;
+        driver_call!(@bind [$($f)::+] [$($bound)* arg] $($rest,)*)
+    }};
+    (@bind [$($f:ident)::+] [$($bound:ident)*]) => {{
+        let start = ::std::time::Instant::now();
+        let result = $($f)::+($($bound),*);
+        $crate::driver_call::log_call(
+            &[$(stringify!($f)),+],
+            &[$(&$bound as &dyn ::std::fmt::Debug),*],
+            &result,
+            start.elapsed(),
+        );
+        result
+    }};
+}
+
+#[cfg(feature = "driver-call-log")]
+pub(crate) fn log_call(
+    path: &[&str],
+    args: &[&dyn std::fmt::Debug],
+    result: &cuda_driver_sys::CUresult,
+    duration: std::time::Duration,
+) {
+    if !log::log_enabled!(log::Level::Trace) {
+        return;
+    }
+
+    let name = path.last().copied().unwrap_or("<unknown>");
+    let args = args
+        .iter()
+        .map(|arg| format!("{:?}", arg))
+        .collect::<Vec<_>>()
+        .join(", ");
+    log::trace!("{}({}) -> {:?} in {:?}", name, args, result, duration);
+}
+
+/// Define a function which looks up a driver function by symbol name the first time it is called,
+/// eg. `driver_fn!(upload_fn: GraphUploadFn = b"cuGraphUpload\0")`. The function returns `None`
+/// if the loaded driver doesn't export the symbol.
+macro_rules! driver_fn {
+    ($name:ident: $ty:ty = $symbol:expr) => {
+        fn $name() -> Option<$ty> {
+            use std::sync::OnceLock;
+
+            static FUNCTION: OnceLock<Option<$ty>> = OnceLock::new();
+            *FUNCTION.get_or_init(|| {
+                let symbol = $crate::driver_call::driver_symbol($symbol);
+                if symbol.is_null() {
+                    None
+                } else {
+                    Some(unsafe { ::std::mem::transmute::<*mut ::std::ffi::c_void, $ty>(symbol) })
+                }
+            })
+        }
+    };
+}
+
+#[cfg(target_os = "linux")]
+pub(crate) fn driver_symbol(name: &[u8]) -> *mut std::ffi::c_void {
+    unsafe {
+        libc::dlsym(
+            libc::RTLD_DEFAULT,
+            name.as_ptr() as *const std::os::raw::c_char,
+        )
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn driver_symbol(_name: &[u8]) -> *mut std::ffi::c_void {
+    std::ptr::null_mut()
+}
+
+/// The minimum driver version selected by the `cuda-11-*` features, in the format returned by
+/// `cuDriverGetVersion`, or 0 if no minimum was selected.
+pub(crate) const MINIMUM_DRIVER_VERSION: i32 = if cfg!(feature = "cuda-11-4") {
+    11040
+} else if cfg!(feature = "cuda-11-3") {
+    11030
+} else if cfg!(feature = "cuda-11-2") {
+    11020
+} else if cfg!(feature = "cuda-11-1") {
+    11010
+} else if cfg!(feature = "cuda-11-0") {
+    11000
+} else {
+    0
+};
+
+/// Returns `InsufficientDriver` if the driver is older than `MINIMUM_DRIVER_VERSION`.
+pub(crate) fn check_driver_version() -> crate::error::CudaResult<()> {
+    if MINIMUM_DRIVER_VERSION == 0 {
+        return Ok(());
+    }
+    let version = crate::CudaApiVersion::get()?;
+    if version.version < MINIMUM_DRIVER_VERSION {
+        Err(crate::error::CudaError::InsufficientDriver)
+    } else {
+        Ok(())
+    }
+}
@@ -77,12 +77,22 @@ pub enum CudaError {
     InvalidAddressSpace = 717,
     InvalidProgramCounter = 718,
     LaunchFailed = 719,
+    CooperativeLaunchTooLarge = 720,
     NotPermitted = 800,
     NotSupported = 801,
+    GraphExecUpdateFailure = 910,
     UnknownError = 999,
 
     // RustaCUDA errors
     InvalidMemoryAllocation = 100_100,
+    UnifiedMemoryNotSupported = 100_101,
+    ContextMismatch = 100_102,
+    EventNotRecorded = 100_103,
+    MapHostFlagMissing = 100_104,
+    SymbolNotFound = 100_105,
+    InsufficientDriver = 100_106,
+    ExclusiveDeviceInUse = 100_107,
+    DeviceProhibited = 100_108,
 
     #[doc(hidden)]
     __Nonexhaustive,
@@ -91,14 +101,48 @@ impl fmt::Display for CudaError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
             CudaError::InvalidMemoryAllocation => write!(f, "Invalid memory allocation"),
+            CudaError::UnifiedMemoryNotSupported => {
+                write!(f, "Unified memory is not supported on this device")
+            }
+            CudaError::ContextMismatch => write!(
+                f,
+                "The function or event was created in a different context than the one the stream belongs to"
+            ),
+            CudaError::EventNotRecorded => write!(f, "The event has never been recorded"),
+            CudaError::MapHostFlagMissing => write!(
+                f,
+                "Mapped host memory requires a context created with ContextFlags::MAP_HOST, or a \
+                 device with unified addressing"
+            ),
+            CudaError::SymbolNotFound => {
+                write!(f, "The module has no symbol with the given name")
+            }
+            CudaError::InsufficientDriver => write!(
+                f,
+                "The installed CUDA driver is older than the version this program requires"
+            ),
+            CudaError::ExclusiveDeviceInUse => write!(
+                f,
+                "The device is in exclusive-process compute mode and another process already has a \
+                 context on it; run nvidia-smi to find that process, or change the compute mode with \
+                 `nvidia-smi -c DEFAULT`"
+            ),
+            CudaError::DeviceProhibited => write!(
+                f,
+                "The device is in prohibited compute mode, so no contexts can be created on it; \
+                 change the compute mode with `nvidia-smi -c DEFAULT`"
+            ),
             CudaError::__Nonexhaustive => write!(f, "__Nonexhaustive"),
             other if (other as u32) <= 999 => {
                 let value = other as u32;
                 let mut ptr: *const c_char = ptr::null();
                 unsafe {
-                    cuGetErrorString(mem::transmute(value), &mut ptr as *mut *const c_char)
-                        .to_result()
-                        .map_err(|_| fmt::Error)?;
+                    driver_call!(cuGetErrorString(
+                        mem::transmute::<u32, cudaError_enum>(value),
+                        &mut ptr as *mut *const c_char
+                    ))
+                    .to_result()
+                    .map_err(|_| fmt::Error)?;
                     let cstr = CStr::from_ptr(ptr);
                     write!(f, "{:?}", cstr)
                 }
@@ -116,6 +160,23 @@ pub type CudaResult<T> = Result<T, CudaError>;
 /// Special result type for `drop` functions which includes the un-dropped value with the error.
 pub type DropResult<T> = Result<(), (CudaError, T)>;
 
+/// Handling of errors from the driver calls which release resources in `Drop` impls.
+pub(crate) trait DropExpect {
+    /// Like `Result::expect`, except that errors meaning the driver has already shut down are
+    /// ignored. This happens when destructors run during process exit, such as thread-local
+    /// destructors on Windows or objects owned by a Python host, and the driver has freed
+    /// everything already.
+    fn expect_in_drop(self, message: &str);
+}
+impl DropExpect for CudaResult<()> {
+    fn expect_in_drop(self, message: &str) {
+        match self {
+            Ok(()) | Err(CudaError::Deinitialized) | Err(CudaError::NotInitialized) => {}
+            Err(e) => panic!("{}: {:?}", message, e),
+        }
+    }
+}
+
 pub(crate) trait ToResult {
     fn to_result(self) -> CudaResult<()>;
 }
@@ -210,8 +271,14 @@ impl ToResult for cudaError_enum {
             cudaError_enum::CUDA_ERROR_INVALID_ADDRESS_SPACE => Err(CudaError::InvalidAddressSpace),
             cudaError_enum::CUDA_ERROR_INVALID_PC => Err(CudaError::InvalidProgramCounter),
             cudaError_enum::CUDA_ERROR_LAUNCH_FAILED => Err(CudaError::LaunchFailed),
+            cudaError_enum::CUDA_ERROR_COOPERATIVE_LAUNCH_TOO_LARGE => {
+                Err(CudaError::CooperativeLaunchTooLarge)
+            }
             cudaError_enum::CUDA_ERROR_NOT_PERMITTED => Err(CudaError::NotPermitted),
             cudaError_enum::CUDA_ERROR_NOT_SUPPORTED => Err(CudaError::NotSupported),
+            cudaError_enum::CUDA_ERROR_GRAPH_EXEC_UPDATE_FAILURE => {
+                Err(CudaError::GraphExecUpdateFailure)
+            }
             _ => Err(CudaError::UnknownError),
         }
     }
@@ -8,20 +8,32 @@
 //! streams within the same context by specifying dependent tasks (not supported
 //! yet by RustaCUDA).
 //!
-//! Events may be reused multiple times.
+//! Events may be reused multiple times. Each call to `record` replaces the work the event tracks
+//! with the work submitted to the stream so far; `query`, `synchronize` and `elapsed_time_f32`
+//! always refer to the most recent recording. An event which has never been recorded has no
+//! work to wait for, so `query` reports it as ready and `synchronize` returns immediately, but it
+//! can't be used to measure time. [`Event::state`](struct.Event.html#method.state) tells these
+//! cases apart.
 
 // TODO: I'm not sure that these events are/can be safe by Rust's model of safety; they inherently
 // create state which can be mutated even while an immutable borrow is held.
 
-use crate::error::{CudaError, CudaResult, DropResult, ToResult};
+use crate::context::ContextToken;
+use crate::error::{CudaError, CudaResult, DropExpect, DropResult, ToResult};
 use crate::stream::Stream;
+use crate::sync::lock_ignoring_poison;
+use crate::teardown::{self, Resource};
 use cuda_driver_sys::{
     cuEventCreate, cuEventDestroy_v2, cuEventElapsedTime, cuEventQuery, cuEventRecord,
-    cuEventSynchronize, CUevent,
+    cuEventSynchronize, cuStreamWaitEvent, CUevent,
 };
 
 use std::mem;
+use std::ops::Deref;
 use std::ptr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
 
 bitflags! {
     /// Bit flags for configuring a CUDA Event.
@@ -60,11 +72,29 @@ pub enum EventStatus {
     NotReady,
 }
 
+/// The state of an [`Event`](struct.Event.html).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EventState {
+    /// The event has never been recorded.
+    Created,
+    /// The event has been recorded, but the work it tracks has not completed yet.
+    Recorded,
+    /// The event has been recorded and the work it tracks has completed.
+    Completed,
+}
+
 /// An event to track work submitted to a stream.
 ///
 /// See the module-level documentation for more information.
 #[derive(Debug)]
-pub struct Event(CUevent);
+pub struct Event {
+    inner: CUevent,
+    recorded: AtomicBool,
+    context: Option<ContextToken>,
+}
+// Events can be recorded, queried and destroyed from any thread.
+unsafe impl Send for Event {}
+unsafe impl Sync for Event {}
 
 impl Event {
     /// Create a new event with the specified flags.
@@ -86,8 +116,13 @@ impl Event {
     pub fn new(flags: EventFlags) -> CudaResult<Self> {
         unsafe {
             let mut event: CUevent = mem::zeroed();
-            cuEventCreate(&mut event, flags.bits()).to_result()?;
-            Ok(Event(event))
+            driver_call!(cuEventCreate(&mut event, flags.bits())).to_result()?;
+            teardown::created(Resource::Event);
+            Ok(Event {
+                inner: event,
+                recorded: AtomicBool::new(false),
+                context: ContextToken::current(),
+            })
         }
     }
 
@@ -103,7 +138,8 @@ impl Event {
     /// # Errors
     ///
     /// If the event and stream are not from the same context, an error is
-    /// returned.
+    /// returned. In debug builds or with the `context-check` feature, this is
+    /// `ContextMismatch`.
     ///
     /// # Example
     ///
@@ -125,12 +161,57 @@ impl Event {
     /// }
     /// ```
     pub fn record(&self, stream: &Stream) -> CudaResult<()> {
+        self.check_stream(stream)?;
         unsafe {
-            cuEventRecord(self.0, stream.as_inner()).to_result()?;
+            driver_call!(cuEventRecord(self.inner, stream.as_inner())).to_result()?;
+            self.recorded.store(true, Ordering::Release);
             Ok(())
         }
     }
 
+    /// Returns `true` if `record` has succeeded on this event at least once.
+    pub fn is_recorded(&self) -> bool {
+        self.recorded.load(Ordering::Acquire)
+    }
+
+    /// Returns the state of this event: whether it has been recorded and, if so, whether the work
+    /// it tracks has completed.
+    ///
+    /// # Errors
+    ///
+    /// If querying the event fails, returns the CUDA error value. Errors from previous
+    /// asynchronous work may also be returned.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rustacuda::quick_init;
+    /// # use rustacuda::stream::{Stream, StreamFlags};
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # let _context = quick_init()?;
+    /// use rustacuda::event::{Event, EventFlags, EventState};
+    ///
+    /// let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+    /// let event = Event::new(EventFlags::DEFAULT)?;
+    /// assert_eq!(EventState::Created, event.state()?);
+    ///
+    /// event.record(&stream)?;
+    /// event.synchronize()?;
+    /// assert_eq!(EventState::Completed, event.state()?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn state(&self) -> CudaResult<EventState> {
+        if !self.is_recorded() {
+            return Ok(EventState::Created);
+        }
+        match self.query()? {
+            EventStatus::Ready => Ok(EventState::Completed),
+            EventStatus::NotReady => Ok(EventState::Recorded),
+        }
+    }
+
     /// Return whether the stream this event was recorded on (see `record`) has processed this event
     /// yet or not. A return value of `EventStatus::Ready` indicates that all work submitted before
     /// the event has been completed.
@@ -163,7 +244,7 @@ impl Event {
     /// }
     /// ```
     pub fn query(&self) -> CudaResult<EventStatus> {
-        let result = unsafe { cuEventQuery(self.0).to_result() };
+        let result = unsafe { driver_call!(cuEventQuery(self.inner)).to_result() };
 
         match result {
             Ok(()) => Ok(EventStatus::Ready),
@@ -204,7 +285,7 @@ impl Event {
     /// ```
     pub fn synchronize(&self) -> CudaResult<()> {
         unsafe {
-            cuEventSynchronize(self.0).to_result()?;
+            driver_call!(cuEventSynchronize(self.inner)).to_result()?;
             Ok(())
         }
     }
@@ -217,11 +298,12 @@ impl Event {
     ///
     /// # Errors
     ///
+    /// `CudaError::EventNotRecorded` is returned if `record` has not been called on either event.
+    ///
     /// `CudaError::NotReady` is returned if either event is not yet complete.
     ///
     /// `CudaError::InvalidHandle` is returned if
     /// - the two events are not from the same context, or if
-    /// - `record` has not been called on either event, or if
     /// - the `DISABLE_TIMING` flag is set on either event.
     ///
     /// # Example
@@ -275,18 +357,27 @@ impl Event {
     /// ```
     pub fn elapsed_time_f32(&self, start: &Self) -> CudaResult<f32> {
         unsafe {
+            if !self.is_recorded() || !start.is_recorded() {
+                return Err(CudaError::EventNotRecorded);
+            }
             let mut millis: f32 = 0.0;
-            cuEventElapsedTime(&mut millis, start.0, self.0).to_result()?;
+            driver_call!(cuEventElapsedTime(&mut millis, start.inner, self.inner)).to_result()?;
             Ok(millis)
         }
     }
 
+    /// Returns `ContextMismatch` if this event was created in a different context than the one
+    /// `stream` belongs to. Only checked in debug builds or with the `context-check` feature.
+    pub(crate) fn check_stream(&self, stream: &Stream) -> CudaResult<()> {
+        ContextToken::check_stream(self.context, stream, "The event")
+    }
+
     // Get the inner `CUevent` from the `Event`.
     //
     // Necessary for certain CUDA functions outside of this
     // module that expect a bare `CUevent`.
     pub(crate) fn as_inner(&self) -> CUevent {
-        self.0
+        self.inner
     }
 
     /// Destroy an `Event` returning an error.
@@ -316,18 +407,26 @@ impl Event {
     /// # }
     /// ```
     pub fn drop(mut event: Event) -> DropResult<Event> {
-        if event.0.is_null() {
+        if event.inner.is_null() {
             return Ok(());
         }
 
         unsafe {
-            let inner = mem::replace(&mut event.0, ptr::null_mut());
-            match cuEventDestroy_v2(inner).to_result() {
+            let inner = mem::replace(&mut event.inner, ptr::null_mut());
+            match driver_call!(cuEventDestroy_v2(inner)).to_result() {
                 Ok(()) => {
+                    teardown::destroyed(Resource::Event);
                     mem::forget(event);
                     Ok(())
                 }
-                Err(e) => Err((e, Event(inner))),
+                Err(e) => Err((
+                    e,
+                    Event {
+                        inner,
+                        recorded: AtomicBool::new(event.is_recorded()),
+                        context: event.context,
+                    },
+                )),
             }
         }
     }
@@ -335,19 +434,425 @@ impl Event {
 
 impl Drop for Event {
     fn drop(&mut self) {
-        unsafe { cuEventDestroy_v2(self.0) }
+        unsafe { driver_call!(cuEventDestroy_v2(self.inner)) }
             .to_result()
-            .expect("Failed to destroy CUDA event");
+            .expect_in_drop("Failed to destroy CUDA event");
+        teardown::destroyed(Resource::Event);
+    }
+}
+
+/// A pool of events created with the same flags, for recording events at a high rate without
+/// creating and destroying one each time.
+///
+/// Events taken from the pool with [`get`](#method.get) are returned to it when dropped. An
+/// event is only handed out again once its last recording has completed, so work still waiting on
+/// it is never affected: events which are dropped while pending are set aside and checked again
+/// by later calls to `get`.
+///
+/// # Examples
+///
+/// ```
+/// # use rustacuda::quick_init;
+/// # use std::error::Error;
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// # let _context = quick_init()?;
+/// use rustacuda::event::{EventFlags, EventPool};
+/// use rustacuda::stream::{Stream, StreamFlags};
+///
+/// let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+/// let pool = EventPool::with_capacity(EventFlags::DISABLE_TIMING, 8)?;
+/// for _ in 0..100 {
+///     // ... submit some work ...
+///     let done = pool.get()?;
+///     done.record(&stream)?;
+///     done.synchronize()?;
+/// }
+/// assert_eq!(8, pool.available());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct EventPool {
+    flags: EventFlags,
+    // A Mutex rather than a RefCell, so that the pool is Sync whenever Event is Send.
+    events: Mutex<PoolEvents>,
+}
+
+#[derive(Debug, Default)]
+struct PoolEvents {
+    // Events whose last recording has completed.
+    free: Vec<Event>,
+    // Events which were returned to the pool while their last recording was still pending.
+    pending: Vec<Event>,
+}
+
+impl EventPool {
+    /// Create a new, empty pool of events with the given flags. This does not create any events.
+    pub fn new(flags: EventFlags) -> Self {
+        EventPool {
+            flags,
+            events: Mutex::new(PoolEvents::default()),
+        }
+    }
+
+    /// Create a pool with `count` events created up front.
+    ///
+    /// # Errors
+    ///
+    /// If creating an event fails, returns the CUDA error value.
+    pub fn with_capacity(flags: EventFlags, count: usize) -> CudaResult<Self> {
+        let free = (0..count)
+            .map(|_| Event::new(flags))
+            .collect::<CudaResult<Vec<_>>>()?;
+        Ok(EventPool {
+            flags,
+            events: Mutex::new(PoolEvents {
+                free,
+                pending: Vec::new(),
+            }),
+        })
+    }
+
+    /// Take an event from the pool, creating one if no event in the pool has completed.
+    ///
+    /// The event may have been recorded before; record it again before waiting on it.
+    ///
+    /// # Errors
+    ///
+    /// If querying a pending event or creating an event fails, returns the CUDA error value.
+    pub fn get(&self) -> CudaResult<PooledEvent<'_>> {
+        let mut events = self.events();
+        if events.free.is_empty() {
+            let mut i = 0;
+            while i < events.pending.len() {
+                if events.pending[i].query()? == EventStatus::Ready {
+                    let event = events.pending.swap_remove(i);
+                    events.free.push(event);
+                } else {
+                    i += 1;
+                }
+            }
+        }
+        let event = match events.free.pop() {
+            Some(event) => event,
+            None => Event::new(self.flags)?,
+        };
+        Ok(PooledEvent {
+            event: Some(event),
+            pool: self,
+        })
+    }
+
+    /// Returns the number of events in the pool which are not in use, including any whose last
+    /// recording is still pending.
+    pub fn available(&self) -> usize {
+        let events = self.events();
+        events.free.len() + events.pending.len()
+    }
+
+    fn events(&self) -> MutexGuard<'_, PoolEvents> {
+        lock_ignoring_poison(&self.events)
+    }
+
+    fn recycle(&self, event: Event) {
+        let mut events = self.events();
+        match event.query() {
+            Ok(EventStatus::Ready) => events.free.push(event),
+            Ok(EventStatus::NotReady) => events.pending.push(event),
+            // The event can't be trusted; destroy it rather than hand it out again.
+            Err(_) => drop(event),
+        }
+    }
+}
+
+/// An event taken from an [`EventPool`](struct.EventPool.html).
+///
+/// This dereferences to the `Event`. When dropped, the event is returned to the pool.
+#[derive(Debug)]
+pub struct PooledEvent<'a> {
+    event: Option<Event>,
+    pool: &'a EventPool,
+}
+impl Deref for PooledEvent<'_> {
+    type Target = Event;
+
+    fn deref(&self) -> &Event {
+        self.event.as_ref().unwrap()
+    }
+}
+impl Drop for PooledEvent<'_> {
+    fn drop(&mut self) {
+        if let Some(event) = self.event.take() {
+            self.pool.recycle(event);
+        }
+    }
+}
+
+/// Times the work submitted to a stream between its creation and a call to
+/// [`stop`](#method.stop).
+///
+/// This wraps the usual pair of timing events: a start event is recorded when the timer is
+/// created, and `stop` records a stop event, waits for it and returns the time between the two.
+/// A timer can be [`restart`](#method.restart)ed to time several iterations without creating new
+/// events, and the results collected in a [`TimingStats`](struct.TimingStats.html).
+///
+/// # Examples
+///
+/// ```
+/// # use rustacuda::quick_init;
+/// # use std::error::Error;
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// # let _context = quick_init()?;
+/// use rustacuda::event::{GpuTimer, TimingStats};
+/// use rustacuda::stream::{Stream, StreamFlags};
+///
+/// let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+/// let timer = GpuTimer::start(&stream)?;
+/// // ... submit some work ...
+/// println!("took {:?}", timer.stop(&stream)?);
+///
+/// let mut stats = TimingStats::new();
+/// for _ in 0..10 {
+///     timer.restart(&stream)?;
+///     // ... submit some work ...
+///     stats.add(timer.stop(&stream)?);
+/// }
+/// println!("mean {:?}, max {:?}", stats.mean(), stats.max());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct GpuTimer {
+    start: Event,
+    stop: Event,
+}
+impl GpuTimer {
+    /// Create a timer and record its start event on `stream`.
+    ///
+    /// # Errors
+    ///
+    /// If creating or recording the events fails, returns the CUDA error value.
+    pub fn start(stream: &Stream) -> CudaResult<Self> {
+        let timer = GpuTimer {
+            start: Event::new(EventFlags::DEFAULT)?,
+            stop: Event::new(EventFlags::DEFAULT)?,
+        };
+        timer.start.record(stream)?;
+        Ok(timer)
+    }
+
+    /// Record the start event again on `stream`, so that the next call to `stop` times the work
+    /// submitted from now on.
+    ///
+    /// # Errors
+    ///
+    /// If recording the event fails, returns the CUDA error value.
+    pub fn restart(&self, stream: &Stream) -> CudaResult<()> {
+        self.start.record(stream)
+    }
+
+    /// Record the stop event on `stream`, block until it completes and return the time elapsed
+    /// since the start event.
+    ///
+    /// The start and stop events should be recorded on the same stream; otherwise the result
+    /// depends on how the two streams' work was interleaved. The resolution is approximately 0.5
+    /// microseconds.
+    ///
+    /// # Errors
+    ///
+    /// If recording, synchronizing or timing the events fails, returns the CUDA error value.
+    pub fn stop(&self, stream: &Stream) -> CudaResult<Duration> {
+        self.stop.record(stream)?;
+        self.stop.synchronize()?;
+        let millis = self.stop.elapsed_time_f32(&self.start)?;
+        // Events recorded on different streams can complete in the opposite order.
+        Ok(Duration::from_secs_f64(f64::from(millis.max(0.0)) / 1000.0))
+    }
+}
+
+/// Running statistics over a series of durations, such as those returned by
+/// [`GpuTimer::stop`](struct.GpuTimer.html#method.stop).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TimingStats {
+    count: u32,
+    total: Duration,
+    min: Duration,
+    max: Duration,
+}
+impl TimingStats {
+    /// Create an empty set of statistics.
+    pub fn new() -> Self {
+        TimingStats::default()
+    }
+
+    /// Add a duration to the statistics.
+    pub fn add(&mut self, duration: Duration) {
+        if self.count == 0 {
+            self.min = duration;
+            self.max = duration;
+        } else {
+            self.min = self.min.min(duration);
+            self.max = self.max.max(duration);
+        }
+        self.count += 1;
+        self.total += duration;
+    }
+
+    /// Returns the number of durations added.
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    /// Returns the sum of the durations added.
+    pub fn total(&self) -> Duration {
+        self.total
+    }
+
+    /// Returns the shortest duration added, or `None` if there are none.
+    pub fn min(&self) -> Option<Duration> {
+        self.nonempty(self.min)
+    }
+
+    /// Returns the longest duration added, or `None` if there are none.
+    pub fn max(&self) -> Option<Duration> {
+        self.nonempty(self.max)
+    }
+
+    /// Returns the mean of the durations added, or `None` if there are none.
+    pub fn mean(&self) -> Option<Duration> {
+        self.nonempty(self.total).map(|total| total / self.count)
+    }
+
+    fn nonempty(&self, value: Duration) -> Option<Duration> {
+        if self.count == 0 {
+            None
+        } else {
+            Some(value)
+        }
+    }
+}
+impl Extend<Duration> for TimingStats {
+    fn extend<I: IntoIterator<Item = Duration>>(&mut self, iter: I) {
+        for duration in iter {
+            self.add(duration);
+        }
+    }
+}
+
+/// Make a set of streams rendezvous: no work submitted to any of the streams after this call
+/// starts until all work submitted to every one of them before this call has completed.
+///
+/// This is a barrier between the phases of an algorithm which spreads each phase across several
+/// streams. It is done on the device, without blocking the host. The returned event completes
+/// when every stream has reached the barrier, so it can also be waited on by other streams or
+/// synchronized with on the host.
+///
+/// # Errors
+///
+/// Returns `InvalidValue` if `streams` is empty. If the streams belong to different contexts or
+/// the events can't be created or recorded, returns the CUDA error value.
+///
+/// # Example
+///
+/// ```
+/// # use rustacuda::quick_init;
+/// # use std::error::Error;
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// # let _context = quick_init()?;
+/// use rustacuda::event::barrier_all;
+/// use rustacuda::stream::{Stream, StreamFlags};
+///
+/// let streams = [
+///     Stream::new(StreamFlags::NON_BLOCKING, None)?,
+///     Stream::new(StreamFlags::NON_BLOCKING, None)?,
+/// ];
+///
+/// // ... submit the first phase to both streams ...
+///
+/// let barrier = barrier_all(&[&streams[0], &streams[1]])?;
+///
+/// // ... submit the second phase, which may read anything the first phase wrote ...
+///
+/// barrier.synchronize()?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn barrier_all(streams: &[&Stream]) -> CudaResult<Event> {
+    let (first, rest) = streams.split_first().ok_or(CudaError::InvalidValue)?;
+
+    // The first stream waits for all of the others, and then the others wait for the first.
+    for stream in rest {
+        let arrived = Event::new(EventFlags::DISABLE_TIMING)?;
+        arrived.record(stream)?;
+        unsafe {
+            driver_call!(cuStreamWaitEvent(first.as_inner(), arrived.as_inner(), 0)).to_result()?;
+        }
+    }
+    let barrier = Event::new(EventFlags::DISABLE_TIMING)?;
+    barrier.record(first)?;
+    for stream in rest {
+        unsafe {
+            driver_call!(cuStreamWaitEvent(stream.as_inner(), barrier.as_inner(), 0))
+                .to_result()?;
+        }
     }
+    Ok(barrier)
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::context::{Context, ContextFlags};
+    use crate::device::Device;
     use crate::quick_init;
     use crate::stream::StreamFlags;
     use std::error::Error;
 
+    #[test]
+    fn test_barrier_all() -> Result<(), Box<dyn Error>> {
+        let _context = quick_init()?;
+        assert_eq!(Err(CudaError::InvalidValue), barrier_all(&[]).map(|_| ()));
+
+        let streams = [
+            Stream::new(StreamFlags::NON_BLOCKING, None)?,
+            Stream::new(StreamFlags::NON_BLOCKING, None)?,
+            Stream::new(StreamFlags::NON_BLOCKING, None)?,
+        ];
+        let barrier = barrier_all(&[&streams[0], &streams[1], &streams[2]])?;
+        barrier.synchronize()?;
+        assert_eq!(EventStatus::Ready, barrier.query()?);
+
+        let single = barrier_all(&[&streams[1]])?;
+        single.synchronize()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_event_state() -> Result<(), Box<dyn Error>> {
+        let _context = quick_init()?;
+        let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+        let start = Event::new(EventFlags::DEFAULT)?;
+        let stop = Event::new(EventFlags::DEFAULT)?;
+        assert!(!start.is_recorded());
+        assert_eq!(EventState::Created, start.state()?);
+        assert_eq!(
+            Err(CudaError::EventNotRecorded),
+            stop.elapsed_time_f32(&start)
+        );
+
+        start.record(&stream)?;
+        assert!(start.is_recorded());
+        assert_eq!(
+            Err(CudaError::EventNotRecorded),
+            stop.elapsed_time_f32(&start)
+        );
+        stop.record(&stream)?;
+        stop.synchronize()?;
+        assert_eq!(EventState::Completed, stop.state()?);
+        let _millis = stop.elapsed_time_f32(&start)?;
+        Ok(())
+    }
+
     #[test]
     fn test_new_with_flags() -> Result<(), Box<dyn Error>> {
         let _context = quick_init()?;
@@ -363,6 +868,7 @@ mod test {
         let event = Event::new(EventFlags::DEFAULT)?;
         let result = event.record(&stream);
         assert_eq!(result, Err(CudaError::InvalidHandle));
+        assert!(!event.is_recorded());
         Ok(())
     }
 
@@ -418,4 +924,71 @@ mod test {
         assert_eq!(result, Err(CudaError::InvalidHandle));
         Ok(())
     }
+
+    #[test]
+    fn test_event_pool() -> Result<(), Box<dyn Error>> {
+        let _context = quick_init()?;
+        let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+        let pool = EventPool::with_capacity(EventFlags::DISABLE_TIMING, 2)?;
+        assert_eq!(2, pool.available());
+
+        let first = pool.get()?;
+        let second = pool.get()?;
+        let third = pool.get()?;
+        assert_eq!(0, pool.available());
+        first.record(&stream)?;
+        first.synchronize()?;
+        let first_handle = first.inner;
+        drop(first);
+        drop((second, third));
+        assert_eq!(3, pool.available());
+
+        // Completed events are reused rather than new ones created.
+        let events = [pool.get()?, pool.get()?, pool.get()?];
+        assert!(events.iter().any(|event| event.inner == first_handle));
+        assert_eq!(0, pool.available());
+        Ok(())
+    }
+
+    #[test]
+    fn test_gpu_timer() -> Result<(), Box<dyn Error>> {
+        let _context = quick_init()?;
+        let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+        let timer = GpuTimer::start(&stream)?;
+        let mut stats = TimingStats::new();
+        stats.add(timer.stop(&stream)?);
+        timer.restart(&stream)?;
+        stats.add(timer.stop(&stream)?);
+        assert_eq!(2, stats.count());
+        assert!(stats.min() <= stats.mean() && stats.mean() <= stats.max());
+        Ok(())
+    }
+
+    #[test]
+    fn test_timing_stats() {
+        let mut stats = TimingStats::new();
+        assert_eq!(None, stats.mean());
+        assert_eq!(None, stats.min());
+        stats.extend(vec![
+            Duration::from_millis(3),
+            Duration::from_millis(1),
+            Duration::from_millis(5),
+        ]);
+        assert_eq!(3, stats.count());
+        assert_eq!(Some(Duration::from_millis(1)), stats.min());
+        assert_eq!(Some(Duration::from_millis(5)), stats.max());
+        assert_eq!(Some(Duration::from_millis(3)), stats.mean());
+        assert_eq!(Duration::from_millis(9), stats.total());
+    }
+
+    #[test]
+    #[cfg(any(debug_assertions, feature = "context-check"))]
+    fn test_record_in_other_context() -> Result<(), Box<dyn Error>> {
+        let _first = quick_init()?;
+        let event = Event::new(EventFlags::DEFAULT)?;
+        let _second = Context::create_and_push(ContextFlags::SCHED_AUTO, Device::get_device(0)?)?;
+        let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+        assert_eq!(Err(CudaError::ContextMismatch), event.record(&stream));
+        Ok(())
+    }
 }
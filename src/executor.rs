@@ -0,0 +1,281 @@
+//! Scheduling of dependent tasks over multiple streams.
+//!
+//! Spreading work over several streams lets independent kernels and copies overlap, but every
+//! dependency between work on different streams needs an event recorded on one stream and waited
+//! on by the other. Getting those pairs right by hand is error-prone: a missing wait is a race, and
+//! an unnecessary one serializes the streams.
+//!
+//! A [`TaskGraph`](struct.TaskGraph.html) holds tasks, which are closures that enqueue work on the
+//! stream they are given, along with the tasks each one depends on.
+//! [`run`](struct.TaskGraph.html#method.run) assigns each task to a stream of a
+//! [`StreamPool`](../stream/struct.StreamPool.html) and inserts the events needed for its
+//! dependencies. A task continues on the stream of one of its dependencies where possible, which
+//! needs no event, and otherwise starts on an idle stream so that independent tasks run
+//! concurrently.
+//!
+//! Because dependencies can only name tasks which were added before, the graph can never contain
+//! a cycle. Tasks which use the same buffer can share it through a `RefCell`, since only one task
+//! runs at a time on the host.
+//!
+//! # Examples
+//!
+//! ```
+//! # use rustacuda::*;
+//! # use std::error::Error;
+//! # fn main() -> Result<(), Box<dyn Error>> {
+//! # let _ctx = quick_init()?;
+//! use rustacuda::executor::TaskGraph;
+//! use rustacuda::memory::{AsyncCopyDestination, DeviceBuffer};
+//! use rustacuda::stream::StreamPool;
+//! use std::cell::RefCell;
+//!
+//! let a = RefCell::new(DeviceBuffer::from_slice(&[0u32; 64])?);
+//! let b = RefCell::new(DeviceBuffer::from_slice(&[0u32; 64])?);
+//! let mut result = [0u32; 64];
+//!
+//! let pool = StreamPool::new(2)?;
+//! let mut graph = TaskGraph::new();
+//! let upload_a = graph.add_task(&[], |stream| unsafe {
+//!     a.borrow_mut().async_copy_from(&[1u32; 64][..], stream)
+//! })?;
+//! let upload_b = graph.add_task(&[], |stream| unsafe {
+//!     b.borrow_mut().async_copy_from(&[2u32; 64][..], stream)
+//! })?;
+//! // Launch a kernel reading `a` and `b` here, then download the result.
+//! graph.add_task(&[upload_a, upload_b], |stream| unsafe {
+//!     a.borrow().async_copy_to(&mut result[..], stream)
+//! })?;
+//! graph.run(&pool)?;
+//! pool.synchronize()?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::{CudaError, CudaResult};
+use crate::event::{Event, EventFlags};
+use crate::stream::{Stream, StreamPool, StreamWaitEventFlags};
+use std::fmt;
+
+/// Identifies a task within the [`TaskGraph`](struct.TaskGraph.html) it was added to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TaskId(usize);
+
+type Enqueue<'a> = Box<dyn FnOnce(&Stream) -> CudaResult<()> + 'a>;
+
+struct Task<'a> {
+    dependencies: Vec<usize>,
+    enqueue: Enqueue<'a>,
+}
+
+/// A set of tasks which enqueue work on streams, and the dependencies between them.
+///
+/// See the [module-level documentation](index.html) for more details.
+#[derive(Default)]
+pub struct TaskGraph<'a> {
+    tasks: Vec<Task<'a>>,
+}
+impl<'a> TaskGraph<'a> {
+    /// Create an empty task graph.
+    pub fn new() -> Self {
+        TaskGraph { tasks: Vec::new() }
+    }
+
+    /// Returns the number of tasks in the graph.
+    pub fn len(&self) -> usize {
+        self.tasks.len()
+    }
+
+    /// Returns `true` if the graph has no tasks.
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+
+    /// Add a task which enqueues work on the stream it is given, once the work of all of
+    /// `dependencies` has completed.
+    ///
+    /// The closure is called by [`run`](#method.run), and should only enqueue asynchronous work;
+    /// synchronizing the stream would stall the tasks after it.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidValue` if a dependency is not a task of this graph.
+    pub fn add_task<F>(&mut self, dependencies: &[TaskId], enqueue: F) -> CudaResult<TaskId>
+    where
+        F: FnOnce(&Stream) -> CudaResult<()> + 'a,
+    {
+        if dependencies.iter().any(|dep| dep.0 >= self.tasks.len()) {
+            return Err(CudaError::InvalidValue);
+        }
+        let mut dependencies: Vec<usize> = dependencies.iter().map(|dep| dep.0).collect();
+        dependencies.sort_unstable();
+        dependencies.dedup();
+        self.tasks.push(Task {
+            dependencies,
+            enqueue: Box::new(enqueue),
+        });
+        Ok(TaskId(self.tasks.len() - 1))
+    }
+
+    /// Enqueue every task, in the order they were added, on the streams of `pool`.
+    ///
+    /// This only enqueues the work; use [`StreamPool::synchronize`](../stream/struct.StreamPool.html#method.synchronize)
+    /// to wait for it. The pool's streams are used whether or not they are checked out.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error from a task, or from creating, recording or waiting on an event.
+    /// Tasks after the failing one are not enqueued, but work already enqueued keeps running.
+    pub fn run(self, pool: &StreamPool) -> CudaResult<()> {
+        let streams = pool.streams();
+        let plan = plan(&self.tasks, streams.len());
+
+        // One event per dependent task on another stream, since waiting consumes the event.
+        let mut pending: Vec<Vec<Event>> = (0..self.tasks.len()).map(|_| Vec::new()).collect();
+        for (i, task) in self.tasks.into_iter().enumerate() {
+            let stream = &streams[plan.streams[i]];
+            for &dep in &task.dependencies {
+                if plan.streams[dep] != plan.streams[i] {
+                    let event = pending[dep]
+                        .pop()
+                        .expect("an event is recorded for each cross-stream dependent");
+                    stream.wait_event(event, StreamWaitEventFlags::DEFAULT)?;
+                }
+            }
+            (task.enqueue)(stream)?;
+            for _ in 0..plan.cross_stream_dependents[i] {
+                let event = Event::new(EventFlags::DISABLE_TIMING)?;
+                event.record(stream)?;
+                pending[i].push(event);
+            }
+        }
+        Ok(())
+    }
+}
+impl fmt::Debug for TaskGraph<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list()
+            .entries(self.tasks.iter().map(|task| &task.dependencies))
+            .finish()
+    }
+}
+
+/// The stream index assigned to each task, and how many of its dependents run on other streams.
+#[derive(Debug, PartialEq, Eq)]
+struct Plan {
+    streams: Vec<usize>,
+    cross_stream_dependents: Vec<usize>,
+}
+
+fn plan(tasks: &[Task<'_>], stream_count: usize) -> Plan {
+    let mut streams: Vec<usize> = Vec::with_capacity(tasks.len());
+    let mut cross_stream_dependents = vec![0; tasks.len()];
+    // The last task assigned to each stream.
+    let mut tails: Vec<Option<usize>> = vec![None; stream_count];
+    let mut next = 0;
+    for (i, task) in tasks.iter().enumerate() {
+        let continued = task
+            .dependencies
+            .iter()
+            .map(|&dep| streams[dep])
+            .find(|&stream| {
+                task.dependencies
+                    .contains(&tails[stream].unwrap_or(usize::MAX))
+            });
+        let stream = continued
+            .or_else(|| tails.iter().position(Option::is_none))
+            .unwrap_or_else(|| {
+                next = (next + 1) % stream_count;
+                next
+            });
+        for &dep in &task.dependencies {
+            if streams[dep] != stream {
+                cross_stream_dependents[dep] += 1;
+            }
+        }
+        tails[stream] = Some(i);
+        streams.push(stream);
+    }
+    Plan {
+        streams,
+        cross_stream_dependents,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::memory::{AsyncCopyDestination, DeviceBuffer};
+    use crate::quick_init;
+    use std::cell::RefCell;
+
+    fn graph_of(dependencies: &[&[usize]]) -> TaskGraph<'static> {
+        let mut graph = TaskGraph::new();
+        for deps in dependencies {
+            let deps: Vec<TaskId> = deps.iter().map(|&dep| TaskId(dep)).collect();
+            let _ = graph.add_task(&deps, |_| Ok(())).unwrap();
+        }
+        graph
+    }
+
+    #[test]
+    fn test_plan() {
+        // Two independent chains joined at the end.
+        let graph = graph_of(&[&[], &[], &[0], &[1], &[2, 3]]);
+        assert_eq!(
+            Plan {
+                streams: vec![0, 1, 0, 1, 0],
+                cross_stream_dependents: vec![0, 0, 0, 1, 0],
+            },
+            plan(&graph.tasks, 2)
+        );
+
+        // A fork: only one child can continue on its parent's stream.
+        let graph = graph_of(&[&[], &[0], &[0]]);
+        assert_eq!(
+            Plan {
+                streams: vec![0, 0, 1],
+                cross_stream_dependents: vec![1, 0, 0],
+            },
+            plan(&graph.tasks, 3)
+        );
+
+        // With one stream, nothing needs an event.
+        let graph = graph_of(&[&[], &[], &[0, 1]]);
+        assert_eq!(vec![0, 0, 0], plan(&graph.tasks, 1).cross_stream_dependents);
+    }
+
+    #[test]
+    fn test_run() {
+        let _context = quick_init().unwrap();
+        let a = RefCell::new(DeviceBuffer::from_slice(&[0u32; 256]).unwrap());
+        let b = RefCell::new(DeviceBuffer::from_slice(&[0u32; 256]).unwrap());
+        let mut host = [0u32; 256];
+
+        let pool = StreamPool::new(2).unwrap();
+        let mut graph = TaskGraph::new();
+        let first = graph
+            .add_task(&[], |stream| unsafe {
+                a.borrow_mut().async_copy_from(&[7u32; 256][..], stream)
+            })
+            .unwrap();
+        let other = graph.add_task(&[], |_| Ok(())).unwrap();
+        let second = graph
+            .add_task(&[other, first], |stream| unsafe {
+                b.borrow_mut().async_copy_from(&*a.borrow(), stream)
+            })
+            .unwrap();
+        assert_eq!(
+            Err(CudaError::InvalidValue),
+            graph.add_task(&[TaskId(5)], |_| Ok(())).map(|_| ())
+        );
+        let _ = graph
+            .add_task(&[second], |stream| unsafe {
+                b.borrow().async_copy_to(&mut host[..], stream)
+            })
+            .unwrap();
+        assert_eq!(4, graph.len());
+        graph.run(&pool).unwrap();
+        pool.synchronize().unwrap();
+        assert_eq!([7u32; 256], host);
+    }
+}
@@ -1,9 +1,12 @@
 //! Functions and types for working with CUDA kernels.
 
-use crate::context::{CacheConfig, SharedMemoryConfig};
-use crate::error::{CudaResult, ToResult};
+use crate::context::{CacheConfig, ContextToken, CurrentContext, SharedMemoryConfig};
+use crate::device::{Device, DeviceAttribute};
+use crate::error::{CudaError, CudaResult, ToResult};
 use crate::module::Module;
-use cuda_driver_sys::CUfunction;
+use crate::stream::Stream;
+use cuda_driver_sys::{CUfunction, CUDA_LAUNCH_PARAMS};
+use std::ffi::c_void;
 use std::marker::PhantomData;
 use std::mem::transmute;
 
@@ -117,6 +120,205 @@ impl<'a> From<&'a BlockSize> for BlockSize {
     }
 }
 
+/// Complete launch configuration for a kernel: the grid and block dimensions, plus the number of
+/// bytes of dynamic shared memory per thread block.
+///
+/// A `LaunchConfig` can be passed to the [`launch!`](../macro.launch.html) macro in place of the
+/// separate grid, block and shared memory parameters.
+///
+/// [Launch attributes](enum.LaunchAttribute.html) such as thread block cluster dimensions or
+/// programmatic stream serialization can be added with
+/// [`with_attribute`](#method.with_attribute). A launch with attributes uses `cuLaunchKernelEx`,
+/// which was added in CUDA 12.0, after the driver bindings RustaCUDA uses, so it is looked up in
+/// the driver library at runtime; with older drivers the launch returns `NotSupported`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LaunchConfig {
+    /// Dimensions of the grid, in blocks
+    pub grid: GridSize,
+    /// Dimensions of each thread block, in threads
+    pub block: BlockSize,
+    /// Bytes of dynamic shared memory available to each thread block
+    pub shared_mem_bytes: u32,
+    /// Launch attributes, applied in order
+    pub attributes: Vec<LaunchAttribute>,
+}
+impl LaunchConfig {
+    /// Create a launch configuration with the given grid and block sizes and no dynamic shared
+    /// memory.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rustacuda::function::LaunchConfig;
+    /// let config = LaunchConfig::new(4, (16, 16));
+    /// assert_eq!(config.block.y, 16);
+    /// ```
+    #[inline]
+    pub fn new<G, B>(grid: G, block: B) -> LaunchConfig
+    where
+        G: Into<GridSize>,
+        B: Into<BlockSize>,
+    {
+        LaunchConfig {
+            grid: grid.into(),
+            block: block.into(),
+            shared_mem_bytes: 0,
+            attributes: Vec::new(),
+        }
+    }
+
+    /// Set the number of bytes of dynamic shared memory available to each thread block.
+    #[inline]
+    pub fn with_shared_mem_bytes(mut self, shared_mem_bytes: u32) -> LaunchConfig {
+        self.shared_mem_bytes = shared_mem_bytes;
+        self
+    }
+
+    /// Add a launch attribute.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rustacuda::function::{LaunchAttribute, LaunchConfig};
+    /// let config = LaunchConfig::new(8, 128).with_attribute(LaunchAttribute::ClusterDimension(2, 1, 1));
+    /// assert_eq!(1, config.attributes.len());
+    /// ```
+    #[inline]
+    pub fn with_attribute(mut self, attribute: LaunchAttribute) -> LaunchConfig {
+        self.attributes.push(attribute);
+        self
+    }
+
+    /// Create a one-dimensional launch configuration with one thread per element, for `n`
+    /// elements.
+    ///
+    /// Blocks of 256 threads are used (or fewer, if the current device does not support blocks of
+    /// that size), with enough blocks to cover `n` elements. The kernel must still check its
+    /// index against `n`, since the last block may be partially full. If more blocks would be
+    /// needed than the device supports, the grid is clamped to the device maximum, so kernels
+    /// which may be launched on very large inputs should use a grid-stride loop.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidValue` if `n` is zero. If querying the current device fails, returns the
+    /// error from CUDA.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # let _context = rustacuda::quick_init().unwrap();
+    /// use rustacuda::function::LaunchConfig;
+    /// let config = LaunchConfig::elementwise(1000).unwrap();
+    /// assert!(config.grid.x * config.block.x >= 1000);
+    /// ```
+    pub fn elementwise(n: usize) -> CudaResult<LaunchConfig> {
+        if n == 0 {
+            return Err(CudaError::InvalidValue);
+        }
+        let device = CurrentContext::get_device()?;
+        let max_threads = device_limit(device, DeviceAttribute::MaxThreadsPerBlock)?
+            .min(device_limit(device, DeviceAttribute::MaxBlockDimX)?);
+        let max_blocks = device_limit(device, DeviceAttribute::MaxGridDimX)?;
+
+        let block = ELEMENTWISE_BLOCK_SIZE.min(max_threads);
+        Ok(LaunchConfig::new(
+            blocks_for(n, block).min(max_blocks),
+            block,
+        ))
+    }
+
+    /// Create a two-dimensional launch configuration with one thread per pixel of a `width` by
+    /// `height` image, using square blocks of `tile` by `tile` threads.
+    ///
+    /// The tile size is reduced if the current device does not support blocks that large, and the
+    /// grid is clamped to the device maximum as with [`elementwise`](#method.elementwise). The
+    /// kernel must check its coordinates against `width` and `height`, since the blocks at the
+    /// right and bottom edges may be partially outside the image.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidValue` if `width`, `height` or `tile` is zero. If querying the current
+    /// device fails, returns the error from CUDA.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # let _context = rustacuda::quick_init().unwrap();
+    /// use rustacuda::function::LaunchConfig;
+    /// let config = LaunchConfig::tiled_2d(1920, 1080, 16).unwrap();
+    /// assert_eq!((16, 16), (config.block.x, config.block.y));
+    /// assert_eq!((120, 68), (config.grid.x, config.grid.y));
+    /// ```
+    pub fn tiled_2d(width: usize, height: usize, tile: u32) -> CudaResult<LaunchConfig> {
+        if width == 0 || height == 0 || tile == 0 {
+            return Err(CudaError::InvalidValue);
+        }
+        let device = CurrentContext::get_device()?;
+        let max_threads = device_limit(device, DeviceAttribute::MaxThreadsPerBlock)?;
+        let mut tile = tile
+            .min(device_limit(device, DeviceAttribute::MaxBlockDimX)?)
+            .min(device_limit(device, DeviceAttribute::MaxBlockDimY)?);
+        while tile * tile > max_threads {
+            tile -= 1;
+        }
+
+        let grid_x =
+            blocks_for(width, tile).min(device_limit(device, DeviceAttribute::MaxGridDimX)?);
+        let grid_y =
+            blocks_for(height, tile).min(device_limit(device, DeviceAttribute::MaxGridDimY)?);
+        Ok(LaunchConfig::new((grid_x, grid_y), (tile, tile)))
+    }
+}
+
+/// An attribute of a kernel launch, which changes how the driver schedules it.
+///
+/// Attributes are added to a [`LaunchConfig`](struct.LaunchConfig.html) with
+/// [`with_attribute`](struct.LaunchConfig.html#method.with_attribute).
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum LaunchAttribute {
+    /// Launch the thread blocks in clusters of the given x, y and z dimensions, in blocks. The grid
+    /// dimensions must be multiples of the cluster dimensions. Requires compute capability 9.0.
+    ClusterDimension(u32, u32, u32),
+
+    /// Whether the kernel may start before the previous kernel in the stream has completed, once
+    /// every block of that kernel has triggered programmatic launch completion or exited. The
+    /// kernel must wait for the previous kernel's results with `cudaGridDependencySynchronize`.
+    /// Requires compute capability 9.0.
+    ProgrammaticStreamSerialization(bool),
+
+    /// The memory synchronization domain of the kernel, which limits the memory operations that
+    /// its fences wait for.
+    MemSyncDomain(MemSyncDomain),
+}
+
+/// Memory synchronization domains, for
+/// [`LaunchAttribute::MemSyncDomain`](enum.LaunchAttribute.html#variant.MemSyncDomain).
+#[repr(u32)]
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+pub enum MemSyncDomain {
+    /// The domain of ordinary kernels
+    Default = 0,
+    /// The domain of kernels which communicate with other devices, such as collective
+    /// communication kernels, so that their fences don't wait for unrelated local traffic
+    Remote = 1,
+
+    #[doc(hidden)]
+    __Nonexhaustive,
+}
+
+/// Block size used by [`LaunchConfig::elementwise`](struct.LaunchConfig.html#method.elementwise).
+const ELEMENTWISE_BLOCK_SIZE: u32 = 256;
+
+fn device_limit(device: Device, attr: DeviceAttribute) -> CudaResult<u32> {
+    Ok(device.get_attribute(attr)?.max(1) as u32)
+}
+
+/// Number of blocks of `block` threads needed to cover `n` items, saturating at `u32::MAX`.
+fn blocks_for(n: usize, block: u32) -> u32 {
+    n.div_ceil(block as usize).min(u32::MAX as usize) as u32
+}
+
 /// All supported function attributes for [Function::get_attribute](struct.Function.html#method.get_attribute)
 #[repr(u32)]
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
@@ -157,16 +359,35 @@ pub enum FunctionAttribute {
 #[derive(Debug)]
 pub struct Function<'a> {
     inner: CUfunction,
+    context: Option<ContextToken>,
     module: PhantomData<&'a Module>,
 }
+// Functions can be launched and their attributes read from any thread.
+unsafe impl Send for Function<'_> {}
+unsafe impl Sync for Function<'_> {}
 impl<'a> Function<'a> {
-    pub(crate) fn new(inner: CUfunction, _module: &Module) -> Function {
+    pub(crate) fn new(inner: CUfunction, module: &Module) -> Function<'_> {
         Function {
             inner,
+            context: module.context(),
             module: PhantomData,
         }
     }
 
+    /// Returns `ContextIsDestroyed` if the context this function was loaded into has been
+    /// destroyed. Only checked in debug builds or with the `context-check` feature.
+    pub(crate) fn check_context(&self) -> CudaResult<()> {
+        ContextToken::check(self.context)
+    }
+
+    /// Returns `ContextMismatch` if this function was loaded into a different context than the
+    /// one `stream` belongs to. Like `check_context`, this is only checked in debug builds or with
+    /// the `context-check` feature, and
+    /// only for contexts created by RustaCUDA.
+    pub(crate) fn check_stream(&self, stream: &Stream) -> CudaResult<()> {
+        ContextToken::check_stream(self.context, stream, "The kernel function")
+    }
+
     /// Returns information about a function.
     ///
     /// # Examples
@@ -191,12 +412,12 @@ impl<'a> Function<'a> {
     pub fn get_attribute(&self, attr: FunctionAttribute) -> CudaResult<i32> {
         unsafe {
             let mut val = 0i32;
-            cuda_driver_sys::cuFuncGetAttribute(
+            driver_call!(cuda_driver_sys::cuFuncGetAttribute(
                 &mut val as *mut i32,
                 // This should be safe, as the repr and values of FunctionAttribute should match.
                 ::std::mem::transmute(attr),
                 self.inner,
-            )
+            ))
             .to_result()?;
             Ok(val)
         }
@@ -232,7 +453,13 @@ impl<'a> Function<'a> {
     /// # }
     /// ```
     pub fn set_cache_config(&mut self, config: CacheConfig) -> CudaResult<()> {
-        unsafe { cuda_driver_sys::cuFuncSetCacheConfig(self.inner, transmute(config)).to_result() }
+        unsafe {
+            driver_call!(cuda_driver_sys::cuFuncSetCacheConfig(
+                self.inner,
+                transmute::<CacheConfig, cuda_driver_sys::CUfunc_cache>(config)
+            ))
+            .to_result()
+        }
     }
 
     /// Sets the preferred shared memory configuration for this function.
@@ -260,7 +487,13 @@ impl<'a> Function<'a> {
     /// # }
     /// ```
     pub fn set_shared_memory_config(&mut self, cfg: SharedMemoryConfig) -> CudaResult<()> {
-        unsafe { cuda_driver_sys::cuFuncSetSharedMemConfig(self.inner, transmute(cfg)).to_result() }
+        unsafe {
+            driver_call!(cuda_driver_sys::cuFuncSetSharedMemConfig(
+                self.inner,
+                transmute::<SharedMemoryConfig, cuda_driver_sys::CUsharedconfig>(cfg)
+            ))
+            .to_result()
+        }
     }
 
     pub(crate) fn to_inner(&self) -> CUfunction {
@@ -268,6 +501,100 @@ impl<'a> Function<'a> {
     }
 }
 
+bitflags! {
+    /// Bit flags for controlling the synchronization performed by
+    /// [`launch_cooperative_multi_device`](fn.launch_cooperative_multi_device.html).
+    pub struct CooperativeLaunchFlags: u32 {
+        /// No flags set. The kernels wait for all prior work in every participating stream before
+        /// starting, and later work in every participating stream waits for all of the kernels to
+        /// finish.
+        const DEFAULT = 0x00;
+
+        /// Each kernel only waits for prior work in its own stream before starting.
+        const NO_PRE_LAUNCH_SYNC = 0x01;
+
+        /// Later work in each stream only waits for the kernel in that stream to finish.
+        const NO_POST_LAUNCH_SYNC = 0x02;
+    }
+}
+
+/// One device's part of a cooperative multi-device launch.
+///
+/// See [`launch_cooperative_multi_device`](fn.launch_cooperative_multi_device.html).
+#[derive(Debug)]
+pub struct MultiDeviceLaunch<'a> {
+    /// The kernel to launch. It must be loaded into the context that `stream` belongs to.
+    pub function: &'a Function<'a>,
+    /// The launch configuration.
+    pub config: LaunchConfig,
+    /// The stream to launch the kernel in. Each launch must use a stream on a different device.
+    pub stream: &'a Stream,
+    /// Pointers to each of the kernel's arguments, as built by the [`launch!`](../macro.launch.html)
+    /// macro: `&[&arg1 as *const _ as *mut c_void, ...]`.
+    pub args: &'a [*mut c_void],
+}
+
+/// Launch a kernel cooperatively on several devices at once.
+///
+/// The kernels may synchronize across all participating devices using the grid group
+/// synchronization functions of the cooperative groups API. Each device must support cooperative
+/// multi-device launches (see `DeviceAttribute::CooperativeMultiDeviceLaunch`), and the total
+/// number of blocks launched on each device must not exceed what can be simultaneously resident
+/// on it.
+///
+/// # Errors
+///
+/// All launches must use the same launch configuration and the same number of arguments, since
+/// the driver requires the kernels to be identical on every device, and launch attributes are not
+/// supported. If `launches` is empty or this is not the case, returns `InvalidValue` without
+/// launching anything. If too many blocks are
+/// requested, returns `CooperativeLaunchTooLarge`. Otherwise, returns the error from CUDA.
+///
+/// # Safety
+///
+/// As with the [`launch!`](../macro.launch.html) macro, the kernel must accept the arguments given
+/// to it, and the host must not access memory the kernels could write until the streams have been
+/// synchronized. The argument pointers must remain valid until this function returns.
+pub unsafe fn launch_cooperative_multi_device(
+    launches: &[MultiDeviceLaunch],
+    flags: CooperativeLaunchFlags,
+) -> CudaResult<()> {
+    let first = launches.first().ok_or(CudaError::InvalidValue)?;
+    if !first.config.attributes.is_empty()
+        || launches
+            .iter()
+            .any(|l| l.config != first.config || l.args.len() != first.args.len())
+    {
+        return Err(CudaError::InvalidValue);
+    }
+    for launch in launches {
+        launch.function.check_context()?;
+        launch.function.check_stream(launch.stream)?;
+    }
+
+    let mut params: Vec<CUDA_LAUNCH_PARAMS> = launches
+        .iter()
+        .map(|l| CUDA_LAUNCH_PARAMS {
+            function: l.function.to_inner(),
+            gridDimX: l.config.grid.x,
+            gridDimY: l.config.grid.y,
+            gridDimZ: l.config.grid.z,
+            blockDimX: l.config.block.x,
+            blockDimY: l.config.block.y,
+            blockDimZ: l.config.block.z,
+            sharedMemBytes: l.config.shared_mem_bytes,
+            hStream: l.stream.as_inner(),
+            kernelParams: l.args.as_ptr() as *mut *mut c_void,
+        })
+        .collect();
+    driver_call!(cuda_driver_sys::cuLaunchCooperativeKernelMultiDevice(
+        params.as_mut_ptr(),
+        params.len() as u32,
+        flags.bits(),
+    ))
+    .to_result()
+}
+
 /// Launch a kernel function asynchronously.
 ///
 /// # Syntax:
@@ -301,6 +628,25 @@ impl<'a> Function<'a> {
 /// In this variant, the `function` parameter must be a variable. Use this form to avoid looking up
 /// the kernel function for each call.
 ///
+/// Either form also accepts a [`LaunchConfig`](function/struct.LaunchConfig.html) in place of the
+/// grid, block and shared memory size parameters, which also carries any launch attributes:
+///
+/// ```ignore
+/// let result = launch!(function<<<config, stream>>>(parameter1, parameter2...));
+/// ```
+///
+/// With the `verification` feature enabled, the four-parameter forms can be followed by
+/// `verify(output, tolerance, reference)` to check the kernel against a host implementation. The
+/// `reference` closure is given a host copy of `output` as it was before the launch and must fill
+/// in the expected results; the macro then returns a
+/// [`VerificationError`](verification/enum.VerificationError.html) if any element of `output`
+/// differs by more than `tolerance`. See the [`verification`](verification/index.html) module.
+///
+/// ```ignore
+/// let result = launch!(function<<<grid, block, shared_memory_size, stream>>>(parameter1, parameter2...)
+///     verify(output, tolerance, |expected| ...));
+/// ```
+///
 /// # Safety
 ///
 /// Launching kernels must be done in an `unsafe` block. Calling a kernel is similar to calling a
@@ -381,13 +727,44 @@ impl<'a> Function<'a> {
 ///
 #[macro_export]
 macro_rules! launch {
+    ($module:ident . $function:ident <<<$config:expr, $stream:ident>>>( $( $arg:expr),* )) => {
+        {
+            let name = std::ffi::CString::new(stringify!($function))
+                .expect("Rust identifiers never contain nul bytes");
+            let function = $module.get_function(&name);
+            match function {
+                Ok(f) => launch!(f<<<$config, $stream>>>( $($arg),* ) ),
+                Err(e) => Err(e.into()),
+            }
+        }
+    };
+    ($function:ident <<<$config:expr, $stream:ident>>>( $( $arg:expr),* )) => {
+        {
+            let config: $crate::function::LaunchConfig = $config;
+            fn assert_impl_devicecopy<T: $crate::memory::DeviceCopy>(_val: T) {}
+            if false {
+                $(
+                    assert_impl_devicecopy($arg);
+                )*
+            };
+
+            $stream.launch_with_config(&$function, &config,
+                &[
+                    $(
+                        &$arg as *const _ as *mut ::std::ffi::c_void,
+                    )*
+                ]
+            )
+        }
+    };
     ($module:ident . $function:ident <<<$grid:expr, $block:expr, $shared:expr, $stream:ident>>>( $( $arg:expr),* )) => {
         {
-            let name = std::ffi::CString::new(stringify!($function)).unwrap();
+            let name = std::ffi::CString::new(stringify!($function))
+                .expect("Rust identifiers never contain nul bytes");
             let function = $module.get_function(&name);
             match function {
                 Ok(f) => launch!(f<<<$grid, $block, $shared, $stream>>>( $($arg),* ) ),
-                Err(e) => Err(e),
+                Err(e) => Err(e.into()),
             }
         }
     };
@@ -409,6 +786,28 @@ macro_rules! launch {
             )
         }
     };
+    ($module:ident . $function:ident <<<$grid:expr, $block:expr, $shared:expr, $stream:ident>>>( $( $arg:expr),* ) verify($output:expr, $tolerance:expr, $reference:expr)) => {
+        {
+            let name = std::ffi::CString::new(stringify!($function))
+                .expect("Rust identifiers never contain nul bytes");
+            let function = $module.get_function(&name);
+            match function {
+                Ok(f) => launch!(f<<<$grid, $block, $shared, $stream>>>( $($arg),* ) verify($output, $tolerance, $reference)),
+                Err(e) => Err($crate::verification::VerificationError::from($crate::error::CudaError::from(e))),
+            }
+        }
+    };
+    ($function:ident <<<$grid:expr, $block:expr, $shared:expr, $stream:ident>>>( $( $arg:expr),* ) verify($output:expr, $tolerance:expr, $reference:expr)) => {
+        {
+            match $crate::verification::HostMirror::capture(&$stream, &$output) {
+                Ok(mirror) => match launch!($function<<<$grid, $block, $shared, $stream>>>( $($arg),* )) {
+                    Ok(()) => mirror.verify(&$stream, &$output, $tolerance, $reference),
+                    Err(e) => Err($crate::verification::VerificationError::from(e)),
+                },
+                Err(e) => Err($crate::verification::VerificationError::from(e)),
+            }
+        }
+    };
 }
 
 #[cfg(test)]
@@ -444,4 +843,118 @@ mod test {
         }
         Ok(())
     }
+
+    #[test]
+    fn test_cooperative_multi_device_mismatched_config() -> Result<(), Box<dyn Error>> {
+        let _context = quick_init();
+        let ptx_text = CString::new(include_str!("../resources/add.ptx"))?;
+        let module = Module::load_from_string(&ptx_text)?;
+        let function_name = CString::new("sum")?;
+        let function = module.get_function(&function_name)?;
+        let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+
+        let launch = |config| MultiDeviceLaunch {
+            function: &function,
+            config,
+            stream: &stream,
+            args: &[],
+        };
+        let result = unsafe {
+            launch_cooperative_multi_device(
+                &[
+                    launch(LaunchConfig::new(1, 32)),
+                    launch(LaunchConfig::new(2, 32)),
+                ],
+                CooperativeLaunchFlags::DEFAULT,
+            )
+        };
+        assert_eq!(Err(CudaError::InvalidValue), result);
+
+        let result =
+            unsafe { launch_cooperative_multi_device(&[], CooperativeLaunchFlags::DEFAULT) };
+        assert_eq!(Err(CudaError::InvalidValue), result);
+        Ok(())
+    }
+
+    #[test]
+    fn test_launch_with_config() -> Result<(), Box<dyn Error>> {
+        let _context = quick_init();
+        let ptx_text = CString::new(include_str!("../resources/add.ptx"))?;
+        let module = Module::load_from_string(&ptx_text)?;
+
+        unsafe {
+            let mut in_x = DeviceBuffer::from_slice(&[2.0f32; 128])?;
+            let mut in_y = DeviceBuffer::from_slice(&[1.0f32; 128])?;
+            let mut out: DeviceBuffer<f32> = DeviceBuffer::uninitialized(128)?;
+
+            let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+            let config = LaunchConfig::new(1, 128);
+            launch!(module.sum<<<config, stream>>>(in_x.as_device_ptr(), in_y.as_device_ptr(), out.as_device_ptr(), out.len()))?;
+            stream.synchronize()?;
+
+            let mut out_host = [0f32; 128];
+            out.copy_to(&mut out_host[..])?;
+            for x in out_host.iter() {
+                assert_eq!(3, *x as u32);
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_launch_with_attributes() -> Result<(), Box<dyn Error>> {
+        let _context = quick_init()?;
+        let ptx_text = CString::new(include_str!("../resources/add.ptx"))?;
+        let module = Module::load_from_string(&ptx_text)?;
+
+        unsafe {
+            let mut in_x = DeviceBuffer::from_slice(&[2.0f32; 128])?;
+            let mut in_y = DeviceBuffer::from_slice(&[1.0f32; 128])?;
+            let mut out = DeviceBuffer::from_slice(&[0.0f32; 128])?;
+
+            let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+            let config = LaunchConfig::new(1, 128)
+                .with_attribute(LaunchAttribute::MemSyncDomain(MemSyncDomain::Default))
+                .with_attribute(LaunchAttribute::ProgrammaticStreamSerialization(false));
+            let result = launch!(module.sum<<<config, stream>>>(in_x.as_device_ptr(), in_y.as_device_ptr(), out.as_device_ptr(), out.len()));
+            if result == Err(CudaError::NotSupported) {
+                // Drivers older than CUDA 12.0 can't launch with attributes.
+                return Ok(());
+            }
+            result?;
+            stream.synchronize()?;
+
+            let mut out_host = [0f32; 128];
+            out.copy_to(&mut out_host[..])?;
+            for x in out_host.iter() {
+                assert_eq!(3, *x as u32);
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_launch_presets() -> Result<(), Box<dyn Error>> {
+        let _context = quick_init()?;
+        let config = LaunchConfig::elementwise(1000)?;
+        assert_eq!((4, 256), (config.grid.x, config.block.x));
+
+        let config = LaunchConfig::tiled_2d(100, 33, 16)?;
+        assert_eq!((7, 3), (config.grid.x, config.grid.y));
+        assert_eq!((16, 16), (config.block.x, config.block.y));
+
+        // No current device supports 64x64 thread blocks, so the tile must shrink.
+        let config = LaunchConfig::tiled_2d(100, 100, 64)?;
+        assert!(config.block.x * config.block.y <= 1024);
+
+        assert_eq!(
+            Some(CudaError::InvalidValue),
+            LaunchConfig::elementwise(0).err()
+        );
+        assert_eq!(
+            Some(CudaError::InvalidValue),
+            LaunchConfig::tiled_2d(1, 1, 0).err()
+        );
+        Ok(())
+    }
 }
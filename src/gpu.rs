@@ -0,0 +1,257 @@
+//! A batteries-included entry point for simple programs.
+//!
+//! [`Gpu`](struct.Gpu.html) bundles together everything a typical program needs to run kernels
+//! on one device: a context, a stream, a cache of loaded modules, and a pool of page-locked
+//! staging buffers for fast transfers. It is meant to get new users from zero to a running kernel
+//! quickly. Everything it does can also be done with the lower-level modules, which remain the
+//! way to go for multiple streams, multiple devices or custom context flags.
+//!
+//! # Examples
+//!
+//! ```
+//! # use std::error::Error;
+//! # fn main() -> Result<(), Box<dyn Error>> {
+//! use rustacuda::function::LaunchConfig;
+//! use rustacuda::gpu::Gpu;
+//! use std::ffi::c_void;
+//!
+//! let gpu = Gpu::new(0)?;
+//! gpu.load_module("add", include_str!("../resources/add.ptx"))?;
+//!
+//! let mut x = gpu.upload(&[1.0f32; 10])?;
+//! let mut y = gpu.upload(&[2.0f32; 10])?;
+//! let mut out = gpu.upload(&[0.0f32; 10])?;
+//! let (mut x_ptr, mut y_ptr, mut out_ptr, mut n) =
+//!     (x.as_device_ptr(), y.as_device_ptr(), out.as_device_ptr(), 10i32);
+//! unsafe {
+//!     gpu.launch("add", "sum", LaunchConfig::new(1, 10), &[
+//!         &mut x_ptr as *mut _ as *mut c_void,
+//!         &mut y_ptr as *mut _ as *mut c_void,
+//!         &mut out_ptr as *mut _ as *mut c_void,
+//!         &mut n as *mut _ as *mut c_void,
+//!     ])?;
+//! }
+//! assert_eq!(vec![3.0f32; 10], gpu.download(&out)?);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::context::{Context, ContextFlags};
+use crate::device::Device;
+use crate::error::{CudaError, CudaResult, ToResult};
+use crate::function::LaunchConfig;
+use crate::memory::{DeviceBuffer, DeviceCopy, DeviceSlice, StagingPool};
+use crate::module::Module;
+use crate::stream::{Stream, StreamFlags};
+use crate::CudaFlags;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ffi::{c_void, CString};
+use std::mem::{size_of, size_of_val};
+use std::ptr;
+use std::rc::Rc;
+
+/// A device together with a context, a stream, a module cache and a staging pool.
+///
+/// See the [module-level documentation](index.html) for an example.
+///
+/// Creating a `Gpu` creates a new context and makes it current on the calling thread, so a `Gpu`
+/// should be used from the thread that created it.
+#[derive(Debug)]
+pub struct Gpu {
+    // Fields are dropped in declaration order, and everything must be dropped before the context.
+    staging: StagingPool<u8>,
+    modules: RefCell<HashMap<String, Rc<Module>>>,
+    stream: Stream,
+    device: Device,
+    context: Context,
+}
+impl Gpu {
+    /// Initialize CUDA and create a `Gpu` for the device with the given index.
+    ///
+    /// # Errors
+    ///
+    /// If there is no device with the given index, returns `InvalidDevice`. Otherwise, if
+    /// initialization fails, returns the CUDA error value.
+    pub fn new(device_index: u32) -> CudaResult<Gpu> {
+        crate::init(CudaFlags::empty())?;
+        let device = Device::get_device(device_index)?;
+        let context =
+            Context::create_and_push(ContextFlags::MAP_HOST | ContextFlags::SCHED_AUTO, device)?;
+        let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+        Ok(Gpu {
+            staging: StagingPool::new(),
+            modules: RefCell::new(HashMap::new()),
+            stream,
+            device,
+            context,
+        })
+    }
+
+    /// Returns the device.
+    pub fn device(&self) -> Device {
+        self.device
+    }
+
+    /// Returns the context.
+    pub fn context(&self) -> &Context {
+        &self.context
+    }
+
+    /// Returns the stream which `upload`, `download` and `launch` use.
+    pub fn stream(&self) -> &Stream {
+        &self.stream
+    }
+
+    /// Load a module from PTX source and cache it under `name`. If a module with that name was
+    /// already loaded, returns the cached module without loading `ptx` again.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidValue` if `ptx` contains a nul byte. Otherwise, if the module can't be
+    /// loaded, returns the CUDA error value.
+    pub fn load_module(&self, name: &str, ptx: &str) -> CudaResult<Rc<Module>> {
+        if let Some(module) = self.module(name) {
+            return Ok(module);
+        }
+        let ptx = CString::new(ptx).map_err(|_| CudaError::InvalidValue)?;
+        let module = Rc::new(Module::load_from_string(&ptx)?);
+        let _ = self
+            .modules
+            .borrow_mut()
+            .insert(name.to_owned(), module.clone());
+        Ok(module)
+    }
+
+    /// Returns the module cached under `name`, if there is one.
+    pub fn module(&self, name: &str) -> Option<Rc<Module>> {
+        self.modules.borrow().get(name).cloned()
+    }
+
+    /// Copy `data` into a new device buffer, staging it through page-locked memory.
+    ///
+    /// # Errors
+    ///
+    /// If allocating memory or copying fails, returns the CUDA error value.
+    pub fn upload<T: DeviceCopy>(&self, data: &[T]) -> CudaResult<DeviceBuffer<T>> {
+        let bytes = size_of_val(data);
+        if bytes == 0 {
+            return DeviceBuffer::from_slice(data);
+        }
+        unsafe {
+            let mut staging = self.staging.get_uninitialized(bytes)?;
+            ptr::copy_nonoverlapping(data.as_ptr() as *const u8, staging.as_mut_ptr(), bytes);
+            let mut buffer = DeviceBuffer::uninitialized(data.len())?;
+            driver_call!(cuda_driver_sys::cuMemcpyHtoDAsync_v2(
+                buffer.as_device_ptr().as_raw_mut() as u64,
+                staging.as_ptr() as *const c_void,
+                bytes,
+                self.stream.as_inner(),
+            ))
+            .to_result()?;
+            // The staging buffer must not be reused until the copy has finished.
+            self.stream.synchronize()?;
+            Ok(buffer)
+        }
+    }
+
+    /// Copy the contents of a device buffer into a new `Vec`, staging it through page-locked
+    /// memory. This waits for all work previously submitted to the stream, such as kernel
+    /// launches, to complete.
+    ///
+    /// # Errors
+    ///
+    /// If copying fails, returns the CUDA error value. Errors from previously launched kernels
+    /// may also be returned here.
+    pub fn download<T: DeviceCopy>(&self, buffer: &DeviceSlice<T>) -> CudaResult<Vec<T>> {
+        let bytes = buffer.len() * size_of::<T>();
+        let mut vec = Vec::with_capacity(buffer.len());
+        if bytes == 0 {
+            self.stream.synchronize()?;
+            return Ok(vec);
+        }
+        unsafe {
+            let staging = self.staging.get_uninitialized(bytes)?;
+            driver_call!(cuda_driver_sys::cuMemcpyDtoHAsync_v2(
+                staging.as_ptr() as *mut c_void,
+                buffer.as_ptr() as u64,
+                bytes,
+                self.stream.as_inner(),
+            ))
+            .to_result()?;
+            self.stream.synchronize()?;
+            ptr::copy_nonoverlapping(staging.as_ptr(), vec.as_mut_ptr() as *mut u8, bytes);
+            vec.set_len(buffer.len());
+        }
+        Ok(vec)
+    }
+
+    /// Launch the kernel `kernel` from the module cached under `module` on this `Gpu`'s stream.
+    /// Like all kernel launches, this is asynchronous; use `download` or `synchronize` to wait
+    /// for it.
+    ///
+    /// `args` holds a pointer to each of the kernel's arguments, in order.
+    ///
+    /// # Errors
+    ///
+    /// Returns `NotFound` if no module is cached under `module`, `InvalidValue` if `kernel`
+    /// contains a nul byte, and `SymbolNotFound` if the module has no kernel named `kernel`.
+    /// Otherwise, if the launch fails, returns the CUDA error value.
+    ///
+    /// # Safety
+    ///
+    /// As with the [`launch!`](../macro.launch.html) macro, the kernel must accept the arguments
+    /// given to it, and the memory it accesses must remain valid until it completes.
+    #[track_caller]
+    pub unsafe fn launch(
+        &self,
+        module: &str,
+        kernel: &str,
+        config: LaunchConfig,
+        args: &[*mut c_void],
+    ) -> CudaResult<()> {
+        let module = self.module(module).ok_or(CudaError::NotFound)?;
+        let kernel = CString::new(kernel).map_err(|_| CudaError::InvalidValue)?;
+        let function = module.get_function(&kernel)?;
+        self.stream.launch_with_config(&function, &config, args)
+    }
+
+    /// Wait for all work submitted to this `Gpu`'s stream to complete.
+    ///
+    /// # Errors
+    ///
+    /// Errors from previously launched kernels may be returned here.
+    pub fn synchronize(&self) -> CudaResult<()> {
+        self.stream.synchronize()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_upload_download() {
+        let gpu = Gpu::new(0).unwrap();
+        let buffer = gpu.upload(&[1u64, 2, 3]).unwrap();
+        assert_eq!(vec![1u64, 2, 3], gpu.download(&buffer).unwrap());
+
+        let empty = gpu.upload::<u32>(&[]).unwrap();
+        assert!(gpu.download(&empty).unwrap().is_empty());
+        // Both transfers reused the same staging buffer.
+        assert_eq!(1, gpu.staging.cached_buffers());
+    }
+
+    #[test]
+    fn test_module_cache() {
+        let gpu = Gpu::new(0).unwrap();
+        let ptx = include_str!("../resources/add.ptx");
+        let first = gpu.load_module("add", ptx).unwrap();
+        let second = gpu.load_module("add", "not loaded again").unwrap();
+        assert!(Rc::ptr_eq(&first, &second));
+        assert!(gpu.module("other").is_none());
+
+        let result = unsafe { gpu.launch("other", "sum", LaunchConfig::new(1, 1), &[]) };
+        assert_eq!(Err(CudaError::NotFound), result);
+    }
+}
@@ -0,0 +1,1745 @@
+//! CUDA graphs, which record a sequence of work once and launch it repeatedly.
+//!
+//! Launching each kernel of a pipeline individually costs a few microseconds of CPU time per
+//! launch. A graph is recorded once, for example by capturing the work submitted to a stream with
+//! [`Stream::begin_capture`](../stream/struct.Stream.html#method.begin_capture) and
+//! [`Stream::end_capture`](../stream/struct.Stream.html#method.end_capture), and then
+//! instantiated into a [`GraphExec`](struct.GraphExec.html), which launches the whole graph with a
+//! single call.
+//!
+//! Graphs can also be built explicitly, node by node, which suits schedulers that construct the
+//! DAG programmatically rather than by replaying stream work. [`Graph`](struct.Graph.html) has
+//! methods to add kernel, memcpy, memset, host, child-graph and empty nodes, each of which takes
+//! the nodes it depends on; further edges can be added with
+//! [`add_dependencies`](struct.Graph.html#method.add_dependencies). Memory allocation and free
+//! nodes let a graph own its temporary buffers, which the driver recycles between launches.
+//!
+//! Kernel arguments are copied into the graph when it is built, so changing them normally means
+//! updating the executable graph. For tight control loops which change their parameters on every
+//! launch, a [`PinnedParams`](struct.PinnedParams.html) block avoids that: the kernels read their
+//! parameters through a device pointer, and a memcpy node at the start of the graph refreshes them
+//! from page-locked host memory on every launch, without any allocation.
+//!
+//! An instantiated graph can be [uploaded](struct.GraphExec.html#method.upload) to the device
+//! ahead of its first launch, so that the first launch is as fast as the later ones.
+//!
+//! The kernel arguments of an instantiated graph can be changed between launches with
+//! [`GraphExec::set_params_batch`](struct.GraphExec.html#method.set_params_batch), so that (for
+//! example) each launch can read from a different input buffer without re-instantiating the
+//! graph. When more than the arguments change, a graph with the same topology (such as one
+//! captured again from the same code) can be applied to an instantiated graph with
+//! [`GraphExec::update`](struct.GraphExec.html#method.update), which is much cheaper than
+//! instantiating it.
+//!
+//! Graphs built and instantiated elsewhere, such as by a library or through the driver API
+//! directly, can be wrapped with [`GraphExec::from_raw`](struct.GraphExec.html#method.from_raw)
+//! and [`GraphNode::from_raw`](struct.GraphNode.html#method.from_raw).
+//!
+//! Detailed documentation about graphs can be found in the
+//! [CUDA Driver API](https://docs.nvidia.com/cuda/cuda-driver-api/group__CUDA__GRAPH.html)
+
+#[cfg(feature = "cuda-11-4")]
+use crate::context::CurrentContext;
+use crate::error::{CudaError, CudaResult, DropExpect, DropResult, ToResult};
+use crate::event::{Event, EventFlags};
+use crate::function::{BlockSize, Function, GridSize};
+use crate::memory::{
+    AsyncCopyDestination, DeviceBox, DeviceCopy, DevicePointer, DeviceSlice, LockedBuffer,
+};
+use crate::stream::Stream;
+use cuda_driver_sys::CUgraphExecUpdateResult;
+#[cfg(feature = "cuda-11-1")]
+use cuda_driver_sys::CUresult;
+#[cfg(feature = "cuda-11-1")]
+use cuda_driver_sys::CUstream;
+use cuda_driver_sys::{
+    CUcontext, CUdeviceptr, CUgraph, CUgraphExec, CUgraphNode, CUmemorytype, CUDA_HOST_NODE_PARAMS,
+    CUDA_KERNEL_NODE_PARAMS, CUDA_MEMCPY3D, CUDA_MEMSET_NODE_PARAMS,
+};
+use std::cell::RefCell;
+use std::error::Error;
+use std::fmt;
+use std::marker::PhantomData;
+use std::mem::{self, size_of, size_of_val};
+use std::os::raw::c_void;
+use std::panic;
+use std::ptr;
+use std::sync::Arc;
+
+/// A closure run by a host node. Executable graphs share the closures of the graph they were
+/// instantiated from, since they can outlive it.
+type HostCallback = Arc<Box<dyn Fn() + Send + Sync>>;
+
+/// A graph of work, such as kernel launches and memory copies, with dependencies between them.
+///
+/// See the module-level documentation for more information.
+pub struct Graph {
+    inner: CUgraph,
+    host_callbacks: RefCell<Vec<HostCallback>>,
+}
+// A graph can be moved to another thread, but the driver doesn't synchronize access to graphs, so
+// it is not Sync.
+unsafe impl Send for Graph {}
+impl Graph {
+    /// Create a new, empty graph.
+    ///
+    /// # Errors
+    ///
+    /// If the graph cannot be created, returns the CUDA error value.
+    pub fn new() -> CudaResult<Graph> {
+        let mut inner: CUgraph = ptr::null_mut();
+        unsafe {
+            driver_call!(cuda_driver_sys::cuGraphCreate(&mut inner, 0)).to_result()?;
+        }
+        Ok(Graph::from_inner(inner))
+    }
+
+    // Wrap a graph created by the driver, such as the result of a stream capture. Takes ownership.
+    pub(crate) fn from_inner(inner: CUgraph) -> Graph {
+        Graph {
+            inner,
+            host_callbacks: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Add an empty node, which does no work. Empty nodes are useful to join several
+    /// dependencies into one, or to fork one dependency into several.
+    ///
+    /// # Errors
+    ///
+    /// If a dependency doesn't belong to this graph or the node cannot be added, returns the CUDA
+    /// error value.
+    pub fn add_empty_node(&self, dependencies: &[GraphNode<'_>]) -> CudaResult<GraphNode<'_>> {
+        let dependencies = raw_nodes(dependencies);
+        let mut node = ptr::null_mut();
+        unsafe {
+            driver_call!(cuda_driver_sys::cuGraphAddEmptyNode(
+                &mut node,
+                self.inner,
+                dependencies.as_ptr(),
+                dependencies.len()
+            ))
+            .to_result()?;
+        }
+        Ok(GraphNode::from_inner(node))
+    }
+
+    /// Add a node which launches `func`, after all of `dependencies` have completed.
+    ///
+    /// The arguments are given in the same form as for [`launch!`](../macro.launch.html): one
+    /// pointer to each argument value, in order. The argument values are copied, so they only need
+    /// to live for the duration of this call.
+    ///
+    /// # Errors
+    ///
+    /// If a dependency doesn't belong to this graph or the node cannot be added, returns the CUDA
+    /// error value.
+    ///
+    /// # Safety
+    ///
+    /// The argument list must have the number and types of arguments the kernel expects, with
+    /// every pointer pointing to a valid value of the corresponding type. The module `func` was
+    /// loaded from, and any device memory the kernel accesses, must remain valid while any graph
+    /// instantiated from this one is launched.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rustacuda::*;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # let _ctx = quick_init()?;
+    /// use rustacuda::graph::Graph;
+    /// use rustacuda::memory::*;
+    /// use rustacuda::module::Module;
+    /// use rustacuda::stream::{Stream, StreamFlags};
+    /// use std::ffi::{c_void, CString};
+    ///
+    /// let ptx = CString::new(include_str!("../resources/add.ptx"))?;
+    /// let module = Module::load_from_string(&ptx)?;
+    /// let sum = module.get_function(&CString::new("sum")?)?;
+    /// let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+    ///
+    /// let mut x = DeviceBuffer::from_slice(&[0.0f32; 4])?;
+    /// let mut y = DeviceBuffer::from_slice(&[2.0f32; 4])?;
+    /// let mut out = DeviceBuffer::from_slice(&[0.0f32; 4])?;
+    /// let input = [1.0f32; 4];
+    /// let mut output = [0.0f32; 4];
+    ///
+    /// let graph = Graph::new()?;
+    /// let (mut a, mut b, mut c, mut n) =
+    ///     (x.as_device_ptr(), y.as_device_ptr(), out.as_device_ptr(), 4i32);
+    /// let args = [
+    ///     &mut a as *mut _ as *mut c_void,
+    ///     &mut b as *mut _ as *mut c_void,
+    ///     &mut c as *mut _ as *mut c_void,
+    ///     &mut n as *mut _ as *mut c_void,
+    /// ];
+    /// unsafe {
+    ///     let upload = graph.add_memcpy_htod_node(&[], &mut x, &input)?;
+    ///     let add = graph.add_kernel_node(&[upload], &sum, 1, 4, 0, &args)?;
+    ///     graph.add_memcpy_dtoh_node(&[add], &mut output, &out)?;
+    /// }
+    /// graph.instantiate()?.launch(&stream)?;
+    /// stream.synchronize()?;
+    /// assert_eq!([3.0f32; 4], output);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub unsafe fn add_kernel_node<G, B>(
+        &self,
+        dependencies: &[GraphNode<'_>],
+        func: &Function,
+        grid_size: G,
+        block_size: B,
+        shared_mem_bytes: u32,
+        args: &[*mut c_void],
+    ) -> CudaResult<GraphNode<'_>>
+    where
+        G: Into<GridSize>,
+        B: Into<BlockSize>,
+    {
+        let grid_size: GridSize = grid_size.into();
+        let block_size: BlockSize = block_size.into();
+        func.check_context()?;
+        let params = CUDA_KERNEL_NODE_PARAMS {
+            func: func.to_inner(),
+            gridDimX: grid_size.x,
+            gridDimY: grid_size.y,
+            gridDimZ: grid_size.z,
+            blockDimX: block_size.x,
+            blockDimY: block_size.y,
+            blockDimZ: block_size.z,
+            sharedMemBytes: shared_mem_bytes,
+            kernelParams: args.as_ptr() as *mut *mut c_void,
+            extra: ptr::null_mut(),
+        };
+        let dependencies = raw_nodes(dependencies);
+        let mut node = ptr::null_mut();
+        driver_call!(cuda_driver_sys::cuGraphAddKernelNode(
+            &mut node,
+            self.inner,
+            dependencies.as_ptr(),
+            dependencies.len(),
+            &params as *const _
+        ))
+        .to_result()?;
+        Ok(GraphNode::from_inner(node))
+    }
+
+    /// Add a node which copies `src` from the host to `dst` on the device.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidValue` if the slices have different lengths or are empty. If a dependency
+    /// doesn't belong to this graph or the node cannot be added, returns the CUDA error value.
+    ///
+    /// # Safety
+    ///
+    /// Both slices must remain valid while any graph instantiated from this one is launched, and
+    /// `src` must not be modified while such a launch is running.
+    pub unsafe fn add_memcpy_htod_node<T: DeviceCopy>(
+        &self,
+        dependencies: &[GraphNode<'_>],
+        dst: &mut DeviceSlice<T>,
+        src: &[T],
+    ) -> CudaResult<GraphNode<'_>> {
+        if dst.len() != src.len() {
+            return Err(CudaError::InvalidValue);
+        }
+        self.add_memcpy_node(
+            dependencies,
+            Endpoint::Device(dst.as_mut_ptr() as CUdeviceptr),
+            Endpoint::Host(src.as_ptr() as *mut c_void),
+            size_of_val(src),
+        )
+    }
+
+    /// Add a node which copies `src` from the device to `dst` on the host.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidValue` if the slices have different lengths or are empty. If a dependency
+    /// doesn't belong to this graph or the node cannot be added, returns the CUDA error value.
+    ///
+    /// # Safety
+    ///
+    /// Both slices must remain valid while any graph instantiated from this one is launched, and
+    /// `dst` must not be accessed while such a launch is running.
+    pub unsafe fn add_memcpy_dtoh_node<T: DeviceCopy>(
+        &self,
+        dependencies: &[GraphNode<'_>],
+        dst: &mut [T],
+        src: &DeviceSlice<T>,
+    ) -> CudaResult<GraphNode<'_>> {
+        if dst.len() != src.len() {
+            return Err(CudaError::InvalidValue);
+        }
+        self.add_memcpy_node(
+            dependencies,
+            Endpoint::Host(dst.as_mut_ptr() as *mut c_void),
+            Endpoint::Device(src.as_ptr() as CUdeviceptr),
+            size_of::<T>() * src.len(),
+        )
+    }
+
+    /// Add a node which copies `src` to `dst`, both on the device.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidValue` if the slices have different lengths or are empty. If a dependency
+    /// doesn't belong to this graph or the node cannot be added, returns the CUDA error value.
+    ///
+    /// # Safety
+    ///
+    /// Both slices must remain valid while any graph instantiated from this one is launched.
+    pub unsafe fn add_memcpy_dtod_node<T: DeviceCopy>(
+        &self,
+        dependencies: &[GraphNode<'_>],
+        dst: &mut DeviceSlice<T>,
+        src: &DeviceSlice<T>,
+    ) -> CudaResult<GraphNode<'_>> {
+        if dst.len() != src.len() {
+            return Err(CudaError::InvalidValue);
+        }
+        self.add_memcpy_node(
+            dependencies,
+            Endpoint::Device(dst.as_mut_ptr() as CUdeviceptr),
+            Endpoint::Device(src.as_ptr() as CUdeviceptr),
+            size_of::<T>() * src.len(),
+        )
+    }
+
+    unsafe fn add_memcpy_node(
+        &self,
+        dependencies: &[GraphNode<'_>],
+        dst: Endpoint,
+        src: Endpoint,
+        bytes: usize,
+    ) -> CudaResult<GraphNode<'_>> {
+        if bytes == 0 {
+            return Err(CudaError::InvalidValue);
+        }
+        let mut params: CUDA_MEMCPY3D = mem::zeroed();
+        match src {
+            Endpoint::Host(ptr) => {
+                params.srcMemoryType = CUmemorytype::CU_MEMORYTYPE_HOST;
+                params.srcHost = ptr;
+            }
+            Endpoint::Device(ptr) => {
+                params.srcMemoryType = CUmemorytype::CU_MEMORYTYPE_DEVICE;
+                params.srcDevice = ptr;
+            }
+        }
+        match dst {
+            Endpoint::Host(ptr) => {
+                params.dstMemoryType = CUmemorytype::CU_MEMORYTYPE_HOST;
+                params.dstHost = ptr;
+            }
+            Endpoint::Device(ptr) => {
+                params.dstMemoryType = CUmemorytype::CU_MEMORYTYPE_DEVICE;
+                params.dstDevice = ptr;
+            }
+        }
+        params.srcPitch = bytes;
+        params.srcHeight = 1;
+        params.dstPitch = bytes;
+        params.dstHeight = 1;
+        params.WidthInBytes = bytes;
+        params.Height = 1;
+        params.Depth = 1;
+
+        let mut ctx: CUcontext = ptr::null_mut();
+        driver_call!(cuda_driver_sys::cuCtxGetCurrent(&mut ctx as *mut CUcontext)).to_result()?;
+        let dependencies = raw_nodes(dependencies);
+        let mut node = ptr::null_mut();
+        driver_call!(cuda_driver_sys::cuGraphAddMemcpyNode(
+            &mut node,
+            self.inner,
+            dependencies.as_ptr(),
+            dependencies.len(),
+            &params as *const _,
+            ctx
+        ))
+        .to_result()?;
+        Ok(GraphNode::from_inner(node))
+    }
+
+    /// Add a node which sets every element of `dst` to `value`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidValue` if `T` is not 1, 2 or 4 bytes in size or `dst` is empty. If a
+    /// dependency doesn't belong to this graph or the node cannot be added, returns the CUDA error
+    /// value.
+    ///
+    /// # Safety
+    ///
+    /// `dst` must remain valid while any graph instantiated from this one is launched.
+    pub unsafe fn add_memset_node<T: DeviceCopy>(
+        &self,
+        dependencies: &[GraphNode<'_>],
+        dst: &mut DeviceSlice<T>,
+        value: T,
+    ) -> CudaResult<GraphNode<'_>> {
+        let bits = &value as *const T;
+        let value = match size_of::<T>() {
+            1 => u32::from(ptr::read_unaligned(bits as *const u8)),
+            2 => u32::from(ptr::read_unaligned(bits as *const u16)),
+            4 => ptr::read_unaligned(bits as *const u32),
+            _ => return Err(CudaError::InvalidValue),
+        };
+        if dst.is_empty() {
+            return Err(CudaError::InvalidValue);
+        }
+        let params = CUDA_MEMSET_NODE_PARAMS {
+            dst: dst.as_mut_ptr() as CUdeviceptr,
+            pitch: 0,
+            value,
+            elementSize: size_of::<T>() as u32,
+            width: dst.len(),
+            height: 1,
+        };
+
+        let mut ctx: CUcontext = ptr::null_mut();
+        driver_call!(cuda_driver_sys::cuCtxGetCurrent(&mut ctx as *mut CUcontext)).to_result()?;
+        let dependencies = raw_nodes(dependencies);
+        let mut node = ptr::null_mut();
+        driver_call!(cuda_driver_sys::cuGraphAddMemsetNode(
+            &mut node,
+            self.inner,
+            dependencies.as_ptr(),
+            dependencies.len(),
+            &params as *const _,
+            ctx
+        ))
+        .to_result()?;
+        Ok(GraphNode::from_inner(node))
+    }
+
+    /// Add a node which allocates device memory for `count` values of `T` each time the graph is
+    /// launched, and returns the node along with the address of the allocation.
+    ///
+    /// The address is fixed when the node is added, so it can be passed to the nodes which depend
+    /// on this one, such as kernels writing temporary results. The memory is only valid between
+    /// this node and the node from [`add_mem_free_node`](#method.add_mem_free_node) which frees
+    /// it, and the driver recycles it between launches, so a graph can own all of its scratch
+    /// buffers. An allocation which the graph doesn't free must be freed with `cuda_free` before
+    /// the graph is launched again. Like other allocations, the memory is uninitialized.
+    ///
+    /// Only available with the `cuda-11-4` feature. `cuGraphAddMemAllocNode` was added in CUDA
+    /// 11.4, after the driver bindings RustaCUDA uses, so it is looked up in the driver library at
+    /// runtime.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidValue` if the allocation would be empty or its size overflows a usize, or
+    /// `NotSupported` if the driver doesn't support memory nodes. If a dependency doesn't belong
+    /// to this graph or the node cannot be added, returns the CUDA error value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rustacuda::*;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # let _ctx = quick_init()?;
+    /// use rustacuda::error::CudaError;
+    /// use rustacuda::graph::Graph;
+    /// use rustacuda::memory::DeviceSlice;
+    ///
+    /// let graph = Graph::new()?;
+    /// let (alloc, scratch) = match graph.add_mem_alloc_node::<f32>(&[], 1024) {
+    ///     Err(CudaError::NotSupported) => return Ok(()),
+    ///     result => result?,
+    /// };
+    /// unsafe {
+    ///     let slice = DeviceSlice::from_raw_parts_mut(scratch, 1024);
+    ///     let clear = graph.add_memset_node(&[alloc], slice, 0.0f32)?;
+    ///     // ... kernels using the scratch buffer ...
+    ///     graph.add_mem_free_node(&[clear], scratch)?;
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "cuda-11-4")]
+    pub fn add_mem_alloc_node<T: DeviceCopy>(
+        &self,
+        dependencies: &[GraphNode<'_>],
+        count: usize,
+    ) -> CudaResult<(GraphNode<'_>, DevicePointer<T>)> {
+        let bytes = count.checked_mul(size_of::<T>()).unwrap_or(0);
+        if bytes == 0 {
+            return Err(CudaError::InvalidValue);
+        }
+        let add = mem_alloc_node_fn().ok_or(CudaError::NotSupported)?;
+        let device = CurrentContext::get_device()?;
+        let mut params = MemAllocNodeParams {
+            pool_props: MemPoolProps {
+                alloc_type: CU_MEM_ALLOCATION_TYPE_PINNED,
+                handle_types: 0,
+                location: MemLocation {
+                    kind: CU_MEM_LOCATION_TYPE_DEVICE,
+                    id: device.into_inner(),
+                },
+                win32_security_attributes: ptr::null_mut(),
+                reserved: [0; 64],
+            },
+            access_descs: ptr::null(),
+            access_desc_count: 0,
+            bytesize: bytes,
+            dptr: 0,
+        };
+        let dependencies = raw_nodes(dependencies);
+        let mut node = ptr::null_mut();
+        unsafe {
+            add(
+                &mut node,
+                self.inner,
+                dependencies.as_ptr(),
+                dependencies.len(),
+                &mut params,
+            )
+            .to_result()?;
+            Ok((
+                GraphNode::from_inner(node),
+                DevicePointer::wrap(params.dptr as *mut T),
+            ))
+        }
+    }
+
+    /// Add a node which frees `ptr`, which must have been allocated by a node from
+    /// [`add_mem_alloc_node`](#method.add_mem_alloc_node), in this graph or an earlier launch of
+    /// another one. Nodes which use the memory must be among its dependencies, directly or
+    /// indirectly.
+    ///
+    /// Only available with the `cuda-11-4` feature.
+    ///
+    /// # Errors
+    ///
+    /// Returns `NotSupported` if the driver doesn't support memory nodes. If `ptr` wasn't
+    /// allocated by a memory node, a dependency doesn't belong to this graph or the node cannot be
+    /// added, returns the CUDA error value.
+    ///
+    /// # Safety
+    ///
+    /// No work may use the memory after this node.
+    #[cfg(feature = "cuda-11-4")]
+    pub unsafe fn add_mem_free_node<T>(
+        &self,
+        dependencies: &[GraphNode<'_>],
+        ptr: DevicePointer<T>,
+    ) -> CudaResult<GraphNode<'_>> {
+        let add = mem_free_node_fn().ok_or(CudaError::NotSupported)?;
+        let dependencies = raw_nodes(dependencies);
+        let mut node = ptr::null_mut();
+        add(
+            &mut node,
+            self.inner,
+            dependencies.as_ptr(),
+            dependencies.len(),
+            ptr.as_raw() as CUdeviceptr,
+        )
+        .to_result()?;
+        Ok(GraphNode::from_inner(node))
+    }
+
+    /// Add a node which calls `callback` on a driver thread.
+    ///
+    /// The callback is kept alive for as long as this graph or any graph instantiated from it, and
+    /// until every launch of those graphs has finished, so it may be dropped on a driver thread
+    /// once the last one completes. Like a stream callback, it must not make any CUDA API calls,
+    /// and it blocks later work in the graph until it returns. Separately instantiated graphs may
+    /// run it concurrently. If it panics, the panic is caught and ignored.
+    ///
+    /// # Errors
+    ///
+    /// If a dependency doesn't belong to this graph or the node cannot be added, returns the CUDA
+    /// error value.
+    pub fn add_host_node<F>(
+        &self,
+        dependencies: &[GraphNode<'_>],
+        callback: F,
+    ) -> CudaResult<GraphNode<'_>>
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        let callback: HostCallback = Arc::new(Box::new(callback));
+        let params = CUDA_HOST_NODE_PARAMS {
+            fn_: Some(host_node_wrapper),
+            userData: &*callback as *const Box<dyn Fn() + Send + Sync> as *mut c_void,
+        };
+        let dependencies = raw_nodes(dependencies);
+        let mut node = ptr::null_mut();
+        unsafe {
+            driver_call!(cuda_driver_sys::cuGraphAddHostNode(
+                &mut node,
+                self.inner,
+                dependencies.as_ptr(),
+                dependencies.len(),
+                &params as *const _
+            ))
+            .to_result()?;
+        }
+        self.host_callbacks.borrow_mut().push(callback);
+        Ok(GraphNode::from_inner(node))
+    }
+
+    /// Add a node which runs a copy of `child`. Later changes to `child` don't affect this graph.
+    ///
+    /// # Errors
+    ///
+    /// If a dependency doesn't belong to this graph or the node cannot be added, returns the CUDA
+    /// error value.
+    pub fn add_child_graph_node(
+        &self,
+        dependencies: &[GraphNode<'_>],
+        child: &Graph,
+    ) -> CudaResult<GraphNode<'_>> {
+        let dependencies = raw_nodes(dependencies);
+        let mut node = ptr::null_mut();
+        unsafe {
+            driver_call!(cuda_driver_sys::cuGraphAddChildGraphNode(
+                &mut node,
+                self.inner,
+                dependencies.as_ptr(),
+                dependencies.len(),
+                child.inner
+            ))
+            .to_result()?;
+        }
+        // The copy still calls the child's host closures.
+        self.host_callbacks
+            .borrow_mut()
+            .extend(child.host_callbacks.borrow().iter().cloned());
+        Ok(GraphNode::from_inner(node))
+    }
+
+    /// Add dependency edges to the graph. Each pair `(from, to)` makes `to` wait for `from` to
+    /// complete.
+    ///
+    /// # Errors
+    ///
+    /// If a node doesn't belong to this graph, an edge already exists or would create a cycle,
+    /// returns the CUDA error value.
+    pub fn add_dependencies(&self, edges: &[(GraphNode<'_>, GraphNode<'_>)]) -> CudaResult<()> {
+        let from: Vec<CUgraphNode> = edges.iter().map(|(from, _)| from.as_inner()).collect();
+        let to: Vec<CUgraphNode> = edges.iter().map(|(_, to)| to.as_inner()).collect();
+        unsafe {
+            driver_call!(cuda_driver_sys::cuGraphAddDependencies(
+                self.inner,
+                from.as_ptr(),
+                to.as_ptr(),
+                edges.len()
+            ))
+            .to_result()
+        }
+    }
+
+    /// Returns the nodes of the graph, in no particular order.
+    ///
+    /// # Errors
+    ///
+    /// If the nodes cannot be queried, returns the CUDA error value.
+    pub fn nodes(&self) -> CudaResult<Vec<GraphNode<'_>>> {
+        let mut count = 0;
+        unsafe {
+            driver_call!(cuda_driver_sys::cuGraphGetNodes(
+                self.inner,
+                ptr::null_mut(),
+                &mut count
+            ))
+            .to_result()?;
+            let mut nodes: Vec<CUgraphNode> = vec![ptr::null_mut(); count];
+            driver_call!(cuda_driver_sys::cuGraphGetNodes(
+                self.inner,
+                nodes.as_mut_ptr(),
+                &mut count
+            ))
+            .to_result()?;
+            nodes.truncate(count);
+            Ok(nodes.into_iter().map(GraphNode::from_inner).collect())
+        }
+    }
+
+    /// Instantiate the graph, creating an executable graph which can be launched on a stream.
+    ///
+    /// The executable graph is a snapshot: later changes to this graph don't affect it, and it
+    /// remains valid after this graph is destroyed.
+    ///
+    /// # Errors
+    ///
+    /// If the graph cannot be instantiated, returns the CUDA error value.
+    pub fn instantiate(&self) -> CudaResult<GraphExec> {
+        let mut inner: CUgraphExec = ptr::null_mut();
+        unsafe {
+            driver_call!(cuda_driver_sys::cuGraphInstantiate(
+                &mut inner,
+                self.inner,
+                ptr::null_mut(),
+                ptr::null_mut(),
+                0,
+            ))
+            .to_result()?;
+        }
+        Ok(GraphExec {
+            inner,
+            host_callbacks: self.host_callbacks.borrow().clone(),
+        })
+    }
+
+    /// Destroy a `Graph`, returning an error.
+    ///
+    /// This function destroys the given graph and returns the error and the un-destroyed graph on
+    /// failure.
+    pub fn drop(mut graph: Graph) -> DropResult<Graph> {
+        if graph.inner.is_null() {
+            return Ok(());
+        }
+
+        unsafe {
+            let inner = mem::replace(&mut graph.inner, ptr::null_mut());
+            match driver_call!(cuda_driver_sys::cuGraphDestroy(inner)).to_result() {
+                Ok(()) => {
+                    drop(mem::take(&mut graph.host_callbacks));
+                    mem::forget(graph);
+                    Ok(())
+                }
+                Err(e) => Err((
+                    e,
+                    Graph {
+                        inner,
+                        host_callbacks: mem::take(&mut graph.host_callbacks),
+                    },
+                )),
+            }
+        }
+    }
+}
+impl fmt::Debug for Graph {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Graph")
+            .field("inner", &self.inner)
+            .field("host_nodes", &self.host_callbacks.borrow().len())
+            .finish()
+    }
+}
+impl Drop for Graph {
+    fn drop(&mut self) {
+        if self.inner.is_null() {
+            return;
+        }
+
+        unsafe {
+            let inner = mem::replace(&mut self.inner, ptr::null_mut());
+            // No choice but to panic here.
+            driver_call!(cuda_driver_sys::cuGraphDestroy(inner))
+                .to_result()
+                .expect_in_drop("Failed to destroy CUDA graph.");
+        }
+    }
+}
+
+/// A handle to a node of a [`Graph`](struct.Graph.html).
+///
+/// Nodes are owned by their graph, so a `GraphNode` borrows the graph it belongs to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct GraphNode<'a> {
+    inner: CUgraphNode,
+    graph: PhantomData<&'a Graph>,
+}
+impl<'a> GraphNode<'a> {
+    pub(crate) fn from_inner(inner: CUgraphNode) -> GraphNode<'a> {
+        GraphNode {
+            inner,
+            graph: PhantomData,
+        }
+    }
+
+    // Get the inner `CUgraphNode` from the `GraphNode`.
+    pub(crate) fn as_inner(self) -> CUgraphNode {
+        self.inner
+    }
+
+    /// Wrap a raw `CUgraphNode` handle.
+    ///
+    /// # Safety
+    ///
+    /// `node` must be a node of a graph which outlives the lifetime `'a`.
+    pub unsafe fn from_raw(node: CUgraphNode) -> GraphNode<'a> {
+        GraphNode::from_inner(node)
+    }
+
+    /// Returns the raw `CUgraphNode` handle.
+    pub fn as_raw(self) -> CUgraphNode {
+        self.inner
+    }
+}
+
+/// An instantiated graph, which can be launched on a stream.
+///
+/// Created by [`Graph::instantiate`](struct.Graph.html#method.instantiate), or from a raw handle
+/// with [`GraphExec::from_raw`](#method.from_raw).
+pub struct GraphExec {
+    inner: CUgraphExec,
+    host_callbacks: Vec<HostCallback>,
+}
+// As with `Graph`, the driver doesn't synchronize access to executable graphs.
+unsafe impl Send for GraphExec {}
+impl GraphExec {
+    /// Wrap a raw `CUgraphExec` handle, taking ownership of it. The executable graph is destroyed
+    /// when the returned `GraphExec` is dropped.
+    ///
+    /// # Safety
+    ///
+    /// `exec` must be a valid executable graph which is not owned or destroyed by anything else.
+    pub unsafe fn from_raw(exec: CUgraphExec) -> GraphExec {
+        GraphExec {
+            inner: exec,
+            host_callbacks: Vec::new(),
+        }
+    }
+
+    /// Returns the raw `CUgraphExec` handle. The `GraphExec` keeps ownership of it.
+    pub fn as_raw(&self) -> CUgraphExec {
+        self.inner
+    }
+
+    /// Launch the graph on `stream`. Like a kernel launch, this is asynchronous.
+    ///
+    /// An executable graph can't run concurrently with itself; each launch waits for the previous
+    /// launch of the same graph to complete.
+    ///
+    /// # Errors
+    ///
+    /// If the graph cannot be launched, returns the CUDA error value. Errors from the work in the
+    /// graph may also be returned from a later call.
+    #[track_caller]
+    pub fn launch(&self, stream: &Stream) -> CudaResult<()> {
+        unsafe {
+            driver_call!(cuda_driver_sys::cuGraphLaunch(
+                self.inner,
+                stream.as_inner()
+            ))
+            .to_result()?;
+        }
+        stream.trace(|| "graph launch".to_string());
+        self.retain_host_callbacks(stream)
+    }
+
+    // Destroying or updating an executable graph doesn't wait for its queued launches, so each
+    // launch holds on to the host closures until it has finished. If the callback can't be
+    // enqueued, `add_callback` leaks it, which still keeps the closures alive.
+    fn retain_host_callbacks(&self, stream: &Stream) -> CudaResult<()> {
+        if self.host_callbacks.is_empty() {
+            return Ok(());
+        }
+        let callbacks = self.host_callbacks.clone();
+        stream.add_callback(Box::new(move |_| drop(callbacks)))
+    }
+
+    /// Upload the graph to the device on `stream` without launching it, so that the cost of
+    /// setting it up on the device isn't paid by its first launch. Like a launch, this is
+    /// asynchronous.
+    ///
+    /// Only available with the `cuda-11-1` feature. `cuGraphUpload` was added in CUDA 11.1, after
+    /// the driver bindings RustaCUDA uses, so it is looked up in the driver library at runtime. On
+    /// platforms other than Linux, this does nothing and the first launch uploads the graph as
+    /// usual.
+    ///
+    /// # Errors
+    ///
+    /// If the upload fails, returns the CUDA error value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rustacuda::*;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # let _ctx = quick_init()?;
+    /// use rustacuda::graph::Graph;
+    /// use rustacuda::stream::{Stream, StreamFlags};
+    ///
+    /// let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+    /// let exec = Graph::new()?.instantiate()?;
+    /// exec.upload(&stream)?;
+    /// exec.launch(&stream)?;
+    /// stream.synchronize()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "cuda-11-1")]
+    pub fn upload(&self, stream: &Stream) -> CudaResult<()> {
+        match upload_fn() {
+            Some(upload) => unsafe { upload(self.inner, stream.as_inner()).to_result() },
+            None => Ok(()),
+        }
+    }
+
+    /// Replace the arguments of several kernel nodes at once, without re-instantiating the graph.
+    ///
+    /// Each entry pairs a kernel node of the graph this was instantiated from with its new
+    /// arguments, given in the same form as for [`launch!`](../macro.launch.html): one pointer to
+    /// each argument value, in order. The argument values are copied, so they only need to live
+    /// for the duration of this call. The function, launch configuration and other nodes are left
+    /// unchanged. The new arguments are used by every launch after this call; launches that are
+    /// already queued are not affected.
+    ///
+    /// # Errors
+    ///
+    /// If an update fails, for example because the node is not a kernel node or doesn't belong to
+    /// the graph this was instantiated from, returns the CUDA error value. The updates before the
+    /// failing one have already been applied, and the ones after it have not.
+    ///
+    /// # Safety
+    ///
+    /// Each argument list must have the number and types of arguments the node's kernel expects,
+    /// with every pointer pointing to a valid value of the corresponding type, and any device
+    /// pointers among the arguments must remain valid while the graph is launched.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rustacuda::*;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # let _ctx = quick_init()?;
+    /// use rustacuda::memory::*;
+    /// use rustacuda::module::Module;
+    /// use rustacuda::stream::{Stream, StreamFlags};
+    /// use std::ffi::{c_void, CString};
+    ///
+    /// let ptx = CString::new(include_str!("../resources/add.ptx"))?;
+    /// let module = Module::load_from_string(&ptx)?;
+    /// let sum = module.get_function(&CString::new("sum")?)?;
+    /// let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+    ///
+    /// let mut x = DeviceBuffer::from_slice(&[1.0f32; 10])?;
+    /// let mut y = DeviceBuffer::from_slice(&[2.0f32; 10])?;
+    /// let mut out = DeviceBuffer::from_slice(&[0.0f32; 10])?;
+    /// let mut other_out = DeviceBuffer::from_slice(&[0.0f32; 10])?;
+    ///
+    /// stream.begin_capture()?;
+    /// unsafe {
+    ///     launch!(sum<<<1, 10, 0, stream>>>(
+    ///         x.as_device_ptr(), y.as_device_ptr(), out.as_device_ptr(), out.len() as i32
+    ///     ))?;
+    /// }
+    /// let graph = stream.end_capture()?;
+    /// let mut exec = graph.instantiate()?;
+    /// exec.launch(&stream)?;
+    ///
+    /// // Write the next launch's results to `other_out` instead.
+    /// let node = graph.nodes()?[0];
+    /// let (mut a, mut b, mut c, mut n) =
+    ///     (x.as_device_ptr(), y.as_device_ptr(), other_out.as_device_ptr(), other_out.len() as i32);
+    /// let args = [
+    ///     &mut a as *mut _ as *mut c_void,
+    ///     &mut b as *mut _ as *mut c_void,
+    ///     &mut c as *mut _ as *mut c_void,
+    ///     &mut n as *mut _ as *mut c_void,
+    /// ];
+    /// unsafe { exec.set_params_batch(&[(node, &args[..])])? };
+    /// exec.launch(&stream)?;
+    /// stream.synchronize()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub unsafe fn set_params_batch(
+        &mut self,
+        updates: &[(GraphNode<'_>, &[*mut c_void])],
+    ) -> CudaResult<()> {
+        for &(node, args) in updates {
+            let mut params: CUDA_KERNEL_NODE_PARAMS = mem::zeroed();
+            driver_call!(cuda_driver_sys::cuGraphKernelNodeGetParams(
+                node.as_inner(),
+                &mut params
+            ))
+            .to_result()?;
+            params.kernelParams = args.as_ptr() as *mut *mut c_void;
+            params.extra = ptr::null_mut();
+            driver_call!(cuda_driver_sys::cuGraphExecKernelNodeSetParams(
+                self.inner,
+                node.as_inner(),
+                &params
+            ))
+            .to_result()?;
+        }
+        Ok(())
+    }
+
+    /// Update this executable graph in place to match `graph`, without re-instantiating it.
+    ///
+    /// `graph` must have the same topology as the graph this was instantiated from: the same
+    /// nodes, of the same types, with the same dependencies. Kernel arguments and launch
+    /// configurations, memcpy and memset parameters and host node closures may differ, but kernel
+    /// nodes must launch functions from the same context. Re-capturing the same stream work with
+    /// different buffers produces such a graph. Launches that are already queued are not
+    /// affected.
+    ///
+    /// # Errors
+    ///
+    /// If `graph` can't be applied, returns a [`GraphUpdateError`](struct.GraphUpdateError.html)
+    /// with the reason and, where the driver reports one, the node of `graph` which caused it.
+    /// This executable graph is left unchanged and can still be launched. Other failures are
+    /// returned with a reason of `GraphUpdateFailure::Error`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rustacuda::*;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # let _ctx = quick_init()?;
+    /// use rustacuda::memory::*;
+    /// use rustacuda::module::Module;
+    /// use rustacuda::stream::{Stream, StreamFlags};
+    /// use std::ffi::CString;
+    ///
+    /// let ptx = CString::new(include_str!("../resources/add.ptx"))?;
+    /// let module = Module::load_from_string(&ptx)?;
+    /// let sum = module.get_function(&CString::new("sum")?)?;
+    /// let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+    ///
+    /// let mut x = DeviceBuffer::from_slice(&[1.0f32; 10])?;
+    /// let mut y = DeviceBuffer::from_slice(&[2.0f32; 10])?;
+    /// let mut frames = [
+    ///     DeviceBuffer::from_slice(&[0.0f32; 10])?,
+    ///     DeviceBuffer::from_slice(&[0.0f32; 10])?,
+    /// ];
+    ///
+    /// let mut exec = None;
+    /// for out in frames.iter_mut() {
+    ///     stream.begin_capture()?;
+    ///     unsafe {
+    ///         launch!(sum<<<1, 10, 0, stream>>>(
+    ///             x.as_device_ptr(), y.as_device_ptr(), out.as_device_ptr(), out.len() as i32
+    ///         ))?;
+    ///     }
+    ///     let graph = stream.end_capture()?;
+    ///     match exec {
+    ///         None => exec = Some(graph.instantiate()?),
+    ///         Some(ref mut exec) => exec.update(&graph).map_err(|e| e.error)?,
+    ///     }
+    ///     exec.as_ref().unwrap().launch(&stream)?;
+    /// }
+    /// stream.synchronize()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn update<'g>(&mut self, graph: &'g Graph) -> Result<(), GraphUpdateError<'g>> {
+        let mut error_node: CUgraphNode = ptr::null_mut();
+        let mut result = CUgraphExecUpdateResult::CU_GRAPH_EXEC_UPDATE_SUCCESS;
+        let status = unsafe {
+            driver_call!(cuda_driver_sys::cuGraphExecUpdate(
+                self.inner,
+                graph.inner,
+                &mut error_node,
+                &mut result
+            ))
+            .to_result()
+        };
+        match status {
+            Ok(()) => {
+                // Host nodes may now run the closures of the new graph. Launches queued before the
+                // update hold on to the old closures until they finish.
+                self.host_callbacks = graph.host_callbacks.borrow().clone();
+                Ok(())
+            }
+            Err(error) => Err(GraphUpdateError {
+                error,
+                reason: GraphUpdateFailure::from_raw(result),
+                node: if error_node.is_null() {
+                    None
+                } else {
+                    Some(GraphNode::from_inner(error_node))
+                },
+            }),
+        }
+    }
+
+    /// Destroy a `GraphExec`, returning an error.
+    ///
+    /// This function destroys the given executable graph and returns the error and the
+    /// un-destroyed executable graph on failure.
+    pub fn drop(mut exec: GraphExec) -> DropResult<GraphExec> {
+        if exec.inner.is_null() {
+            return Ok(());
+        }
+
+        unsafe {
+            let inner = mem::replace(&mut exec.inner, ptr::null_mut());
+            match driver_call!(cuda_driver_sys::cuGraphExecDestroy(inner)).to_result() {
+                Ok(()) => {
+                    drop(mem::take(&mut exec.host_callbacks));
+                    mem::forget(exec);
+                    Ok(())
+                }
+                Err(e) => Err((
+                    e,
+                    GraphExec {
+                        inner,
+                        host_callbacks: mem::take(&mut exec.host_callbacks),
+                    },
+                )),
+            }
+        }
+    }
+}
+impl fmt::Debug for GraphExec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GraphExec")
+            .field("inner", &self.inner)
+            .field("host_nodes", &self.host_callbacks.len())
+            .finish()
+    }
+}
+impl Drop for GraphExec {
+    fn drop(&mut self) {
+        if self.inner.is_null() {
+            return;
+        }
+
+        unsafe {
+            let inner = mem::replace(&mut self.inner, ptr::null_mut());
+            // No choice but to panic here.
+            driver_call!(cuda_driver_sys::cuGraphExecDestroy(inner))
+                .to_result()
+                .expect_in_drop("Failed to destroy CUDA graph exec.");
+        }
+    }
+}
+
+/// The reason [`GraphExec::update`](struct.GraphExec.html#method.update) could not apply a graph.
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+pub enum GraphUpdateFailure {
+    /// The update failed for a reason other than those below, or the driver gave no reason.
+    Error,
+    /// The graph has different nodes or dependencies.
+    TopologyChanged,
+    /// A node has a different type.
+    NodeTypeChanged,
+    /// A kernel node launches a function from a different context.
+    FunctionChanged,
+    /// A node's parameters changed in a way that can't be updated, such as a memcpy between
+    /// different devices.
+    ParametersChanged,
+    /// A node of the graph doesn't support updates.
+    NotSupported,
+
+    #[doc(hidden)]
+    __Nonexhaustive,
+}
+impl GraphUpdateFailure {
+    fn from_raw(result: CUgraphExecUpdateResult) -> Self {
+        match result {
+            CUgraphExecUpdateResult::CU_GRAPH_EXEC_UPDATE_ERROR_TOPOLOGY_CHANGED => {
+                GraphUpdateFailure::TopologyChanged
+            }
+            CUgraphExecUpdateResult::CU_GRAPH_EXEC_UPDATE_ERROR_NODE_TYPE_CHANGED => {
+                GraphUpdateFailure::NodeTypeChanged
+            }
+            CUgraphExecUpdateResult::CU_GRAPH_EXEC_UPDATE_ERROR_FUNCTION_CHANGED => {
+                GraphUpdateFailure::FunctionChanged
+            }
+            CUgraphExecUpdateResult::CU_GRAPH_EXEC_UPDATE_ERROR_PARAMETERS_CHANGED => {
+                GraphUpdateFailure::ParametersChanged
+            }
+            CUgraphExecUpdateResult::CU_GRAPH_EXEC_UPDATE_ERROR_NOT_SUPPORTED => {
+                GraphUpdateFailure::NotSupported
+            }
+            _ => GraphUpdateFailure::Error,
+        }
+    }
+}
+
+/// The error returned when [`GraphExec::update`](struct.GraphExec.html#method.update) fails.
+///
+/// It borrows the new graph through `node`; map it to its `error` to propagate it past the graph's
+/// lifetime.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GraphUpdateError<'g> {
+    /// The error returned by CUDA, usually `GraphExecUpdateFailure`.
+    pub error: CudaError,
+    /// Why the graph couldn't be applied.
+    pub reason: GraphUpdateFailure,
+    /// The node of the new graph which caused the failure, if the driver reported one.
+    pub node: Option<GraphNode<'g>>,
+}
+impl<'g> fmt::Display for GraphUpdateError<'g> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({:?})", self.error, self.reason)
+    }
+}
+impl<'g> Error for GraphUpdateError<'g> {}
+
+/// A block of kernel parameters kept in page-locked host memory and mirrored on the device, for
+/// relaunching a graph with new parameters without allocating or updating the graph.
+///
+/// The kernels in the graph take a pointer to the parameters, from
+/// [`as_device_ptr`](#method.as_device_ptr), instead of the parameters themselves. The graph starts
+/// with a node from [`add_upload_node`](#method.add_upload_node) (or, for captured graphs, an
+/// upload enqueued with [`enqueue_upload`](#method.enqueue_upload)) which copies the host block to
+/// the device, so every launch uses the parameters the host last wrote. Launching with
+/// [`launch`](#method.launch) records when the launch has read the block, so that
+/// [`host_mut`](#method.host_mut) can wait for that before handing out the block to be changed.
+///
+/// # Examples
+///
+/// ```
+/// # use rustacuda::*;
+/// # use std::error::Error;
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// # let _ctx = quick_init()?;
+/// use rustacuda::graph::{Graph, PinnedParams};
+/// use rustacuda::memory::*;
+/// use rustacuda::module::Module;
+/// use rustacuda::stream::{Stream, StreamFlags};
+/// use std::ffi::{c_void, CString};
+///
+/// let ptx = CString::new(include_str!("../resources/add.ptx"))?;
+/// let module = Module::load_from_string(&ptx)?;
+/// let sum = module.get_function(&CString::new("sum")?)?;
+/// let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+///
+/// // The kernel adds the parameter block to `x`.
+/// let mut params = PinnedParams::new(&[0.0f32; 4])?;
+/// let mut x = DeviceBuffer::from_slice(&[1.0f32; 4])?;
+/// let mut out = DeviceBuffer::from_slice(&[0.0f32; 4])?;
+/// let (mut a, mut b, mut c, mut n) =
+///     (x.as_device_ptr(), params.as_device_ptr(), out.as_device_ptr(), 4i32);
+/// let args = [
+///     &mut a as *mut _ as *mut c_void,
+///     &mut b as *mut _ as *mut c_void,
+///     &mut c as *mut _ as *mut c_void,
+///     &mut n as *mut _ as *mut c_void,
+/// ];
+///
+/// let graph = Graph::new()?;
+/// unsafe {
+///     let upload = params.add_upload_node(&graph, &[])?;
+///     graph.add_kernel_node(&[upload], &sum, 1, 4, 0, &args)?;
+/// }
+/// let exec = graph.instantiate()?;
+/// for step in 0..100 {
+///     *params.host_mut()? = [step as f32; 4];
+///     params.launch(&exec, &stream)?;
+/// }
+/// stream.synchronize()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct PinnedParams<T: DeviceCopy> {
+    host: LockedBuffer<T>,
+    device: DeviceBox<T>,
+    read: Event,
+}
+impl<T: DeviceCopy> PinnedParams<T> {
+    /// Allocate a parameter block on the host and the device, both initialized to `value`.
+    ///
+    /// # Errors
+    ///
+    /// If an allocation fails, returns the error from CUDA.
+    pub fn new(value: &T) -> CudaResult<Self> {
+        let mut host = unsafe { LockedBuffer::uninitialized(1)? };
+        // DeviceCopy types can be duplicated by copying their bits.
+        unsafe { ptr::copy_nonoverlapping(value, host.as_mut_ptr(), 1) };
+        Ok(PinnedParams {
+            host,
+            device: DeviceBox::new(value)?,
+            read: Event::new(EventFlags::DISABLE_TIMING)?,
+        })
+    }
+
+    /// Returns the host copy of the parameters.
+    pub fn host(&self) -> &T {
+        &self.host[0]
+    }
+
+    /// Wait until the last launch made with [`launch`](#method.launch) has copied the parameters
+    /// to the device, then return the host copy to be changed for the next launch.
+    ///
+    /// # Errors
+    ///
+    /// If waiting fails, returns the error from CUDA.
+    pub fn host_mut(&mut self) -> CudaResult<&mut T> {
+        self.read.synchronize()?;
+        Ok(&mut self.host[0])
+    }
+
+    /// Returns the device pointer to pass to the kernels which read the parameters.
+    pub fn as_device_ptr(&mut self) -> DevicePointer<T> {
+        self.device.as_device_ptr()
+    }
+
+    /// Add a node to `graph` which copies the host parameters to the device, after all of
+    /// `dependencies` have completed. The kernels which read the parameters should depend on it.
+    ///
+    /// # Errors
+    ///
+    /// If the node cannot be added, returns the CUDA error value.
+    ///
+    /// # Safety
+    ///
+    /// This block must outlive every graph instantiated from `graph`, and the host parameters must
+    /// only be changed through [`host_mut`](#method.host_mut) while those graphs are launched.
+    pub unsafe fn add_upload_node<'g>(
+        &mut self,
+        graph: &'g Graph,
+        dependencies: &[GraphNode<'_>],
+    ) -> CudaResult<GraphNode<'g>> {
+        let device = DeviceSlice::from_raw_parts_mut(self.device.as_device_ptr(), 1);
+        graph.add_memcpy_htod_node(dependencies, device, &self.host)
+    }
+
+    /// Enqueue a copy of the host parameters to the device on `stream`. While `stream` is being
+    /// captured, this records the copy into the captured graph in the same way as
+    /// [`add_upload_node`](#method.add_upload_node).
+    ///
+    /// # Errors
+    ///
+    /// If the copy cannot be enqueued, returns the CUDA error value.
+    ///
+    /// # Safety
+    ///
+    /// This block must outlive the copy and, if it is captured, every graph instantiated from the
+    /// captured graph. The host parameters must only be changed through
+    /// [`host_mut`](#method.host_mut) while the copy or those graphs are running.
+    pub unsafe fn enqueue_upload(&mut self, stream: &Stream) -> CudaResult<()> {
+        self.device.async_copy_from(&self.host[0], stream)
+    }
+
+    /// Launch `exec` on `stream` and record when the launch has finished with the parameters, so
+    /// that [`host_mut`](#method.host_mut) waits for it.
+    ///
+    /// # Errors
+    ///
+    /// If the graph cannot be launched or the launch cannot be recorded, returns the CUDA error
+    /// value.
+    pub fn launch(&self, exec: &GraphExec, stream: &Stream) -> CudaResult<()> {
+        exec.launch(stream)?;
+        self.read.record(stream)
+    }
+}
+
+// One end of a memcpy node.
+enum Endpoint {
+    Host(*mut c_void),
+    Device(CUdeviceptr),
+}
+
+fn raw_nodes(nodes: &[GraphNode<'_>]) -> Vec<CUgraphNode> {
+    nodes.iter().map(|node| node.as_inner()).collect()
+}
+
+unsafe extern "C" fn host_node_wrapper(callback: *mut c_void) {
+    // Stop panics from unwinding across the FFI
+    let _ = panic::catch_unwind(|| {
+        let callback = &*(callback as *const Box<dyn Fn() + Send + Sync>);
+        callback();
+    });
+}
+
+// The functions below were added to the driver after the bindings RustaCUDA uses, so they are
+// looked up at runtime, and their parameter types are declared here.
+
+#[cfg(feature = "cuda-11-1")]
+type GraphUploadFn = unsafe extern "C" fn(CUgraphExec, CUstream) -> CUresult;
+#[cfg(feature = "cuda-11-4")]
+type MemAllocNodeFn = unsafe extern "C" fn(
+    *mut CUgraphNode,
+    CUgraph,
+    *const CUgraphNode,
+    usize,
+    *mut MemAllocNodeParams,
+) -> CUresult;
+#[cfg(feature = "cuda-11-4")]
+type MemFreeNodeFn = unsafe extern "C" fn(
+    *mut CUgraphNode,
+    CUgraph,
+    *const CUgraphNode,
+    usize,
+    CUdeviceptr,
+) -> CUresult;
+
+#[cfg(feature = "cuda-11-4")]
+const CU_MEM_ALLOCATION_TYPE_PINNED: u32 = 1;
+#[cfg(feature = "cuda-11-4")]
+const CU_MEM_LOCATION_TYPE_DEVICE: u32 = 1;
+
+// CUmemLocation
+#[cfg(feature = "cuda-11-4")]
+#[repr(C)]
+struct MemLocation {
+    kind: u32,
+    id: i32,
+}
+
+// CUmemPoolProps
+#[cfg(feature = "cuda-11-4")]
+#[repr(C)]
+struct MemPoolProps {
+    alloc_type: u32,
+    handle_types: u32,
+    location: MemLocation,
+    win32_security_attributes: *mut c_void,
+    reserved: [u8; 64],
+}
+
+// CUDA_MEM_ALLOC_NODE_PARAMS
+#[cfg(feature = "cuda-11-4")]
+#[repr(C)]
+struct MemAllocNodeParams {
+    pool_props: MemPoolProps,
+    access_descs: *const c_void,
+    access_desc_count: usize,
+    bytesize: usize,
+    dptr: CUdeviceptr,
+}
+
+#[cfg(feature = "cuda-11-1")]
+driver_fn!(upload_fn: GraphUploadFn = b"cuGraphUpload\0");
+#[cfg(feature = "cuda-11-4")]
+driver_fn!(mem_alloc_node_fn: MemAllocNodeFn = b"cuGraphAddMemAllocNode\0");
+#[cfg(feature = "cuda-11-4")]
+driver_fn!(mem_free_node_fn: MemFreeNodeFn = b"cuGraphAddMemFreeNode\0");
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::error::CudaError;
+    use crate::memory::{CopyDestination, DeviceBuffer};
+    use crate::module::Module;
+    use crate::stream::StreamFlags;
+    use std::ffi::CString;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn test_set_params_batch() {
+        let _context = crate::quick_init().unwrap();
+        let ptx = CString::new(include_str!("../resources/add.ptx")).unwrap();
+        let module = Module::load_from_string(&ptx).unwrap();
+        let sum = module.get_function(&CString::new("sum").unwrap()).unwrap();
+        let stream = Stream::new(StreamFlags::NON_BLOCKING, None).unwrap();
+
+        let mut x = DeviceBuffer::from_slice(&[1.0f32; 4]).unwrap();
+        let mut y = DeviceBuffer::from_slice(&[2.0f32; 4]).unwrap();
+        let mut first = DeviceBuffer::from_slice(&[0.0f32; 4]).unwrap();
+        let mut second = DeviceBuffer::from_slice(&[0.0f32; 4]).unwrap();
+
+        let (mut a, mut b, mut c, mut n) = (
+            x.as_device_ptr(),
+            y.as_device_ptr(),
+            first.as_device_ptr(),
+            4i32,
+        );
+        let mut args = [
+            &mut a as *mut _ as *mut c_void,
+            &mut b as *mut _ as *mut c_void,
+            &mut c as *mut _ as *mut c_void,
+            &mut n as *mut _ as *mut c_void,
+        ];
+        stream.begin_capture().unwrap();
+        unsafe { stream.launch(&sum, 1, 4, 0, &args).unwrap() };
+        let graph = stream.end_capture().unwrap();
+        let nodes = graph.nodes().unwrap();
+        assert_eq!(1, nodes.len());
+
+        let mut exec = graph.instantiate().unwrap();
+        let mut c2 = second.as_device_ptr();
+        args[2] = &mut c2 as *mut _ as *mut c_void;
+        unsafe { exec.set_params_batch(&[(nodes[0], &args[..])]).unwrap() };
+        #[cfg(feature = "cuda-11-1")]
+        exec.upload(&stream).unwrap();
+        exec.launch(&stream).unwrap();
+        stream.synchronize().unwrap();
+
+        let mut host = [0.0f32; 4];
+        first.copy_to(&mut host[..]).unwrap();
+        assert_eq!([0.0f32; 4], host);
+        second.copy_to(&mut host[..]).unwrap();
+        assert_eq!([3.0f32; 4], host);
+        GraphExec::drop(exec).unwrap();
+        Graph::drop(graph).unwrap();
+    }
+
+    #[test]
+    fn test_update() {
+        let _context = crate::quick_init().unwrap();
+        let ptx = CString::new(include_str!("../resources/add.ptx")).unwrap();
+        let module = Module::load_from_string(&ptx).unwrap();
+        let sum = module.get_function(&CString::new("sum").unwrap()).unwrap();
+        let stream = Stream::new(StreamFlags::NON_BLOCKING, None).unwrap();
+
+        let mut x = DeviceBuffer::from_slice(&[1.0f32; 4]).unwrap();
+        let mut y = DeviceBuffer::from_slice(&[2.0f32; 4]).unwrap();
+        let mut outputs = [
+            DeviceBuffer::from_slice(&[0.0f32; 4]).unwrap(),
+            DeviceBuffer::from_slice(&[0.0f32; 4]).unwrap(),
+        ];
+        let mut graphs = Vec::new();
+        for out in outputs.iter_mut() {
+            let (mut a, mut b, mut c, mut n) = (
+                x.as_device_ptr(),
+                y.as_device_ptr(),
+                out.as_device_ptr(),
+                4i32,
+            );
+            let args = [
+                &mut a as *mut _ as *mut c_void,
+                &mut b as *mut _ as *mut c_void,
+                &mut c as *mut _ as *mut c_void,
+                &mut n as *mut _ as *mut c_void,
+            ];
+            stream.begin_capture().unwrap();
+            unsafe { stream.launch(&sum, 1, 4, 0, &args).unwrap() };
+            graphs.push(stream.end_capture().unwrap());
+        }
+
+        let mut exec = graphs[0].instantiate().unwrap();
+        exec.update(&graphs[1]).unwrap();
+        exec.launch(&stream).unwrap();
+        stream.synchronize().unwrap();
+        let mut host = [0.0f32; 4];
+        outputs[0].copy_to(&mut host[..]).unwrap();
+        assert_eq!([0.0f32; 4], host);
+        outputs[1].copy_to(&mut host[..]).unwrap();
+        assert_eq!([3.0f32; 4], host);
+
+        let empty = Graph::new().unwrap();
+        let err = exec.update(&empty).unwrap_err();
+        assert_eq!(CudaError::GraphExecUpdateFailure, err.error);
+        assert_eq!(GraphUpdateFailure::TopologyChanged, err.reason);
+        exec.launch(&stream).unwrap();
+        stream.synchronize().unwrap();
+    }
+
+    #[test]
+    fn test_manual_graph() {
+        let _context = crate::quick_init().unwrap();
+        let ptx = CString::new(include_str!("../resources/add.ptx")).unwrap();
+        let module = Module::load_from_string(&ptx).unwrap();
+        let sum = module.get_function(&CString::new("sum").unwrap()).unwrap();
+        let stream = Stream::new(StreamFlags::NON_BLOCKING, None).unwrap();
+
+        let mut x = DeviceBuffer::from_slice(&[0.0f32; 4]).unwrap();
+        let mut y = DeviceBuffer::from_slice(&[0.0f32; 4]).unwrap();
+        let mut out = DeviceBuffer::from_slice(&[0.0f32; 4]).unwrap();
+        let mut copy = DeviceBuffer::from_slice(&[0.0f32; 4]).unwrap();
+        let mut wide = DeviceBuffer::from_slice(&[0.0f64; 2]).unwrap();
+        let input = [1.0f32, 2.0, 3.0, 4.0];
+        let mut output = [0.0f32; 4];
+        let calls = Arc::new(AtomicUsize::new(0));
+
+        let (mut a, mut b, mut c, mut n) = (
+            x.as_device_ptr(),
+            y.as_device_ptr(),
+            out.as_device_ptr(),
+            4i32,
+        );
+        let args = [
+            &mut a as *mut _ as *mut c_void,
+            &mut b as *mut _ as *mut c_void,
+            &mut c as *mut _ as *mut c_void,
+            &mut n as *mut _ as *mut c_void,
+        ];
+
+        let child = Graph::new().unwrap();
+        let counter = calls.clone();
+        let _ = child
+            .add_host_node(&[], move || {
+                let _ = counter.fetch_add(1, Ordering::SeqCst);
+            })
+            .unwrap();
+
+        let graph = Graph::new().unwrap();
+        unsafe {
+            let upload = graph.add_memcpy_htod_node(&[], &mut x, &input).unwrap();
+            let fill = graph.add_memset_node(&[], &mut y, 10.0f32).unwrap();
+            let join = graph.add_empty_node(&[upload, fill]).unwrap();
+            let add = graph
+                .add_kernel_node(&[join], &sum, 1, 4, 0, &args)
+                .unwrap();
+            let duplicate = graph.add_memcpy_dtod_node(&[], &mut copy, &out).unwrap();
+            graph.add_dependencies(&[(add, duplicate)]).unwrap();
+            let download = graph
+                .add_memcpy_dtoh_node(&[duplicate], &mut output, &copy)
+                .unwrap();
+            let _ = graph.add_child_graph_node(&[download], &child).unwrap();
+            assert_eq!(
+                CudaError::InvalidValue,
+                graph.add_memset_node(&[], &mut wide, 1.0f64).unwrap_err()
+            );
+        }
+        assert_eq!(7, graph.nodes().unwrap().len());
+        drop(child);
+
+        let exec = graph.instantiate().unwrap();
+        drop(graph);
+        exec.launch(&stream).unwrap();
+        stream.synchronize().unwrap();
+        assert_eq!([11.0f32, 12.0, 13.0, 14.0], output);
+        assert_eq!(1, calls.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn test_host_node_outlives_dropped_exec() {
+        let _context = crate::quick_init().unwrap();
+        let stream = Stream::new(StreamFlags::NON_BLOCKING, None).unwrap();
+        let calls = Arc::new(AtomicUsize::new(0));
+
+        let graph = Graph::new().unwrap();
+        let counter = calls.clone();
+        let _ = graph
+            .add_host_node(&[], move || {
+                let _ = counter.fetch_add(1, Ordering::SeqCst);
+            })
+            .unwrap();
+        let exec = graph.instantiate().unwrap();
+        drop(graph);
+        for _ in 0..4 {
+            exec.launch(&stream).unwrap();
+        }
+        drop(exec);
+        stream.synchronize().unwrap();
+        assert_eq!(4, calls.load(Ordering::SeqCst));
+        // The launches have released their references to the closure.
+        assert_eq!(1, Arc::strong_count(&calls));
+    }
+
+    #[test]
+    fn test_update_keeps_queued_host_nodes() {
+        let _context = crate::quick_init().unwrap();
+        let stream = Stream::new(StreamFlags::NON_BLOCKING, None).unwrap();
+        let old_calls = Arc::new(AtomicUsize::new(0));
+        let new_calls = Arc::new(AtomicUsize::new(0));
+        let host_graph = |calls: &Arc<AtomicUsize>| {
+            let graph = Graph::new().unwrap();
+            let counter = calls.clone();
+            let _ = graph
+                .add_host_node(&[], move || {
+                    let _ = counter.fetch_add(1, Ordering::SeqCst);
+                })
+                .unwrap();
+            graph
+        };
+
+        let mut exec = host_graph(&old_calls).instantiate().unwrap();
+        exec.launch(&stream).unwrap();
+        exec.update(&host_graph(&new_calls)).unwrap();
+        stream.synchronize().unwrap();
+        assert_eq!(1, old_calls.load(Ordering::SeqCst));
+        assert_eq!(1, Arc::strong_count(&old_calls));
+
+        exec.launch(&stream).unwrap();
+        stream.synchronize().unwrap();
+        assert_eq!(1, new_calls.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn test_pinned_params_relaunch() {
+        let _context = crate::quick_init().unwrap();
+        let ptx = CString::new(include_str!("../resources/add.ptx")).unwrap();
+        let module = Module::load_from_string(&ptx).unwrap();
+        let sum = module.get_function(&CString::new("sum").unwrap()).unwrap();
+        let stream = Stream::new(StreamFlags::NON_BLOCKING, None).unwrap();
+
+        let mut params = PinnedParams::new(&[1.0f32; 4]).unwrap();
+        let mut x = DeviceBuffer::from_slice(&[1.0f32; 4]).unwrap();
+        let mut out = DeviceBuffer::from_slice(&[0.0f32; 4]).unwrap();
+        let (mut a, mut b, mut c, mut n) = (
+            x.as_device_ptr(),
+            params.as_device_ptr(),
+            out.as_device_ptr(),
+            4i32,
+        );
+        let args = [
+            &mut a as *mut _ as *mut c_void,
+            &mut b as *mut _ as *mut c_void,
+            &mut c as *mut _ as *mut c_void,
+            &mut n as *mut _ as *mut c_void,
+        ];
+
+        let graph = Graph::new().unwrap();
+        unsafe {
+            let upload = params.add_upload_node(&graph, &[]).unwrap();
+            let _ = graph
+                .add_kernel_node(&[upload], &sum, 1, 4, 0, &args)
+                .unwrap();
+        }
+        let exec = graph.instantiate().unwrap();
+
+        let mut host = [0.0f32; 4];
+        params.launch(&exec, &stream).unwrap();
+        stream.synchronize().unwrap();
+        out.copy_to(&mut host[..]).unwrap();
+        assert_eq!([2.0f32; 4], host);
+
+        *params.host_mut().unwrap() = [5.0, 6.0, 7.0, 8.0];
+        assert_eq!(&[5.0f32, 6.0, 7.0, 8.0], params.host());
+        params.launch(&exec, &stream).unwrap();
+        stream.synchronize().unwrap();
+        out.copy_to(&mut host[..]).unwrap();
+        assert_eq!([6.0f32, 7.0, 8.0, 9.0], host);
+    }
+
+    #[test]
+    #[cfg(feature = "cuda-11-4")]
+    fn test_mem_nodes() {
+        let _context = crate::quick_init().unwrap();
+        let stream = Stream::new(StreamFlags::NON_BLOCKING, None).unwrap();
+        let graph = Graph::new().unwrap();
+        assert_eq!(
+            CudaError::InvalidValue,
+            graph.add_mem_alloc_node::<u32>(&[], 0).unwrap_err()
+        );
+        let (alloc, scratch) = match graph.add_mem_alloc_node::<u32>(&[], 16) {
+            Err(CudaError::NotSupported) => return,
+            result => result.unwrap(),
+        };
+
+        let mut output = [0u32; 16];
+        unsafe {
+            let slice = DeviceSlice::from_raw_parts_mut(scratch, 16);
+            let fill = graph.add_memset_node(&[alloc], slice, 7u32).unwrap();
+            let download = graph
+                .add_memcpy_dtoh_node(&[fill], &mut output, slice)
+                .unwrap();
+            let _ = graph.add_mem_free_node(&[download], scratch).unwrap();
+        }
+        let exec = graph.instantiate().unwrap();
+        for _ in 0..2 {
+            exec.launch(&stream).unwrap();
+        }
+        stream.synchronize().unwrap();
+        assert_eq!([7u32; 16], output);
+    }
+
+    #[test]
+    fn test_set_params_rejects_foreign_node() {
+        let _context = crate::quick_init().unwrap();
+        let empty = Graph::new().unwrap();
+        assert!(empty.nodes().unwrap().is_empty());
+        let mut exec = empty.instantiate().unwrap();
+
+        let other = Graph::new().unwrap();
+        let mut node = ptr::null_mut();
+        unsafe {
+            driver_call!(cuda_driver_sys::cuGraphAddEmptyNode(
+                &mut node,
+                other.inner,
+                ptr::null(),
+                0
+            ))
+            .to_result()
+            .unwrap();
+        }
+        let node = GraphNode::from_inner(node);
+        assert_eq!(
+            CudaError::InvalidValue,
+            unsafe { exec.set_params_batch(&[(node, &[][..])]) }.unwrap_err()
+        );
+    }
+}
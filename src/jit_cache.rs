@@ -0,0 +1,255 @@
+//! Control over the caches which save the cost of compiling kernels.
+//!
+//! Two caches are involved in loading a kernel:
+//!
+//! - The CUDA driver compiles PTX to machine code for the current device when a module is loaded,
+//!   and keeps the result in its JIT cache. The cache is configured through environment variables
+//!   which the driver reads once, during [`init`](../fn.init.html); the `set_driver_cache_*`
+//!   functions set them from Rust.
+//! - With the `nvrtc` feature, [`Module::load_from_nvrtc_program`](../module/struct.Module.html#method.load_from_nvrtc_program)
+//!   can cache the PTX it compiles in a directory, keyed by the source, the NVRTC version and the
+//!   compiler options. [`default_cache_dir`](fn.default_cache_dir.html) returns the conventional
+//!   location for that directory and [`evict`](fn.evict.html) keeps it from growing without
+//!   bound.
+//!
+//! # Examples
+//!
+//! ```
+//! use rustacuda::jit_cache;
+//!
+//! // Must happen before `rustacuda::init`, while no other threads are running.
+//! unsafe { jit_cache::set_driver_cache_max_size(1 << 30) };
+//!
+//! if let Some(dir) = jit_cache::default_cache_dir() {
+//!     // Pass `Some(&dir)` to `Module::load_from_nvrtc_program`, and occasionally trim the cache:
+//!     let _ = jit_cache::evict(&dir, 256 << 20);
+//! }
+//! ```
+
+use std::env;
+use std::ffi::{CStr, CString};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::SystemTime;
+
+/// Set the directory the driver keeps its JIT cache in, like setting `CUDA_CACHE_PATH`.
+///
+/// This only has an effect if it is called before [`init`](../fn.init.html), and it affects the
+/// whole process.
+///
+/// # Safety
+///
+/// This sets an environment variable, which is only sound while no other thread reads or writes
+/// the environment. Call it at the start of `main`, before spawning threads and before `init`.
+pub unsafe fn set_driver_cache_path(path: &Path) {
+    env::set_var("CUDA_CACHE_PATH", path);
+}
+
+/// Set the maximum size in bytes of the driver's JIT cache, like setting `CUDA_CACHE_MAXSIZE`.
+///
+/// This only has an effect if it is called before [`init`](../fn.init.html), and it affects the
+/// whole process.
+///
+/// # Safety
+///
+/// This sets an environment variable, which is only sound while no other thread reads or writes
+/// the environment. Call it at the start of `main`, before spawning threads and before `init`.
+pub unsafe fn set_driver_cache_max_size(bytes: u64) {
+    env::set_var("CUDA_CACHE_MAXSIZE", bytes.to_string());
+}
+
+/// Enable or disable the driver's JIT cache, like setting `CUDA_CACHE_DISABLE`. Disabling it makes
+/// the driver compile PTX every time a module is loaded, which is mostly useful for measuring
+/// cold-start times.
+///
+/// This only has an effect if it is called before [`init`](../fn.init.html), and it affects the
+/// whole process.
+///
+/// # Safety
+///
+/// This sets an environment variable, which is only sound while no other thread reads or writes
+/// the environment. Call it at the start of `main`, before spawning threads and before `init`.
+pub unsafe fn set_driver_cache_enabled(enabled: bool) {
+    env::set_var("CUDA_CACHE_DISABLE", if enabled { "0" } else { "1" });
+}
+
+/// Returns the conventional directory for RustaCUDA's compiled kernel cache:
+/// `$XDG_CACHE_HOME/rustacuda` or `~/.cache/rustacuda` on Unix, and `%LOCALAPPDATA%\rustacuda`
+/// on Windows. Returns `None` if the relevant environment variables are not set.
+///
+/// The directory is not created until something is cached in it.
+pub fn default_cache_dir() -> Option<PathBuf> {
+    let base = if cfg!(windows) {
+        env::var_os("LOCALAPPDATA").map(PathBuf::from)
+    } else {
+        env::var_os("XDG_CACHE_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".cache")))
+    };
+    base.map(|dir| dir.join("rustacuda"))
+}
+
+/// Delete the least recently used kernels from the cache in `dir` until the cache holds at most
+/// `max_bytes` bytes. Returns the number of bytes freed.
+///
+/// Only files created by the cache are considered, so it is safe to point this at a directory
+/// which holds other files as well. A missing directory counts as an empty cache.
+///
+/// # Errors
+///
+/// If the directory can't be read or a file can't be deleted, returns the I/O error.
+pub fn evict(dir: &Path, max_bytes: u64) -> io::Result<u64> {
+    let mut entries = cache_entries(dir)?;
+    let mut total: u64 = entries.iter().map(|(_, len, _)| len).sum();
+    // Oldest first.
+    entries.sort_by_key(|(_, _, used)| *used);
+
+    let mut freed = 0;
+    for (path, len, _) in entries {
+        if total <= max_bytes {
+            break;
+        }
+        fs::remove_file(&path)?;
+        total -= len;
+        freed += len;
+    }
+    Ok(freed)
+}
+
+/// Delete every kernel from the cache in `dir`. Returns the number of bytes freed.
+///
+/// # Errors
+///
+/// If the directory can't be read or a file can't be deleted, returns the I/O error.
+pub fn clear(dir: &Path) -> io::Result<u64> {
+    evict(dir, 0)
+}
+
+/// Returns the total size in bytes of the kernels in the cache in `dir`.
+///
+/// # Errors
+///
+/// If the directory can't be read, returns the I/O error.
+pub fn cache_size(dir: &Path) -> io::Result<u64> {
+    Ok(cache_entries(dir)?.iter().map(|(_, len, _)| len).sum())
+}
+
+// Returns the path, size and last use time of every cached kernel in `dir`.
+fn cache_entries(dir: &Path) -> io::Result<Vec<(PathBuf, u64, SystemTime)>> {
+    let read_dir = match fs::read_dir(dir) {
+        Ok(read_dir) => read_dir,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let mut entries = Vec::new();
+    for entry in read_dir {
+        let entry = entry?;
+        let path = entry.path();
+        if path.extension().is_none_or(|ext| ext != "ptx") {
+            continue;
+        }
+        let metadata = entry.metadata()?;
+        let used = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+        entries.push((path, metadata.len(), used));
+    }
+    Ok(entries)
+}
+
+// Returns the file which caches the PTX compiled from `source`. `key` identifies the compiler
+// version and options.
+fn cache_file(dir: &Path, source: &CStr, key: &str) -> PathBuf {
+    // FNV-1a, so that cache file names are stable across Rust versions.
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in source.to_bytes().iter().chain(&[0]).chain(key.as_bytes()) {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    dir.join(format!("{:016x}.ptx", hash))
+}
+
+/// Look up the PTX compiled from `source` with the compiler and options identified by `key` in the
+/// cache in `dir`. A hit marks the entry as recently used, so that `evict` keeps it.
+#[cfg_attr(not(feature = "nvrtc"), allow(dead_code))]
+pub(crate) fn load(dir: &Path, source: &CStr, key: &str) -> Option<CString> {
+    let file = cache_file(dir, source, key);
+    let ptx = CString::new(fs::read(&file).ok()?).ok()?;
+    if let Ok(f) = fs::File::options().append(true).open(&file) {
+        let _ = f.set_modified(SystemTime::now());
+    }
+    Some(ptx)
+}
+
+/// Store the PTX compiled from `source` with the compiler and options identified by `key` in the
+/// cache in `dir`, replacing any existing entry. Failures are ignored, since the cache is only an
+/// optimization.
+#[cfg_attr(not(feature = "nvrtc"), allow(dead_code))]
+pub(crate) fn store(dir: &Path, source: &CStr, key: &str, ptx: &CStr) {
+    let file = cache_file(dir, source, key);
+    // Write to a temporary file first so that concurrent readers never see partial PTX.
+    let tmp = file.with_extension(format!("ptx.{}.tmp", process::id()));
+    let written = fs::create_dir_all(dir)
+        .and_then(|_| fs::write(&tmp, ptx.to_bytes()))
+        .and_then(|_| fs::rename(&tmp, &file));
+    if written.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::time::Duration;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("rustacuda-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_store_and_load() {
+        let dir = temp_dir("store");
+        let source = CString::new("source").unwrap();
+        let ptx = CString::new("ptx").unwrap();
+        assert_eq!(None, load(&dir, &source, "sm_70"));
+
+        store(&dir, &source, "sm_70", &ptx);
+        assert_eq!(Some(ptx), load(&dir, &source, "sm_70"));
+        assert_eq!(None, load(&dir, &source, "sm_80"));
+        assert_eq!(3, cache_size(&dir).unwrap());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_evict_oldest_first() {
+        let dir = temp_dir("evict");
+        assert_eq!(0, evict(&dir, 0).unwrap());
+
+        let now = SystemTime::now();
+        for (i, name) in ["a", "b", "c"].iter().enumerate() {
+            let source = CString::new(*name).unwrap();
+            store(&dir, &source, "sm_70", &CString::new("0123456789").unwrap());
+            // Make "a" the oldest and "c" the newest.
+            let age = Duration::from_secs(100 * (3 - i as u64));
+            fs::File::options()
+                .append(true)
+                .open(cache_file(&dir, &source, "sm_70"))
+                .unwrap()
+                .set_modified(now - age)
+                .unwrap();
+        }
+        fs::write(dir.join("unrelated.txt"), "not a kernel").unwrap();
+
+        assert_eq!(10, evict(&dir, 25).unwrap());
+        let a = CString::new("a").unwrap();
+        assert!(!cache_file(&dir, &a, "sm_70").exists());
+        assert_eq!(20, cache_size(&dir).unwrap());
+
+        assert_eq!(20, clear(&dir).unwrap());
+        assert!(dir.join("unrelated.txt").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,157 @@
+//! Generation of C headers describing structs shared between Rust and CUDA C kernels.
+//!
+//! A struct passed to a kernel, or stored in device memory and read by one, has to be declared
+//! twice: once in Rust and once in CUDA C. If the two drift apart, the kernel silently reads
+//! garbage. Deriving [`KernelStruct`](trait.KernelStruct.html) on a `#[repr(C)]` struct records
+//! its layout, and a [`KernelHeader`](struct.KernelHeader.html) turns that into a C header with
+//! the struct's declaration and `static_assert`s on its size and field offsets, which the kernel
+//! source can `#include`.
+//!
+//! A procedural macro can't write files, so the header has to be written by code which runs as
+//! part of the build. Usually this is a test which writes the header next to the kernel source,
+//! so that the header is regenerated (and the change shows up in version control) whenever the
+//! Rust definition changes. The header is only rewritten when its contents change, so it doesn't
+//! cause unnecessary rebuilds of the kernels.
+//!
+//! # Examples
+//!
+//! ```
+//! #[macro_use]
+//! extern crate rustacuda;
+//! extern crate rustacuda_core;
+//!
+//! use rustacuda::kernel_header::KernelHeader;
+//! use rustacuda::memory::DevicePointer;
+//!
+//! #[derive(Clone, Copy, KernelStruct)]
+//! #[repr(C)]
+//! struct Particle {
+//!     position: [f32; 3],
+//!     mass: f32,
+//! }
+//!
+//! #[derive(Clone, Copy, KernelStruct)]
+//! #[repr(C)]
+//! struct StepParams {
+//!     particles: DevicePointer<Particle>,
+//!     count: u32,
+//!     dt: f32,
+//! }
+//!
+//! # fn main() {
+//! let header = KernelHeader::new()
+//!     .add::<Particle>()
+//!     .add::<StepParams>();
+//! assert!(header.render().contains("    Particle* particles;\n"));
+//! # let dir = std::env::temp_dir().join(format!("rustacuda-doc-{}", std::process::id()));
+//! # std::fs::create_dir_all(&dir).unwrap();
+//! # let path = dir.join("step_params.h");
+//! // In a test: header.write("kernels/step_params.h").unwrap();
+//! header.write(&path).unwrap();
+//! # std::fs::remove_dir_all(&dir).unwrap();
+//! # }
+//! ```
+
+pub use rustacuda_core::{CType, KernelField, KernelStruct};
+
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::mem::{align_of, size_of};
+use std::path::Path;
+
+/// Returns the C declaration of `T`, followed by `static_assert`s which check that the C
+/// compiler lays it out the same way as Rust.
+pub fn c_declaration<T: KernelStruct>() -> String {
+    let name = T::C_NAME;
+    let mut decl = String::new();
+    let _ = writeln!(decl, "typedef struct {} {{", name);
+    for field in T::FIELDS {
+        let _ = write!(
+            decl,
+            "    {}{} {}",
+            field.c_type,
+            "*".repeat(field.pointers),
+            field.name
+        );
+        if field.array_len != 0 {
+            let _ = write!(decl, "[{}]", field.array_len);
+        }
+        decl.push_str(";\n");
+    }
+    let _ = writeln!(decl, "}} {};", name);
+    let _ = writeln!(
+        decl,
+        "static_assert(sizeof({0}) == {1}, \"{0} does not match its Rust definition\");",
+        name,
+        size_of::<T>()
+    );
+    let _ = writeln!(
+        decl,
+        "static_assert(alignof({0}) == {1}, \"{0} does not match its Rust definition\");",
+        name,
+        align_of::<T>()
+    );
+    for field in T::FIELDS {
+        let _ = writeln!(
+            decl,
+            "static_assert(offsetof({0}, {1}) == {2}, \"{0}.{1} does not match its Rust definition\");",
+            name, field.name, field.offset
+        );
+    }
+    decl
+}
+
+/// A C header declaring a set of [`KernelStruct`](trait.KernelStruct.html)s.
+///
+/// Structs are declared in the order they are added, so a struct must be added after any structs
+/// it contains by value.
+#[derive(Debug, Clone, Default)]
+pub struct KernelHeader {
+    declarations: Vec<String>,
+}
+impl KernelHeader {
+    /// Create a header which declares no structs.
+    pub fn new() -> Self {
+        KernelHeader::default()
+    }
+
+    /// Add the declaration of `T` to the header.
+    pub fn add<T: KernelStruct>(mut self) -> Self {
+        self.declarations.push(c_declaration::<T>());
+        self
+    }
+
+    /// Returns the contents of the header.
+    pub fn render(&self) -> String {
+        let mut header = String::from(
+            "// Generated by RustaCUDA from the Rust definitions of these structs. Do not edit.\n\
+             #pragma once\n\
+             \n\
+             #include <assert.h>\n\
+             #include <stdalign.h>\n\
+             #include <stdbool.h>\n\
+             #include <stddef.h>\n\
+             #include <stdint.h>\n",
+        );
+        for decl in &self.declarations {
+            header.push('\n');
+            header.push_str(decl);
+        }
+        header
+    }
+
+    /// Write the header to `path`, unless the file already has exactly these contents.
+    ///
+    /// # Errors
+    ///
+    /// If the file can't be written, returns the I/O error.
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        let contents = self.render();
+        match fs::read(path) {
+            Ok(existing) if existing == contents.as_bytes() => Ok(()),
+            _ => fs::write(path, contents),
+        }
+    }
+}
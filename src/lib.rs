@@ -152,7 +152,6 @@
 
 #[macro_use]
 extern crate bitflags;
-//extern crate cuda_sys;
 extern crate rustacuda_core;
 
 #[allow(unused_imports, clippy::useless_attribute)]
@@ -161,21 +160,44 @@ extern crate rustacuda_derive;
 #[doc(hidden)]
 pub use rustacuda_derive::*;
 
+#[macro_use]
+mod driver_call;
+
 pub mod context;
+#[cfg(feature = "cpu-fallback")]
+pub mod cpu;
 pub mod device;
 pub mod error;
 pub mod event;
+pub mod executor;
 pub mod function;
+pub mod gpu;
+pub mod graph;
+pub mod jit_cache;
+pub mod kernel_header;
 pub mod memory;
 pub mod module;
+#[cfg(feature = "nvml")]
+pub mod nvml;
 pub mod prelude;
+pub mod replay;
 pub mod stream;
+pub mod utils;
+#[cfg(feature = "verification")]
+pub mod verification;
 
 mod derive_compile_fail;
+#[cfg(feature = "nvrtc")]
+mod nvrtc;
+mod nvtx;
+mod sync;
+mod teardown;
+mod trace;
 
-use crate::context::{Context, ContextFlags};
+use crate::context::{Context, ContextFlags, ContextHandle, CurrentContext};
 use crate::device::Device;
-use crate::error::{CudaResult, ToResult};
+use crate::error::{CudaError, CudaResult, ToResult};
+use crate::stream::{Stream, StreamFlags};
 use cuda_driver_sys::{cuDriverGetVersion, cuInit};
 
 bitflags! {
@@ -196,8 +218,12 @@ bitflags! {
 ///
 /// The `flags` parameter is used to configure the CUDA API. Currently no flags are defined, so
 /// it must be `CudaFlags::empty()`.
+///
+/// If RustaCUDA was built with one of the `cuda-11-*` features, this returns
+/// `CudaError::InsufficientDriver` when the installed driver is older than that version.
 pub fn init(flags: CudaFlags) -> CudaResult<()> {
-    unsafe { cuInit(flags.bits()).to_result() }
+    unsafe { driver_call!(cuInit(flags.bits())).to_result()? };
+    driver_call::check_driver_version()
 }
 
 /// Shortcut for initializing the CUDA Driver API and creating a CUDA context with default settings
@@ -207,9 +233,100 @@ pub fn init(flags: CudaFlags) -> CudaResult<()> {
 /// complex needs (multiple devices, custom flags, etc.) should use `init` and create their own
 /// context.
 pub fn quick_init() -> CudaResult<Context> {
+    quick_init_on(0)
+}
+
+/// Like [`quick_init`](fn.quick_init.html), but creates the context for the device with the given
+/// ordinal instead of the first device.
+///
+/// # Errors
+///
+/// Returns `InvalidDevice` if there is no device with that ordinal.
+pub fn quick_init_on(device_index: u32) -> CudaResult<Context> {
+    init(CudaFlags::empty())?;
+    let device = Device::get_device(device_index)?;
+    quick_init_with(ContextFlags::MAP_HOST | ContextFlags::SCHED_AUTO, device)
+}
+
+/// Initialize the CUDA Driver API and create a context with the given flags for `device`, making
+/// it current.
+pub fn quick_init_with(flags: ContextFlags, device: Device) -> CudaResult<Context> {
     init(CudaFlags::empty())?;
-    let device = Device::get_device(0)?;
-    Context::create_and_push(ContextFlags::MAP_HOST | ContextFlags::SCHED_AUTO, device)
+    Context::create_and_push(flags, device)
+}
+
+/// A context together with a stream in it, for examples and tools which need both and want to pass
+/// them around as one value.
+///
+/// The stream is dropped before the context it belongs to.
+///
+/// # Examples
+///
+/// ```
+/// # use rustacuda::*;
+/// # use std::error::Error;
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// use rustacuda::memory::{AsyncCopyDestination, DeviceBuffer};
+///
+/// let session = CudaSession::new()?;
+/// let mut buffer = DeviceBuffer::from_slice(&[0u32; 16])?;
+/// unsafe { buffer.async_copy_from(&[1u32; 16][..], session.stream())? };
+/// session.stream().synchronize()?;
+/// println!("Ran on {}", session.device()?.name()?);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct CudaSession {
+    // Declared before the context so that it is dropped first.
+    stream: Stream,
+    context: Context,
+}
+impl CudaSession {
+    /// Create a session on the first device, with a context created like
+    /// [`quick_init`](fn.quick_init.html) and a non-blocking stream.
+    pub fn new() -> CudaResult<Self> {
+        CudaSession::on(0)
+    }
+
+    /// Create a session on the device with the given ordinal.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidDevice` if there is no device with that ordinal.
+    pub fn on(device_index: u32) -> CudaResult<Self> {
+        CudaSession::from_context(quick_init_on(device_index)?)
+    }
+
+    /// Create a session from an existing context, which must be current, and a new non-blocking
+    /// stream in it.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidContext` if `context` is not the current context. If the stream can't be
+    /// created, returns the CUDA error value.
+    pub fn from_context(context: Context) -> CudaResult<Self> {
+        if CurrentContext::get_current()?.get_inner() != context.get_inner() {
+            return Err(CudaError::InvalidContext);
+        }
+        let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+        Ok(CudaSession { stream, context })
+    }
+
+    /// Returns the session's context.
+    pub fn context(&self) -> &Context {
+        &self.context
+    }
+
+    /// Returns the session's stream.
+    pub fn stream(&self) -> &Stream {
+        &self.stream
+    }
+
+    /// Returns the device of the session's context.
+    pub fn device(&self) -> CudaResult<Device> {
+        self.context.get_unowned().get_device()
+    }
 }
 
 /// Struct representing the CUDA API version number.
@@ -222,7 +339,7 @@ impl CudaApiVersion {
     pub fn get() -> CudaResult<CudaApiVersion> {
         unsafe {
             let mut version: i32 = 0;
-            cuDriverGetVersion(&mut version as *mut i32).to_result()?;
+            driver_call!(cuDriverGetVersion(&mut version as *mut i32)).to_result()?;
             Ok(CudaApiVersion { version })
         }
     }
@@ -251,6 +368,35 @@ mod test {
         assert_eq!(version.minor(), 2);
     }
 
+    #[test]
+    fn test_thread_safety() {
+        fn send_sync<T: Send + Sync>() {}
+        fn send<T: Send>() {}
+
+        send_sync::<Context>();
+        send_sync::<module::Module>();
+        send_sync::<function::Function<'static>>();
+        send_sync::<Stream>();
+        send_sync::<event::Event>();
+        send_sync::<memory::DeviceBuffer<f32>>();
+        send_sync::<memory::DeviceBox<f32>>();
+        send_sync::<memory::LockedBuffer<f32>>();
+        send_sync::<memory::LockedVec<f32>>();
+        send_sync::<memory::UnifiedBuffer<f32>>();
+        send_sync::<memory::UnifiedVec<f32>>();
+        send::<graph::Graph>();
+        send::<graph::GraphExec>();
+    }
+
+    #[test]
+    fn test_session_from_context_not_current() {
+        let context = quick_init().unwrap();
+        let device = Device::get_device(0).unwrap();
+        let _other = Context::create_and_push(ContextFlags::SCHED_AUTO, device).unwrap();
+        let err = CudaSession::from_context(context).unwrap_err();
+        assert_eq!(CudaError::InvalidContext, err);
+    }
+
     #[test]
     fn test_init_twice() {
         init(CudaFlags::empty()).unwrap();
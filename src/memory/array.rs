@@ -3,14 +3,20 @@
 //! Detailed documentation about allocating CUDA Arrays can be found in the
 //! [CUDA Driver API](https://docs.nvidia.com/cuda/cuda-driver-api/group__CUDA__MEM.html#group__CUDA__MEM_1gc2322c70b38c2984536c90ed118bb1d7)
 
-use std::mem::MaybeUninit;
-use std::os::raw::c_uint;
+use std::marker::PhantomData;
+use std::mem::{self, size_of, size_of_val, MaybeUninit};
+use std::os::raw::{c_uint, c_void};
 
-use cuda_driver_sys::{CUarray, CUarray_format, CUarray_format_enum};
+use cuda_driver_sys::{
+    CUarray, CUarray_format, CUarray_format_enum, CUdeviceptr, CUmemorytype_enum, CUDA_MEMCPY3D,
+};
 
 use crate::context::CurrentContext;
 use crate::device::DeviceAttribute;
 use crate::error::*;
+use crate::memory::texture::{SurfaceObject, TextureDesc, TextureObject};
+use crate::memory::{AsyncCopyDestination, CopyDestination, DeviceBuffer, DeviceCopy, DeviceSlice};
+use crate::stream::Stream;
 
 /// Describes the format used for a CUDA Array.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -61,6 +67,15 @@ impl ArrayFormat {
             ArrayFormat::Float => CUarray_format_enum::CU_AD_FORMAT_FLOAT,
         }
     }
+
+    /// Returns the size in bytes of a single channel of this format.
+    pub fn size_in_bytes(self) -> usize {
+        match self {
+            ArrayFormat::UnsignedInt8 | ArrayFormat::SignedInt8 => 1,
+            ArrayFormat::UnsignedInt16 | ArrayFormat::SignedInt16 | ArrayFormat::Half => 2,
+            ArrayFormat::UnsignedInt32 | ArrayFormat::SignedInt32 | ArrayFormat::Float => 4,
+        }
+    }
 }
 
 bitflags! {
@@ -217,146 +232,120 @@ pub struct ArrayObject {
 
 impl ArrayObject {
     /// Constructs a generic ArrayObject from an `ArrayDescriptor`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidValue` if the descriptor does not describe a valid array: the width is zero,
+    /// the depth is non-zero without a height (unless the array is `LAYERED`), a `CUBEMAP` is not
+    /// square or does not have a depth of 6 (or a multiple of 6 if `LAYERED`), the number of
+    /// channels is not 1, 2 or 4, or the dimensions exceed the limits of the current device.
     pub fn from_descriptor(descriptor: &ArrayDescriptor) -> CudaResult<Self> {
-        // We validate the descriptor up front in debug mode. This provides a good error message to
-        // the user when they get something wrong, but doesn't re-validate in release mode.
+        // We validate the descriptor up front in debug mode. This catches mistakes before they
+        // reach the driver, but doesn't re-validate in release mode where the driver will reject
+        // invalid descriptors with the same error.
         if cfg!(debug_assertions) {
-            assert_ne!(
-                0,
-                descriptor.width(),
-                "Cannot allocate an array with 0 Width"
-            );
+            // Cannot allocate an array with 0 Width.
+            if descriptor.width() == 0 {
+                return Err(CudaError::InvalidValue);
+            }
 
-            if !descriptor.flags().contains(ArrayObjectFlags::LAYERED) && descriptor.depth() > 0 {
-                assert_ne!(
-                    0,
-                    descriptor.height(),
-                    "If Depth is non-zero and the descriptor is not LAYERED, then Height must also \
-                    be non-zero."
-                );
+            // If Depth is non-zero and the descriptor is not LAYERED, then Height must also be
+            // non-zero.
+            if !descriptor.flags().contains(ArrayObjectFlags::LAYERED)
+                && descriptor.depth() > 0
+                && descriptor.height() == 0
+            {
+                return Err(CudaError::InvalidValue);
             }
 
             if descriptor.flags().contains(ArrayObjectFlags::CUBEMAP) {
-                assert_eq!(
-                    descriptor.height(),
-                    descriptor.width(),
-                    "Height and Width must be equal for CUBEMAP arrays."
-                );
+                // Height and Width must be equal for CUBEMAP arrays.
+                if descriptor.height() != descriptor.width() {
+                    return Err(CudaError::InvalidValue);
+                }
 
-                if descriptor.flags().contains(ArrayObjectFlags::LAYERED) {
-                    assert_eq!(
-                        0,
-                        descriptor.depth() % 6,
-                        "Depth must be a multiple of 6 when the array descriptor is for a LAYERED \
-                         CUBEMAP."
-                    );
+                // Depth must be a multiple of 6 for a LAYERED CUBEMAP and exactly 6 otherwise.
+                let depth_valid = if descriptor.flags().contains(ArrayObjectFlags::LAYERED) {
+                    descriptor.depth().checked_rem(6) == Some(0)
                 } else {
-                    assert_eq!(
-                        6,
-                        descriptor.depth(),
-                        "Depth must be equal to 6 when the array descriptor is for a CUBEMAP."
-                    );
+                    descriptor.depth() == 6
+                };
+                if !depth_valid {
+                    return Err(CudaError::InvalidValue);
                 }
             }
 
-            assert!(
-                descriptor.num_channels() == 1
-                    || descriptor.num_channels() == 2
-                    || descriptor.num_channels() == 4,
-                "NumChannels was set to {}. It must be 1, 2, or 4.",
-                descriptor.num_channels()
-            );
+            // NumChannels must be 1, 2, or 4.
+            match descriptor.num_channels() {
+                1 | 2 | 4 => {}
+                _ => return Err(CudaError::InvalidValue),
+            }
 
             // Exhaustively check bounds of arrays
             let device = CurrentContext::get_device()?;
 
-            let attr = |attr| Ok(1..=(device.get_attribute(attr)? as usize));
+            let attr = |attr| CudaResult::Ok(1..=(device.get_attribute(attr)? as usize));
 
-            let (description, bounds) = if descriptor.flags().contains(ArrayObjectFlags::CUBEMAP) {
+            let bounds = if descriptor.flags().contains(ArrayObjectFlags::CUBEMAP) {
                 if descriptor.flags().contains(ArrayObjectFlags::LAYERED) {
-                    (
-                        "Layered Cubemap",
-                        vec![[
-                            attr(DeviceAttribute::MaximumTextureCubemapLayeredWidth)?,
-                            attr(DeviceAttribute::MaximumTextureCubemapLayeredWidth)?,
-                            attr(DeviceAttribute::MaximumTextureCubemapLayeredLayers)?,
-                        ]],
-                    )
+                    vec![[
+                        attr(DeviceAttribute::MaximumTextureCubemapLayeredWidth)?,
+                        attr(DeviceAttribute::MaximumTextureCubemapLayeredWidth)?,
+                        attr(DeviceAttribute::MaximumTextureCubemapLayeredLayers)?,
+                    ]]
                 } else {
-                    (
-                        "Cubemap",
-                        vec![[
-                            attr(DeviceAttribute::MaximumTextureCubemapWidth)?,
-                            attr(DeviceAttribute::MaximumTextureCubemapWidth)?,
-                            6..=6,
-                        ]],
-                    )
+                    vec![[
+                        attr(DeviceAttribute::MaximumTextureCubemapWidth)?,
+                        attr(DeviceAttribute::MaximumTextureCubemapWidth)?,
+                        6..=6,
+                    ]]
                 }
             } else if descriptor.flags().contains(ArrayObjectFlags::LAYERED) {
                 if descriptor.height() > 0 {
-                    (
-                        "2D Layered",
-                        vec![[
-                            attr(DeviceAttribute::MaximumTexture2DLayeredWidth)?,
-                            attr(DeviceAttribute::MaximumTexture2DLayeredHeight)?,
-                            attr(DeviceAttribute::MaximumTexture2DLayeredLayers)?,
-                        ]],
-                    )
+                    vec![[
+                        attr(DeviceAttribute::MaximumTexture2DLayeredWidth)?,
+                        attr(DeviceAttribute::MaximumTexture2DLayeredHeight)?,
+                        attr(DeviceAttribute::MaximumTexture2DLayeredLayers)?,
+                    ]]
                 } else {
-                    (
-                        "1D Layered",
-                        vec![[
-                            attr(DeviceAttribute::MaximumTexture1DLayeredWidth)?,
-                            0..=0,
-                            attr(DeviceAttribute::MaximumTexture1DLayeredLayers)?,
-                        ]],
-                    )
+                    vec![[
+                        attr(DeviceAttribute::MaximumTexture1DLayeredWidth)?,
+                        0..=0,
+                        attr(DeviceAttribute::MaximumTexture1DLayeredLayers)?,
+                    ]]
                 }
             } else if descriptor.depth() > 0 {
-                (
-                    "3D",
-                    vec![
-                        [
-                            attr(DeviceAttribute::MaximumTexture3DWidth)?,
-                            attr(DeviceAttribute::MaximumTexture3DHeight)?,
-                            attr(DeviceAttribute::MaximumTexture3DDepth)?,
-                        ],
-                        [
-                            attr(DeviceAttribute::MaximumTexture3DWidthAlternate)?,
-                            attr(DeviceAttribute::MaximumTexture3DHeightAlternate)?,
-                            attr(DeviceAttribute::MaximumTexture3DDepthAlternate)?,
-                        ],
+                vec![
+                    [
+                        attr(DeviceAttribute::MaximumTexture3DWidth)?,
+                        attr(DeviceAttribute::MaximumTexture3DHeight)?,
+                        attr(DeviceAttribute::MaximumTexture3DDepth)?,
+                    ],
+                    [
+                        attr(DeviceAttribute::MaximumTexture3DWidthAlternate)?,
+                        attr(DeviceAttribute::MaximumTexture3DHeightAlternate)?,
+                        attr(DeviceAttribute::MaximumTexture3DDepthAlternate)?,
                     ],
-                )
+                ]
             } else if descriptor.height() > 0 {
                 if descriptor
                     .flags()
                     .contains(ArrayObjectFlags::TEXTURE_GATHER)
                 {
-                    (
-                        "2D Texture Gather",
-                        vec![[
-                            attr(DeviceAttribute::MaximumTexture2DGatherWidth)?,
-                            attr(DeviceAttribute::MaximumTexture2DGatherHeight)?,
-                            0..=0,
-                        ]],
-                    )
+                    vec![[
+                        attr(DeviceAttribute::MaximumTexture2DGatherWidth)?,
+                        attr(DeviceAttribute::MaximumTexture2DGatherHeight)?,
+                        0..=0,
+                    ]]
                 } else {
-                    (
-                        "2D",
-                        vec![[
-                            attr(DeviceAttribute::MaximumTexture2DWidth)?,
-                            attr(DeviceAttribute::MaximumTexture2DHeight)?,
-                            0..=0,
-                        ]],
-                    )
+                    vec![[
+                        attr(DeviceAttribute::MaximumTexture2DWidth)?,
+                        attr(DeviceAttribute::MaximumTexture2DHeight)?,
+                        0..=0,
+                    ]]
                 }
             } else {
-                assert!(descriptor.width() > 0);
-                (
-                    "1D",
-                    vec![[attr(DeviceAttribute::MaximumTexture1DWidth)?, 0..=0, 0..=0]],
-                )
+                vec![[attr(DeviceAttribute::MaximumTexture1DWidth)?, 0..=0, 0..=0]]
             };
 
             let bounds_invalid = |x: &[::std::ops::RangeInclusive<usize>; 3]| {
@@ -365,21 +354,20 @@ impl ArrayObject {
                     && (descriptor.depth() >= *x[2].start() && descriptor.depth() <= *x[2].end())
             };
 
+            // The dimensions of the array must fall within the device's limits for its kind.
             if !bounds.iter().any(bounds_invalid) {
-                panic!(
-                    "The dimensions of the {} ArrayObject did not fall within the valid bounds for \
-                     the array. descriptor = {:?}, dims = {:?}, valid bounds = {:?}",
-                     description,
-                     descriptor,
-                     [descriptor.width(), descriptor.height(), descriptor.depth()],
-                     bounds
-                );
+                return Err(CudaError::InvalidValue);
             }
         }
 
         let mut handle = MaybeUninit::uninit();
-        unsafe { cuda_driver_sys::cuArray3DCreate_v2(handle.as_mut_ptr(), &descriptor.desc) }
-            .to_result()?;
+        unsafe {
+            driver_call!(cuda_driver_sys::cuArray3DCreate_v2(
+                handle.as_mut_ptr(),
+                &descriptor.desc
+            ))
+        }
+        .to_result()?;
         Ok(Self {
             handle: unsafe { handle.assume_init() },
         })
@@ -631,7 +619,10 @@ impl ArrayObject {
         // Use "zeroed" incase CUDA_ARRAY3D_DESCRIPTOR has uninitialized padding
         let mut raw_descriptor = MaybeUninit::zeroed();
         unsafe {
-            cuda_driver_sys::cuArray3DGetDescriptor_v2(raw_descriptor.as_mut_ptr(), self.handle)
+            driver_call!(cuda_driver_sys::cuArray3DGetDescriptor_v2(
+                raw_descriptor.as_mut_ptr(),
+                self.handle
+            ))
         }
         .to_result()?;
 
@@ -640,16 +631,494 @@ impl ArrayObject {
         }))
     }
 
+    /// Copy the data in `source` into a single layer of this array.
+    ///
+    /// For layered arrays, `layer` is the index of the layer; for 3D arrays, it is the index of a
+    /// 2D slice along the depth. `source` must hold exactly one layer of data, laid out row by row
+    /// with the channels of each element interleaved.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidValue` if `layer` is out of range or the size of `source` doesn't match the
+    /// size of a layer. Otherwise, if the copy fails, returns the error from CUDA.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # let _context = rustacuda::quick_init().unwrap();
+    /// use rustacuda::memory::array::{ArrayFormat, ArrayObject};
+    /// let mut array = ArrayObject::new_layered([4, 4], 3, ArrayFormat::Float, 1).unwrap();
+    /// array.copy_layer_from(1, &[1.0f32; 16]).unwrap();
+    ///
+    /// let mut layer = [0.0f32; 16];
+    /// array.copy_layer_to(1, &mut layer).unwrap();
+    /// assert_eq!([1.0f32; 16], layer);
+    /// ```
+    pub fn copy_layer_from<T: DeviceCopy>(&mut self, layer: usize, source: &[T]) -> CudaResult<()> {
+        let [width, height, _] = self.extent()?;
+        let mut copy = self.host_copy([0, 0, layer], [width, height, 1], size_of_val(source))?;
+        set_host_source(&mut copy, source);
+        unsafe { driver_call!(cuda_driver_sys::cuMemcpy3D_v2(&copy as *const _)).to_result() }
+    }
+
+    /// Copy a single layer of this array into `dest`.
+    ///
+    /// See [`copy_layer_from`](#method.copy_layer_from) for the meaning of `layer` and the
+    /// layout of the data.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidValue` if `layer` is out of range or the size of `dest` doesn't match the
+    /// size of a layer. Otherwise, if the copy fails, returns the error from CUDA.
+    pub fn copy_layer_to<T: DeviceCopy>(&self, layer: usize, dest: &mut [T]) -> CudaResult<()> {
+        let [width, height, _] = self.extent()?;
+        let mut copy = self.host_copy([0, 0, layer], [width, height, 1], size_of_val(dest))?;
+        set_host_dest(&mut copy, dest);
+        unsafe { driver_call!(cuda_driver_sys::cuMemcpy3D_v2(&copy as *const _)).to_result() }
+    }
+
+    /// Copy the data in `source` into the region of this array which starts at `offset` and has
+    /// the size `extent`, leaving the rest of the array unchanged.
+    ///
+    /// `offset` and `extent` are given in elements as `[x, y, z]`. The array's missing dimensions
+    /// count as having a size of one, so a region of a 2D array has the form `[x, y, 0]` +
+    /// `[width, height, 1]`; for layered arrays, `z` is the layer. `source` must hold exactly
+    /// the region's data, laid out row by row with the channels of each element interleaved.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidValue` if the region doesn't fit in the array or the size of `source`
+    /// doesn't match the size of the region. Otherwise, if the copy fails, returns the error from
+    /// CUDA.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # let _context = rustacuda::quick_init().unwrap();
+    /// use rustacuda::memory::array::{ArrayFormat, ArrayObject};
+    /// let mut array = ArrayObject::new_2d([256, 256], ArrayFormat::UnsignedInt8, 4).unwrap();
+    ///
+    /// // Update one 16x16 tile of an RGBA texture.
+    /// let tile = [255u8; 16 * 16 * 4];
+    /// array.copy_region_from([32, 48, 0], [16, 16, 1], &tile).unwrap();
+    /// ```
+    pub fn copy_region_from<T: DeviceCopy>(
+        &mut self,
+        offset: [usize; 3],
+        extent: [usize; 3],
+        source: &[T],
+    ) -> CudaResult<()> {
+        let mut copy = self.host_copy(offset, extent, size_of_val(source))?;
+        set_host_source(&mut copy, source);
+        unsafe { driver_call!(cuda_driver_sys::cuMemcpy3D_v2(&copy as *const _)).to_result() }
+    }
+
+    /// Copy the region of this array which starts at `offset` and has the size `extent` into
+    /// `dest`.
+    ///
+    /// See [`copy_region_from`](#method.copy_region_from) for the meaning of `offset` and
+    /// `extent` and the layout of the data.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidValue` if the region doesn't fit in the array or the size of `dest`
+    /// doesn't match the size of the region. Otherwise, if the copy fails, returns the error from
+    /// CUDA.
+    pub fn copy_region_to<T: DeviceCopy>(
+        &self,
+        offset: [usize; 3],
+        extent: [usize; 3],
+        dest: &mut [T],
+    ) -> CudaResult<()> {
+        let mut copy = self.host_copy(offset, extent, size_of_val(dest))?;
+        set_host_dest(&mut copy, dest);
+        unsafe { driver_call!(cuda_driver_sys::cuMemcpy3D_v2(&copy as *const _)).to_result() }
+    }
+
+    /// Asynchronously copy the data in `source` into a region of this array. See
+    /// [`copy_region_from`](#method.copy_region_from).
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidValue` if the region doesn't fit in the array or the size of `source`
+    /// doesn't match the size of the region. Otherwise, if the copy fails, returns the error from
+    /// CUDA.
+    ///
+    /// # Safety
+    ///
+    /// `source` must not be modified or dropped until the copy has completed. It should be
+    /// page-locked for the copy to be truly asynchronous.
+    #[track_caller]
+    pub unsafe fn async_copy_region_from<T: DeviceCopy>(
+        &mut self,
+        offset: [usize; 3],
+        extent: [usize; 3],
+        source: &[T],
+        stream: &Stream,
+    ) -> CudaResult<()> {
+        let mut copy = self.host_copy(offset, extent, size_of_val(source))?;
+        set_host_source(&mut copy, source);
+        driver_call!(cuda_driver_sys::cuMemcpy3DAsync_v2(
+            &copy as *const _,
+            stream.as_inner()
+        ))
+        .to_result()?;
+        stream.trace(|| format!("host-to-array async copy of {} bytes", size_of_val(source)));
+        Ok(())
+    }
+
+    /// Asynchronously copy a region of this array into `dest`. See
+    /// [`copy_region_from`](#method.copy_region_from).
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidValue` if the region doesn't fit in the array or the size of `dest`
+    /// doesn't match the size of the region. Otherwise, if the copy fails, returns the error from
+    /// CUDA.
+    ///
+    /// # Safety
+    ///
+    /// `dest` must not be read, modified or dropped until the copy has completed. It should be
+    /// page-locked for the copy to be truly asynchronous.
+    #[track_caller]
+    pub unsafe fn async_copy_region_to<T: DeviceCopy>(
+        &self,
+        offset: [usize; 3],
+        extent: [usize; 3],
+        dest: &mut [T],
+        stream: &Stream,
+    ) -> CudaResult<()> {
+        let mut copy = self.host_copy(offset, extent, size_of_val(dest))?;
+        set_host_dest(&mut copy, dest);
+        driver_call!(cuda_driver_sys::cuMemcpy3DAsync_v2(
+            &copy as *const _,
+            stream.as_inner()
+        ))
+        .to_result()?;
+        stream.trace(|| format!("array-to-host async copy of {} bytes", size_of_val(dest)));
+        Ok(())
+    }
+
+    // Returns the width, height and depth of the array in elements, counting missing dimensions
+    // as one.
+    fn extent(&self) -> CudaResult<[usize; 3]> {
+        let [width, height, depth] = self.descriptor()?.dims();
+        Ok([width, height.max(1), depth.max(1)])
+    }
+
+    // Build a copy between the region of this array at `offset` with size `extent` (both in
+    // elements) and `host_bytes` bytes of tightly packed host or device memory. The caller fills
+    // in the linear side of the copy.
+    fn host_copy(
+        &self,
+        offset: [usize; 3],
+        extent: [usize; 3],
+        host_bytes: usize,
+    ) -> CudaResult<CUDA_MEMCPY3D> {
+        let descriptor = self.descriptor()?;
+        let element_size = descriptor.format().size_in_bytes() * descriptor.num_channels() as usize;
+        let dims = self.extent()?;
+        for i in 0..3 {
+            match offset[i].checked_add(extent[i]) {
+                Some(end) if end <= dims[i] => {}
+                _ => return Err(CudaError::InvalidValue),
+            }
+        }
+        let bytes = extent
+            .iter()
+            .try_fold(element_size, |acc, &n| acc.checked_mul(n));
+        if bytes != Some(host_bytes) {
+            return Err(CudaError::InvalidValue);
+        }
+
+        // All-zero is a valid starting point for this plain C struct.
+        let mut copy: CUDA_MEMCPY3D = unsafe { mem::zeroed() };
+        copy.WidthInBytes = extent[0] * element_size;
+        copy.Height = extent[1];
+        copy.Depth = extent[2];
+        // Fill in the array as both source and destination; the host side overwrites one of them.
+        copy.srcMemoryType = CUmemorytype_enum::CU_MEMORYTYPE_ARRAY;
+        copy.srcArray = self.handle;
+        copy.srcXInBytes = offset[0] * element_size;
+        copy.srcY = offset[1];
+        copy.srcZ = offset[2];
+        copy.dstMemoryType = CUmemorytype_enum::CU_MEMORYTYPE_ARRAY;
+        copy.dstArray = self.handle;
+        copy.dstXInBytes = offset[0] * element_size;
+        copy.dstY = offset[1];
+        copy.dstZ = offset[2];
+        Ok(copy)
+    }
+
+    /// Returns the raw CUDA array handle, for use with driver API functions that RustaCUDA does
+    /// not wrap.
+    pub fn as_raw(&self) -> CUarray {
+        self.handle
+    }
+
+    /// Constructs an ArrayObject from a raw CUDA array handle.
+    ///
+    /// After calling this function, the array is owned by the ArrayObject, and the ArrayObject
+    /// destructor will destroy it. This function may accept any handle produced by `cuArrayCreate`
+    /// or `cuArray3DCreate`, such as one taken from `ArrayObject::into_raw`. Arrays which belong
+    /// to someone else, such as those mapped from graphics resources, must not be wrapped this way
+    /// unless the `ArrayObject` is later converted back with `into_raw` instead of being dropped.
+    ///
+    /// # Safety
+    ///
+    /// This function is unsafe because improper use may lead to memory problems. For example, a
+    /// double free may occur if this function is called twice on the same handle, or the program
+    /// may crash if the handle is not a valid CUDA array in the current context.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # let _context = rustacuda::quick_init().unwrap();
+    /// use rustacuda::memory::array::{ArrayFormat, ArrayObject};
+    /// let array = ArrayObject::new_1d(10, ArrayFormat::Float, 1).unwrap();
+    /// let handle = array.into_raw();
+    /// let array = unsafe { ArrayObject::from_raw(handle) };
+    /// ```
+    pub unsafe fn from_raw(handle: CUarray) -> Self {
+        ArrayObject { handle }
+    }
+
+    /// Consumes the ArrayObject, returning the raw CUDA array handle.
+    ///
+    /// After calling this function, the caller is responsible for destroying the array with
+    /// `cuArrayDestroy`. The easiest way to do so is to create a new ArrayObject using the
+    /// `ArrayObject::from_raw` function.
+    pub fn into_raw(self) -> CUarray {
+        let handle = self.handle;
+        mem::forget(self);
+        handle
+    }
+
+    /// Creates a texture object which samples this array as described by `desc`.
+    ///
+    /// The texture's handle can be passed to a kernel as a `cudaTextureObject_t` using
+    /// [`TextureObject::as_raw`](../texture/struct.TextureObject.html#method.as_raw).
+    ///
+    /// # Errors
+    ///
+    /// If the texture object cannot be created, returns the CUDA error value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # let _context = rustacuda::quick_init().unwrap();
+    /// use rustacuda::memory::array::{ArrayFormat, ArrayObject};
+    /// use rustacuda::memory::texture::{AddressMode, FilterMode, TextureDesc};
+    /// let array = ArrayObject::new_2d([64, 64], ArrayFormat::Float, 1).unwrap();
+    /// let texture = array
+    ///     .as_texture(&TextureDesc::new(AddressMode::Clamp, FilterMode::Linear))
+    ///     .unwrap();
+    /// let kernel_arg = texture.as_raw();
+    /// ```
+    pub fn as_texture(&self, desc: &TextureDesc) -> CudaResult<TextureObject<'_>> {
+        TextureObject::new(self, desc)
+    }
+
+    /// Creates a surface object which reads from and writes to this array.
+    ///
+    /// The surface's handle can be passed to a kernel as a `cudaSurfaceObject_t` using
+    /// [`SurfaceObject::as_raw`](../texture/struct.SurfaceObject.html#method.as_raw).
+    ///
+    /// # Errors
+    ///
+    /// If the surface object cannot be created, returns the CUDA error value. In particular, the
+    /// array must have been created with `ArrayObjectFlags::SURFACE_LDST`.
+    pub fn as_surface(&self) -> CudaResult<SurfaceObject<'_>> {
+        SurfaceObject::new(self)
+    }
+
     /// Try to destroy an `ArrayObject`. Can fail - if it does, returns the CUDA error and the
     /// un-destroyed array object
     pub fn drop(array: ArrayObject) -> DropResult<ArrayObject> {
-        match unsafe { cuda_driver_sys::cuArrayDestroy(array.handle) }.to_result() {
+        match unsafe { driver_call!(cuda_driver_sys::cuArrayDestroy(array.handle)) }.to_result() {
             Ok(()) => Ok(()),
             Err(e) => Err((e, array)),
         }
     }
 }
 
+fn set_host_source<T>(copy: &mut CUDA_MEMCPY3D, source: &[T]) {
+    copy.srcMemoryType = CUmemorytype_enum::CU_MEMORYTYPE_HOST;
+    copy.srcArray = std::ptr::null_mut();
+    copy.srcHost = source.as_ptr() as *const c_void;
+    copy.srcPitch = copy.WidthInBytes;
+    copy.srcHeight = copy.Height;
+    copy.srcXInBytes = 0;
+    copy.srcY = 0;
+    copy.srcZ = 0;
+}
+
+fn set_host_dest<T>(copy: &mut CUDA_MEMCPY3D, dest: &mut [T]) {
+    copy.dstMemoryType = CUmemorytype_enum::CU_MEMORYTYPE_HOST;
+    copy.dstArray = std::ptr::null_mut();
+    copy.dstHost = dest.as_mut_ptr() as *mut c_void;
+    copy.dstPitch = copy.WidthInBytes;
+    copy.dstHeight = copy.Height;
+    copy.dstXInBytes = 0;
+    copy.dstY = 0;
+    copy.dstZ = 0;
+}
+
+fn set_device_source<T: DeviceCopy>(copy: &mut CUDA_MEMCPY3D, source: &DeviceSlice<T>) {
+    copy.srcMemoryType = CUmemorytype_enum::CU_MEMORYTYPE_DEVICE;
+    copy.srcArray = std::ptr::null_mut();
+    copy.srcDevice = source.as_ptr() as CUdeviceptr;
+    copy.srcPitch = copy.WidthInBytes;
+    copy.srcHeight = copy.Height;
+    copy.srcXInBytes = 0;
+    copy.srcY = 0;
+    copy.srcZ = 0;
+}
+
+fn set_device_dest<T: DeviceCopy>(copy: &mut CUDA_MEMCPY3D, dest: &mut DeviceSlice<T>) {
+    copy.dstMemoryType = CUmemorytype_enum::CU_MEMORYTYPE_DEVICE;
+    copy.dstArray = std::ptr::null_mut();
+    copy.dstDevice = dest.as_mut_ptr() as CUdeviceptr;
+    copy.dstPitch = copy.WidthInBytes;
+    copy.dstHeight = copy.Height;
+    copy.dstXInBytes = 0;
+    copy.dstY = 0;
+    copy.dstZ = 0;
+}
+
+impl crate::private::Sealed for ArrayObject {}
+
+/// Copies between the whole array and host memory. The host data must be exactly the size of
+/// the array, laid out row by row and layer by layer with the channels of each element
+/// interleaved. Unlike copies between slices, a size mismatch returns `InvalidValue` rather than
+/// panicking, since the size of the array is only known at runtime.
+impl<T: DeviceCopy> CopyDestination<[T]> for ArrayObject {
+    fn copy_from(&mut self, source: &[T]) -> CudaResult<()> {
+        let mut copy = self.host_copy([0; 3], self.extent()?, size_of_val(source))?;
+        set_host_source(&mut copy, source);
+        unsafe { driver_call!(cuda_driver_sys::cuMemcpy3D_v2(&copy as *const _)).to_result() }
+    }
+
+    fn copy_to(&self, dest: &mut [T]) -> CudaResult<()> {
+        let mut copy = self.host_copy([0; 3], self.extent()?, size_of_val(dest))?;
+        set_host_dest(&mut copy, dest);
+        unsafe { driver_call!(cuda_driver_sys::cuMemcpy3D_v2(&copy as *const _)).to_result() }
+    }
+}
+
+/// Asynchronous copies between the whole array and host memory, with the same layout and errors
+/// as the synchronous copies. The host memory should be page-locked for the copy to be truly
+/// asynchronous.
+impl<T: DeviceCopy> AsyncCopyDestination<[T]> for ArrayObject {
+    unsafe fn async_copy_from(&mut self, source: &[T], stream: &Stream) -> CudaResult<()> {
+        let mut copy = self.host_copy([0; 3], self.extent()?, size_of_val(source))?;
+        set_host_source(&mut copy, source);
+        driver_call!(cuda_driver_sys::cuMemcpy3DAsync_v2(
+            &copy as *const _,
+            stream.as_inner()
+        ))
+        .to_result()?;
+        stream.trace(|| format!("host-to-array async copy of {} bytes", size_of_val(source)));
+        Ok(())
+    }
+
+    unsafe fn async_copy_to(&self, dest: &mut [T], stream: &Stream) -> CudaResult<()> {
+        let mut copy = self.host_copy([0; 3], self.extent()?, size_of_val(dest))?;
+        set_host_dest(&mut copy, dest);
+        driver_call!(cuda_driver_sys::cuMemcpy3DAsync_v2(
+            &copy as *const _,
+            stream.as_inner()
+        ))
+        .to_result()?;
+        stream.trace(|| format!("array-to-host async copy of {} bytes", size_of_val(dest)));
+        Ok(())
+    }
+}
+
+/// Copies between the whole array and linear device memory, without going through the host. The
+/// device memory must be exactly the size of the array and uses the same layout as host copies.
+///
+/// # Examples
+///
+/// ```
+/// # let _context = rustacuda::quick_init().unwrap();
+/// use rustacuda::memory::array::{ArrayFormat, ArrayObject};
+/// use rustacuda::memory::*;
+/// // For example, the output of a kernel which is then sampled as a texture.
+/// let output = DeviceBuffer::from_slice(&[0.5f32; 64]).unwrap();
+/// let mut array = ArrayObject::new_2d([8, 8], ArrayFormat::Float, 1).unwrap();
+/// array.copy_from(&output).unwrap();
+/// ```
+impl<T: DeviceCopy> CopyDestination<DeviceSlice<T>> for ArrayObject {
+    fn copy_from(&mut self, source: &DeviceSlice<T>) -> CudaResult<()> {
+        let bytes = source.len() * size_of::<T>();
+        let mut copy = self.host_copy([0; 3], self.extent()?, bytes)?;
+        set_device_source(&mut copy, source);
+        unsafe { driver_call!(cuda_driver_sys::cuMemcpy3D_v2(&copy as *const _)).to_result() }
+    }
+
+    fn copy_to(&self, dest: &mut DeviceSlice<T>) -> CudaResult<()> {
+        let bytes = dest.len() * size_of::<T>();
+        let mut copy = self.host_copy([0; 3], self.extent()?, bytes)?;
+        set_device_dest(&mut copy, dest);
+        unsafe { driver_call!(cuda_driver_sys::cuMemcpy3D_v2(&copy as *const _)).to_result() }
+    }
+}
+impl<T: DeviceCopy> CopyDestination<DeviceBuffer<T>> for ArrayObject {
+    fn copy_from(&mut self, source: &DeviceBuffer<T>) -> CudaResult<()> {
+        self.copy_from(source as &DeviceSlice<T>)
+    }
+
+    fn copy_to(&self, dest: &mut DeviceBuffer<T>) -> CudaResult<()> {
+        self.copy_to(dest as &mut DeviceSlice<T>)
+    }
+}
+
+impl<T: DeviceCopy> AsyncCopyDestination<DeviceSlice<T>> for ArrayObject {
+    unsafe fn async_copy_from(
+        &mut self,
+        source: &DeviceSlice<T>,
+        stream: &Stream,
+    ) -> CudaResult<()> {
+        let bytes = source.len() * size_of::<T>();
+        let mut copy = self.host_copy([0; 3], self.extent()?, bytes)?;
+        set_device_source(&mut copy, source);
+        driver_call!(cuda_driver_sys::cuMemcpy3DAsync_v2(
+            &copy as *const _,
+            stream.as_inner()
+        ))
+        .to_result()?;
+        stream.trace(|| format!("device-to-array async copy of {} bytes", bytes));
+        Ok(())
+    }
+
+    unsafe fn async_copy_to(&self, dest: &mut DeviceSlice<T>, stream: &Stream) -> CudaResult<()> {
+        let bytes = dest.len() * size_of::<T>();
+        let mut copy = self.host_copy([0; 3], self.extent()?, bytes)?;
+        set_device_dest(&mut copy, dest);
+        driver_call!(cuda_driver_sys::cuMemcpy3DAsync_v2(
+            &copy as *const _,
+            stream.as_inner()
+        ))
+        .to_result()?;
+        stream.trace(|| format!("array-to-device async copy of {} bytes", bytes));
+        Ok(())
+    }
+}
+impl<T: DeviceCopy> AsyncCopyDestination<DeviceBuffer<T>> for ArrayObject {
+    unsafe fn async_copy_from(
+        &mut self,
+        source: &DeviceBuffer<T>,
+        stream: &Stream,
+    ) -> CudaResult<()> {
+        self.async_copy_from(source as &DeviceSlice<T>, stream)
+    }
+
+    unsafe fn async_copy_to(&self, dest: &mut DeviceBuffer<T>, stream: &Stream) -> CudaResult<()> {
+        self.async_copy_to(dest as &mut DeviceSlice<T>, stream)
+    }
+}
+
 impl std::fmt::Debug for ArrayObject {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         self.descriptor().fmt(f)
@@ -658,9 +1127,271 @@ impl std::fmt::Debug for ArrayObject {
 
 impl Drop for ArrayObject {
     fn drop(&mut self) {
-        unsafe { cuda_driver_sys::cuArrayDestroy(self.handle) }
+        unsafe { driver_call!(cuda_driver_sys::cuArrayDestroy(self.handle)) }
             .to_result()
-            .expect("Failed to destroy CUDA Array")
+            .expect_in_drop("Failed to destroy CUDA Array")
+    }
+}
+
+/// Element types which can be stored in a [`TypedArrayObject`](struct.TypedArrayObject.html).
+///
+/// This is implemented for the integer and floating point types CUDA arrays support, and for
+/// arrays of one, two or four of them, which are stored as that many channels.
+///
+/// # Safety
+///
+/// The type must have the size and layout of `NUM_CHANNELS` consecutive values of `FORMAT`. For
+/// example, a half-precision float type from another crate can implement this with
+/// `FORMAT = ArrayFormat::Half` and `NUM_CHANNELS = 1`.
+pub unsafe trait ArrayElement: DeviceCopy {
+    /// The format of each channel.
+    const FORMAT: ArrayFormat;
+    /// The number of channels; 1, 2 or 4.
+    const NUM_CHANNELS: c_uint;
+}
+
+macro_rules! impl_array_element {
+    ($($t:ty => $format:ident),*) => {
+        $(
+            unsafe impl ArrayElement for $t {
+                const FORMAT: ArrayFormat = ArrayFormat::$format;
+                const NUM_CHANNELS: c_uint = 1;
+            }
+            unsafe impl ArrayElement for [$t; 1] {
+                const FORMAT: ArrayFormat = ArrayFormat::$format;
+                const NUM_CHANNELS: c_uint = 1;
+            }
+            unsafe impl ArrayElement for [$t; 2] {
+                const FORMAT: ArrayFormat = ArrayFormat::$format;
+                const NUM_CHANNELS: c_uint = 2;
+            }
+            unsafe impl ArrayElement for [$t; 4] {
+                const FORMAT: ArrayFormat = ArrayFormat::$format;
+                const NUM_CHANNELS: c_uint = 4;
+            }
+        )*
+    };
+}
+impl_array_element!(
+    u8 => UnsignedInt8,
+    u16 => UnsignedInt16,
+    u32 => UnsignedInt32,
+    i8 => SignedInt8,
+    i16 => SignedInt16,
+    i32 => SignedInt32,
+    f32 => Float
+);
+
+/// A CUDA array whose format and number of channels are determined by the element type `T`.
+///
+/// Copies to and from a `TypedArrayObject` take slices of `T`, so passing data of the wrong
+/// format is a compile error rather than a runtime error. Only the number of elements is checked
+/// at runtime.
+///
+/// # Examples
+///
+/// ```
+/// # let _context = rustacuda::quick_init().unwrap();
+/// use rustacuda::memory::array::TypedArrayObject;
+/// use rustacuda::memory::CopyDestination;
+///
+/// // An RGBA image with four 8-bit channels per pixel.
+/// let mut image = TypedArrayObject::<[u8; 4]>::new_2d([16, 16]).unwrap();
+/// image.copy_from(&[[255, 0, 0, 255]; 256][..]).unwrap();
+///
+/// let mut pixels = [[0u8; 4]; 256];
+/// image.copy_to(&mut pixels[..]).unwrap();
+/// assert_eq!([255, 0, 0, 255], pixels[0]);
+/// ```
+#[derive(Debug)]
+pub struct TypedArrayObject<T: ArrayElement> {
+    array: ArrayObject,
+    _element: PhantomData<T>,
+}
+impl<T: ArrayElement> TypedArrayObject<T> {
+    /// Allocates a new CUDA array with the given dimensions. See
+    /// [`ArrayObject::new`](struct.ArrayObject.html#method.new) for the meaning of `dims`.
+    ///
+    /// # Errors
+    ///
+    /// If the dimensions are invalid or the allocation fails, returns the CUDA error value.
+    pub fn new(dims: [usize; 3]) -> CudaResult<Self> {
+        Self::from_descriptor(&ArrayDescriptor::new(
+            dims,
+            T::FORMAT,
+            T::NUM_CHANNELS,
+            Default::default(),
+        ))
+    }
+
+    /// Allocates a new 1D CUDA array.
+    ///
+    /// # Errors
+    ///
+    /// If the width is invalid or the allocation fails, returns the CUDA error value.
+    pub fn new_1d(width: usize) -> CudaResult<Self> {
+        Self::new([width, 0, 0])
+    }
+
+    /// Allocates a new 2D CUDA array.
+    ///
+    /// # Errors
+    ///
+    /// If the dimensions are invalid or the allocation fails, returns the CUDA error value.
+    pub fn new_2d(dims: [usize; 2]) -> CudaResult<Self> {
+        Self::new([dims[0], dims[1], 0])
+    }
+
+    /// Allocates a new CUDA array as described by `descriptor`, after replacing its format and
+    /// number of channels with those of `T`. Use this to set flags such as `SURFACE_LDST`.
+    ///
+    /// # Errors
+    ///
+    /// If the descriptor is invalid or the allocation fails, returns the CUDA error value.
+    pub fn from_descriptor(descriptor: &ArrayDescriptor) -> CudaResult<Self> {
+        let mut descriptor = *descriptor;
+        descriptor.set_format(T::FORMAT);
+        descriptor.set_num_channels(T::NUM_CHANNELS);
+        Ok(TypedArrayObject {
+            array: ArrayObject::from_descriptor(&descriptor)?,
+            _element: PhantomData,
+        })
+    }
+
+    /// Wraps an existing array.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidValue`, along with the array, if its format or number of channels doesn't
+    /// match `T`.
+    pub fn from_array(array: ArrayObject) -> Result<Self, (CudaError, ArrayObject)> {
+        match array.descriptor() {
+            Ok(d) if d.format() == T::FORMAT && d.num_channels() == T::NUM_CHANNELS => {
+                Ok(TypedArrayObject {
+                    array,
+                    _element: PhantomData,
+                })
+            }
+            Ok(_) => Err((CudaError::InvalidValue, array)),
+            Err(e) => Err((e, array)),
+        }
+    }
+
+    /// Returns the number of elements in the array.
+    ///
+    /// # Errors
+    ///
+    /// If the array's descriptor can't be read, returns the CUDA error value.
+    pub fn num_elements(&self) -> CudaResult<usize> {
+        Ok(self.array.extent()?.iter().product())
+    }
+
+    /// Returns the underlying untyped array.
+    pub fn as_array(&self) -> &ArrayObject {
+        &self.array
+    }
+
+    /// Consumes the `TypedArrayObject`, returning the underlying untyped array.
+    pub fn into_array(self) -> ArrayObject {
+        self.array
+    }
+
+    /// Copy the data in `source` into a single layer of this array. See
+    /// [`ArrayObject::copy_layer_from`](struct.ArrayObject.html#method.copy_layer_from).
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidValue` if `layer` is out of range or `source` doesn't hold exactly one
+    /// layer. Otherwise, if the copy fails, returns the error from CUDA.
+    pub fn copy_layer_from(&mut self, layer: usize, source: &[T]) -> CudaResult<()> {
+        self.array.copy_layer_from(layer, source)
+    }
+
+    /// Copy a single layer of this array into `dest`. See
+    /// [`ArrayObject::copy_layer_to`](struct.ArrayObject.html#method.copy_layer_to).
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidValue` if `layer` is out of range or `dest` doesn't hold exactly one
+    /// layer. Otherwise, if the copy fails, returns the error from CUDA.
+    pub fn copy_layer_to(&self, layer: usize, dest: &mut [T]) -> CudaResult<()> {
+        self.array.copy_layer_to(layer, dest)
+    }
+
+    /// Copy the data in `source` into a region of this array. See
+    /// [`ArrayObject::copy_region_from`](struct.ArrayObject.html#method.copy_region_from).
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidValue` if the region doesn't fit in the array or `source` doesn't hold
+    /// exactly the region's data. Otherwise, if the copy fails, returns the error from CUDA.
+    pub fn copy_region_from(
+        &mut self,
+        offset: [usize; 3],
+        extent: [usize; 3],
+        source: &[T],
+    ) -> CudaResult<()> {
+        self.array.copy_region_from(offset, extent, source)
+    }
+
+    /// Copy a region of this array into `dest`. See
+    /// [`ArrayObject::copy_region_to`](struct.ArrayObject.html#method.copy_region_to).
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidValue` if the region doesn't fit in the array or `dest` doesn't hold
+    /// exactly the region's data. Otherwise, if the copy fails, returns the error from CUDA.
+    pub fn copy_region_to(
+        &self,
+        offset: [usize; 3],
+        extent: [usize; 3],
+        dest: &mut [T],
+    ) -> CudaResult<()> {
+        self.array.copy_region_to(offset, extent, dest)
+    }
+}
+
+impl<T: ArrayElement> crate::private::Sealed for TypedArrayObject<T> {}
+
+/// Copies between the whole array and host memory, as for `ArrayObject`, but only with slices of
+/// the array's element type.
+impl<T: ArrayElement> CopyDestination<[T]> for TypedArrayObject<T> {
+    fn copy_from(&mut self, source: &[T]) -> CudaResult<()> {
+        self.array.copy_from(source)
+    }
+
+    fn copy_to(&self, dest: &mut [T]) -> CudaResult<()> {
+        self.array.copy_to(dest)
+    }
+}
+
+impl<T: ArrayElement> CopyDestination<DeviceSlice<T>> for TypedArrayObject<T> {
+    fn copy_from(&mut self, source: &DeviceSlice<T>) -> CudaResult<()> {
+        self.array.copy_from(source)
+    }
+
+    fn copy_to(&self, dest: &mut DeviceSlice<T>) -> CudaResult<()> {
+        self.array.copy_to(dest)
+    }
+}
+
+impl<T: ArrayElement> CopyDestination<DeviceBuffer<T>> for TypedArrayObject<T> {
+    fn copy_from(&mut self, source: &DeviceBuffer<T>) -> CudaResult<()> {
+        self.array.copy_from(source)
+    }
+
+    fn copy_to(&self, dest: &mut DeviceBuffer<T>) -> CudaResult<()> {
+        self.array.copy_to(dest)
+    }
+}
+
+impl<T: ArrayElement> AsyncCopyDestination<[T]> for TypedArrayObject<T> {
+    unsafe fn async_copy_from(&mut self, source: &[T], stream: &Stream) -> CudaResult<()> {
+        self.array.async_copy_from(source, stream)
+    }
+
+    unsafe fn async_copy_to(&self, dest: &mut [T], stream: &Stream) -> CudaResult<()> {
+        self.array.async_copy_to(dest, stream)
     }
 }
 
@@ -738,67 +1469,244 @@ mod test {
     }
 
     #[test]
-    #[should_panic]
     fn fail_on_zero_width_1d_array() {
         let _context = crate::quick_init().unwrap();
 
-        let _ = ArrayObject::new_1d(0, ArrayFormat::Float, 1).unwrap();
+        assert_eq!(
+            Some(CudaError::InvalidValue),
+            ArrayObject::new_1d(0, ArrayFormat::Float, 1).err()
+        );
     }
 
     #[test]
-    #[should_panic]
     fn fail_on_zero_size_widths() {
         let _context = crate::quick_init().unwrap();
 
-        let _ = ArrayObject::new([0, 10, 20], ArrayFormat::Float, 1).unwrap();
+        assert_eq!(
+            Some(CudaError::InvalidValue),
+            ArrayObject::new([0, 10, 20], ArrayFormat::Float, 1).err()
+        );
     }
 
     #[test]
-    #[should_panic]
     fn fail_cubemaps_with_unmatching_width_height() {
         let _context = crate::quick_init().unwrap();
 
         let mut descriptor = ArrayDescriptor::from_dims_format([2, 3, 6], ArrayFormat::Float);
         descriptor.set_flags(ArrayObjectFlags::CUBEMAP);
 
-        let _ = ArrayObject::from_descriptor(&descriptor).unwrap();
+        assert_eq!(
+            Some(CudaError::InvalidValue),
+            ArrayObject::from_descriptor(&descriptor).err()
+        );
     }
 
     #[test]
-    #[should_panic]
     fn fail_cubemaps_with_non_six_depth() {
         let _context = crate::quick_init().unwrap();
 
         let mut descriptor = ArrayDescriptor::from_dims_format([4, 4, 5], ArrayFormat::Float);
         descriptor.set_flags(ArrayObjectFlags::CUBEMAP);
 
-        let _ = ArrayObject::from_descriptor(&descriptor).unwrap();
+        assert_eq!(
+            Some(CudaError::InvalidValue),
+            ArrayObject::from_descriptor(&descriptor).err()
+        );
     }
 
     #[test]
-    #[should_panic]
     fn fail_cubemaps_with_non_six_multiple_depth() {
         let _context = crate::quick_init().unwrap();
 
         let mut descriptor = ArrayDescriptor::from_dims_format([4, 4, 10], ArrayFormat::Float);
         descriptor.set_flags(ArrayObjectFlags::LAYERED | ArrayObjectFlags::CUBEMAP);
 
-        let _ = ArrayObject::from_descriptor(&descriptor).unwrap();
+        assert_eq!(
+            Some(CudaError::InvalidValue),
+            ArrayObject::from_descriptor(&descriptor).err()
+        );
     }
 
     #[test]
-    #[should_panic]
     fn fail_with_depth_without_height() {
         let _context = crate::quick_init().unwrap();
 
-        let _ = ArrayObject::new([10, 0, 20], ArrayFormat::Float, 1).unwrap();
+        assert_eq!(
+            Some(CudaError::InvalidValue),
+            ArrayObject::new([10, 0, 20], ArrayFormat::Float, 1).err()
+        );
     }
 
     #[test]
-    #[should_panic]
     fn fails_on_invalid_num_channels() {
         let _context = crate::quick_init().unwrap();
 
-        let _ = ArrayObject::new([1, 2, 3], ArrayFormat::Float, 3).unwrap();
+        assert_eq!(
+            Some(CudaError::InvalidValue),
+            ArrayObject::new([1, 2, 3], ArrayFormat::Float, 3).err()
+        );
+    }
+
+    #[test]
+    fn host_round_trip_2d() {
+        let _context = crate::quick_init().unwrap();
+
+        let mut array = ArrayObject::new_2d([3, 2], ArrayFormat::UnsignedInt16, 2).unwrap();
+        let data: Vec<[u16; 2]> = (0..6).map(|i| [i, i * 10]).collect();
+        array.copy_from(&data[..]).unwrap();
+
+        let mut out = vec![[0u16; 2]; 6];
+        array.copy_to(&mut out[..]).unwrap();
+        assert_eq!(data, out);
+    }
+
+    #[test]
+    fn host_round_trip_async_3d() {
+        use crate::memory::LockedBuffer;
+        use crate::stream::StreamFlags;
+
+        let _context = crate::quick_init().unwrap();
+        let stream = Stream::new(StreamFlags::NON_BLOCKING, None).unwrap();
+
+        let mut array = ArrayObject::new([4, 3, 2], ArrayFormat::Float, 1).unwrap();
+        let mut data = LockedBuffer::new(&0.0f32, 24).unwrap();
+        for (i, x) in data.iter_mut().enumerate() {
+            *x = i as f32;
+        }
+        let mut out = LockedBuffer::new(&0.0f32, 24).unwrap();
+        unsafe {
+            array.async_copy_from(data.as_slice(), &stream).unwrap();
+            array.async_copy_to(out.as_mut_slice(), &stream).unwrap();
+        }
+        stream.synchronize().unwrap();
+        assert_eq!(data.as_slice(), out.as_slice());
+
+        // The second slice along the depth holds the last twelve elements.
+        let mut slice = [0.0f32; 12];
+        array.copy_layer_to(1, &mut slice).unwrap();
+        assert_eq!(&data[12..], &slice[..]);
+    }
+
+    #[test]
+    fn host_copy_validates_size() {
+        let _context = crate::quick_init().unwrap();
+
+        let mut array = ArrayObject::new_layered([4, 4], 2, ArrayFormat::UnsignedInt8, 4).unwrap();
+        assert_eq!(
+            Err(CudaError::InvalidValue),
+            array.copy_from(&[0u8; 64][..])
+        );
+        assert_eq!(
+            Err(CudaError::InvalidValue),
+            array.copy_layer_from(2, &[0u32; 16])
+        );
+        array.copy_from(&[0u32; 32][..]).unwrap();
+        array.copy_layer_from(1, &[7u32; 16]).unwrap();
+        let mut all = [0u32; 32];
+        array.copy_to(&mut all[..]).unwrap();
+        assert_eq!([0u32; 16], all[..16]);
+        assert_eq!([7u32; 16], all[16..]);
+    }
+
+    #[test]
+    fn region_copies() {
+        let _context = crate::quick_init().unwrap();
+
+        let mut array = TypedArrayObject::<u16>::new_2d([6, 4]).unwrap();
+        array.copy_from(&[0u16; 24][..]).unwrap();
+        array
+            .copy_region_from([2, 1, 0], [3, 2, 1], &[1, 2, 3, 4, 5, 6])
+            .unwrap();
+
+        let mut all = [0u16; 24];
+        array.copy_to(&mut all[..]).unwrap();
+        #[rustfmt::skip]
+        let expected = [
+            0, 0, 0, 0, 0, 0,
+            0, 0, 1, 2, 3, 0,
+            0, 0, 4, 5, 6, 0,
+            0, 0, 0, 0, 0, 0,
+        ];
+        assert_eq!(expected, all);
+
+        let mut column = [0u16; 4];
+        array
+            .copy_region_to([3, 0, 0], [1, 4, 1], &mut column)
+            .unwrap();
+        assert_eq!([0, 2, 5, 0], column);
+
+        assert_eq!(
+            Err(CudaError::InvalidValue),
+            array.copy_region_to([4, 0, 0], [3, 1, 1], &mut column[..3])
+        );
+        assert_eq!(
+            Err(CudaError::InvalidValue),
+            array.copy_region_to([0, 0, 1], [1, 1, 1], &mut column[..1])
+        );
+    }
+
+    #[test]
+    fn device_memory_round_trip() {
+        let _context = crate::quick_init().unwrap();
+
+        let data: Vec<u32> = (0..24).collect();
+        let source = DeviceBuffer::from_slice(&data).unwrap();
+        let mut array = ArrayObject::new([4, 3, 2], ArrayFormat::UnsignedInt32, 1).unwrap();
+        array.copy_from(&source).unwrap();
+
+        let mut dest = DeviceBuffer::from_slice(&[0u32; 24]).unwrap();
+        array.copy_to(&mut dest).unwrap();
+        let mut host = [0u32; 24];
+        dest.copy_to(&mut host[..]).unwrap();
+        assert_eq!(&data[..], &host[..]);
+
+        let stream = Stream::new(crate::stream::StreamFlags::NON_BLOCKING, None).unwrap();
+        let mut short = DeviceBuffer::from_slice(&[0u32; 23]).unwrap();
+        unsafe {
+            assert_eq!(
+                Err(CudaError::InvalidValue),
+                array.async_copy_to(&mut *short, &stream)
+            );
+        }
+    }
+
+    #[test]
+    fn raw_handle_round_trip() {
+        let _context = crate::quick_init().unwrap();
+
+        let array = ArrayObject::new_2d([8, 4], ArrayFormat::UnsignedInt16, 2).unwrap();
+        let handle = array.into_raw();
+        let array = unsafe { ArrayObject::from_raw(handle) };
+        assert_eq!(handle, array.as_raw());
+        assert_eq!([8, 4, 0], array.descriptor().unwrap().dims());
+    }
+
+    #[test]
+    fn typed_array_format_from_element() {
+        let _context = crate::quick_init().unwrap();
+
+        let mut array = TypedArrayObject::<[f32; 2]>::new([3, 2, 0]).unwrap();
+        let descriptor = array.as_array().descriptor().unwrap();
+        assert_eq!(ArrayFormat::Float, descriptor.format());
+        assert_eq!(2, descriptor.num_channels());
+        assert_eq!(6, array.num_elements().unwrap());
+
+        let data = [[1.0f32, 2.0]; 6];
+        array.copy_from(&data[..]).unwrap();
+        let mut out = [[0.0f32; 2]; 6];
+        array.copy_to(&mut out[..]).unwrap();
+        assert_eq!(data, out);
+        assert_eq!(Err(CudaError::InvalidValue), array.copy_to(&mut out[..5]));
+    }
+
+    #[test]
+    fn typed_array_from_array_checks_format() {
+        let _context = crate::quick_init().unwrap();
+
+        let array = ArrayObject::new_2d([4, 4], ArrayFormat::UnsignedInt8, 4).unwrap();
+        let (e, array) = TypedArrayObject::<u32>::from_array(array).unwrap_err();
+        assert_eq!(CudaError::InvalidValue, e);
+        let array = TypedArrayObject::<[u8; 4]>::from_array(array).unwrap();
+        assert_eq!(16, array.num_elements().unwrap());
     }
 }
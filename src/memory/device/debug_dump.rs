@@ -0,0 +1,182 @@
+use crate::error::{CudaError, CudaResult};
+use crate::memory::device::{CopyDestination, DeviceSlice};
+use crate::memory::DeviceCopy;
+use std::any::type_name;
+use std::fmt::{Debug, Write};
+use std::mem::size_of;
+use std::ops::{Bound, RangeBounds};
+use std::ptr;
+use std::slice;
+
+/// Bytes shown on each line of a hexdump.
+const BYTES_PER_LINE: usize = 16;
+
+impl<T: DeviceCopy> DeviceSlice<T> {
+    /// Reinterpret this slice as a slice of its bytes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # let _context = rustacuda::quick_init().unwrap();
+    /// use rustacuda::memory::*;
+    /// let a = DeviceBuffer::from_slice(&[1u32, 2, 3]).unwrap();
+    /// assert_eq!(12, a.as_bytes().len());
+    /// ```
+    pub fn as_bytes(&self) -> &DeviceSlice<u8> {
+        unsafe {
+            DeviceSlice::from_slice(slice::from_raw_parts(
+                self.as_ptr() as *const u8,
+                self.len() * size_of::<T>(),
+            ))
+        }
+    }
+
+    /// Reinterpret this slice as a mutable slice of its bytes.
+    ///
+    /// # Safety
+    ///
+    /// Any bytes written through the returned slice must leave every element a valid `T`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # let _context = rustacuda::quick_init().unwrap();
+    /// use rustacuda::memory::*;
+    /// let mut a = DeviceBuffer::from_slice(&[0u32; 2]).unwrap();
+    /// unsafe { a.as_bytes_mut()[4..8].copy_from(&[0xffu8; 4][..]).unwrap() };
+    /// let mut host = [0u32; 2];
+    /// a.copy_to(&mut host[..]).unwrap();
+    /// assert_eq!([0, u32::MAX], host);
+    /// ```
+    pub unsafe fn as_bytes_mut(&mut self) -> &mut DeviceSlice<u8> {
+        let len = self.len() * size_of::<T>();
+        DeviceSlice::from_slice_mut(slice::from_raw_parts_mut(self.as_mut_ptr() as *mut u8, len))
+    }
+
+    /// Copy the elements in `range` to the host and render them as a hexdump followed by their
+    /// `Debug` representation, for print-style debugging of kernel outputs.
+    ///
+    /// Byte offsets in the hexdump are relative to the start of this slice, and the elements are
+    /// labelled with their indices in it. This performs a synchronous copy, so keep the range
+    /// small and don't leave calls in performance-sensitive code.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidValue` if `range` is out of bounds. If the copy fails, returns the error
+    /// from CUDA.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # let _context = rustacuda::quick_init().unwrap();
+    /// use rustacuda::memory::*;
+    /// let output = DeviceBuffer::from_slice(&[1.0f32, 2.0, -0.5, 4.0]).unwrap();
+    /// println!("{}", output.debug_dump(1..3).unwrap());
+    /// // DeviceSlice<f32> [1..3] of 4 elements
+    /// // 00000004  00 00 00 40 00 00 00 bf                          |...@....|
+    /// // [1] 2.0
+    /// // [2] -0.5
+    /// ```
+    pub fn debug_dump<R: RangeBounds<usize>>(&self, range: R) -> CudaResult<String>
+    where
+        T: Debug,
+    {
+        let start = match range.start_bound() {
+            Bound::Included(&start) => start,
+            Bound::Excluded(&start) => start.checked_add(1).ok_or(CudaError::InvalidValue)?,
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&end) => end.checked_add(1).ok_or(CudaError::InvalidValue)?,
+            Bound::Excluded(&end) => end,
+            Bound::Unbounded => self.len(),
+        };
+        if start > end || end > self.len() {
+            return Err(CudaError::InvalidValue);
+        }
+
+        let window = &self[start..end];
+        let mut bytes = vec![0u8; window.len() * size_of::<T>()];
+        window.as_bytes().copy_to(&mut bytes)?;
+
+        let mut dump = String::new();
+        let _ = writeln!(
+            dump,
+            "DeviceSlice<{}> [{}..{}] of {} elements",
+            type_name::<T>(),
+            start,
+            end,
+            self.len()
+        );
+        let base = start * size_of::<T>();
+        for (line, chunk) in bytes.chunks(BYTES_PER_LINE).enumerate() {
+            let _ = write!(dump, "{:08x} ", base + line * BYTES_PER_LINE);
+            for i in 0..BYTES_PER_LINE {
+                match chunk.get(i) {
+                    Some(byte) => {
+                        let _ = write!(dump, " {:02x}", byte);
+                    }
+                    None => dump.push_str("   "),
+                }
+            }
+            dump.push_str("  |");
+            dump.extend(chunk.iter().map(|&byte| {
+                if byte.is_ascii_graphic() || byte == b' ' {
+                    byte as char
+                } else {
+                    '.'
+                }
+            }));
+            dump.push_str("|\n");
+        }
+        if size_of::<T>() > 0 {
+            for (i, element) in bytes.chunks_exact(size_of::<T>()).enumerate() {
+                // The bytes were copied from elements of type T, and may not be aligned for it.
+                let value = unsafe { ptr::read_unaligned(element.as_ptr() as *const T) };
+                let _ = writeln!(dump, "[{}] {:?}", start + i, value);
+            }
+        }
+        Ok(dump)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::memory::DeviceBuffer;
+
+    #[test]
+    fn test_debug_dump() {
+        let _context = crate::quick_init().unwrap();
+        let buffer = DeviceBuffer::from_slice(&[0x4142_4344u32, 1, 2, 3, 4, 5]).unwrap();
+        assert_eq!(24, buffer.as_bytes().len());
+
+        let dump = buffer.debug_dump(..=4).unwrap();
+        let lines: Vec<&str> = dump.lines().collect();
+        assert_eq!("DeviceSlice<u32> [0..5] of 6 elements", lines[0]);
+        assert_eq!(
+            "00000000  44 43 42 41 01 00 00 00 02 00 00 00 03 00 00 00  |DCBA............|",
+            lines[1]
+        );
+        assert_eq!(
+            "00000010  04 00 00 00                                      |....|",
+            lines[2]
+        );
+        assert_eq!("[0] 1094861636", lines[3]);
+        assert_eq!("[4] 4", lines[7]);
+
+        let dump = buffer.debug_dump(5..).unwrap();
+        assert!(dump.starts_with("DeviceSlice<u32> [5..6] of 6 elements\n00000014  05"));
+        assert!(dump.ends_with("[5] 5\n"));
+
+        assert_eq!(
+            "DeviceSlice<u32> [6..6] of 6 elements\n",
+            buffer.debug_dump(6..6).unwrap()
+        );
+        assert_eq!(Err(CudaError::InvalidValue), buffer.debug_dump(2..7));
+        assert_eq!(
+            Err(CudaError::InvalidValue),
+            buffer.debug_dump((Bound::Included(4), Bound::Excluded(2)))
+        );
+    }
+}
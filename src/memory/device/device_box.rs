@@ -1,4 +1,4 @@
-use crate::error::{CudaResult, DropResult, ToResult};
+use crate::error::{CudaResult, DropExpect, DropResult, ToResult};
 use crate::memory::device::AsyncCopyDestination;
 use crate::memory::device::CopyDestination;
 use crate::memory::malloc::{cuda_free, cuda_malloc};
@@ -6,7 +6,7 @@ use crate::memory::DeviceCopy;
 use crate::memory::DevicePointer;
 use crate::stream::Stream;
 use std::fmt::{self, Pointer};
-use std::mem;
+use std::mem::{self, size_of};
 
 use std::os::raw::c_void;
 
@@ -17,6 +17,9 @@ use std::os::raw::c_void;
 pub struct DeviceBox<T> {
     ptr: DevicePointer<T>,
 }
+// See "Thread Safety" in the `memory` module documentation.
+unsafe impl<T: Send> Send for DeviceBox<T> {}
+unsafe impl<T: Sync> Sync for DeviceBox<T> {}
 impl<T: DeviceCopy> DeviceBox<T> {
     /// Allocate device memory and place val into it.
     ///
@@ -92,11 +95,11 @@ impl<T> DeviceBox<T> {
     pub unsafe fn zeroed() -> CudaResult<Self> {
         let mut new_box = DeviceBox::uninitialized()?;
         if mem::size_of::<T>() != 0 {
-            cuda_driver_sys::cuMemsetD8_v2(
+            driver_call!(cuda_driver_sys::cuMemsetD8_v2(
                 new_box.as_device_ptr().as_raw_mut() as u64,
                 0,
                 mem::size_of::<T>(),
-            )
+            ))
             .to_result()?;
         }
         Ok(new_box)
@@ -244,7 +247,7 @@ impl<T> Drop for DeviceBox<T> {
         let ptr = mem::replace(&mut self.ptr, DevicePointer::null());
         // No choice but to panic if this fails.
         unsafe {
-            cuda_free(ptr).expect("Failed to deallocate CUDA memory.");
+            cuda_free(ptr).expect_in_drop("Failed to deallocate CUDA memory.");
         }
     }
 }
@@ -259,11 +262,11 @@ impl<T: DeviceCopy> CopyDestination<T> for DeviceBox<T> {
         let size = mem::size_of::<T>();
         if size != 0 {
             unsafe {
-                cuda_driver_sys::cuMemcpyHtoD_v2(
+                driver_call!(cuda_driver_sys::cuMemcpyHtoD_v2(
                     self.ptr.as_raw_mut() as u64,
                     val as *const T as *const c_void,
                     size,
-                )
+                ))
                 .to_result()?
             }
         }
@@ -274,11 +277,11 @@ impl<T: DeviceCopy> CopyDestination<T> for DeviceBox<T> {
         let size = mem::size_of::<T>();
         if size != 0 {
             unsafe {
-                cuda_driver_sys::cuMemcpyDtoH_v2(
+                driver_call!(cuda_driver_sys::cuMemcpyDtoH_v2(
                     val as *const T as *mut c_void,
                     self.ptr.as_raw() as u64,
                     size,
-                )
+                ))
                 .to_result()?
             }
         }
@@ -290,11 +293,11 @@ impl<T: DeviceCopy> CopyDestination<DeviceBox<T>> for DeviceBox<T> {
         let size = mem::size_of::<T>();
         if size != 0 {
             unsafe {
-                cuda_driver_sys::cuMemcpyDtoD_v2(
+                driver_call!(cuda_driver_sys::cuMemcpyDtoD_v2(
                     self.ptr.as_raw_mut() as u64,
                     val.ptr.as_raw() as u64,
                     size,
-                )
+                ))
                 .to_result()?
             }
         }
@@ -305,28 +308,60 @@ impl<T: DeviceCopy> CopyDestination<DeviceBox<T>> for DeviceBox<T> {
         let size = mem::size_of::<T>();
         if size != 0 {
             unsafe {
-                cuda_driver_sys::cuMemcpyDtoD_v2(
+                driver_call!(cuda_driver_sys::cuMemcpyDtoD_v2(
                     val.ptr.as_raw_mut() as u64,
                     self.ptr.as_raw() as u64,
                     size,
-                )
+                ))
                 .to_result()?
             }
         }
         Ok(())
     }
 }
+impl<T: DeviceCopy> AsyncCopyDestination<T> for DeviceBox<T> {
+    unsafe fn async_copy_from(&mut self, val: &T, stream: &Stream) -> CudaResult<()> {
+        let size = size_of::<T>();
+        if size != 0 {
+            driver_call!(cuda_driver_sys::cuMemcpyHtoDAsync_v2(
+                self.ptr.as_raw_mut() as u64,
+                val as *const T as *const c_void,
+                size,
+                stream.as_inner(),
+            ))
+            .to_result()?;
+            stream.trace(|| format!("host-to-device async copy of {} bytes", size));
+        }
+        Ok(())
+    }
+
+    unsafe fn async_copy_to(&self, val: &mut T, stream: &Stream) -> CudaResult<()> {
+        let size = size_of::<T>();
+        if size != 0 {
+            driver_call!(cuda_driver_sys::cuMemcpyDtoHAsync_v2(
+                val as *mut T as *mut c_void,
+                self.ptr.as_raw() as u64,
+                size,
+                stream.as_inner(),
+            ))
+            .to_result()?;
+            stream.trace(|| format!("device-to-host async copy of {} bytes", size));
+        }
+        Ok(())
+    }
+}
 impl<T: DeviceCopy> AsyncCopyDestination<DeviceBox<T>> for DeviceBox<T> {
     unsafe fn async_copy_from(&mut self, val: &DeviceBox<T>, stream: &Stream) -> CudaResult<()> {
         let size = mem::size_of::<T>();
         if size != 0 {
-            cuda_driver_sys::cuMemcpyDtoDAsync_v2(
+            driver_call!(cuda_driver_sys::cuMemcpyDtoDAsync_v2(
                 self.ptr.as_raw_mut() as u64,
                 val.ptr.as_raw() as u64,
                 size,
                 stream.as_inner(),
-            )
-            .to_result()?
+            ))
+            .to_result()?;
+            stream.trace(|| format!("device-to-device async copy of {} bytes", size));
         }
         Ok(())
     }
@@ -334,13 +369,14 @@ impl<T: DeviceCopy> AsyncCopyDestination<DeviceBox<T>> for DeviceBox<T> {
     unsafe fn async_copy_to(&self, val: &mut DeviceBox<T>, stream: &Stream) -> CudaResult<()> {
         let size = mem::size_of::<T>();
         if size != 0 {
-            cuda_driver_sys::cuMemcpyDtoDAsync_v2(
+            driver_call!(cuda_driver_sys::cuMemcpyDtoDAsync_v2(
                 val.ptr.as_raw_mut() as u64,
                 self.ptr.as_raw() as u64,
                 size,
                 stream.as_inner(),
-            )
-            .to_result()?
+            ))
+            .to_result()?;
+            stream.trace(|| format!("device-to-device async copy of {} bytes", size));
         }
         Ok(())
     }
@@ -421,6 +457,21 @@ mod test_device_box {
         assert_eq!(5, h);
     }
 
+    #[test]
+    fn test_async_copy_host_to_device() {
+        let _context = crate::quick_init().unwrap();
+        let stream = Stream::new(crate::stream::StreamFlags::NON_BLOCKING, None).unwrap();
+        let y = crate::memory::LockedBuffer::new(&5u64, 1).unwrap();
+        let mut z = crate::memory::LockedBuffer::new(&0u64, 1).unwrap();
+        let mut x = DeviceBox::new(&0u64).unwrap();
+        unsafe {
+            x.async_copy_from(&y[0], &stream).unwrap();
+            x.async_copy_to(&mut z[0], &stream).unwrap();
+        }
+        stream.synchronize().unwrap();
+        assert_eq!(5, z[0]);
+    }
+
     #[test]
     fn test_device_pointer_implements_traits_safely() {
         let _context = crate::quick_init().unwrap();
@@ -1,11 +1,13 @@
-use crate::error::{CudaResult, DropResult, ToResult};
+use crate::error::{CudaError, CudaResult, DropExpect, DropResult, ToResult};
 use crate::memory::device::{AsyncCopyDestination, CopyDestination, DeviceSlice};
 use crate::memory::malloc::{cuda_free, cuda_malloc};
 use crate::memory::DeviceCopy;
 use crate::memory::DevicePointer;
 use crate::stream::Stream;
-use std::mem;
+use std::convert::TryFrom;
+use std::mem::{self, size_of, MaybeUninit};
 use std::ops::{Deref, DerefMut};
+use std::os::raw::c_void;
 
 use std::ptr;
 
@@ -15,6 +17,9 @@ pub struct DeviceBuffer<T> {
     buf: DevicePointer<T>,
     capacity: usize,
 }
+// See "Thread Safety" in the `memory` module documentation.
+unsafe impl<T: Send> Send for DeviceBuffer<T> {}
+unsafe impl<T: Sync> Sync for DeviceBuffer<T> {}
 impl<T> DeviceBuffer<T> {
     /// Allocate a new device buffer large enough to hold `size` `T`'s, but without
     /// initializing the contents.
@@ -49,6 +54,60 @@ impl<T> DeviceBuffer<T> {
         })
     }
 
+    /// Allocate a new device buffer large enough to hold `size` `T`'s, with the contents typed as
+    /// `MaybeUninit<T>`.
+    ///
+    /// Unlike [`uninitialized`](#method.uninitialized), this is safe to call: the buffer can't be
+    /// read as `T` until it is converted with [`assume_init`](#method.assume_init), which makes
+    /// the point where the contents are asserted to be initialized explicit.
+    ///
+    /// # Errors
+    ///
+    /// If the allocation fails, returns the error from CUDA. If `size` is large enough that
+    /// `size * mem::sizeof::<T>()` overflows usize, then returns InvalidMemoryAllocation.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # let _context = rustacuda::quick_init().unwrap();
+    /// use rustacuda::memory::*;
+    /// use std::mem::MaybeUninit;
+    /// let mut buffer = DeviceBuffer::<u64>::uninit(5).unwrap();
+    /// buffer.copy_from(&[MaybeUninit::new(0u64); 5]).unwrap();
+    /// let buffer = unsafe { buffer.assume_init() };
+    /// ```
+    pub fn uninit(size: usize) -> CudaResult<DeviceBuffer<MaybeUninit<T>>> {
+        unsafe { DeviceBuffer::uninitialized(size) }
+    }
+
+    /// Allocate a new device buffer large enough to hold `len` `T`'s, to be filled in by a kernel
+    /// or a copy.
+    ///
+    /// This is the same as [`uninit`](#method.uninit), under a name that says what the buffer is
+    /// for. Until the buffer is converted with [`assume_init`](#method.assume_init), it can only
+    /// be written to: pass [`as_output_ptr`](#method.as_output_ptr) to a kernel, or copy data into
+    /// it with [`write_from`](#method.write_from).
+    ///
+    /// # Errors
+    ///
+    /// If the allocation fails, returns the error from CUDA. If `len` is large enough that
+    /// `len * mem::sizeof::<T>()` overflows usize, then returns InvalidMemoryAllocation.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # let _context = rustacuda::quick_init().unwrap();
+    /// use rustacuda::memory::*;
+    /// let mut output = DeviceBuffer::<f32>::with_capacity_for_kernel_output(128).unwrap();
+    /// let output_ptr = output.as_output_ptr();
+    /// // Launch a kernel which writes every element through `output_ptr`, then:
+    /// # output.write_from(&[0.0f32; 128]).unwrap();
+    /// let output = unsafe { output.assume_init() };
+    /// ```
+    pub fn with_capacity_for_kernel_output(len: usize) -> CudaResult<DeviceBuffer<MaybeUninit<T>>> {
+        DeviceBuffer::uninit(len)
+    }
+
     /// Allocate a new device buffer large enough to hold `size` `T`'s and fill the contents with
     /// zeroes (`0u8`).
     ///
@@ -76,8 +135,12 @@ impl<T> DeviceBuffer<T> {
     pub unsafe fn zeroed(size: usize) -> CudaResult<Self> {
         let ptr = if size > 0 && mem::size_of::<T>() > 0 {
             let mut ptr = cuda_malloc(size)?;
-            cuda_driver_sys::cuMemsetD8_v2(ptr.as_raw_mut() as u64, 0, size * mem::size_of::<T>())
-                .to_result()?;
+            driver_call!(cuda_driver_sys::cuMemsetD8_v2(
+                ptr.as_raw_mut() as u64,
+                0,
+                size * mem::size_of::<T>()
+            ))
+            .to_result()?;
             ptr
         } else {
             DevicePointer::wrap(ptr::NonNull::dangling().as_ptr() as *mut T)
@@ -225,6 +288,62 @@ impl<T: DeviceCopy> DeviceBuffer<T> {
         Ok(uninit)
     }
 }
+impl<T> DeviceBuffer<MaybeUninit<T>> {
+    /// Converts a `DeviceBuffer<MaybeUninit<T>>` into a `DeviceBuffer<T>` without copying or
+    /// reallocating.
+    ///
+    /// # Safety
+    ///
+    /// As with `MaybeUninit::assume_init`, the caller must guarantee that every element of the
+    /// buffer has been initialized, for example by a kernel or a copy from the host.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # let _context = rustacuda::quick_init().unwrap();
+    /// use rustacuda::memory::*;
+    /// use std::mem::MaybeUninit;
+    /// let mut buffer = DeviceBuffer::<u64>::uninit(3).unwrap();
+    /// buffer.copy_from(&[MaybeUninit::new(1u64); 3]).unwrap();
+    /// let buffer = unsafe { buffer.assume_init() };
+    /// let mut host_values = [0u64; 3];
+    /// buffer.copy_to(&mut host_values).unwrap();
+    /// assert_eq!([1u64, 1, 1], host_values);
+    /// ```
+    pub unsafe fn assume_init(mut self) -> DeviceBuffer<T> {
+        let capacity = self.capacity;
+        let ptr = DevicePointer::wrap(self.buf.as_raw_mut() as *mut T);
+        mem::forget(self);
+        DeviceBuffer::from_raw_parts(ptr, capacity)
+    }
+
+    /// Returns a device pointer to the buffer typed as `T`, for passing to a kernel which writes
+    /// its output to this buffer.
+    ///
+    /// The pointer must not be used to read elements which haven't been written yet.
+    pub fn as_output_ptr(&mut self) -> DevicePointer<T> {
+        unsafe { DevicePointer::wrap(self.buf.as_raw_mut() as *mut T) }
+    }
+
+    /// Copy initialized data from the host into this buffer.
+    ///
+    /// # Errors
+    ///
+    /// If a CUDA error occurs, return the error.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the length of `src` is not the same as the length of the buffer.
+    pub fn write_from(&mut self, src: &[T]) -> CudaResult<()>
+    where
+        T: DeviceCopy,
+    {
+        // MaybeUninit<T> has the same layout as T, and initialized values are valid MaybeUninits.
+        let src =
+            unsafe { std::slice::from_raw_parts(src.as_ptr() as *const MaybeUninit<T>, src.len()) };
+        self.copy_from(src)
+    }
+}
 impl<T> Deref for DeviceBuffer<T> {
     type Target = DeviceSlice<T>;
 
@@ -245,6 +364,53 @@ impl<T> DerefMut for DeviceBuffer<T> {
         }
     }
 }
+impl<T: DeviceCopy> TryFrom<&[T]> for DeviceBuffer<T> {
+    type Error = CudaError;
+
+    /// Allocate a device buffer and copy `slice` into it. See
+    /// [`from_slice`](struct.DeviceBuffer.html#method.from_slice).
+    fn try_from(slice: &[T]) -> CudaResult<Self> {
+        DeviceBuffer::from_slice(slice)
+    }
+}
+impl<T: DeviceCopy> TryFrom<Vec<T>> for DeviceBuffer<T> {
+    type Error = CudaError;
+
+    /// Allocate a device buffer and copy the contents of `vec` into it.
+    fn try_from(vec: Vec<T>) -> CudaResult<Self> {
+        DeviceBuffer::from_slice(&vec)
+    }
+}
+impl<T: DeviceCopy> TryFrom<&DeviceSlice<T>> for Vec<T> {
+    type Error = CudaError;
+
+    /// Copy the contents of a device slice into a new `Vec`.
+    fn try_from(slice: &DeviceSlice<T>) -> CudaResult<Self> {
+        let mut vec = Vec::with_capacity(slice.len());
+        let size = size_of::<T>() * slice.len();
+        if size != 0 {
+            unsafe {
+                driver_call!(cuda_driver_sys::cuMemcpyDtoH_v2(
+                    vec.as_mut_ptr() as *mut c_void,
+                    slice.as_ptr() as u64,
+                    size,
+                ))
+                .to_result()?;
+            }
+        }
+        // The copy initialized every element (trivially so for zero-sized types).
+        unsafe { vec.set_len(slice.len()) };
+        Ok(vec)
+    }
+}
+impl<T: DeviceCopy> TryFrom<&DeviceBuffer<T>> for Vec<T> {
+    type Error = CudaError;
+
+    /// Copy the contents of a device buffer into a new `Vec`.
+    fn try_from(buffer: &DeviceBuffer<T>) -> CudaResult<Self> {
+        Vec::try_from(&**buffer)
+    }
+}
 impl<T> Drop for DeviceBuffer<T> {
     fn drop(&mut self) {
         if self.buf.is_null() {
@@ -255,7 +421,7 @@ impl<T> Drop for DeviceBuffer<T> {
             // No choice but to panic if this fails.
             let ptr = mem::replace(&mut self.buf, DevicePointer::null());
             unsafe {
-                cuda_free(ptr).expect("Failed to deallocate CUDA Device memory.");
+                cuda_free(ptr).expect_in_drop("Failed to deallocate CUDA Device memory.");
             }
         }
         self.capacity = 0;
@@ -279,6 +445,169 @@ mod test_device_buffer {
         drop(buf);
     }
 
+    #[test]
+    fn test_uninit_assume_init() {
+        let _context = crate::quick_init().unwrap();
+        let mut buf = DeviceBuffer::<u64>::uninit(4).unwrap();
+        buf.copy_from(&[MaybeUninit::new(7u64); 4]).unwrap();
+        let buf = unsafe { buf.assume_init() };
+        let mut end = [0u64; 4];
+        buf.copy_to(&mut end).unwrap();
+        assert_eq!([7u64; 4], end);
+    }
+
+    #[test]
+    fn test_kernel_output() {
+        use crate::module::Module;
+        use std::ffi::{c_void, CString};
+
+        let _context = crate::quick_init().unwrap();
+        let ptx = CString::new(include_str!("../../../resources/add.ptx")).unwrap();
+        let module = Module::load_from_string(&ptx).unwrap();
+        let stream = Stream::new(StreamFlags::NON_BLOCKING, None).unwrap();
+
+        let mut x = DeviceBuffer::from_slice(&[2.0f32; 64]).unwrap();
+        let mut y = DeviceBuffer::from_slice(&[1.0f32; 64]).unwrap();
+        let mut output = DeviceBuffer::<f32>::with_capacity_for_kernel_output(64).unwrap();
+        let sum = module.get_function(&CString::new("sum").unwrap()).unwrap();
+        let (mut x_ptr, mut y_ptr, mut out_ptr, mut count) = (
+            x.as_device_ptr(),
+            y.as_device_ptr(),
+            output.as_output_ptr(),
+            64i32,
+        );
+        unsafe {
+            stream
+                .launch(
+                    &sum,
+                    1,
+                    64,
+                    0,
+                    &[
+                        &mut x_ptr as *mut _ as *mut c_void,
+                        &mut y_ptr as *mut _ as *mut c_void,
+                        &mut out_ptr as *mut _ as *mut c_void,
+                        &mut count as *mut _ as *mut c_void,
+                    ],
+                )
+                .unwrap();
+        }
+        stream.synchronize().unwrap();
+        let out = unsafe { output.assume_init() };
+        let mut host = [0.0f32; 64];
+        out.copy_to(&mut host[..]).unwrap();
+        assert_eq!([3.0f32; 64], host);
+
+        let mut out = DeviceBuffer::<u32>::with_capacity_for_kernel_output(3).unwrap();
+        out.write_from(&[1, 2, 3]).unwrap();
+        let out = unsafe { out.assume_init() };
+        let mut host = [0u32; 3];
+        out.copy_to(&mut host[..]).unwrap();
+        assert_eq!([1, 2, 3], host);
+    }
+
+    #[test]
+    fn test_try_from_conversions() {
+        let _context = crate::quick_init().unwrap();
+        let buffer = DeviceBuffer::try_from(vec![1u32, 2, 3, 4]).unwrap();
+        assert_eq!(vec![1u32, 2, 3, 4], Vec::try_from(&buffer).unwrap());
+
+        let buffer = DeviceBuffer::try_from(&[5u64, 6][..]).unwrap();
+        assert_eq!(vec![6u64], Vec::try_from(&buffer[1..]).unwrap());
+
+        let buffer = DeviceBuffer::try_from(vec![ZeroSizedType; 3]).unwrap();
+        assert_eq!(3, Vec::try_from(&buffer).unwrap().len());
+    }
+
+    #[test]
+    fn test_uninit_zero_length() {
+        let _context = crate::quick_init().unwrap();
+        let buf = DeviceBuffer::<u64>::uninit(0).unwrap();
+        let buf = unsafe { buf.assume_init() };
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn test_reduce_to_host() {
+        use crate::module::Module;
+        use std::ffi::CString;
+
+        let _context = crate::quick_init().unwrap();
+        let ptx = CString::new(include_str!("../../../resources/reduce.ptx")).unwrap();
+        let module = Module::load_from_string(&ptx).unwrap();
+        let sum = module
+            .get_function(&CString::new("sum_f32").unwrap())
+            .unwrap();
+        let stream = Stream::new(StreamFlags::NON_BLOCKING, None).unwrap();
+
+        // Large enough to need more than one pass.
+        let buf = DeviceBuffer::from_slice(&vec![1.0f32; 300_000]).unwrap();
+        assert_eq!(
+            300_000.0,
+            unsafe { buf.reduce_to_host(&sum, &stream) }.unwrap()
+        );
+
+        let empty = DeviceBuffer::<f32>::from_slice(&[]).unwrap();
+        assert_eq!(
+            CudaError::InvalidValue,
+            unsafe { empty.reduce_to_host(&sum, &stream) }.unwrap_err()
+        );
+    }
+
+    #[test]
+    fn test_gather_scatter() {
+        let _context = crate::quick_init().unwrap();
+        let stream = Stream::new(StreamFlags::NON_BLOCKING, None).unwrap();
+
+        // Elements with 2-byte alignment are moved a byte at a time.
+        let src = DeviceBuffer::from_slice(&[[1u16, 2, 3], [4, 5, 6], [7, 8, 9]]).unwrap();
+        let indices = DeviceBuffer::from_slice(&[2u32, 7, 0]).unwrap();
+        let mut dst = DeviceBuffer::from_slice(&[[0u16; 3]; 3]).unwrap();
+        dst.copy_indexed_from(&src, &indices, &stream).unwrap();
+        let mut host = [[0u16; 3]; 3];
+        dst.copy_to(&mut host[..]).unwrap();
+        // The out-of-bounds index leaves its element unchanged.
+        assert_eq!([[7, 8, 9], [0, 0, 0], [1, 2, 3]], host);
+
+        let src = DeviceBuffer::from_slice(&[1u64, 2, 3, 4]).unwrap();
+        let indices = DeviceBuffer::from_slice(&[3u32, 2, 1, 0]).unwrap();
+        let mut dst = DeviceBuffer::from_slice(&[0u64; 4]).unwrap();
+        src.scatter_to(&mut dst, &indices, &stream).unwrap();
+        let mut host = [0u64; 4];
+        dst.copy_to(&mut host[..]).unwrap();
+        assert_eq!([4, 3, 2, 1], host);
+
+        let short = DeviceBuffer::from_slice(&[0u32]).unwrap();
+        assert_eq!(
+            CudaError::InvalidValue,
+            dst.copy_indexed_from(&src, &short, &stream).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn test_device_eq() {
+        let _context = crate::quick_init().unwrap();
+        let stream = Stream::new(StreamFlags::NON_BLOCKING, None).unwrap();
+
+        let a = DeviceBuffer::from_slice(&[1u64, 2, 3, 4]).unwrap();
+        let mut b = DeviceBuffer::from_slice(&[1u64, 2, 3, 4]).unwrap();
+        assert!(a.device_eq(&b, &stream).unwrap());
+        b[3..].copy_from(&[5u64][..]).unwrap();
+        assert!(!a.device_eq(&b, &stream).unwrap());
+        assert!(a[..3].device_eq(&b[..3], &stream).unwrap());
+        assert!(!a.device_eq(&b[..3], &stream).unwrap());
+
+        // Elements with 2-byte alignment are compared a byte at a time.
+        let c = DeviceBuffer::from_slice(&[[1u16, 2, 3]; 5]).unwrap();
+        let mut d = DeviceBuffer::from_slice(&[[1u16, 2, 3]; 5]).unwrap();
+        assert!(c[1..].device_eq(&d[1..], &stream).unwrap());
+        d[4..].copy_from(&[[1u16, 2, 4]][..]).unwrap();
+        assert!(!c.device_eq(&d, &stream).unwrap());
+
+        let empty = DeviceBuffer::<u64>::from_slice(&[]).unwrap();
+        assert!(empty.device_eq(&a[..0], &stream).unwrap());
+    }
+
     #[test]
     fn test_copy_to_from_device() {
         let _context = crate::quick_init().unwrap();
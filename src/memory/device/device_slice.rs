@@ -1,11 +1,14 @@
-use crate::error::{CudaResult, ToResult};
+use crate::error::{CudaError, CudaResult, ToResult};
+use crate::function::Function;
 use crate::memory::device::AsyncCopyDestination;
+use crate::memory::device::DeviceBox;
 use crate::memory::device::{CopyDestination, DeviceBuffer};
 use crate::memory::DeviceCopy;
 use crate::memory::DevicePointer;
+use crate::module;
 use crate::stream::Stream;
 use std::iter::{ExactSizeIterator, FusedIterator};
-use std::mem;
+use std::mem::{self, align_of, size_of};
 use std::ops::{
     Index, IndexMut, Range, RangeFrom, RangeFull, RangeInclusive, RangeTo, RangeToInclusive,
 };
@@ -306,6 +309,397 @@ impl<T> DeviceSlice<T> {
     }
 }
 
+/// Number of threads per block used by [`DeviceSlice::reduce_to_host`](struct.DeviceSlice.html#method.reduce_to_host).
+pub const REDUCE_BLOCK_SIZE: u32 = 256;
+const REDUCE_MAX_BLOCKS: usize = 1024;
+
+impl<T: DeviceCopy> DeviceSlice<T> {
+    /// Reduce this slice to a single value on the device using `kernel`, then copy the result
+    /// back to the host.
+    ///
+    /// The reduction operator is supplied by the kernel, which must have the signature
+    /// `extern "C" __global__ void reduce(const T* input, T* output, unsigned int count)`. It is
+    /// launched with blocks of [`REDUCE_BLOCK_SIZE`](constant.REDUCE_BLOCK_SIZE.html) threads and
+    /// `REDUCE_BLOCK_SIZE * size_of::<T>()` bytes of dynamic shared memory, and each block must
+    /// reduce the elements `blockIdx.x, blockIdx.x + gridDim.x, ...` of `input` (up to `count`)
+    /// and write the result to `output[blockIdx.x]`. The kernel is launched repeatedly on the
+    /// partial results until a single value remains.
+    ///
+    /// All work is enqueued in `stream`, and this function waits only for `stream` to finish
+    /// before returning the result.
+    ///
+    /// # Errors
+    ///
+    /// If the slice is empty or longer than `u32::MAX` elements, returns `InvalidValue`. If a
+    /// launch, allocation or copy fails, returns the error from CUDA.
+    ///
+    /// # Safety
+    ///
+    /// The kernel must follow the contract above, and must not write to `input`. As with any
+    /// kernel launch, the slice must not be modified by other work while the reduction is running.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rustacuda::*;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # let _ctx = quick_init()?;
+    /// use rustacuda::memory::*;
+    /// use rustacuda::module::Module;
+    /// use rustacuda::stream::{Stream, StreamFlags};
+    /// use std::ffi::CString;
+    ///
+    /// let ptx = CString::new(include_str!("../../../resources/reduce.ptx"))?;
+    /// let module = Module::load_from_string(&ptx)?;
+    /// let sum = module.get_function(&CString::new("sum_f32")?)?;
+    /// let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+    ///
+    /// let buffer = DeviceBuffer::from_slice(&[1.0f32; 1000])?;
+    /// let total = unsafe { buffer.reduce_to_host(&sum, &stream)? };
+    /// assert_eq!(1000.0, total);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub unsafe fn reduce_to_host(&self, kernel: &Function, stream: &Stream) -> CudaResult<T> {
+        if self.is_empty() || self.len() > u32::MAX as usize {
+            return Err(CudaError::InvalidValue);
+        }
+        let shared_mem_bytes = REDUCE_BLOCK_SIZE * size_of::<T>() as u32;
+
+        let mut input = self.as_ptr();
+        let mut count = self.len();
+        // Keep each pass's output alive until the stream has finished with it.
+        let mut partials: Vec<DeviceBuffer<T>> = Vec::new();
+        loop {
+            let blocks = count
+                .div_ceil(REDUCE_BLOCK_SIZE as usize)
+                .min(REDUCE_MAX_BLOCKS);
+            let mut output = DeviceBuffer::uninitialized(blocks)?;
+            let mut output_ptr = output.as_device_ptr().as_raw_mut();
+            let mut count_arg = count as u32;
+            stream.launch(
+                kernel,
+                blocks as u32,
+                REDUCE_BLOCK_SIZE,
+                shared_mem_bytes,
+                &[
+                    &mut input as *mut *const T as *mut c_void,
+                    &mut output_ptr as *mut *mut T as *mut c_void,
+                    &mut count_arg as *mut u32 as *mut c_void,
+                ],
+            )?;
+            input = output.as_ptr();
+            count = blocks;
+            partials.push(output);
+            if blocks == 1 {
+                break;
+            }
+        }
+
+        let mut result = mem::MaybeUninit::<T>::uninit();
+        driver_call!(cuda_driver_sys::cuMemcpyDtoHAsync_v2(
+            result.as_mut_ptr() as *mut c_void,
+            input as u64,
+            size_of::<T>(),
+            stream.as_inner(),
+        ))
+        .to_result()?;
+        stream.synchronize()?;
+        Ok(result.assume_init())
+    }
+
+    /// Gather elements of `src` into this slice, so that `self[i] = src[indices[i]]`.
+    ///
+    /// This is the fundamental permutation primitive used by sorting and compaction pipelines.
+    /// It is implemented with a kernel embedded in RustaCUDA, which is loaded into the current
+    /// context the first time it is used there. Indices that are out of bounds for `src` are
+    /// skipped, leaving the corresponding element of this slice unchanged.
+    ///
+    /// The kernel is enqueued in `stream` after any work already in it. This function blocks
+    /// until `stream` has finished, since the slices are only borrowed for the duration of the
+    /// call.
+    ///
+    /// # Errors
+    ///
+    /// If `indices` is not the same length as this slice, or the slices are too large to be
+    /// indexed with 32-bit integers, returns `InvalidValue`. If loading or launching the kernel
+    /// fails, returns the error from CUDA.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # let _context = rustacuda::quick_init().unwrap();
+    /// use rustacuda::memory::*;
+    /// use rustacuda::stream::{Stream, StreamFlags};
+    /// let stream = Stream::new(StreamFlags::NON_BLOCKING, None).unwrap();
+    /// let src = DeviceBuffer::from_slice(&[10u64, 20, 30, 40]).unwrap();
+    /// let indices = DeviceBuffer::from_slice(&[3u32, 0, 2]).unwrap();
+    /// let mut dst = DeviceBuffer::from_slice(&[0u64; 3]).unwrap();
+    /// dst.copy_indexed_from(&src, &indices, &stream).unwrap();
+    ///
+    /// let mut host = [0u64; 3];
+    /// dst.copy_to(&mut host[..]).unwrap();
+    /// assert_eq!([40, 10, 30], host);
+    /// ```
+    pub fn copy_indexed_from(
+        &mut self,
+        src: &DeviceSlice<T>,
+        indices: &DeviceSlice<u32>,
+        stream: &Stream,
+    ) -> CudaResult<()> {
+        if self.len() != indices.len() {
+            return Err(CudaError::InvalidValue);
+        }
+        permute(
+            "gather",
+            self.as_mut_ptr(),
+            src.as_ptr(),
+            indices,
+            src.len(),
+            stream,
+        )
+    }
+
+    /// Scatter the elements of this slice into `dst`, so that `dst[indices[i]] = self[i]`.
+    ///
+    /// This is the inverse of [`copy_indexed_from`](#method.copy_indexed_from). Indices that are
+    /// out of bounds for `dst` are skipped. If `indices` contains duplicates, which of the
+    /// corresponding elements ends up in `dst` is unspecified.
+    ///
+    /// The kernel is enqueued in `stream` after any work already in it. This function blocks
+    /// until `stream` has finished, since the slices are only borrowed for the duration of the
+    /// call.
+    ///
+    /// # Errors
+    ///
+    /// If `indices` is not the same length as this slice, or the slices are too large to be
+    /// indexed with 32-bit integers, returns `InvalidValue`. If loading or launching the kernel
+    /// fails, returns the error from CUDA.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # let _context = rustacuda::quick_init().unwrap();
+    /// use rustacuda::memory::*;
+    /// use rustacuda::stream::{Stream, StreamFlags};
+    /// let stream = Stream::new(StreamFlags::NON_BLOCKING, None).unwrap();
+    /// let src = DeviceBuffer::from_slice(&[1.0f32, 2.0, 3.0]).unwrap();
+    /// let indices = DeviceBuffer::from_slice(&[2u32, 0, 1]).unwrap();
+    /// let mut dst = DeviceBuffer::from_slice(&[0.0f32; 3]).unwrap();
+    /// src.scatter_to(&mut dst, &indices, &stream).unwrap();
+    ///
+    /// let mut host = [0.0f32; 3];
+    /// dst.copy_to(&mut host[..]).unwrap();
+    /// assert_eq!([2.0, 3.0, 1.0], host);
+    /// ```
+    pub fn scatter_to(
+        &self,
+        dst: &mut DeviceSlice<T>,
+        indices: &DeviceSlice<u32>,
+        stream: &Stream,
+    ) -> CudaResult<()> {
+        if self.len() != indices.len() {
+            return Err(CudaError::InvalidValue);
+        }
+        permute(
+            "scatter",
+            dst.as_mut_ptr(),
+            self.as_ptr(),
+            indices,
+            dst.len(),
+            stream,
+        )
+    }
+
+    /// Returns `true` if this slice and `other` hold the same bytes, comparing them on the device
+    /// so that large buffers can be checked without copying either side to the host.
+    ///
+    /// The comparison is bitwise, like `memcmp`: NaNs with the same bits are equal, `0.0` and
+    /// `-0.0` are not, and any padding bytes are compared too. Slices of different lengths are
+    /// never equal. It is implemented with a kernel embedded in RustaCUDA, which is loaded into
+    /// the current context the first time it is used there. If the kernel can't be loaded, the
+    /// slices are instead copied to the host in chunks and compared there.
+    ///
+    /// The comparison is enqueued in `stream` after any work already in it. This function blocks
+    /// until `stream` has finished, in order to read back the result.
+    ///
+    /// # Errors
+    ///
+    /// If launching the kernel, allocating or copying fails, returns the error from CUDA.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # let _context = rustacuda::quick_init().unwrap();
+    /// use rustacuda::memory::*;
+    /// use rustacuda::stream::{Stream, StreamFlags};
+    /// let stream = Stream::new(StreamFlags::NON_BLOCKING, None).unwrap();
+    /// let expected = DeviceBuffer::from_slice(&[1u32; 100_000]).unwrap();
+    /// let mut actual = DeviceBuffer::from_slice(&[1u32; 100_000]).unwrap();
+    /// assert!(actual.device_eq(&expected, &stream).unwrap());
+    ///
+    /// actual[99_999..].copy_from(&[2u32][..]).unwrap();
+    /// assert!(!actual.device_eq(&expected, &stream).unwrap());
+    /// ```
+    pub fn device_eq(&self, other: &DeviceSlice<T>, stream: &Stream) -> CudaResult<bool> {
+        if self.len() != other.len() {
+            return Ok(false);
+        }
+        if self.len() * size_of::<T>() == 0 || self.as_ptr() == other.as_ptr() {
+            return Ok(true);
+        }
+        match compare_on_device(self, other, stream)? {
+            Some(equal) => Ok(equal),
+            None => compare_on_host(self.as_bytes(), other.as_bytes(), stream),
+        }
+    }
+}
+
+/// The gather and scatter kernels.
+const PERMUTE_PTX: &str = include_str!("../../../resources/permute.ptx");
+/// Number of threads per block used by the gather and scatter kernels.
+const PERMUTE_BLOCK_SIZE: u32 = 256;
+
+/// Launch the `gather` or `scatter` kernel from `resources/permute.ptx` and wait for it.
+///
+/// The wait keeps the borrowed slices alive until the kernel has finished with them.
+///
+/// `bound` is the length of the slice that `indices` index into.
+fn permute<T>(
+    op: &str,
+    mut dst: *mut T,
+    mut src: *const T,
+    indices: &DeviceSlice<u32>,
+    bound: usize,
+    stream: &Stream,
+) -> CudaResult<()> {
+    // Move each element as machine words no wider than its alignment, so that every access is
+    // aligned even for sub-slices.
+    let word_size = match align_of::<T>() {
+        align if align >= 8 => 8,
+        align if align >= 4 => 4,
+        _ => 1,
+    };
+    let words = size_of::<T>() / word_size;
+    let total = indices
+        .len()
+        .checked_mul(words)
+        .filter(|&total| total <= u32::MAX as usize && bound <= u32::MAX as usize)
+        .ok_or(CudaError::InvalidValue)?;
+    if total == 0 {
+        return Ok(());
+    }
+
+    let mut indices_ptr = indices.as_ptr();
+    let mut total_arg = total as u32;
+    let mut words_arg = words as u32;
+    let mut bound_arg = bound as u32;
+    let name = format!("{}_b{}", op, word_size * 8);
+    module::with_builtin_function(PERMUTE_PTX, &name, |kernel| unsafe {
+        stream.launch(
+            kernel,
+            (total as u32).div_ceil(PERMUTE_BLOCK_SIZE),
+            PERMUTE_BLOCK_SIZE,
+            0,
+            &[
+                &mut dst as *mut *mut T as *mut c_void,
+                &mut src as *mut *const T as *mut c_void,
+                &mut indices_ptr as *mut *const u32 as *mut c_void,
+                &mut total_arg as *mut u32 as *mut c_void,
+                &mut words_arg as *mut u32 as *mut c_void,
+                &mut bound_arg as *mut u32 as *mut c_void,
+            ],
+        )
+    })??;
+    stream.synchronize()
+}
+
+/// The comparison kernels.
+const COMPARE_PTX: &str = include_str!("../../../resources/compare.ptx");
+/// Number of threads per block used by the comparison kernels.
+const COMPARE_BLOCK_SIZE: u32 = 256;
+/// Maximum number of words compared by each launch of a comparison kernel.
+const COMPARE_MAX_WORDS: usize = 1 << 30;
+/// Size in bytes of the chunks compared on the host when the comparison kernel is unavailable.
+const COMPARE_HOST_CHUNK: usize = 1 << 20;
+
+/// Compare two non-empty slices of the same length with the kernels from `resources/compare.ptx`.
+///
+/// Returns `None` if the kernels can't be loaded.
+fn compare_on_device<T>(
+    a: &DeviceSlice<T>,
+    b: &DeviceSlice<T>,
+    stream: &Stream,
+) -> CudaResult<Option<bool>> {
+    // Compare machine words no wider than the alignment of T, so that every access is aligned
+    // even for sub-slices.
+    let word_size = match align_of::<T>() {
+        align if align >= 8 => 8,
+        align if align >= 4 => 4,
+        _ => 1,
+    };
+    let name = format!("compare_b{}", word_size * 8);
+
+    let mut differs = DeviceBox::new(&0u32)?;
+    let mut differs_ptr = differs.as_device_ptr().as_raw_mut();
+    let total = a.len() * size_of::<T>() / word_size;
+    let launched = module::with_builtin_function(COMPARE_PTX, &name, |kernel| {
+        let mut offset = 0;
+        while offset < total {
+            let count = (total - offset).min(COMPARE_MAX_WORDS);
+            let mut a_ptr = unsafe { (a.as_ptr() as *const u8).add(offset * word_size) };
+            let mut b_ptr = unsafe { (b.as_ptr() as *const u8).add(offset * word_size) };
+            let mut count_arg = count as u32;
+            unsafe {
+                stream.launch(
+                    kernel,
+                    (count as u32).div_ceil(COMPARE_BLOCK_SIZE),
+                    COMPARE_BLOCK_SIZE,
+                    0,
+                    &[
+                        &mut a_ptr as *mut *const u8 as *mut c_void,
+                        &mut b_ptr as *mut *const u8 as *mut c_void,
+                        &mut differs_ptr as *mut *mut u32 as *mut c_void,
+                        &mut count_arg as *mut u32 as *mut c_void,
+                    ],
+                )?;
+            }
+            offset += count;
+        }
+        CudaResult::Ok(())
+    });
+    match launched {
+        Ok(result) => result?,
+        Err(_) => return Ok(None),
+    }
+    // The slices and flag must stay alive until the kernels have finished.
+    stream.synchronize()?;
+    let mut result = 0u32;
+    differs.copy_to(&mut result)?;
+    Ok(Some(result == 0))
+}
+
+/// Compare two byte slices of the same length by copying them to the host in chunks.
+fn compare_on_host(a: &DeviceSlice<u8>, b: &DeviceSlice<u8>, stream: &Stream) -> CudaResult<bool> {
+    // Synchronous copies aren't ordered with the work in a non-blocking stream.
+    stream.synchronize()?;
+    let mut a_host = vec![0u8; COMPARE_HOST_CHUNK.min(a.len())];
+    let mut b_host = a_host.clone();
+    for (a_chunk, b_chunk) in a
+        .chunks(COMPARE_HOST_CHUNK)
+        .zip(b.chunks(COMPARE_HOST_CHUNK))
+    {
+        let len = a_chunk.len();
+        a_chunk.copy_to(&mut a_host[..len])?;
+        b_chunk.copy_to(&mut b_host[..len])?;
+        if a_host[..len] != b_host[..len] {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
 /// An iterator over a [`DeviceSlice`](struct.DeviceSlice.html) in (non-overlapping) chunks
 /// (`chunk_size` elements at a time).
 ///
@@ -446,11 +840,11 @@ impl<T: DeviceCopy, I: AsRef<[T]> + AsMut<[T]> + ?Sized> CopyDestination<I> for
         let size = mem::size_of::<T>() * self.len();
         if size != 0 {
             unsafe {
-                cuda_driver_sys::cuMemcpyHtoD_v2(
+                driver_call!(cuda_driver_sys::cuMemcpyHtoD_v2(
                     self.0.as_mut_ptr() as u64,
                     val.as_ptr() as *const c_void,
                     size,
-                )
+                ))
                 .to_result()?
             }
         }
@@ -466,11 +860,11 @@ impl<T: DeviceCopy, I: AsRef<[T]> + AsMut<[T]> + ?Sized> CopyDestination<I> for
         let size = mem::size_of::<T>() * self.len();
         if size != 0 {
             unsafe {
-                cuda_driver_sys::cuMemcpyDtoH_v2(
+                driver_call!(cuda_driver_sys::cuMemcpyDtoH_v2(
                     val.as_mut_ptr() as *mut c_void,
                     self.as_ptr() as u64,
                     size,
-                )
+                ))
                 .to_result()?
             }
         }
@@ -486,11 +880,11 @@ impl<T: DeviceCopy> CopyDestination<DeviceSlice<T>> for DeviceSlice<T> {
         let size = mem::size_of::<T>() * self.len();
         if size != 0 {
             unsafe {
-                cuda_driver_sys::cuMemcpyDtoD_v2(
+                driver_call!(cuda_driver_sys::cuMemcpyDtoD_v2(
                     self.0.as_mut_ptr() as u64,
                     val.as_ptr() as u64,
                     size,
-                )
+                ))
                 .to_result()?
             }
         }
@@ -505,11 +899,11 @@ impl<T: DeviceCopy> CopyDestination<DeviceSlice<T>> for DeviceSlice<T> {
         let size = mem::size_of::<T>() * self.len();
         if size != 0 {
             unsafe {
-                cuda_driver_sys::cuMemcpyDtoD_v2(
+                driver_call!(cuda_driver_sys::cuMemcpyDtoD_v2(
                     val.as_mut_ptr() as u64,
                     self.as_ptr() as u64,
                     size,
-                )
+                ))
                 .to_result()?
             }
         }
@@ -536,13 +930,14 @@ impl<T: DeviceCopy, I: AsRef<[T]> + AsMut<[T]> + ?Sized> AsyncCopyDestination<I>
         );
         let size = mem::size_of::<T>() * self.len();
         if size != 0 {
-            cuda_driver_sys::cuMemcpyHtoDAsync_v2(
+            driver_call!(cuda_driver_sys::cuMemcpyHtoDAsync_v2(
                 self.0.as_mut_ptr() as u64,
                 val.as_ptr() as *const c_void,
                 size,
                 stream.as_inner(),
-            )
-            .to_result()?
+            ))
+            .to_result()?;
+            stream.trace(|| format!("host-to-device async copy of {} bytes", size));
         }
         Ok(())
     }
@@ -555,13 +950,14 @@ impl<T: DeviceCopy, I: AsRef<[T]> + AsMut<[T]> + ?Sized> AsyncCopyDestination<I>
         );
         let size = mem::size_of::<T>() * self.len();
         if size != 0 {
-            cuda_driver_sys::cuMemcpyDtoHAsync_v2(
+            driver_call!(cuda_driver_sys::cuMemcpyDtoHAsync_v2(
                 val.as_mut_ptr() as *mut c_void,
                 self.as_ptr() as u64,
                 size,
                 stream.as_inner(),
-            )
-            .to_result()?
+            ))
+            .to_result()?;
+            stream.trace(|| format!("device-to-host async copy of {} bytes", size));
         }
         Ok(())
     }
@@ -574,13 +970,14 @@ impl<T: DeviceCopy> AsyncCopyDestination<DeviceSlice<T>> for DeviceSlice<T> {
         );
         let size = mem::size_of::<T>() * self.len();
         if size != 0 {
-            cuda_driver_sys::cuMemcpyDtoDAsync_v2(
+            driver_call!(cuda_driver_sys::cuMemcpyDtoDAsync_v2(
                 self.0.as_mut_ptr() as u64,
                 val.as_ptr() as u64,
                 size,
                 stream.as_inner(),
-            )
-            .to_result()?
+            ))
+            .to_result()?;
+            stream.trace(|| format!("device-to-device async copy of {} bytes", size));
         }
         Ok(())
     }
@@ -592,13 +989,14 @@ impl<T: DeviceCopy> AsyncCopyDestination<DeviceSlice<T>> for DeviceSlice<T> {
         );
         let size = mem::size_of::<T>() * self.len();
         if size != 0 {
-            cuda_driver_sys::cuMemcpyDtoDAsync_v2(
+            driver_call!(cuda_driver_sys::cuMemcpyDtoDAsync_v2(
                 val.as_mut_ptr() as u64,
                 self.as_ptr() as u64,
                 size,
                 stream.as_inner(),
-            )
-            .to_result()?
+            ))
+            .to_result()?;
+            stream.trace(|| format!("device-to-device async copy of {} bytes", size));
         }
         Ok(())
     }
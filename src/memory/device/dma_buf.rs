@@ -0,0 +1,107 @@
+use crate::error::{CudaError, CudaResult, ToResult};
+use crate::memory::device::DeviceBuffer;
+use crate::memory::DeviceCopy;
+use cuda_driver_sys::{CUdeviceptr, CUresult};
+use std::mem::size_of;
+use std::os::fd::{FromRawFd, OwnedFd};
+use std::os::raw::{c_int, c_ulonglong, c_void};
+
+// From CUmemRangeHandleType in cuda.h.
+const CU_MEM_RANGE_HANDLE_TYPE_DMA_BUF_FD: c_int = 1;
+
+type GetHandleForAddressRangeFn =
+    unsafe extern "C" fn(*mut c_void, CUdeviceptr, usize, c_int, c_ulonglong) -> CUresult;
+
+// `cuMemGetHandleForAddressRange` was added in CUDA 11.7, after the driver bindings RustaCUDA
+// uses.
+driver_fn!(get_handle_fn: GetHandleForAddressRangeFn = b"cuMemGetHandleForAddressRange\0");
+
+impl<T: DeviceCopy> DeviceBuffer<T> {
+    /// Export this buffer as a Linux dma-buf file descriptor, so that it can be shared with other
+    /// kernel subsystems such as V4L2 or DRM without copying.
+    ///
+    /// The address and size of the buffer must be multiples of the host page size. Large
+    /// allocations are generally aligned well enough, so allocating a whole number of pages is
+    /// usually sufficient.
+    ///
+    /// The buffer must outlive every use of the exported memory. Closing the returned file
+    /// descriptor does not free the buffer.
+    ///
+    /// This function is only available on Linux.
+    ///
+    /// # Errors
+    ///
+    /// Returns `NotSupported` if the driver is older than CUDA 11.7 or the device or kernel
+    /// doesn't support dma-buf. Returns `InvalidValue` if the buffer is not page-aligned.
+    /// Otherwise, if the export fails, returns the CUDA error value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # let _context = rustacuda::quick_init().unwrap();
+    /// use rustacuda::error::CudaError;
+    /// use rustacuda::memory::*;
+    /// let buffer = DeviceBuffer::from_slice(&[0u8; 1 << 21]).unwrap();
+    /// match buffer.export_dma_buf() {
+    ///     Ok(fd) => { /* Hand `fd` to V4L2, DRM, etc. */ }
+    ///     Err(CudaError::NotSupported) => { /* Fall back to copying. */ }
+    ///     Err(e) => panic!("{}", e),
+    /// }
+    /// ```
+    pub fn export_dma_buf(&self) -> CudaResult<OwnedFd> {
+        let get_handle = get_handle_fn().ok_or(CudaError::NotSupported)?;
+        let bytes = self.len() * size_of::<T>();
+        // The page size is always a power of two.
+        let page_mask = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as usize - 1;
+        if bytes == 0 || bytes & page_mask != 0 || self.as_ptr() as usize & page_mask != 0 {
+            return Err(CudaError::InvalidValue);
+        }
+        let mut fd: c_int = -1;
+        unsafe {
+            get_handle(
+                &mut fd as *mut c_int as *mut c_void,
+                self.as_ptr() as CUdeviceptr,
+                bytes,
+                CU_MEM_RANGE_HANDLE_TYPE_DMA_BUF_FD,
+                0,
+            )
+            .to_result()?;
+            Ok(OwnedFd::from_raw_fd(fd))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::os::fd::AsRawFd;
+
+    #[test]
+    fn test_export_dma_buf() {
+        let _context = crate::quick_init().unwrap();
+        let buffer = DeviceBuffer::from_slice(&[0u8; 1 << 21]).unwrap();
+        match buffer.export_dma_buf() {
+            Ok(fd) => assert!(fd.as_raw_fd() >= 0),
+            Err(e) => assert_eq!(CudaError::NotSupported, e),
+        }
+    }
+
+    #[test]
+    fn test_export_empty_dma_buf() {
+        let _context = crate::quick_init().unwrap();
+        let buffer = DeviceBuffer::<u8>::from_slice(&[]).unwrap();
+        assert!(buffer.export_dma_buf().is_err());
+    }
+
+    #[test]
+    fn test_export_unaligned_dma_buf() {
+        let _context = crate::quick_init().unwrap();
+        let buffer = DeviceBuffer::from_slice(&[0u8; 1000]).unwrap();
+        if get_handle_fn().is_some() {
+            assert_eq!(
+                Err(CudaError::InvalidValue),
+                buffer.export_dma_buf().map(|_| ())
+            );
+        }
+    }
+}
@@ -1,13 +1,22 @@
 use crate::error::CudaResult;
 use crate::stream::Stream;
 
+mod debug_dump;
 mod device_box;
 mod device_buffer;
 mod device_slice;
+#[cfg(target_os = "linux")]
+mod dma_buf;
+mod ping_pong;
+mod read_only;
+mod readback;
 
 pub use self::device_box::*;
 pub use self::device_buffer::*;
 pub use self::device_slice::*;
+pub use self::ping_pong::*;
+pub use self::read_only::*;
+pub use self::readback::*;
 
 /// Sealed trait implemented by types which can be the source or destination when copying data
 /// to/from the device or from one device allocation to another.
@@ -57,6 +66,7 @@ pub trait AsyncCopyDestination<O: ?Sized>: crate::private::Sealed {
     /// # Errors
     ///
     /// If a CUDA error occurs, return the error.
+    #[track_caller]
     unsafe fn async_copy_from(&mut self, source: &O, stream: &Stream) -> CudaResult<()>;
 
     /// Asynchronously copy data to `dest`. `dest` must be the same size as `self`.
@@ -70,5 +80,6 @@ pub trait AsyncCopyDestination<O: ?Sized>: crate::private::Sealed {
     /// # Errors
     ///
     /// If a CUDA error occurs, return the error.
+    #[track_caller]
     unsafe fn async_copy_to(&self, dest: &mut O, stream: &Stream) -> CudaResult<()>;
 }
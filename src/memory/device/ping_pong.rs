@@ -0,0 +1,191 @@
+use crate::error::{CudaError, CudaResult};
+use crate::memory::device::{CopyDestination, DeviceBuffer, DeviceSlice};
+use crate::memory::{DeviceCopy, DevicePointer};
+use std::mem;
+
+/// A pair of equally-sized device buffers for iterative kernels, such as stencils and iterative
+/// solvers, which read the previous iteration's results from one buffer and write the next
+/// iteration's results to the other.
+///
+/// The *front* buffer holds the most recent results and the *back* buffer is the one the next
+/// iteration writes to. [`swap`](#method.swap) exchanges them after each iteration, and
+/// [`step`](#method.step) does both at once. Because the input and output are handed out together
+/// by [`split`](#method.split) and `step`, a kernel can't accidentally be given the same buffer as
+/// both its input and its output.
+///
+/// # Examples
+///
+/// ```
+/// # let _context = rustacuda::quick_init().unwrap();
+/// use rustacuda::memory::*;
+/// let mut buffers = PingPong::from_slice(&[1.0f32; 1024]).unwrap();
+/// for _ in 0..10 {
+///     buffers.step(|input, output| {
+///         // Launch a kernel which reads from `input` and writes to `output` here.
+/// #       let _ = (input, output);
+///         Ok(())
+///     }).unwrap();
+/// }
+/// let mut results = [0.0f32; 1024];
+/// buffers.front().copy_to(&mut results[..]).unwrap();
+/// ```
+#[derive(Debug)]
+pub struct PingPong<T: DeviceCopy> {
+    front: DeviceBuffer<T>,
+    back: DeviceBuffer<T>,
+}
+impl<T: DeviceCopy> PingPong<T> {
+    /// Create a `PingPong` from a front and back buffer.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidValue` if the buffers have different lengths.
+    pub fn new(front: DeviceBuffer<T>, back: DeviceBuffer<T>) -> CudaResult<Self> {
+        if front.len() != back.len() {
+            return Err(CudaError::InvalidValue);
+        }
+        Ok(PingPong { front, back })
+    }
+
+    /// Allocate a `PingPong` with both buffers initialized with a copy of the data in `slice`.
+    ///
+    /// Initializing the back buffer as well means that elements a kernel doesn't write, such as
+    /// the boundary of a stencil, hold the same values in both buffers.
+    ///
+    /// # Errors
+    ///
+    /// If the allocation or copy fails, returns the error from CUDA.
+    pub fn from_slice(slice: &[T]) -> CudaResult<Self> {
+        let front = DeviceBuffer::from_slice(slice)?;
+        let mut back = unsafe { DeviceBuffer::uninitialized(slice.len())? };
+        back.copy_from(&front)?;
+        Ok(PingPong { front, back })
+    }
+
+    /// Returns the number of elements in each buffer.
+    pub fn len(&self) -> usize {
+        self.front.len()
+    }
+
+    /// Returns `true` if the buffers are empty.
+    pub fn is_empty(&self) -> bool {
+        self.front.is_empty()
+    }
+
+    /// Returns the front buffer, which holds the most recent results.
+    pub fn front(&self) -> &DeviceBuffer<T> {
+        &self.front
+    }
+
+    /// Returns the front buffer mutably, for example to upload new input.
+    pub fn front_mut(&mut self) -> &mut DeviceBuffer<T> {
+        &mut self.front
+    }
+
+    /// Returns the back buffer, which the next iteration writes to.
+    pub fn back(&self) -> &DeviceBuffer<T> {
+        &self.back
+    }
+
+    /// Returns the back buffer mutably.
+    pub fn back_mut(&mut self) -> &mut DeviceBuffer<T> {
+        &mut self.back
+    }
+
+    /// Exchange the front and back buffers. This does not copy any data.
+    pub fn swap(&mut self) {
+        mem::swap(&mut self.front, &mut self.back);
+    }
+
+    /// Borrow the front buffer as the input and the back buffer as the output of the next
+    /// iteration.
+    pub fn split(&mut self) -> (&DeviceSlice<T>, &mut DeviceSlice<T>) {
+        (&self.front, &mut self.back)
+    }
+
+    /// Returns device pointers to the front (input) and back (output) buffers, in that order, for
+    /// passing to a kernel.
+    ///
+    /// The pointers are invalidated if the `PingPong` is dropped, and refer to the other buffers
+    /// once it is swapped.
+    pub fn as_device_ptrs(&mut self) -> (DevicePointer<T>, DevicePointer<T>) {
+        (self.front.as_device_ptr(), self.back.as_device_ptr())
+    }
+
+    /// Run one iteration: call `f` with pointers to the input (front) and output (back) buffers,
+    /// then swap the buffers so the output becomes the new front.
+    ///
+    /// If `f` launches a kernel asynchronously, the next iteration's kernel must be launched on
+    /// the same stream (or otherwise ordered after it) so that it sees the results.
+    ///
+    /// # Errors
+    ///
+    /// If `f` returns an error, the buffers are not swapped and the error is returned.
+    pub fn step<F>(&mut self, f: F) -> CudaResult<()>
+    where
+        F: FnOnce(DevicePointer<T>, DevicePointer<T>) -> CudaResult<()>,
+    {
+        let (input, output) = self.as_device_ptrs();
+        f(input, output)?;
+        self.swap();
+        Ok(())
+    }
+
+    /// Consume the `PingPong`, returning the front and back buffers in that order.
+    pub fn into_inner(self) -> (DeviceBuffer<T>, DeviceBuffer<T>) {
+        (self.front, self.back)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_step_swaps() {
+        let _context = crate::quick_init().unwrap();
+        let mut buffers = PingPong::from_slice(&[1u32, 2, 3, 4]).unwrap();
+        let front = buffers.front().as_ptr();
+        let back = buffers.back().as_ptr();
+
+        buffers
+            .step(|input, output| {
+                assert_eq!(front, input.as_raw());
+                assert_eq!(back, output.as_raw());
+                let output = unsafe { DeviceSlice::from_raw_parts_mut(output, 4) };
+                output.copy_from(&[5u32, 6, 7, 8][..])
+            })
+            .unwrap();
+        assert_eq!(back, buffers.front().as_ptr());
+
+        let mut host = [0u32; 4];
+        buffers.front().copy_to(&mut host[..]).unwrap();
+        assert_eq!([5, 6, 7, 8], host);
+        buffers.back().copy_to(&mut host[..]).unwrap();
+        assert_eq!([1, 2, 3, 4], host);
+
+        // A failed step doesn't swap.
+        assert!(buffers.step(|_, _| Err(CudaError::LaunchFailed)).is_err());
+        assert_eq!(back, buffers.front().as_ptr());
+    }
+
+    #[test]
+    fn test_split() {
+        let _context = crate::quick_init().unwrap();
+        let mut buffers = PingPong::from_slice(&[9u64; 8]).unwrap();
+        let (input, output) = buffers.split();
+        output.copy_from(input).unwrap();
+        assert_eq!(8, buffers.len());
+    }
+
+    #[test]
+    fn test_mismatched_lengths() {
+        let _context = crate::quick_init().unwrap();
+        let front = DeviceBuffer::from_slice(&[0u8; 4]).unwrap();
+        let back = DeviceBuffer::from_slice(&[0u8; 5]).unwrap();
+        assert_eq!(
+            CudaError::InvalidValue,
+            PingPong::new(front, back).unwrap_err()
+        );
+    }
+}
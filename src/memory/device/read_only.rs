@@ -0,0 +1,328 @@
+use crate::context::CurrentContext;
+use crate::device::DeviceAttribute;
+use crate::error::{CudaResult, DropExpect, ToResult};
+use crate::memory::device::{DeviceBuffer, DeviceSlice};
+use crate::memory::malloc;
+use crate::memory::{AllocationKind, DeviceCopy, DevicePointer};
+use cuda_driver_sys::{
+    CUdeviceptr, CUmemAccessDesc, CUmemAccess_flags_enum, CUmemAllocationGranularity_flags_enum,
+    CUmemAllocationProp, CUmemAllocationType_enum, CUmemGenericAllocationHandle, CUmemLocation,
+    CUmemLocationType_enum,
+};
+use std::any::type_name;
+use std::mem::{size_of, MaybeUninit};
+use std::ops::Deref;
+use std::ptr;
+
+/// A device buffer which kernels may read but not write.
+///
+/// Created by [`DeviceBuffer::protect_read_only`](struct.DeviceBuffer.html#method.protect_read_only).
+/// In debug builds, on devices which support virtual memory management, the data is moved into
+/// memory which is mapped read-only, so a kernel which writes to it faults immediately with an
+/// illegal address error instead of silently corrupting its input. Otherwise the buffer is left
+/// where it is, and only the type system prevents writes from the host.
+///
+/// `ReadOnlyBuffer` dereferences to a [`DeviceSlice`](struct.DeviceSlice.html), so it can be
+/// copied to the host and used as the source of device copies like any other buffer.
+#[derive(Debug)]
+pub struct ReadOnlyBuffer<T: DeviceCopy> {
+    storage: Storage<T>,
+}
+
+#[derive(Debug)]
+enum Storage<T: DeviceCopy> {
+    Unprotected(DeviceBuffer<T>),
+    Protected { region: ReadOnlyRegion, len: usize },
+}
+
+impl<T: DeviceCopy> DeviceBuffer<T> {
+    /// Make this buffer read-only for kernels, to catch kernels which write to memory that is
+    /// meant to be an input. This is a debugging aid.
+    ///
+    /// In debug builds, on devices which support virtual memory management, this copies the data
+    /// into new memory which is mapped read-only; a kernel which writes to it then fails with
+    /// `IllegalAddress` at the offending write. In release builds, or if the device doesn't
+    /// support virtual memory management, the buffer is wrapped without copying and writes are
+    /// not detected. [`ReadOnlyBuffer::is_protected`](struct.ReadOnlyBuffer.html#method.is_protected)
+    /// tells which happened.
+    ///
+    /// # Errors
+    ///
+    /// If the read-only memory can't be allocated or the copy fails, returns the CUDA error value.
+    /// The original buffer is freed in either case.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # let _context = rustacuda::quick_init().unwrap();
+    /// use rustacuda::memory::*;
+    /// let input = DeviceBuffer::from_slice(&[1.0f32; 1024]).unwrap();
+    /// let input = input.protect_read_only().unwrap();
+    ///
+    /// // Pass `input.as_device_ptr()` to kernels which only read from it.
+    /// let mut host = [0.0f32; 1024];
+    /// input.copy_to(&mut host[..]).unwrap();
+    /// ```
+    pub fn protect_read_only(self) -> CudaResult<ReadOnlyBuffer<T>> {
+        let bytes = self.len() * size_of::<T>();
+        if !cfg!(debug_assertions) || bytes == 0 || !vmm_supported()? {
+            return Ok(ReadOnlyBuffer {
+                storage: Storage::Unprotected(self),
+            });
+        }
+
+        let region = ReadOnlyRegion::new(bytes, type_name::<T>())?;
+        unsafe {
+            driver_call!(cuda_driver_sys::cuMemcpyDtoD_v2(
+                region.ptr,
+                self.as_ptr() as CUdeviceptr,
+                bytes
+            ))
+            .to_result()?;
+        }
+        region.set_access(CUmemAccess_flags_enum::CU_MEM_ACCESS_FLAGS_PROT_READ)?;
+        Ok(ReadOnlyBuffer {
+            storage: Storage::Protected {
+                region,
+                len: self.len(),
+            },
+        })
+    }
+}
+
+impl<T: DeviceCopy> ReadOnlyBuffer<T> {
+    /// Returns `true` if writes to this buffer from kernels are detected, or `false` if the
+    /// buffer is only read-only by convention.
+    pub fn is_protected(&self) -> bool {
+        matches!(self.storage, Storage::Protected { .. })
+    }
+
+    /// Returns a device pointer to the buffer, for passing to kernels which read from it.
+    pub fn as_device_ptr(&self) -> DevicePointer<T> {
+        unsafe { DevicePointer::wrap(self.as_ptr() as *mut T) }
+    }
+
+    /// Consume the `ReadOnlyBuffer`, returning a writable `DeviceBuffer` with the same contents.
+    ///
+    /// # Errors
+    ///
+    /// If the buffer was protected and the data can't be copied back into writable memory,
+    /// returns the CUDA error value.
+    pub fn into_writable(self) -> CudaResult<DeviceBuffer<T>> {
+        match self.storage {
+            Storage::Unprotected(buffer) => Ok(buffer),
+            Storage::Protected { region, len } => unsafe {
+                let mut buffer = DeviceBuffer::uninitialized(len)?;
+                driver_call!(cuda_driver_sys::cuMemcpyDtoD_v2(
+                    buffer.as_device_ptr().as_raw_mut() as CUdeviceptr,
+                    region.ptr,
+                    len * size_of::<T>()
+                ))
+                .to_result()?;
+                Ok(buffer)
+            },
+        }
+    }
+}
+
+impl<T: DeviceCopy> Deref for ReadOnlyBuffer<T> {
+    type Target = DeviceSlice<T>;
+
+    fn deref(&self) -> &DeviceSlice<T> {
+        match &self.storage {
+            Storage::Unprotected(buffer) => buffer,
+            Storage::Protected { region, len } => unsafe {
+                DeviceSlice::from_raw_parts(DevicePointer::wrap(region.ptr as *mut T), *len)
+            },
+        }
+    }
+}
+
+fn vmm_supported() -> CudaResult<bool> {
+    let device = CurrentContext::get_device()?;
+    Ok(device.get_attribute(DeviceAttribute::VirtualMemoryManagementSupported)? != 0)
+}
+
+/// Device memory allocated and mapped through the virtual memory management API, so that its
+/// access permissions can be changed.
+#[derive(Debug)]
+struct ReadOnlyRegion {
+    handle: CUmemGenericAllocationHandle,
+    ptr: CUdeviceptr,
+    size: usize,
+    device: i32,
+    mapped: bool,
+    // The element type the region was reported to the allocation hook with, once it has been.
+    type_name: Option<&'static str>,
+}
+
+impl ReadOnlyRegion {
+    /// Allocate and map at least `bytes` bytes of readable and writable memory on the current
+    /// device, reporting it to the allocation hook as memory for elements of type `type_name`.
+    fn new(bytes: usize, type_name: &'static str) -> CudaResult<ReadOnlyRegion> {
+        let device = CurrentContext::get_device()?.into_inner();
+        // CUmemAllocationProp contains enums with no zero variant, so it is filled in place and
+        // never read as a Rust value.
+        let mut prop = MaybeUninit::<CUmemAllocationProp>::zeroed();
+        unsafe {
+            let p = prop.as_mut_ptr();
+            ptr::addr_of_mut!((*p).type_)
+                .write(CUmemAllocationType_enum::CU_MEM_ALLOCATION_TYPE_PINNED);
+            ptr::addr_of_mut!((*p).location).write(location(device));
+        }
+
+        let mut granularity = 0;
+        unsafe {
+            driver_call!(cuda_driver_sys::cuMemGetAllocationGranularity(
+                &mut granularity,
+                prop.as_ptr(),
+                CUmemAllocationGranularity_flags_enum::CU_MEM_ALLOC_GRANULARITY_MINIMUM
+            ))
+            .to_result()?;
+        }
+        let size = bytes.div_ceil(granularity) * granularity;
+
+        let mut region = ReadOnlyRegion {
+            handle: 0,
+            ptr: 0,
+            size,
+            device,
+            mapped: false,
+            type_name: None,
+        };
+        unsafe {
+            driver_call!(cuda_driver_sys::cuMemCreate(
+                &mut region.handle,
+                size,
+                prop.as_ptr(),
+                0
+            ))
+            .to_result()?;
+            driver_call!(cuda_driver_sys::cuMemAddressReserve(
+                &mut region.ptr,
+                size,
+                0,
+                0,
+                0
+            ))
+            .to_result()?;
+            driver_call!(cuda_driver_sys::cuMemMap(
+                region.ptr,
+                size,
+                0,
+                region.handle,
+                0
+            ))
+            .to_result()?;
+        }
+        region.mapped = true;
+        region.set_access(CUmemAccess_flags_enum::CU_MEM_ACCESS_FLAGS_PROT_READWRITE)?;
+        // If the hook rejects the region, dropping it releases the memory.
+        unsafe {
+            malloc::allocated(
+                AllocationKind::Virtual,
+                region.ptr as usize,
+                size,
+                type_name,
+                |_| {},
+            )?;
+        }
+        region.type_name = Some(type_name);
+        Ok(region)
+    }
+
+    fn set_access(&self, flags: CUmemAccess_flags_enum) -> CudaResult<()> {
+        let desc = CUmemAccessDesc {
+            location: location(self.device),
+            flags,
+        };
+        unsafe {
+            driver_call!(cuda_driver_sys::cuMemSetAccess(
+                self.ptr,
+                self.size,
+                &desc as *const _,
+                1
+            ))
+            .to_result()
+        }
+    }
+}
+
+impl Drop for ReadOnlyRegion {
+    fn drop(&mut self) {
+        // Undo whichever steps of `new` completed.
+        let release = || unsafe {
+            if self.mapped {
+                driver_call!(cuda_driver_sys::cuMemUnmap(self.ptr, self.size))
+                    .to_result()
+                    .expect_in_drop("Failed to unmap read-only CUDA memory");
+            }
+            if self.ptr != 0 {
+                driver_call!(cuda_driver_sys::cuMemAddressFree(self.ptr, self.size))
+                    .to_result()
+                    .expect_in_drop("Failed to free read-only CUDA memory");
+            }
+            if self.handle != 0 {
+                driver_call!(cuda_driver_sys::cuMemRelease(self.handle))
+                    .to_result()
+                    .expect_in_drop("Failed to release read-only CUDA memory");
+            }
+            Ok(())
+        };
+        match self.type_name {
+            Some(type_name) => {
+                let _ = malloc::freed(
+                    AllocationKind::Virtual,
+                    self.ptr as usize,
+                    type_name,
+                    release,
+                );
+            }
+            None => {
+                let _ = release();
+            }
+        }
+    }
+}
+
+fn location(device: i32) -> CUmemLocation {
+    CUmemLocation {
+        type_: CUmemLocationType_enum::CU_MEM_LOCATION_TYPE_DEVICE,
+        id: device,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::memory::CopyDestination;
+
+    #[test]
+    fn test_protect_read_only() {
+        let _context = crate::quick_init().unwrap();
+        let buffer = DeviceBuffer::from_slice(&[1u32, 2, 3, 4]).unwrap();
+        let buffer = buffer.protect_read_only().unwrap();
+        assert_eq!(
+            cfg!(debug_assertions) && vmm_supported().unwrap(),
+            buffer.is_protected()
+        );
+
+        let mut host = [0u32; 4];
+        buffer.copy_to(&mut host[..]).unwrap();
+        assert_eq!([1, 2, 3, 4], host);
+
+        let mut buffer = buffer.into_writable().unwrap();
+        buffer.copy_from(&[5u32, 6, 7, 8][..]).unwrap();
+        buffer.copy_to(&mut host[..]).unwrap();
+        assert_eq!([5, 6, 7, 8], host);
+    }
+
+    #[test]
+    fn test_protect_empty() {
+        let _context = crate::quick_init().unwrap();
+        let buffer = DeviceBuffer::<u64>::from_slice(&[]).unwrap();
+        let buffer = buffer.protect_read_only().unwrap();
+        assert!(!buffer.is_protected());
+        assert!(buffer.is_empty());
+    }
+}
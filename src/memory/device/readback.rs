@@ -0,0 +1,224 @@
+use crate::error::{CudaError, CudaResult, ToResult};
+use crate::event::{Event, EventFlags};
+use crate::memory::device::DeviceSlice;
+use crate::memory::{DeviceCopy, LockedBuffer};
+use crate::stream::Stream;
+use std::iter::FusedIterator;
+use std::marker::PhantomData;
+use std::mem::size_of;
+use std::os::raw::c_void;
+use std::slice;
+
+impl<T: DeviceCopy> DeviceSlice<T> {
+    /// Copy this slice to a page-locked staging buffer in chunks of `chunk_size` elements, so that
+    /// each chunk can be processed on the host as soon as it arrives, while the later chunks are
+    /// still being copied.
+    ///
+    /// Every copy is enqueued in `stream` before this returns, with an event recorded after each
+    /// one. Iterating over the returned [`ChunkedReadback`](struct.ChunkedReadback.html) waits for
+    /// each chunk's event in turn and yields a view of that chunk of the staging buffer. When the
+    /// slice len is not evenly divided by the chunk size, the last chunk is the remainder.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidValue` if `chunk_size` is zero. If allocating the staging buffer, enqueuing
+    /// a copy or recording an event fails, waits for the copies already enqueued and returns the
+    /// error from CUDA.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # let _context = rustacuda::quick_init().unwrap();
+    /// use rustacuda::memory::*;
+    /// use rustacuda::stream::{Stream, StreamFlags};
+    /// let stream = Stream::new(StreamFlags::NON_BLOCKING, None).unwrap();
+    /// let results = DeviceBuffer::from_slice(&[1u32; 10_000]).unwrap();
+    ///
+    /// let mut total = 0;
+    /// for chunk in &results.readback_chunks(4096, &stream).unwrap() {
+    ///     // Later chunks are still being copied while this one is summed.
+    ///     total += chunk.unwrap().iter().sum::<u32>();
+    /// }
+    /// assert_eq!(10_000, total);
+    /// ```
+    #[track_caller]
+    pub fn readback_chunks(
+        &self,
+        chunk_size: usize,
+        stream: &Stream,
+    ) -> CudaResult<ChunkedReadback<'_, T>> {
+        if chunk_size == 0 {
+            return Err(CudaError::InvalidValue);
+        }
+        let mut readback = ChunkedReadback {
+            buffer: unsafe { LockedBuffer::uninitialized(self.len())? },
+            chunk_size,
+            events: Vec::with_capacity(self.len().div_ceil(chunk_size)),
+            _source: PhantomData,
+        };
+        let base: *mut T = readback.buffer.as_mut_ptr();
+        for (index, chunk) in self.chunks(chunk_size).enumerate() {
+            let enqueued = unsafe {
+                driver_call!(cuda_driver_sys::cuMemcpyDtoHAsync_v2(
+                    base.add(index * chunk_size) as *mut c_void,
+                    chunk.as_ptr() as u64,
+                    chunk.len() * size_of::<T>(),
+                    stream.as_inner(),
+                ))
+                .to_result()
+            }
+            .and_then(|()| {
+                let event = Event::new(EventFlags::DISABLE_TIMING)?;
+                event.record(stream)?;
+                Ok(event)
+            });
+            match enqueued {
+                Ok(event) => {
+                    stream.trace(|| format!("device-to-host async copy of chunk {}", index));
+                    readback.events.push(event)
+                }
+                Err(e) => {
+                    // A copy may be in flight without an event to wait for, so the staging buffer
+                    // can only be freed once the whole stream is idle.
+                    let _ = stream.synchronize();
+                    return Err(e);
+                }
+            }
+        }
+        Ok(readback)
+    }
+}
+
+/// A copy of a [`DeviceSlice`](struct.DeviceSlice.html) to a page-locked staging buffer which
+/// arrives in chunks.
+///
+/// This struct is created by the `readback_chunks` method on `DeviceSlice`. Iterating over a
+/// reference to it yields each chunk of the staging buffer once that chunk's copy has completed.
+/// It can be iterated over any number of times.
+///
+/// Dropping a `ChunkedReadback` waits for the outstanding copies, since they write to the staging
+/// buffer.
+#[derive(Debug)]
+pub struct ChunkedReadback<'a, T: DeviceCopy> {
+    buffer: LockedBuffer<T>,
+    chunk_size: usize,
+    events: Vec<Event>,
+    // The source slice must not be freed or modified while it is being copied.
+    _source: PhantomData<&'a DeviceSlice<T>>,
+}
+impl<'a, T: DeviceCopy> ChunkedReadback<'a, T> {
+    /// Returns the number of chunks.
+    pub fn num_chunks(&self) -> usize {
+        self.events.len()
+    }
+
+    /// Wait for the copy of chunk `index` to complete, then return it.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidValue` if `index` is out of bounds. If waiting fails, returns the error
+    /// from CUDA.
+    pub fn chunk(&self, index: usize) -> CudaResult<&[T]> {
+        let event = self.events.get(index).ok_or(CudaError::InvalidValue)?;
+        event.synchronize()?;
+        let start = index * self.chunk_size;
+        let len = self.chunk_size.min(self.buffer.len() - start);
+        // The copy of this chunk has completed, and the copies still in flight write to other
+        // chunks only.
+        unsafe { Ok(slice::from_raw_parts(self.buffer.as_ptr().add(start), len)) }
+    }
+
+    /// Returns an iterator which waits for each chunk in turn and yields it.
+    pub fn iter(&self) -> ReadbackChunks<'_, 'a, T> {
+        ReadbackChunks {
+            readback: self,
+            next: 0,
+        }
+    }
+
+    /// Wait for every chunk to arrive and return the whole staging buffer.
+    ///
+    /// # Errors
+    ///
+    /// If waiting fails, returns the error from CUDA.
+    pub fn wait(mut self) -> CudaResult<LockedBuffer<T>> {
+        if let Some(last) = self.events.last() {
+            last.synchronize()?;
+        }
+        self.events.clear();
+        Ok(std::mem::replace(&mut self.buffer, unsafe {
+            LockedBuffer::uninitialized(0)?
+        }))
+    }
+}
+impl<'b, 'a, T: DeviceCopy> IntoIterator for &'b ChunkedReadback<'a, T> {
+    type Item = CudaResult<&'b [T]>;
+    type IntoIter = ReadbackChunks<'b, 'a, T>;
+
+    fn into_iter(self) -> ReadbackChunks<'b, 'a, T> {
+        self.iter()
+    }
+}
+impl<'a, T: DeviceCopy> Drop for ChunkedReadback<'a, T> {
+    fn drop(&mut self) {
+        // The copies complete in order, so the last one completing means they all have.
+        if let Some(last) = self.events.last() {
+            let _ = last.synchronize();
+        }
+    }
+}
+
+/// An iterator over the chunks of a [`ChunkedReadback`](struct.ChunkedReadback.html), which waits
+/// for each chunk's copy to complete before yielding it.
+#[derive(Debug, Clone)]
+pub struct ReadbackChunks<'b, 'a, T: DeviceCopy> {
+    readback: &'b ChunkedReadback<'a, T>,
+    next: usize,
+}
+impl<'b, 'a, T: DeviceCopy> Iterator for ReadbackChunks<'b, 'a, T> {
+    type Item = CudaResult<&'b [T]>;
+
+    fn next(&mut self) -> Option<CudaResult<&'b [T]>> {
+        if self.next == self.readback.num_chunks() {
+            return None;
+        }
+        let chunk = self.readback.chunk(self.next);
+        self.next += 1;
+        Some(chunk)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.readback.num_chunks() - self.next;
+        (remaining, Some(remaining))
+    }
+}
+impl<'b, 'a, T: DeviceCopy> ExactSizeIterator for ReadbackChunks<'b, 'a, T> {}
+impl<'b, 'a, T: DeviceCopy> FusedIterator for ReadbackChunks<'b, 'a, T> {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::memory::DeviceBuffer;
+    use crate::stream::StreamFlags;
+
+    #[test]
+    fn test_readback_chunks() {
+        let _context = crate::quick_init().unwrap();
+        let stream = Stream::new(StreamFlags::NON_BLOCKING, None).unwrap();
+        let data: Vec<u64> = (0..1000).collect();
+        let buffer = DeviceBuffer::from_slice(&data).unwrap();
+
+        let readback = buffer.readback_chunks(300, &stream).unwrap();
+        assert_eq!(4, readback.num_chunks());
+        let lens: Vec<usize> = readback.iter().map(|c| c.unwrap().len()).collect();
+        assert_eq!([300, 300, 300, 100], lens[..]);
+        assert_eq!(&data[600..900], readback.chunk(2).unwrap());
+        assert_eq!(Err(CudaError::InvalidValue), readback.chunk(4));
+        assert_eq!(&data[..], readback.wait().unwrap().as_slice());
+
+        assert_eq!(
+            CudaError::InvalidValue,
+            buffer.readback_chunks(0, &stream).unwrap_err()
+        );
+    }
+}
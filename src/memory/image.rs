@@ -0,0 +1,387 @@
+//! Conversions between `image` crate buffers and CUDA memory.
+//!
+//! This module is only compiled when the `image` feature is enabled.
+
+use crate::error::{CudaError, CudaResult, ToResult};
+use crate::memory::array::{ArrayElement, ArrayObject};
+use crate::memory::{cuda_free, cuda_malloc_pitched, DeviceCopy, DevicePointer};
+use cuda_driver_sys::{CUmemorytype_enum, CUDA_MEMCPY2D};
+use image::{GrayImage, ImageBuffer, Pixel, Primitive, RgbaImage};
+use std::mem::{self, size_of};
+use std::os::raw::c_void;
+
+/// An image whose pixels are stored in a `Vec` of their subpixels, like `RgbaImage`.
+pub type Image<P> = ImageBuffer<P, Vec<<P as Pixel>::Subpixel>>;
+
+/// Build a `CUDA_MEMCPY2D` that copies `height` rows of `width_in_bytes` bytes. The caller fills
+/// in the source and destination.
+fn memcpy_2d(width_in_bytes: usize, height: usize) -> CUDA_MEMCPY2D {
+    // All-zero is a valid starting point for this plain C struct.
+    let mut copy: CUDA_MEMCPY2D = unsafe { mem::zeroed() };
+    copy.WidthInBytes = width_in_bytes;
+    copy.Height = height;
+    copy
+}
+
+// Returns the number of channels of `P` if a CUDA array can hold pixels of type `P`.
+fn array_channels<P>() -> CudaResult<u32>
+where
+    P: Pixel,
+    P::Subpixel: ArrayElement,
+{
+    match (P::Subpixel::NUM_CHANNELS, P::CHANNEL_COUNT) {
+        (1, 1) | (1, 2) | (1, 4) => Ok(u32::from(P::CHANNEL_COUNT)),
+        _ => Err(CudaError::InvalidValue),
+    }
+}
+
+// Copy `image` into `array`, which must be a 2D array of matching size and format.
+fn copy_image_to_array<P>(image: &Image<P>, array: &mut ArrayObject) -> CudaResult<()>
+where
+    P: Pixel,
+    P::Subpixel: ArrayElement,
+{
+    let channels = array_channels::<P>()?;
+    let descriptor = array.descriptor()?;
+    let (width, height) = image.dimensions();
+    let (width, height) = (width as usize, height as usize);
+    if descriptor.format() != P::Subpixel::FORMAT
+        || descriptor.num_channels() != channels
+        || descriptor.depth() != 0
+        || descriptor.width() != width
+        || descriptor.height().max(1) != height
+    {
+        return Err(CudaError::InvalidValue);
+    }
+
+    let row_bytes = width * channels as usize * size_of::<P::Subpixel>();
+    let mut copy = memcpy_2d(row_bytes, height);
+    copy.srcMemoryType = CUmemorytype_enum::CU_MEMORYTYPE_HOST;
+    copy.srcHost = image.as_raw().as_ptr() as *const c_void;
+    copy.srcPitch = row_bytes;
+    copy.dstMemoryType = CUmemorytype_enum::CU_MEMORYTYPE_ARRAY;
+    copy.dstArray = array.as_raw();
+    unsafe { driver_call!(cuda_driver_sys::cuMemcpy2D_v2(&copy)).to_result() }
+}
+
+fn image_to_array<P>(image: &Image<P>) -> CudaResult<ArrayObject>
+where
+    P: Pixel,
+    P::Subpixel: ArrayElement,
+{
+    let (width, height) = image.dimensions();
+    let mut array = ArrayObject::new_2d(
+        [width as usize, height as usize],
+        P::Subpixel::FORMAT,
+        array_channels::<P>()?,
+    )?;
+    copy_image_to_array(image, &mut array)?;
+    Ok(array)
+}
+
+fn array_to_image<P>(array: &ArrayObject) -> CudaResult<Image<P>>
+where
+    P: Pixel,
+    P::Subpixel: ArrayElement,
+{
+    let descriptor = array.descriptor()?;
+    let channels = array_channels::<P>()? as usize;
+    if descriptor.format() != P::Subpixel::FORMAT
+        || descriptor.num_channels() as usize != channels
+        || descriptor.depth() != 0
+    {
+        return Err(CudaError::InvalidValue);
+    }
+    // 1D arrays report a height of zero but hold a single row.
+    let (width, height) = (descriptor.width(), descriptor.height().max(1));
+
+    let mut data = vec![P::Subpixel::DEFAULT_MIN_VALUE; width * height * channels];
+    let row_bytes = width * channels * size_of::<P::Subpixel>();
+    let mut copy = memcpy_2d(row_bytes, height);
+    copy.srcMemoryType = CUmemorytype_enum::CU_MEMORYTYPE_ARRAY;
+    copy.srcArray = array.as_raw();
+    copy.dstMemoryType = CUmemorytype_enum::CU_MEMORYTYPE_HOST;
+    copy.dstHost = data.as_mut_ptr() as *mut c_void;
+    copy.dstPitch = row_bytes;
+    unsafe { driver_call!(cuda_driver_sys::cuMemcpy2D_v2(&copy)).to_result()? };
+
+    ImageBuffer::from_raw(width as u32, height as u32, data).ok_or(CudaError::InvalidValue)
+}
+
+impl ArrayObject {
+    /// Creates a 2D `UnsignedInt8` array with four channels and copies an RGBA image into it.
+    ///
+    /// # Errors
+    ///
+    /// If the array cannot be allocated or the copy fails, returns the CUDA error value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # let _context = rustacuda::quick_init().unwrap();
+    /// use rustacuda::memory::array::ArrayObject;
+    /// let image = image::RgbaImage::new(64, 32);
+    /// let array = ArrayObject::from_rgba_image(&image).unwrap();
+    /// assert_eq!([64, 32, 0], array.descriptor().unwrap().dims());
+    /// ```
+    pub fn from_rgba_image(image: &RgbaImage) -> CudaResult<ArrayObject> {
+        image_to_array(image)
+    }
+
+    /// Creates a 2D `UnsignedInt8` array with one channel and copies a grayscale image into it.
+    ///
+    /// # Errors
+    ///
+    /// If the array cannot be allocated or the copy fails, returns the CUDA error value.
+    pub fn from_gray_image(image: &GrayImage) -> CudaResult<ArrayObject> {
+        image_to_array(image)
+    }
+
+    /// Copies the contents of this array into a new RGBA image.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidValue` if the array is not a 1D or 2D `UnsignedInt8` array with four
+    /// channels. If the copy fails, returns the CUDA error value.
+    pub fn to_rgba_image(&self) -> CudaResult<RgbaImage> {
+        array_to_image(self)
+    }
+
+    /// Copies the contents of this array into a new grayscale image.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidValue` if the array is not a 1D or 2D `UnsignedInt8` array with one
+    /// channel. If the copy fails, returns the CUDA error value.
+    pub fn to_gray_image(&self) -> CudaResult<GrayImage> {
+        array_to_image(self)
+    }
+
+    /// Creates a 2D array matching the pixel type of `image` and copies the image into it.
+    ///
+    /// The array's format is that of the pixel's subpixel type, such as `UnsignedInt16` for
+    /// `Luma<u16>` or `Float` for `Rgba<f32>`, and it has one channel per subpixel. CUDA arrays
+    /// only support one, two or four channels, so RGB images must be converted to RGBA first.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidValue` if the pixel type has three channels. If the array cannot be
+    /// allocated or the copy fails, returns the CUDA error value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # let _context = rustacuda::quick_init().unwrap();
+    /// use image::{ImageBuffer, Luma};
+    /// use rustacuda::memory::array::{ArrayFormat, ArrayObject};
+    /// let depth: ImageBuffer<Luma<u16>, Vec<u16>> = ImageBuffer::new(64, 32);
+    /// let array = ArrayObject::from_image(&depth).unwrap();
+    /// assert_eq!(ArrayFormat::UnsignedInt16, array.descriptor().unwrap().format());
+    /// ```
+    pub fn from_image<P>(image: &Image<P>) -> CudaResult<ArrayObject>
+    where
+        P: Pixel,
+        P::Subpixel: ArrayElement,
+    {
+        image_to_array(image)
+    }
+
+    /// Copies `image` into this array, which must be a 2D array of the same size whose format
+    /// and number of channels match the pixel type. Use this to update an existing texture every
+    /// frame without reallocating it.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidValue` if the array doesn't match the image. If the copy fails, returns
+    /// the CUDA error value.
+    pub fn copy_from_image<P>(&mut self, image: &Image<P>) -> CudaResult<()>
+    where
+        P: Pixel,
+        P::Subpixel: ArrayElement,
+    {
+        copy_image_to_array(image, self)
+    }
+
+    /// Copies the contents of this array into a new image with pixels of type `P`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidValue` if the array is not a 1D or 2D array whose format and number of
+    /// channels match `P`. If the copy fails, returns the CUDA error value.
+    pub fn to_image<P>(&self) -> CudaResult<Image<P>>
+    where
+        P: Pixel,
+        P::Subpixel: ArrayElement,
+    {
+        array_to_image(self)
+    }
+}
+
+/// Allocates a pitched 2D region of device memory and copies an image into it.
+///
+/// Returns a pointer to the first row and the pitch in bytes, as with
+/// [`cuda_malloc_pitched`](fn.cuda_malloc_pitched.html). The channels of each pixel are stored
+/// interleaved, so each row holds `width * P::CHANNEL_COUNT` subpixels followed by padding. The
+/// memory must be freed using [`cuda_free`](fn.cuda_free.html).
+///
+/// # Errors
+///
+/// If the allocation or the copy fails, returns the CUDA error value. If the image is empty,
+/// returns InvalidMemoryAllocation.
+///
+/// # Examples
+///
+/// ```
+/// # let _context = rustacuda::quick_init().unwrap();
+/// use rustacuda::memory::*;
+/// let image = image::GrayImage::new(640, 480);
+/// let (buffer, pitch) = upload_image_pitched(&image).unwrap();
+/// assert!(pitch >= 640);
+/// unsafe { cuda_free(buffer).unwrap() };
+/// ```
+pub fn upload_image_pitched<P>(image: &Image<P>) -> CudaResult<(DevicePointer<P::Subpixel>, usize)>
+where
+    P: Pixel,
+    P::Subpixel: DeviceCopy,
+{
+    let (width, height) = image.dimensions();
+    let row_len = width as usize * usize::from(P::CHANNEL_COUNT);
+    let row_bytes = row_len * size_of::<P::Subpixel>();
+    unsafe {
+        let (buffer, pitch) = cuda_malloc_pitched::<P::Subpixel>(row_len, height as usize)?;
+
+        let mut copy = memcpy_2d(row_bytes, height as usize);
+        copy.srcMemoryType = CUmemorytype_enum::CU_MEMORYTYPE_HOST;
+        copy.srcHost = image.as_raw().as_ptr() as *const c_void;
+        copy.srcPitch = row_bytes;
+        copy.dstMemoryType = CUmemorytype_enum::CU_MEMORYTYPE_DEVICE;
+        copy.dstDevice = buffer.as_raw() as u64;
+        copy.dstPitch = pitch;
+        if let Err(e) = driver_call!(cuda_driver_sys::cuMemcpy2D_v2(&copy)).to_result() {
+            // The copy error is more useful to the caller than any error from freeing.
+            let _ = cuda_free(buffer);
+            return Err(e);
+        }
+        Ok((buffer, pitch))
+    }
+}
+
+/// Copies a pitched 2D region of device memory into a new image.
+///
+/// `width` and `height` are in pixels; each row is expected to hold `width * P::CHANNEL_COUNT`
+/// interleaved subpixels followed by padding up to `pitch` bytes.
+///
+/// # Errors
+///
+/// If the copy fails, returns the CUDA error value.
+///
+/// # Safety
+///
+/// The caller must ensure that `buffer` points to an initialized allocation of at least `height`
+/// rows of `pitch` bytes each, and that a row of pixels fits within `pitch`.
+pub unsafe fn download_image_pitched<P>(
+    buffer: DevicePointer<P::Subpixel>,
+    pitch: usize,
+    width: u32,
+    height: u32,
+) -> CudaResult<Image<P>>
+where
+    P: Pixel,
+    P::Subpixel: DeviceCopy,
+{
+    let row_len = width as usize * usize::from(P::CHANNEL_COUNT);
+    let row_bytes = row_len * size_of::<P::Subpixel>();
+    let mut data = vec![P::Subpixel::DEFAULT_MIN_VALUE; row_len * height as usize];
+
+    let mut copy = memcpy_2d(row_bytes, height as usize);
+    copy.srcMemoryType = CUmemorytype_enum::CU_MEMORYTYPE_DEVICE;
+    copy.srcDevice = buffer.as_raw() as u64;
+    copy.srcPitch = pitch;
+    copy.dstMemoryType = CUmemorytype_enum::CU_MEMORYTYPE_HOST;
+    copy.dstHost = data.as_mut_ptr() as *mut c_void;
+    copy.dstPitch = row_bytes;
+    driver_call!(cuda_driver_sys::cuMemcpy2D_v2(&copy)).to_result()?;
+
+    ImageBuffer::from_raw(width, height, data).ok_or(CudaError::InvalidValue)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::memory::array::ArrayFormat;
+    use image::{Luma, LumaA, Rgb, Rgba};
+
+    #[test]
+    fn test_rgba_array_round_trip() {
+        let _context = crate::quick_init().unwrap();
+        let image = RgbaImage::from_fn(17, 9, |x, y| Rgba([x as u8, y as u8, 7, 255]));
+        let array = ArrayObject::from_rgba_image(&image).unwrap();
+        assert_eq!(image, array.to_rgba_image().unwrap());
+    }
+
+    #[test]
+    fn test_gray_array_round_trip() {
+        let _context = crate::quick_init().unwrap();
+        let image = GrayImage::from_fn(31, 5, |x, y| Luma([(x * y) as u8]));
+        let array = ArrayObject::from_gray_image(&image).unwrap();
+        assert_eq!(image, array.to_gray_image().unwrap());
+    }
+
+    #[test]
+    fn test_array_channel_mismatch() {
+        let _context = crate::quick_init().unwrap();
+        let array = ArrayObject::from_gray_image(&GrayImage::new(4, 4)).unwrap();
+        assert_eq!(Some(CudaError::InvalidValue), array.to_rgba_image().err());
+    }
+
+    #[test]
+    fn test_generic_array_round_trip() {
+        let _context = crate::quick_init().unwrap();
+        let image: Image<LumaA<u16>> =
+            ImageBuffer::from_fn(9, 4, |x, y| LumaA([x as u16 * 1000, y as u16]));
+        let mut array = ArrayObject::from_image(&image).unwrap();
+        let descriptor = array.descriptor().unwrap();
+        assert_eq!(ArrayFormat::UnsignedInt16, descriptor.format());
+        assert_eq!(2, descriptor.num_channels());
+        assert_eq!(image, array.to_image::<LumaA<u16>>().unwrap());
+
+        let other = ImageBuffer::from_pixel(9, 4, LumaA([7u16, 8]));
+        array.copy_from_image(&other).unwrap();
+        assert_eq!(other, array.to_image::<LumaA<u16>>().unwrap());
+
+        let wrong_size = ImageBuffer::from_pixel(8, 4, LumaA([7u16, 8]));
+        assert_eq!(
+            Err(CudaError::InvalidValue),
+            array.copy_from_image(&wrong_size)
+        );
+        assert_eq!(
+            Some(CudaError::InvalidValue),
+            array.to_image::<Luma<u16>>().err()
+        );
+        let rgb: Image<Rgb<f32>> = ImageBuffer::new(2, 2);
+        assert_eq!(
+            Some(CudaError::InvalidValue),
+            ArrayObject::from_image(&rgb).err()
+        );
+    }
+
+    #[test]
+    fn test_pitched_float_round_trip() {
+        let _context = crate::quick_init().unwrap();
+        let image = ImageBuffer::from_fn(5, 3, |x, y| Rgba([x as f32, y as f32, 0.5, 1.0]));
+        let (buffer, pitch) = upload_image_pitched(&image).unwrap();
+        let copy = unsafe { download_image_pitched::<Rgba<f32>>(buffer, pitch, 5, 3).unwrap() };
+        assert_eq!(image, copy);
+        unsafe { cuda_free(buffer).unwrap() };
+    }
+
+    #[test]
+    fn test_pitched_round_trip() {
+        let _context = crate::quick_init().unwrap();
+        let image = ImageBuffer::from_fn(13, 7, |x, y| Rgb([x as u8, y as u8, 3]));
+        let (buffer, pitch) = upload_image_pitched(&image).unwrap();
+        let copy = unsafe { download_image_pitched::<Rgb<u8>>(buffer, pitch, 13, 7).unwrap() };
+        assert_eq!(image, copy);
+        unsafe { cuda_free(buffer).unwrap() };
+    }
+}
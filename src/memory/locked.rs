@@ -1,11 +1,39 @@
-use super::DeviceCopy;
+use super::{DeviceCopy, DevicePointer};
+use crate::context::{ContextFlags, CurrentContext};
+use crate::device::DeviceAttribute;
 use crate::error::*;
-use crate::memory::malloc::{cuda_free_locked, cuda_malloc_locked};
-use std::mem;
+use crate::memory::malloc::{
+    cuda_free_locked, cuda_host_get_flags, cuda_malloc_locked, cuda_malloc_locked_with_flags,
+};
+use std::mem::{self, size_of, MaybeUninit};
 use std::ops;
+use std::os::raw::c_void;
 use std::ptr;
 use std::slice;
 
+bitflags! {
+    /// Bit flags for allocating page-locked host memory.
+    ///
+    /// The empty set of flags allocates ordinary page-locked memory, the same as
+    /// [`LockedBuffer::new`](struct.LockedBuffer.html#method.new).
+    pub struct HostAllocFlags: u32 {
+        /// The memory is treated as page-locked by all CUDA contexts, not just the one that
+        /// allocated it. This is required for buffers shared between contexts.
+        const PORTABLE = 0x1;
+
+        /// The memory is mapped into the device address space. The context must have been
+        /// created with `ContextFlags::MAP_HOST` to use this flag, unless the device supports
+        /// unified addressing; otherwise allocating returns `CudaError::MapHostFlagMissing`.
+        const MAPPED = 0x2;
+
+        /// The memory is allocated as write-combined. Write-combined memory is not cached by the
+        /// host, which frees up host cache and can substantially speed up host-to-device
+        /// transfers, but reading from it on the host is very slow. It is best suited to buffers
+        /// that the host only writes to.
+        const WRITE_COMBINED = 0x4;
+    }
+}
+
 /// Fixed-size host-side buffer in page-locked memory.
 ///
 /// See the [`module-level documentation`](../memory/index.html) for more details on page-locked
@@ -15,6 +43,9 @@ pub struct LockedBuffer<T: DeviceCopy> {
     buf: *mut T,
     capacity: usize,
 }
+// See "Thread Safety" in the `memory` module documentation.
+unsafe impl<T: DeviceCopy + Send> Send for LockedBuffer<T> {}
+unsafe impl<T: DeviceCopy + Sync> Sync for LockedBuffer<T> {}
 impl<T: DeviceCopy + Clone> LockedBuffer<T> {
     /// Allocate a new page-locked buffer large enough to hold `size` `T`'s and initialized with
     /// clones of `value`.
@@ -42,6 +73,36 @@ impl<T: DeviceCopy + Clone> LockedBuffer<T> {
         }
     }
 
+    /// Allocate a new page-locked buffer large enough to hold `size` `T`'s, allocated according
+    /// to `flags`, and initialized with clones of `value`.
+    ///
+    /// Write-combined buffers should only be written to by the host, since reading them is very
+    /// slow. See [`HostAllocFlags`](struct.HostAllocFlags.html) for details.
+    ///
+    /// # Errors
+    ///
+    /// If the allocation fails, returns the error from CUDA. If `size` is large enough that
+    /// `size * mem::sizeof::<T>()` overflows usize, then returns InvalidMemoryAllocation.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # let _context = rustacuda::quick_init().unwrap();
+    /// use rustacuda::memory::*;
+    /// let flags = HostAllocFlags::PORTABLE | HostAllocFlags::WRITE_COMBINED;
+    /// let mut buffer = LockedBuffer::new_with_flags(&0u64, 5, flags).unwrap();
+    /// buffer[0] = 1;
+    /// ```
+    pub fn new_with_flags(value: &T, size: usize, flags: HostAllocFlags) -> CudaResult<Self> {
+        unsafe {
+            let mut uninit = LockedBuffer::uninitialized_with_flags(size, flags)?;
+            for x in 0..size {
+                *uninit.get_unchecked_mut(x) = value.clone();
+            }
+            Ok(uninit)
+        }
+    }
+
     /// Allocate a new page-locked buffer of the same size as `slice`, initialized with a clone of
     /// the data in `slice`.
     ///
@@ -67,6 +128,47 @@ impl<T: DeviceCopy + Clone> LockedBuffer<T> {
             Ok(uninit)
         }
     }
+
+    /// Allocate a new page-locked buffer large enough to hold `size` `T`'s and initialized with
+    /// clones of `value`, with the backing memory placed on the host NUMA node `numa_node`.
+    ///
+    /// On multi-socket machines, staging buffers placed on the node closest to the GPU's PCIe
+    /// root avoid crossing the inter-socket link on every transfer. The placement is applied by
+    /// binding the calling thread's memory policy to `numa_node` for the duration of the
+    /// allocation; the previous policy is restored before this function returns.
+    ///
+    /// This function is only available on Linux.
+    ///
+    /// # Errors
+    ///
+    /// If the allocation fails, returns the error from CUDA. If `size` is large enough that
+    /// `size * mem::sizeof::<T>()` overflows usize, then returns InvalidMemoryAllocation. If
+    /// `numa_node` does not exist or the kernel does not support NUMA memory policies, returns
+    /// InvalidValue.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # let _context = rustacuda::quick_init().unwrap();
+    /// use rustacuda::memory::*;
+    /// let mut buffer = LockedBuffer::new_on_node(&0u64, 5, 0).unwrap();
+    /// buffer[0] = 1;
+    /// ```
+    #[cfg(target_os = "linux")]
+    pub fn new_on_node(value: &T, size: usize, numa_node: u32) -> CudaResult<Self> {
+        unsafe {
+            let mut uninit = {
+                // cuMemAllocHost faults in and pins the pages before returning, so they are
+                // placed according to the policy in effect during the call.
+                let _policy = numa::MemPolicyGuard::bind(numa_node)?;
+                LockedBuffer::uninitialized(size)?
+            };
+            for x in 0..size {
+                *uninit.get_unchecked_mut(x) = value.clone();
+            }
+            Ok(uninit)
+        }
+    }
 }
 impl<T: DeviceCopy> LockedBuffer<T> {
     /// Allocate a new page-locked buffer large enough to hold `size` `T`'s, but without
@@ -93,8 +195,99 @@ impl<T: DeviceCopy> LockedBuffer<T> {
     /// }
     /// ```
     pub unsafe fn uninitialized(size: usize) -> CudaResult<Self> {
+        LockedBuffer::uninitialized_with_flags(size, HostAllocFlags::empty())
+    }
+
+    /// Allocate a new page-locked buffer large enough to hold `size` `T`'s, with the contents
+    /// typed as `MaybeUninit<T>`.
+    ///
+    /// Unlike [`uninitialized`](#method.uninitialized), this is safe to call: the buffer can't be
+    /// read as `T` until it is converted with [`assume_init`](#method.assume_init), so it can be
+    /// filled element by element (for example, by parsing a file) without first writing a
+    /// placeholder value to every element.
+    ///
+    /// # Errors
+    ///
+    /// If the allocation fails, returns the error from CUDA. If `size` is large enough that
+    /// `size * mem::sizeof::<T>()` overflows usize, then returns InvalidMemoryAllocation.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # let _context = rustacuda::quick_init().unwrap();
+    /// use rustacuda::memory::*;
+    /// let mut buffer = LockedBuffer::<u32>::uninit(4).unwrap();
+    /// for (i, x) in buffer.iter_mut().enumerate() {
+    ///     x.write(i as u32 * 10);
+    /// }
+    /// let buffer = unsafe { buffer.assume_init() };
+    /// assert_eq!(&[0, 10, 20, 30], buffer.as_slice());
+    /// ```
+    pub fn uninit(size: usize) -> CudaResult<LockedBuffer<MaybeUninit<T>>> {
+        unsafe { LockedBuffer::uninitialized(size) }
+    }
+
+    /// Allocate a new page-locked buffer of the same size as `vec`, and move the contents of
+    /// `vec` into it.
+    ///
+    /// # Errors
+    ///
+    /// If the allocation fails, returns the error from CUDA.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # let _context = rustacuda::quick_init().unwrap();
+    /// use rustacuda::memory::*;
+    /// let buffer = LockedBuffer::from_vec(vec![1u64, 2, 3]).unwrap();
+    /// assert_eq!(&[1, 2, 3], buffer.as_slice());
+    /// ```
+    pub fn from_vec(mut vec: Vec<T>) -> CudaResult<Self> {
+        unsafe {
+            let mut buffer = LockedBuffer::uninitialized(vec.len())?;
+            ptr::copy_nonoverlapping(vec.as_ptr(), buffer.as_mut_ptr(), vec.len());
+            // The elements now belong to the buffer.
+            vec.set_len(0);
+            Ok(buffer)
+        }
+    }
+
+    /// Allocate a new page-locked buffer large enough to hold `size` `T`'s, allocated according
+    /// to `flags`, but without initializing the contents.
+    ///
+    /// # Errors
+    ///
+    /// If the allocation fails, returns the error from CUDA. If `size` is large enough that
+    /// `size * mem::sizeof::<T>()` overflows usize, then returns InvalidMemoryAllocation. If
+    /// `flags` contains `MAPPED` but the current context can't map host memory, returns
+    /// `MapHostFlagMissing`.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that the contents of the buffer are initialized before reading from
+    /// the buffer.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # let _context = rustacuda::quick_init().unwrap();
+    /// use rustacuda::memory::*;
+    /// let mut buffer =
+    ///     unsafe { LockedBuffer::uninitialized_with_flags(5, HostAllocFlags::PORTABLE).unwrap() };
+    /// for i in buffer.iter_mut() {
+    ///     *i = 0u64;
+    /// }
+    /// ```
+    pub unsafe fn uninitialized_with_flags(size: usize, flags: HostAllocFlags) -> CudaResult<Self> {
         let ptr: *mut T = if size > 0 && mem::size_of::<T>() > 0 {
-            cuda_malloc_locked(size)?
+            if flags.contains(HostAllocFlags::MAPPED) {
+                check_map_host()?;
+            }
+            if flags.is_empty() {
+                cuda_malloc_locked(size)?
+            } else {
+                cuda_malloc_locked_with_flags(size, flags)?
+            }
         } else {
             ptr::NonNull::dangling().as_ptr()
         };
@@ -138,6 +331,76 @@ impl<T: DeviceCopy> LockedBuffer<T> {
         self
     }
 
+    /// Returns a device pointer to this buffer, through which kernels can read and write the host
+    /// memory directly without an explicit copy.
+    ///
+    /// This requires a context created with `ContextFlags::MAP_HOST`, and a buffer allocated with
+    /// `HostAllocFlags::MAPPED` (on devices with unified addressing, all page-locked memory is
+    /// mapped). Every access from the device crosses the
+    /// PCIe bus (or, on integrated GPUs such as Jetson, goes straight to the shared system
+    /// memory), so this is best suited to small, latency-critical data or data which is read
+    /// exactly once.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidValue` if the buffer is empty or is not mapped into the device address
+    /// space, or `MapHostFlagMissing` if the current context can't map host memory. Otherwise, if
+    /// getting the device pointer fails, returns the error from CUDA.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # let _context = rustacuda::quick_init().unwrap();
+    /// use rustacuda::memory::*;
+    /// let mut buffer = LockedBuffer::new_with_flags(&0u64, 5, HostAllocFlags::MAPPED).unwrap();
+    /// let device_ptr = buffer.as_device_ptr().unwrap();
+    /// ```
+    pub fn as_device_ptr(&mut self) -> CudaResult<DevicePointer<T>> {
+        if self.capacity == 0 || size_of::<T>() == 0 {
+            return Err(CudaError::InvalidValue);
+        }
+        let mut device: cuda_driver_sys::CUdeviceptr = 0;
+        unsafe {
+            let result = driver_call!(cuda_driver_sys::cuMemHostGetDevicePointer_v2(
+                &mut device,
+                self.buf as *mut c_void,
+                0,
+            ))
+            .to_result();
+            if let Err(e) = result {
+                // The driver's error doesn't say why the memory isn't mapped.
+                check_map_host()?;
+                return Err(e);
+            }
+            Ok(DevicePointer::wrap(device as *mut T))
+        }
+    }
+
+    /// Returns the flags that this buffer was allocated with.
+    ///
+    /// Buffers allocated without flags may still report `HostAllocFlags::MAPPED` if the context
+    /// was created with `ContextFlags::MAP_HOST`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidValue` if the buffer is empty, since no memory was allocated. Otherwise, if
+    /// querying the flags fails, returns the error from CUDA.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # let _context = rustacuda::quick_init().unwrap();
+    /// use rustacuda::memory::*;
+    /// let buffer = LockedBuffer::new_with_flags(&0u64, 5, HostAllocFlags::PORTABLE).unwrap();
+    /// assert!(buffer.flags().unwrap().contains(HostAllocFlags::PORTABLE));
+    /// ```
+    pub fn flags(&self) -> CudaResult<HostAllocFlags> {
+        if self.capacity == 0 || size_of::<T>() == 0 {
+            return Err(CudaError::InvalidValue);
+        }
+        cuda_host_get_flags(self.buf)
+    }
+
     /// Creates a `LockedBuffer<T>` directly from the raw components of another locked buffer.
     ///
     /// # Safety
@@ -223,6 +486,23 @@ impl<T: DeviceCopy> LockedBuffer<T> {
     }
 }
 
+impl<T: DeviceCopy> LockedBuffer<MaybeUninit<T>> {
+    /// Converts a `LockedBuffer<MaybeUninit<T>>` into a `LockedBuffer<T>` without copying or
+    /// reallocating.
+    ///
+    /// # Safety
+    ///
+    /// As with `MaybeUninit::assume_init`, the caller must guarantee that every element of the
+    /// buffer has been initialized.
+    pub unsafe fn assume_init(self) -> LockedBuffer<T> {
+        let buffer = LockedBuffer {
+            buf: self.buf as *mut T,
+            capacity: self.capacity,
+        };
+        mem::forget(self);
+        buffer
+    }
+}
 impl<T: DeviceCopy> AsRef<[T]> for LockedBuffer<T> {
     fn as_ref(&self) -> &[T] {
         self
@@ -260,16 +540,106 @@ impl<T: DeviceCopy> Drop for LockedBuffer<T> {
         if self.capacity > 0 && mem::size_of::<T>() > 0 {
             // No choice but to panic if this fails.
             unsafe {
-                cuda_free_locked(self.buf).expect("Failed to deallocate CUDA page-locked memory.");
+                cuda_free_locked(self.buf)
+                    .expect_in_drop("Failed to deallocate CUDA page-locked memory.");
             }
         }
         self.capacity = 0;
     }
 }
 
+// Mapping host memory requires a context created with MAP_HOST, except on devices with unified
+// addressing, where all page-locked memory is mapped.
+fn check_map_host() -> CudaResult<()> {
+    if CurrentContext::get_flags()?.contains(ContextFlags::MAP_HOST) {
+        return Ok(());
+    }
+    let device = CurrentContext::get_device()?;
+    if device.get_attribute(DeviceAttribute::UnifiedAddressing)? != 0 {
+        return Ok(());
+    }
+    Err(CudaError::MapHostFlagMissing)
+}
+
+#[cfg(target_os = "linux")]
+mod numa {
+    use crate::error::{CudaError, CudaResult};
+    use libc::{c_int, c_ulong, c_void, SYS_get_mempolicy, SYS_set_mempolicy};
+    use std::ptr;
+
+    const MPOL_DEFAULT: c_int = 0;
+    const MPOL_BIND: c_int = 2;
+
+    // Large enough for any node count supported by the kernel's CONFIG_NODES_SHIFT.
+    const MAX_NODES: usize = 1024;
+    const BITS_PER_WORD: usize = c_ulong::BITS as usize;
+    const MASK_WORDS: usize = MAX_NODES / BITS_PER_WORD;
+
+    /// Binds the calling thread's memory policy to a single NUMA node, restoring the previous
+    /// policy when dropped.
+    pub(super) struct MemPolicyGuard {
+        mode: c_int,
+        mask: [c_ulong; MASK_WORDS],
+    }
+    impl MemPolicyGuard {
+        pub(super) fn bind(node: u32) -> CudaResult<Self> {
+            let node = node as usize;
+            if node >= MAX_NODES {
+                return Err(CudaError::InvalidValue);
+            }
+
+            let mut guard = MemPolicyGuard {
+                mode: MPOL_DEFAULT,
+                mask: [0; MASK_WORDS],
+            };
+            let mut mask = [0 as c_ulong; MASK_WORDS];
+            mask[node / BITS_PER_WORD] |= 1 << (node % BITS_PER_WORD);
+
+            unsafe {
+                // The kernel expects maxnode to be one more than the number of bits in the mask.
+                if libc::syscall(
+                    SYS_get_mempolicy,
+                    &mut guard.mode as *mut c_int,
+                    guard.mask.as_mut_ptr(),
+                    (MAX_NODES + 1) as c_ulong,
+                    ptr::null_mut::<c_void>(),
+                    0 as c_ulong,
+                ) != 0
+                {
+                    return Err(CudaError::InvalidValue);
+                }
+                if libc::syscall(
+                    SYS_set_mempolicy,
+                    MPOL_BIND,
+                    mask.as_ptr(),
+                    (MAX_NODES + 1) as c_ulong,
+                ) != 0
+                {
+                    return Err(CudaError::InvalidValue);
+                }
+            }
+            Ok(guard)
+        }
+    }
+    impl Drop for MemPolicyGuard {
+        fn drop(&mut self) {
+            unsafe {
+                // Restoring a policy that was previously in effect can't reasonably fail.
+                let _ = libc::syscall(
+                    SYS_set_mempolicy,
+                    self.mode,
+                    self.mask.as_ptr(),
+                    (MAX_NODES + 1) as c_ulong,
+                );
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::memory::{CopyDestination, DeviceSlice};
     use std::mem;
 
     #[derive(Clone, Debug)]
@@ -284,6 +654,94 @@ mod test {
         buffer[0] = 1;
     }
 
+    #[test]
+    fn test_from_vec() {
+        let _context = crate::quick_init().unwrap();
+        let buffer = LockedBuffer::from_vec(vec![3u16, 2, 1]).unwrap();
+        assert_eq!(&[3u16, 2, 1], buffer.as_slice());
+        let buffer = LockedBuffer::from_vec(Vec::<u64>::new()).unwrap();
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn test_uninit_assume_init() {
+        let _context = crate::quick_init().unwrap();
+        let mut buffer = LockedBuffer::<u8>::uninit(16).unwrap();
+        for x in buffer.iter_mut() {
+            *x = MaybeUninit::new(0xAB);
+        }
+        let buffer = unsafe { buffer.assume_init() };
+        assert_eq!(&[0xABu8; 16], buffer.as_slice());
+    }
+
+    #[test]
+    fn test_new_with_flags() {
+        let _context = crate::quick_init().unwrap();
+        let mut buffer = LockedBuffer::new_with_flags(&0u64, 5, HostAllocFlags::PORTABLE).unwrap();
+        buffer[0] = 1;
+        assert_eq!(&[1u64, 0, 0, 0, 0], buffer.as_slice());
+    }
+
+    #[test]
+    fn test_as_device_ptr() {
+        let _context = crate::quick_init().unwrap();
+        let mut buffer = LockedBuffer::new_with_flags(&1u64, 5, HostAllocFlags::MAPPED).unwrap();
+        let device_ptr = buffer.as_device_ptr().unwrap();
+        assert!(!device_ptr.is_null());
+
+        // Writes through the device pointer are visible in the host buffer
+        let slice = unsafe { DeviceSlice::from_raw_parts_mut(device_ptr, 5) };
+        slice[1..2].copy_from(&[7u64][..]).unwrap();
+        assert_eq!(&[1u64, 7, 1, 1, 1], buffer.as_slice());
+
+        let mut empty = LockedBuffer::new_with_flags(&0u64, 0, HostAllocFlags::MAPPED).unwrap();
+        assert_eq!(CudaError::InvalidValue, empty.as_device_ptr().unwrap_err());
+    }
+
+    #[test]
+    fn test_mapped_without_map_host() {
+        use crate::context::{Context, ContextFlags};
+        use crate::device::Device;
+        crate::init(crate::CudaFlags::empty()).unwrap();
+        let device = Device::get_device(0).unwrap();
+        let _context = Context::create_and_push(ContextFlags::SCHED_AUTO, device).unwrap();
+
+        let result = LockedBuffer::new_with_flags(&0u64, 5, HostAllocFlags::MAPPED);
+        if device
+            .get_attribute(DeviceAttribute::UnifiedAddressing)
+            .unwrap()
+            != 0
+        {
+            let mut buffer = result.unwrap();
+            assert!(!buffer.as_device_ptr().unwrap().is_null());
+        } else {
+            assert_eq!(CudaError::MapHostFlagMissing, result.unwrap_err());
+        }
+    }
+
+    #[test]
+    fn test_flags() {
+        let _context = crate::quick_init().unwrap();
+        let buffer =
+            LockedBuffer::new_with_flags(&0u64, 5, HostAllocFlags::WRITE_COMBINED).unwrap();
+        assert!(buffer
+            .flags()
+            .unwrap()
+            .contains(HostAllocFlags::WRITE_COMBINED));
+
+        let empty = LockedBuffer::new(&0u64, 0).unwrap();
+        assert_eq!(CudaError::InvalidValue, empty.flags().unwrap_err());
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_new_on_node() {
+        let _context = crate::quick_init().unwrap();
+        let mut buffer = LockedBuffer::new_on_node(&0u64, 5, 0).unwrap();
+        buffer[0] = 1;
+        assert_eq!(&[1u64, 0, 0, 0, 0], buffer.as_slice());
+    }
+
     #[test]
     fn test_from_slice() {
         let _context = crate::quick_init().unwrap();
@@ -0,0 +1,379 @@
+use super::DeviceCopy;
+use crate::error::*;
+use crate::memory::malloc::{cuda_free_locked, cuda_malloc_locked, cuda_malloc_locked_with_flags};
+use crate::memory::HostAllocFlags;
+use std::mem::size_of;
+use std::ops::{Deref, DerefMut};
+use std::ptr;
+use std::slice;
+
+/// Growable vector in page-locked host memory.
+///
+/// A `LockedVec` behaves like `std::vec::Vec`, except that the elements are stored in page-locked
+/// memory, so it can be used as the source or destination of asynchronous copies. This allows
+/// records to be appended as they arrive and then uploaded, without knowing the final size up
+/// front as a [`LockedBuffer`](struct.LockedBuffer.html) requires. Since allocating page-locked
+/// memory can fail, the operations that may grow the vector return a `CudaResult`.
+///
+/// Growing the vector moves its contents to a new page-locked allocation with the same
+/// [`HostAllocFlags`](struct.HostAllocFlags.html). The caller must ensure that no asynchronous
+/// copy is using the vector while it is being grown.
+///
+/// See the [`module-level documentation`](../memory/index.html) for more details on page-locked
+/// memory.
+#[derive(Debug)]
+pub struct LockedVec<T: DeviceCopy> {
+    buf: *mut T,
+    len: usize,
+    capacity: usize,
+    flags: HostAllocFlags,
+}
+// See "Thread Safety" in the `memory` module documentation.
+unsafe impl<T: DeviceCopy + Send> Send for LockedVec<T> {}
+unsafe impl<T: DeviceCopy + Sync> Sync for LockedVec<T> {}
+impl<T: DeviceCopy> LockedVec<T> {
+    /// Create a new, empty `LockedVec`. This does not allocate.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # let _context = rustacuda::quick_init().unwrap();
+    /// use rustacuda::memory::*;
+    /// let mut vec = LockedVec::new();
+    /// vec.push(1u64).unwrap();
+    /// ```
+    pub fn new() -> Self {
+        LockedVec::new_with_flags(HostAllocFlags::empty())
+    }
+
+    /// Create a new, empty `LockedVec` whose allocations are made according to `flags`. This does
+    /// not allocate.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # let _context = rustacuda::quick_init().unwrap();
+    /// use rustacuda::memory::*;
+    /// let mut vec = LockedVec::new_with_flags(HostAllocFlags::WRITE_COMBINED);
+    /// vec.extend_from_slice(&[1u64, 2, 3]).unwrap();
+    /// ```
+    pub fn new_with_flags(flags: HostAllocFlags) -> Self {
+        LockedVec {
+            buf: ptr::NonNull::dangling().as_ptr(),
+            len: 0,
+            capacity: if size_of::<T>() == 0 { usize::MAX } else { 0 },
+            flags,
+        }
+    }
+
+    /// Create a new, empty `LockedVec` with room for at least `capacity` `T`'s.
+    ///
+    /// # Errors
+    ///
+    /// If the allocation fails, returns the error from CUDA. If `capacity` is large enough that
+    /// `capacity * mem::sizeof::<T>()` overflows usize, then returns InvalidMemoryAllocation.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # let _context = rustacuda::quick_init().unwrap();
+    /// use rustacuda::memory::*;
+    /// let vec = LockedVec::<u64>::with_capacity(10).unwrap();
+    /// assert!(vec.capacity() >= 10);
+    /// ```
+    pub fn with_capacity(capacity: usize) -> CudaResult<Self> {
+        let mut vec = LockedVec::new();
+        vec.reserve(capacity)?;
+        Ok(vec)
+    }
+
+    /// Returns the number of elements the vector can hold without reallocating.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the flags used to allocate this vector.
+    pub fn flags(&self) -> HostAllocFlags {
+        self.flags
+    }
+
+    /// Reserve capacity for at least `additional` more elements.
+    ///
+    /// # Errors
+    ///
+    /// If the allocation fails, returns the error from CUDA. If the new capacity would overflow,
+    /// returns InvalidMemoryAllocation.
+    pub fn reserve(&mut self, additional: usize) -> CudaResult<()> {
+        let required = self
+            .len
+            .checked_add(additional)
+            .ok_or(CudaError::InvalidMemoryAllocation)?;
+        if required <= self.capacity {
+            return Ok(());
+        }
+        let new_capacity = required.max(self.capacity * 2).max(4);
+
+        unsafe {
+            let new_buf: *mut T = if self.flags.is_empty() {
+                cuda_malloc_locked(new_capacity)?
+            } else {
+                cuda_malloc_locked_with_flags(new_capacity, self.flags)?
+            };
+            ptr::copy_nonoverlapping(self.buf, new_buf, self.len);
+            let old_buf = self.buf;
+            let old_capacity = self.capacity;
+            self.buf = new_buf;
+            self.capacity = new_capacity;
+            if old_capacity > 0 {
+                cuda_free_locked(old_buf)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Append `value` to the end of the vector, growing it if necessary.
+    ///
+    /// # Errors
+    ///
+    /// If the vector must grow and the allocation fails, returns the error from CUDA.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # let _context = rustacuda::quick_init().unwrap();
+    /// use rustacuda::memory::*;
+    /// let mut vec = LockedVec::new();
+    /// vec.push(1u64).unwrap();
+    /// vec.push(2u64).unwrap();
+    /// assert_eq!(&[1, 2], vec.as_slice());
+    /// ```
+    pub fn push(&mut self, value: T) -> CudaResult<()> {
+        if self.len == self.capacity {
+            self.reserve(1)?;
+        }
+        unsafe {
+            ptr::write(self.buf.add(self.len), value);
+        }
+        self.len += 1;
+        Ok(())
+    }
+
+    /// Remove the last element of the vector and return it, or `None` if the vector is empty.
+    pub fn pop(&mut self) -> Option<T> {
+        if self.len == 0 {
+            None
+        } else {
+            self.len -= 1;
+            unsafe { Some(ptr::read(self.buf.add(self.len))) }
+        }
+    }
+
+    /// Append every element of `iter` to the vector.
+    ///
+    /// # Errors
+    ///
+    /// If the vector must grow and the allocation fails, returns the error from CUDA. The elements
+    /// appended before the failure are kept.
+    pub fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) -> CudaResult<()> {
+        let iter = iter.into_iter();
+        self.reserve(iter.size_hint().0)?;
+        for value in iter {
+            self.push(value)?;
+        }
+        Ok(())
+    }
+
+    /// Shorten the vector to `len` elements. Has no effect if the vector is already shorter.
+    pub fn truncate(&mut self, len: usize) {
+        self.len = self.len.min(len);
+    }
+
+    /// Remove all elements from the vector, keeping its allocation.
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    /// Extracts a slice containing the entire vector.
+    pub fn as_slice(&self) -> &[T] {
+        self
+    }
+
+    /// Extracts a mutable slice of the entire vector.
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        self
+    }
+
+    /// Destroy a `LockedVec`, returning an error.
+    ///
+    /// Deallocating page-locked memory can return errors from previous asynchronous work. This
+    /// function destroys the given vector and returns the error and the un-destroyed vector on
+    /// failure.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # let _context = rustacuda::quick_init().unwrap();
+    /// use rustacuda::memory::*;
+    /// let mut x = LockedVec::new();
+    /// x.push(10u32).unwrap();
+    /// match LockedVec::drop(x) {
+    ///     Ok(()) => println!("Successfully destroyed"),
+    ///     Err((e, vec)) => {
+    ///         println!("Failed to destroy vector: {:?}", e);
+    ///         // Do something with vec
+    ///     },
+    /// }
+    /// ```
+    pub fn drop(mut vec: LockedVec<T>) -> DropResult<LockedVec<T>> {
+        if vec.capacity == 0 || size_of::<T>() == 0 {
+            return Ok(());
+        }
+
+        let (len, capacity, flags) = (vec.len, vec.capacity, vec.flags);
+        let ptr = vec.buf;
+        vec.capacity = 0;
+        unsafe {
+            match cuda_free_locked(ptr) {
+                Ok(()) => Ok(()),
+                Err(e) => Err((
+                    e,
+                    LockedVec {
+                        buf: ptr,
+                        len,
+                        capacity,
+                        flags,
+                    },
+                )),
+            }
+        }
+    }
+}
+impl<T: DeviceCopy + Clone> LockedVec<T> {
+    /// Append clones of every element of `values` to the vector.
+    ///
+    /// # Errors
+    ///
+    /// If the vector must grow and the allocation fails, returns the error from CUDA. In that
+    /// case, the vector is left unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # let _context = rustacuda::quick_init().unwrap();
+    /// use rustacuda::memory::*;
+    /// let mut vec = LockedVec::new();
+    /// vec.extend_from_slice(&[1u32, 2]).unwrap();
+    /// vec.extend_from_slice(&[3u32]).unwrap();
+    /// assert_eq!(&[1, 2, 3], vec.as_slice());
+    /// ```
+    pub fn extend_from_slice(&mut self, values: &[T]) -> CudaResult<()> {
+        self.reserve(values.len())?;
+        for value in values {
+            unsafe {
+                ptr::write(self.buf.add(self.len), value.clone());
+            }
+            self.len += 1;
+        }
+        Ok(())
+    }
+}
+impl<T: DeviceCopy> Default for LockedVec<T> {
+    fn default() -> Self {
+        LockedVec::new()
+    }
+}
+impl<T: DeviceCopy> AsRef<[T]> for LockedVec<T> {
+    fn as_ref(&self) -> &[T] {
+        self
+    }
+}
+impl<T: DeviceCopy> AsMut<[T]> for LockedVec<T> {
+    fn as_mut(&mut self) -> &mut [T] {
+        self
+    }
+}
+impl<T: DeviceCopy> Deref for LockedVec<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        unsafe { slice::from_raw_parts(self.buf, self.len) }
+    }
+}
+impl<T: DeviceCopy> DerefMut for LockedVec<T> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        unsafe { slice::from_raw_parts_mut(self.buf, self.len) }
+    }
+}
+impl<T: DeviceCopy> Drop for LockedVec<T> {
+    fn drop(&mut self) {
+        if self.capacity > 0 && size_of::<T>() > 0 {
+            // No choice but to panic if this fails.
+            unsafe {
+                cuda_free_locked(self.buf)
+                    .expect_in_drop("Failed to deallocate CUDA page-locked memory.");
+            }
+        }
+        self.capacity = 0;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::memory::{AsyncCopyDestination, DeviceBuffer};
+    use crate::stream::{Stream, StreamFlags};
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    struct ZeroSizedType;
+    unsafe impl DeviceCopy for ZeroSizedType {}
+
+    #[test]
+    fn test_push_pop() {
+        let _context = crate::quick_init().unwrap();
+        let mut vec = LockedVec::new();
+        for i in 0..100u64 {
+            vec.push(i).unwrap();
+        }
+        assert_eq!(100, vec.len());
+        assert_eq!(Some(99), vec.pop());
+        assert_eq!(4950 - 99, vec.iter().sum::<u64>());
+    }
+
+    #[test]
+    fn test_extend_from_slice_with_flags() {
+        let _context = crate::quick_init().unwrap();
+        let mut vec = LockedVec::new_with_flags(HostAllocFlags::PORTABLE);
+        vec.extend_from_slice(&[1u32, 2, 3]).unwrap();
+        vec.extend_from_slice(&[4u32; 10]).unwrap();
+        assert_eq!(13, vec.len());
+        assert_eq!(HostAllocFlags::PORTABLE, vec.flags());
+        vec.clear();
+        assert!(vec.is_empty());
+    }
+
+    #[test]
+    fn test_async_upload() {
+        let _context = crate::quick_init().unwrap();
+        let stream = Stream::new(StreamFlags::NON_BLOCKING, None).unwrap();
+        let mut vec = LockedVec::new();
+        vec.extend(0..64u32).unwrap();
+        let mut device = DeviceBuffer::from_slice(&[0u32; 64]).unwrap();
+        let mut host = [0u32; 64];
+        unsafe {
+            device.async_copy_from(&vec, &stream).unwrap();
+            device.async_copy_to(&mut host[..], &stream).unwrap();
+        }
+        stream.synchronize().unwrap();
+        assert_eq!(vec.as_slice(), &host[..]);
+    }
+
+    #[test]
+    fn test_zero_sized_type() {
+        let _context = crate::quick_init().unwrap();
+        let mut vec = LockedVec::new();
+        vec.push(ZeroSizedType).unwrap();
+        vec.push(ZeroSizedType).unwrap();
+        assert_eq!(2, vec.len());
+        assert_eq!(Some(ZeroSizedType), vec.pop());
+    }
+}
@@ -1,10 +1,204 @@
 use super::DeviceCopy;
+use crate::context::CurrentContext;
+use crate::device::Device;
 use crate::error::*;
+use crate::memory::unified::apply_default_advice;
 use crate::memory::DevicePointer;
-use crate::memory::UnifiedPointer;
-use std::mem;
+use crate::memory::{HostAllocFlags, MemAttachFlags, UnifiedPointer};
+use crate::sync::{lock_ignoring_poison, read_ignoring_poison, write_ignoring_poison};
+use crate::teardown::{self, Resource};
+use std::any::type_name;
+use std::collections::BTreeMap;
+use std::mem::{self, size_of};
 use std::os::raw::c_void;
 use std::ptr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, RwLock};
+
+/// The kind of memory involved in an [`AllocationEvent`](enum.AllocationEvent.html).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AllocationKind {
+    /// Device memory from [`cuda_malloc`](fn.cuda_malloc.html).
+    Device,
+    /// Pitched device memory from [`cuda_malloc_pitched`](fn.cuda_malloc_pitched.html).
+    Pitched,
+    /// Unified memory from [`cuda_malloc_unified`](fn.cuda_malloc_unified.html).
+    Unified,
+    /// Page-locked host memory from [`cuda_malloc_locked`](fn.cuda_malloc_locked.html).
+    Locked,
+    /// Device memory allocated and mapped through the virtual memory management API, such as the
+    /// memory behind a protected [`ReadOnlyBuffer`](struct.ReadOnlyBuffer.html).
+    Virtual,
+}
+
+/// An allocation or deallocation reported to the hook installed with
+/// [`set_allocation_hook`](fn.set_allocation_hook.html).
+///
+/// Pointers are given as addresses, since the hook may outlive the memory.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AllocationEvent {
+    /// Memory was allocated. If the hook returns an error, the memory is freed again and the
+    /// allocation fails with that error.
+    Allocated {
+        /// The kind of memory allocated.
+        kind: AllocationKind,
+        /// The address of the allocation.
+        ptr: usize,
+        /// The size of the allocation in bytes, including any padding for pitched allocations.
+        bytes: usize,
+        /// The name of the element type the memory was allocated for.
+        type_name: &'static str,
+    },
+    /// Memory was freed. Errors returned by the hook for this event are ignored.
+    Freed {
+        /// The kind of memory freed.
+        kind: AllocationKind,
+        /// The address of the allocation.
+        ptr: usize,
+        /// The size of the allocation in bytes, as reported when it was allocated, or zero if it
+        /// wasn't allocated by RustaCUDA.
+        bytes: usize,
+        /// The name of the element type the memory was allocated for.
+        type_name: &'static str,
+    },
+}
+
+type AllocationHook = dyn Fn(&AllocationEvent) -> CudaResult<()> + Send + Sync;
+
+static HOOK_INSTALLED: AtomicBool = AtomicBool::new(false);
+static ALLOCATION_HOOK: RwLock<Option<Arc<AllocationHook>>> = RwLock::new(None);
+
+/// The kind and size of each live allocation, by address, so that frees can be reported with the
+/// same details as the allocation. This is kept even without a hook, since one may be installed
+/// while the memory is live.
+static LIVE_ALLOCATIONS: Mutex<BTreeMap<usize, (AllocationKind, usize)>> =
+    Mutex::new(BTreeMap::new());
+
+fn live_allocations() -> MutexGuard<'static, BTreeMap<usize, (AllocationKind, usize)>> {
+    lock_ignoring_poison(&LIVE_ALLOCATIONS)
+}
+
+/// Install a hook which is called on every allocation and deallocation made by the functions in
+/// this module, and therefore by every RustaCUDA buffer type, as well as for the memory behind
+/// protected [`ReadOnlyBuffer`](struct.ReadOnlyBuffer.html)s. This replaces any hook installed
+/// previously.
+///
+/// The hook receives the kind of memory, its address, its size and the element type. It can be
+/// used for telemetry, or to enforce a quota by returning an error (such as `OutOfMemory`) from an
+/// [`Allocated`](enum.AllocationEvent.html#variant.Allocated) event. The hook may be called from
+/// any thread, including from inside `Drop` implementations, so it should be cheap and must not
+/// panic.
+///
+/// # Examples
+///
+/// ```
+/// # let _context = rustacuda::quick_init().unwrap();
+/// use rustacuda::error::CudaError;
+/// use rustacuda::memory::*;
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+///
+/// static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+/// set_allocation_hook(|event| {
+///     if let AllocationEvent::Allocated { bytes, .. } = *event {
+///         if bytes > 1 << 30 {
+///             return Err(CudaError::OutOfMemory);
+///         }
+///         ALLOCATED.fetch_add(bytes, Ordering::Relaxed);
+///     }
+///     Ok(())
+/// });
+/// let buffer = DeviceBuffer::from_slice(&[0u64; 16]).unwrap();
+/// assert!(ALLOCATED.load(Ordering::Relaxed) >= 128);
+/// # clear_allocation_hook();
+/// ```
+pub fn set_allocation_hook<F>(hook: F)
+where
+    F: Fn(&AllocationEvent) -> CudaResult<()> + Send + Sync + 'static,
+{
+    let mut slot = write_ignoring_poison(&ALLOCATION_HOOK);
+    *slot = Some(Arc::new(hook));
+    HOOK_INSTALLED.store(true, Ordering::Release);
+}
+
+/// Remove the hook installed with [`set_allocation_hook`](fn.set_allocation_hook.html), if any.
+pub fn clear_allocation_hook() {
+    let mut slot = write_ignoring_poison(&ALLOCATION_HOOK);
+    *slot = None;
+    HOOK_INSTALLED.store(false, Ordering::Release);
+}
+
+fn call_hook(event: &AllocationEvent) -> CudaResult<()> {
+    if !HOOK_INSTALLED.load(Ordering::Acquire) {
+        return Ok(());
+    }
+    // Don't hold the lock while the hook runs, so the hook may itself allocate or replace the hook.
+    let hook = read_ignoring_poison(&ALLOCATION_HOOK).clone();
+    match hook {
+        Some(hook) => hook(event),
+        None => Ok(()),
+    }
+}
+
+/// Report a successful allocation to the hook, freeing the memory with `free` if the hook rejects
+/// it.
+pub(crate) unsafe fn allocated(
+    kind: AllocationKind,
+    ptr: usize,
+    bytes: usize,
+    type_name: &'static str,
+    free: unsafe fn(usize),
+) -> CudaResult<()> {
+    let event = AllocationEvent::Allocated {
+        kind,
+        ptr,
+        bytes,
+        type_name,
+    };
+    call_hook(&event).inspect_err(|_| free(ptr))?;
+    let _ = live_allocations().insert(ptr, (kind, bytes));
+    teardown::created(Resource::Allocation);
+    Ok(())
+}
+
+/// Free the allocation at `ptr` with `free` and report it to the hook. `kind` is only used if the
+/// memory wasn't allocated by RustaCUDA.
+///
+/// The allocation is forgotten before it is freed, since the driver may hand the address out
+/// again as soon as it is free.
+pub(crate) fn freed<F>(
+    kind: AllocationKind,
+    ptr: usize,
+    type_name: &'static str,
+    free: F,
+) -> CudaResult<()>
+where
+    F: FnOnce() -> CudaResult<()>,
+{
+    let live = live_allocations().remove(&ptr);
+    if let Err(e) = free() {
+        if let Some(live) = live {
+            let _ = live_allocations().insert(ptr, live);
+        }
+        return Err(e);
+    }
+    let (kind, bytes) = live.unwrap_or((kind, 0));
+    teardown::destroyed(Resource::Allocation);
+    let _ = call_hook(&AllocationEvent::Freed {
+        kind,
+        ptr,
+        bytes,
+        type_name,
+    });
+    Ok(())
+}
+
+unsafe fn free_device(ptr: usize) {
+    let _ = driver_call!(cuda_driver_sys::cuMemFree_v2(ptr as u64));
+}
+
+unsafe fn free_host(ptr: usize) {
+    let _ = driver_call!(cuda_driver_sys::cuMemFreeHost(ptr as *mut c_void));
+}
 
 /// Unsafe wrapper around the `cuMemAlloc` function, which allocates some device memory and
 /// returns a [`DevicePointer`](struct.DevicePointer.html) pointing to it. The memory is not cleared.
@@ -44,7 +238,18 @@ pub unsafe fn cuda_malloc<T>(count: usize) -> CudaResult<DevicePointer<T>> {
     }
 
     let mut ptr: *mut c_void = ptr::null_mut();
-    cuda_driver_sys::cuMemAlloc_v2(&mut ptr as *mut *mut c_void as *mut u64, size).to_result()?;
+    driver_call!(cuda_driver_sys::cuMemAlloc_v2(
+        &mut ptr as *mut *mut c_void as *mut u64,
+        size
+    ))
+    .to_result()?;
+    allocated(
+        AllocationKind::Device,
+        ptr as usize,
+        size,
+        type_name::<T>(),
+        free_device,
+    )?;
     let ptr = ptr as *mut T;
     Ok(DevicePointer::wrap(ptr as *mut T))
 }
@@ -60,6 +265,7 @@ pub unsafe fn cuda_malloc<T>(count: usize) -> CudaResult<DevicePointer<T>> {
 /// # Errors
 ///
 /// If allocating memory fails, returns the CUDA error value.
+/// If the current device does not support managed memory, returns UnifiedMemoryNotSupported.
 /// If the number of bytes to allocate is zero (either because count is zero or because T is a
 /// zero-sized type), or if the size of the allocation would overflow a usize, returns InvalidValue.
 ///
@@ -83,20 +289,157 @@ pub unsafe fn cuda_malloc<T>(count: usize) -> CudaResult<DevicePointer<T>> {
 /// }
 /// ```
 pub unsafe fn cuda_malloc_unified<T: DeviceCopy>(count: usize) -> CudaResult<UnifiedPointer<T>> {
+    cuda_malloc_unified_with_flags(count, MemAttachFlags::GLOBAL)
+}
+
+/// Unsafe wrapper around the `cuMemAllocManaged` function, which allocates some unified memory
+/// with the given attachment and returns a [`UnifiedPointer`](struct.UnifiedPointer.html)
+/// pointing to it.
+///
+/// This is the same as [`cuda_malloc_unified`](fn.cuda_malloc_unified.html), except that the
+/// allocation is attached according to `flags` rather than always being attached globally. See
+/// [`MemAttachFlags`](struct.MemAttachFlags.html) for the available options.
+///
+/// Any advice set for the current context with
+/// [`CurrentContext::set_unified_advice`](../context/struct.CurrentContext.html#method.set_unified_advice)
+/// is applied to the new allocation.
+///
+/// # Errors
+///
+/// If allocating memory or applying the context's advice fails, returns the CUDA error value.
+/// If the current device does not support managed memory, returns UnifiedMemoryNotSupported.
+/// If the number of bytes to allocate is zero (either because count is zero or because T is a
+/// zero-sized type), or if the size of the allocation would overflow a usize, returns InvalidValue.
+///
+/// # Safety
+///
+/// Since the allocated memory is not initialized, the caller must ensure that it is initialized
+/// before reading from it in any way. Additionally, the caller must ensure that the memory
+/// allocated is freed using cuda_free, or the memory will be leaked.
+///
+/// # Examples
+///
+/// ```
+/// # let _context = rustacuda::quick_init().unwrap();
+/// use rustacuda::memory::*;
+/// unsafe {
+///     let mut unified_buffer = cuda_malloc_unified_with_flags::<u64>(1, MemAttachFlags::HOST).unwrap();
+///     *unified_buffer.as_raw_mut() = 5u64;
+///     cuda_free_unified(unified_buffer).unwrap();
+/// }
+/// ```
+pub unsafe fn cuda_malloc_unified_with_flags<T: DeviceCopy>(
+    count: usize,
+    flags: MemAttachFlags,
+) -> CudaResult<UnifiedPointer<T>> {
     let size = count.checked_mul(mem::size_of::<T>()).unwrap_or(0);
     if size == 0 {
         return Err(CudaError::InvalidMemoryAllocation);
     }
 
     let mut ptr: *mut c_void = ptr::null_mut();
-    cuda_driver_sys::cuMemAllocManaged(
+    let result = driver_call!(cuda_driver_sys::cuMemAllocManaged(
         &mut ptr as *mut *mut c_void as *mut u64,
         size,
-        cuda_driver_sys::CUmemAttach_flags_enum::CU_MEM_ATTACH_GLOBAL as u32,
-    )
+        flags.bits(),
+    ))
+    .to_result();
+    if let Err(e) = result {
+        // The driver reports missing managed memory support with a generic error, so only look up
+        // the device attribute once the allocation has already failed.
+        let supported = CurrentContext::get_device().and_then(Device::supports_managed_memory);
+        return Err(match supported {
+            Ok(false) => CudaError::UnifiedMemoryNotSupported,
+            _ => e,
+        });
+    }
+    allocated(
+        AllocationKind::Unified,
+        ptr as usize,
+        size,
+        type_name::<T>(),
+        free_device,
+    )?;
+    let ptr = UnifiedPointer::wrap(ptr as *mut T);
+    if let Err(e) = apply_default_advice(ptr, count) {
+        let _ = cuda_free_unified(ptr);
+        return Err(e);
+    }
+    Ok(ptr)
+}
+
+/// Unsafe wrapper around the `cuMemAllocPitch` function, which allocates a pitched 2D region of
+/// device memory and returns a [`DevicePointer`](struct.DevicePointer.html) pointing to it along
+/// with the pitch of the allocation. The memory is not cleared.
+///
+/// `width` is the number of `T`'s in each row and `height` is the number of rows. The returned
+/// pitch is the distance in bytes between the starts of consecutive rows, which may be larger than
+/// `width * size_of::<T>()` so that each row is suitably aligned. Pitched allocations are
+/// typically cleared with [`cuda_memset_2d_u8`](fn.cuda_memset_2d_u8.html) and its siblings,
+/// which skip the padding at the end of each row.
+///
+/// Memory buffers allocated using `cuda_malloc_pitched` must be freed using [`cuda_free`](fn.cuda_free.html).
+///
+/// # Errors
+///
+/// If allocating memory fails, returns the CUDA error value.
+/// If the number of bytes to allocate is zero (either because `width` or `height` is zero or
+/// because T is a zero-sized type), or if the size of a row would overflow a usize, returns
+/// InvalidMemoryAllocation.
+///
+/// # Safety
+///
+/// Since the allocated memory is not initialized, the caller must ensure that it is initialized
+/// before copying it to the host in any way. Additionally, the caller must ensure that the memory
+/// allocated is freed using cuda_free, or the memory will be leaked.
+///
+/// # Examples
+///
+/// ```
+/// # let _context = rustacuda::quick_init().unwrap();
+/// use rustacuda::memory::*;
+/// unsafe {
+///     // Allocate space for a 100x100 image of f32's
+///     let (device_buffer, pitch) = cuda_malloc_pitched::<f32>(100, 100).unwrap();
+///     assert!(pitch >= 100 * 4);
+///     cuda_free(device_buffer).unwrap();
+/// }
+/// ```
+pub unsafe fn cuda_malloc_pitched<T>(
+    width: usize,
+    height: usize,
+) -> CudaResult<(DevicePointer<T>, usize)> {
+    let element_size = size_of::<T>();
+    let width_bytes = width.checked_mul(element_size).unwrap_or(0);
+    if width_bytes == 0 || height == 0 {
+        return Err(CudaError::InvalidMemoryAllocation);
+    }
+
+    // The driver only accepts element sizes of 4, 8 or 16 bytes, and uses them to pick the
+    // alignment of each row.
+    let element_size = match element_size {
+        8 => 8,
+        16 => 16,
+        _ => 4,
+    };
+    let mut ptr: u64 = 0;
+    let mut pitch: usize = 0;
+    driver_call!(cuda_driver_sys::cuMemAllocPitch_v2(
+        &mut ptr as *mut u64,
+        &mut pitch as *mut usize,
+        width_bytes,
+        height,
+        element_size,
+    ))
     .to_result()?;
-    let ptr = ptr as *mut T;
-    Ok(UnifiedPointer::wrap(ptr as *mut T))
+    allocated(
+        AllocationKind::Pitched,
+        ptr as usize,
+        pitch * height,
+        type_name::<T>(),
+        free_device,
+    )?;
+    Ok((DevicePointer::wrap(ptr as *mut T), pitch))
 }
 
 /// Free memory allocated with [`cuda_malloc`](fn.cuda_malloc.html).
@@ -128,8 +471,12 @@ pub unsafe fn cuda_free<T>(mut p: DevicePointer<T>) -> CudaResult<()> {
         return Err(CudaError::InvalidMemoryAllocation);
     }
 
-    cuda_driver_sys::cuMemFree_v2(ptr as u64).to_result()?;
-    Ok(())
+    freed(
+        AllocationKind::Device,
+        ptr as usize,
+        type_name::<T>(),
+        || driver_call!(cuda_driver_sys::cuMemFree_v2(ptr as u64)).to_result(),
+    )
 }
 
 /// Free memory allocated with [`cuda_malloc_unified`](fn.cuda_malloc_unified.html).
@@ -161,8 +508,12 @@ pub unsafe fn cuda_free_unified<T: DeviceCopy>(mut p: UnifiedPointer<T>) -> Cuda
         return Err(CudaError::InvalidMemoryAllocation);
     }
 
-    cuda_driver_sys::cuMemFree_v2(ptr as u64).to_result()?;
-    Ok(())
+    freed(
+        AllocationKind::Unified,
+        ptr as usize,
+        type_name::<T>(),
+        || driver_call!(cuda_driver_sys::cuMemFree_v2(ptr as u64)).to_result(),
+    )
 }
 
 /// Unsafe wrapper around the `cuMemAllocHost` function, which allocates some page-locked host memory
@@ -203,11 +554,82 @@ pub unsafe fn cuda_malloc_locked<T>(count: usize) -> CudaResult<*mut T> {
     }
 
     let mut ptr: *mut c_void = ptr::null_mut();
-    cuda_driver_sys::cuMemAllocHost_v2(&mut ptr as *mut *mut c_void, size).to_result()?;
+    driver_call!(cuda_driver_sys::cuMemAllocHost_v2(
+        &mut ptr as *mut *mut c_void,
+        size
+    ))
+    .to_result()?;
+    allocated(
+        AllocationKind::Locked,
+        ptr as usize,
+        size,
+        type_name::<T>(),
+        free_host,
+    )?;
     let ptr = ptr as *mut T;
     Ok(ptr as *mut T)
 }
 
+/// Unsafe wrapper around the `cuMemHostAlloc` function, which allocates some page-locked host
+/// memory with the given flags and returns a raw pointer pointing to it. The memory is not
+/// cleared.
+///
+/// This is the same as [`cuda_malloc_locked`](fn.cuda_malloc_locked.html), except that the
+/// allocation can be made portable, write-combined or mapped into the device address space. See
+/// [`HostAllocFlags`](struct.HostAllocFlags.html) for the available options.
+///
+/// Memory buffers allocated using `cuda_malloc_locked_with_flags` must be freed using
+/// [`cuda_free_locked`](fn.cuda_free_locked.html).
+///
+/// # Errors
+///
+/// If allocating memory fails, returns the CUDA error value.
+/// If the number of bytes to allocate is zero (either because count is zero or because T is a
+/// zero-sized type), or if the size of the allocation would overflow a usize, returns InvalidValue.
+///
+/// # Safety
+///
+/// Since the allocated memory is not initialized, the caller must ensure that it is initialized
+/// before reading from it in any way. Additionally, the caller must ensure that the memory
+/// allocated is freed using `cuda_free_locked`, or the memory will be leaked.
+///
+/// # Examples
+///
+/// ```
+/// # let _context = rustacuda::quick_init().unwrap();
+/// use rustacuda::memory::*;
+/// unsafe {
+///     let locked_buffer =
+///         cuda_malloc_locked_with_flags::<u64>(5, HostAllocFlags::WRITE_COMBINED).unwrap();
+///     cuda_free_locked(locked_buffer).unwrap();
+/// }
+/// ```
+pub unsafe fn cuda_malloc_locked_with_flags<T>(
+    count: usize,
+    flags: HostAllocFlags,
+) -> CudaResult<*mut T> {
+    let size = count.checked_mul(mem::size_of::<T>()).unwrap_or(0);
+    if size == 0 {
+        return Err(CudaError::InvalidMemoryAllocation);
+    }
+
+    let mut ptr: *mut c_void = ptr::null_mut();
+    driver_call!(cuda_driver_sys::cuMemHostAlloc(
+        &mut ptr as *mut *mut c_void,
+        size,
+        flags.bits(),
+    ))
+    .to_result()?;
+    allocated(
+        AllocationKind::Locked,
+        ptr as usize,
+        size,
+        type_name::<T>(),
+        free_host,
+    )?;
+    Ok(ptr as *mut T)
+}
+
 /// Free page-locked memory allocated with [`cuda_malloc_host`](fn.cuda_malloc_host.html).
 ///
 /// # Errors
@@ -236,8 +658,50 @@ pub unsafe fn cuda_free_locked<T>(ptr: *mut T) -> CudaResult<()> {
         return Err(CudaError::InvalidMemoryAllocation);
     }
 
-    cuda_driver_sys::cuMemFreeHost(ptr as *mut c_void).to_result()?;
-    Ok(())
+    freed(
+        AllocationKind::Locked,
+        ptr as usize,
+        type_name::<T>(),
+        || driver_call!(cuda_driver_sys::cuMemFreeHost(ptr as *mut c_void)).to_result(),
+    )
+}
+
+/// Safe wrapper around the `cuMemHostGetFlags` function, which returns the flags that the
+/// page-locked allocation containing `ptr` was allocated with.
+///
+/// This is useful for pointers received from other libraries, to decide whether the memory can
+/// be accessed by the device directly (`HostAllocFlags::MAPPED`) or shared between contexts
+/// (`HostAllocFlags::PORTABLE`). Memory allocated without flags, such as by
+/// [`cuda_malloc_locked`](fn.cuda_malloc_locked.html), may still report `MAPPED` if the context
+/// was created with `ContextFlags::MAP_HOST`.
+///
+/// # Errors
+///
+/// If `ptr` does not point into page-locked memory allocated by CUDA, returns the CUDA error
+/// value (usually `InvalidValue`).
+///
+/// # Examples
+///
+/// ```
+/// # let _context = rustacuda::quick_init().unwrap();
+/// use rustacuda::memory::*;
+/// unsafe {
+///     let locked_buffer = cuda_malloc_locked_with_flags::<u64>(5, HostAllocFlags::PORTABLE).unwrap();
+///     let flags = cuda_host_get_flags(locked_buffer).unwrap();
+///     assert!(flags.contains(HostAllocFlags::PORTABLE));
+///     cuda_free_locked(locked_buffer).unwrap();
+/// }
+/// ```
+pub fn cuda_host_get_flags<T>(ptr: *const T) -> CudaResult<HostAllocFlags> {
+    let mut flags = 0;
+    unsafe {
+        driver_call!(cuda_driver_sys::cuMemHostGetFlags(
+            &mut flags,
+            ptr as *mut c_void
+        ))
+        .to_result()?;
+    }
+    Ok(HostAllocFlags::from_bits_truncate(flags))
 }
 
 #[cfg(test)]
@@ -248,6 +712,66 @@ mod test {
     struct ZeroSizedType;
     unsafe impl DeviceCopy for ZeroSizedType {}
 
+    #[test]
+    fn test_allocation_hook() {
+        use std::sync::atomic::AtomicUsize;
+
+        // Other tests allocate concurrently, so only look at a type used nowhere else.
+        #[derive(Clone, Copy, Debug)]
+        struct Tracked(#[allow(dead_code)] u32);
+        unsafe impl DeviceCopy for Tracked {}
+
+        let _context = crate::quick_init().unwrap();
+        let live = Arc::new(AtomicUsize::new(0));
+        let freed_kinds = Arc::new(Mutex::new(vec![]));
+        let (hook_live, hook_freed_kinds) = (live.clone(), freed_kinds.clone());
+        set_allocation_hook(move |event| {
+            match *event {
+                AllocationEvent::Allocated {
+                    bytes, type_name, ..
+                } if type_name == std::any::type_name::<Tracked>() => {
+                    if bytes > 1024 {
+                        return Err(CudaError::OutOfMemory);
+                    }
+                    let _ = hook_live.fetch_add(bytes, Ordering::SeqCst);
+                }
+                AllocationEvent::Freed {
+                    kind,
+                    bytes,
+                    type_name,
+                    ..
+                } if type_name == std::any::type_name::<Tracked>() => {
+                    let _ = hook_live.fetch_sub(bytes, Ordering::SeqCst);
+                    hook_freed_kinds.lock().unwrap().push(kind);
+                }
+                _ => {}
+            }
+            Ok(())
+        });
+        unsafe {
+            let ptr = cuda_malloc::<Tracked>(16).unwrap();
+            assert_eq!(64, live.load(Ordering::SeqCst));
+            cuda_free(ptr).unwrap();
+            assert_eq!(0, live.load(Ordering::SeqCst));
+
+            let (ptr, _) = cuda_malloc_pitched::<Tracked>(4, 4).unwrap();
+            assert!(live.load(Ordering::SeqCst) >= 64);
+            cuda_free(ptr).unwrap();
+            assert_eq!(0, live.load(Ordering::SeqCst));
+            assert_eq!(
+                vec![AllocationKind::Device, AllocationKind::Pitched],
+                *freed_kinds.lock().unwrap()
+            );
+
+            assert_eq!(
+                CudaError::OutOfMemory,
+                cuda_malloc_locked::<Tracked>(1000).unwrap_err()
+            );
+            assert_eq!(0, live.load(Ordering::SeqCst));
+        }
+        clear_allocation_hook();
+    }
+
     #[test]
     fn test_cuda_malloc() {
         let _context = crate::quick_init().unwrap();
@@ -291,6 +815,32 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_cuda_malloc_pitched() {
+        let _context = crate::quick_init().unwrap();
+        unsafe {
+            let (device_mem, pitch) = cuda_malloc_pitched::<u32>(33, 4).unwrap();
+            assert!(!device_mem.is_null());
+            assert!(pitch >= 33 * 4);
+            cuda_free(device_mem).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_cuda_malloc_pitched_zero_bytes() {
+        let _context = crate::quick_init().unwrap();
+        unsafe {
+            assert_eq!(
+                CudaError::InvalidMemoryAllocation,
+                cuda_malloc_pitched::<u64>(0, 10).unwrap_err()
+            );
+            assert_eq!(
+                CudaError::InvalidMemoryAllocation,
+                cuda_malloc_pitched::<u64>(10, 0).unwrap_err()
+            );
+        }
+    }
+
     #[test]
     fn test_cuda_malloc_unified() {
         let _context = crate::quick_init().unwrap();
@@ -375,6 +925,24 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_cuda_host_get_flags() {
+        let _context = crate::quick_init().unwrap();
+        unsafe {
+            let flags = HostAllocFlags::PORTABLE | HostAllocFlags::WRITE_COMBINED;
+            let locked_mem = cuda_malloc_locked_with_flags::<u64>(4, flags).unwrap();
+            assert!(cuda_host_get_flags(locked_mem).unwrap().contains(flags));
+            // Pointers into the middle of the allocation work too
+            assert!(cuda_host_get_flags(locked_mem.add(2))
+                .unwrap()
+                .contains(flags));
+            cuda_free_locked(locked_mem).unwrap();
+        }
+
+        let pageable = [0u64; 4];
+        assert!(cuda_host_get_flags(pageable.as_ptr()).is_err());
+    }
+
     #[test]
     fn test_cuda_malloc_locked_zero_sized() {
         let _context = crate::quick_init().unwrap();
@@ -397,6 +965,21 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_cuda_malloc_locked_with_flags() {
+        let _context = crate::quick_init().unwrap();
+        unsafe {
+            let flags = HostAllocFlags::PORTABLE | HostAllocFlags::WRITE_COMBINED;
+            let locked = cuda_malloc_locked_with_flags::<u64>(1, flags).unwrap();
+            assert!(!locked.is_null());
+
+            // Write-combined memory is still writable from the host
+            *locked = 64;
+
+            cuda_free_locked(locked).unwrap();
+        }
+    }
+
     #[test]
     fn test_cuda_free_locked_null() {
         let _context = crate::quick_init().unwrap();
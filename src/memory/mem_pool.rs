@@ -0,0 +1,159 @@
+use crate::device::Device;
+use crate::error::*;
+use cuda_driver_sys::{CUdevice, CUresult};
+use std::os::raw::c_void;
+
+/// Handle to a CUDA memory pool, from which stream-ordered allocations are made.
+///
+/// Every device which supports memory pools
+/// ([`DeviceAttribute::MemoryPoolsSupported`](../device/enum.DeviceAttribute.html#variant.MemoryPoolsSupported))
+/// has a default pool, returned by
+/// [`Device::default_mem_pool`](../device/struct.Device.html#method.default_mem_pool). The default
+/// pool is owned by the device and can't be destroyed, so `MemPool` does not own the pool it
+/// refers to and dropping it does nothing.
+///
+/// Memory pools require a CUDA 11.2 or newer driver, so they are only available with the
+/// `cuda-11-2` feature, with which [`init`](../fn.init.html) rejects older drivers.
+///
+/// # Examples
+///
+/// ```
+/// # let _context = rustacuda::quick_init().unwrap();
+/// use rustacuda::device::Device;
+///
+/// let device = Device::get_device(0).unwrap();
+/// if let Ok(pool) = device.default_mem_pool() {
+///     // Keep up to 64 MiB cached in the pool instead of returning it to the OS at every
+///     // synchronization.
+///     pool.set_release_threshold(64 << 20).unwrap();
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MemPool {
+    inner: CUmemoryPool,
+}
+// Pools can be used from any thread.
+unsafe impl Send for MemPool {}
+unsafe impl Sync for MemPool {}
+impl MemPool {
+    /// Wraps a raw `CUmemoryPool` handle.
+    ///
+    /// # Safety
+    ///
+    /// `handle` must be a valid memory pool which outlives the returned value and any copies of it.
+    pub unsafe fn from_raw(handle: *mut c_void) -> Self {
+        MemPool { inner: handle }
+    }
+
+    /// Returns the raw `CUmemoryPool` handle, for passing to CUDA libraries or driver API
+    /// functions that RustaCUDA does not wrap.
+    pub fn as_raw(&self) -> *mut c_void {
+        self.inner
+    }
+
+    /// Returns the number of bytes of unused memory the pool keeps reserved, rather than
+    /// returning it to the OS, when a stream, event or context is synchronized.
+    pub fn release_threshold(&self) -> CudaResult<u64> {
+        let get = pool_get_attribute_fn().ok_or(CudaError::NotSupported)?;
+        let mut threshold = 0u64;
+        unsafe {
+            get(
+                self.inner,
+                CU_MEMPOOL_ATTR_RELEASE_THRESHOLD,
+                &mut threshold as *mut u64 as *mut c_void,
+            )
+            .to_result()?;
+        }
+        Ok(threshold)
+    }
+
+    /// Set the number of bytes of unused memory the pool keeps reserved. The default is 0, which
+    /// releases all unused memory whenever the pool's users synchronize; `u64::MAX` never
+    /// releases memory.
+    pub fn set_release_threshold(&self, bytes: u64) -> CudaResult<()> {
+        let set = pool_set_attribute_fn().ok_or(CudaError::NotSupported)?;
+        let mut threshold = bytes;
+        unsafe {
+            set(
+                self.inner,
+                CU_MEMPOOL_ATTR_RELEASE_THRESHOLD,
+                &mut threshold as *mut u64 as *mut c_void,
+            )
+            .to_result()
+        }
+    }
+
+    /// Release unused memory from the pool back to the OS, until the pool holds at most
+    /// `min_bytes_to_keep` bytes of reserved memory.
+    pub fn trim_to(&self, min_bytes_to_keep: usize) -> CudaResult<()> {
+        let trim = pool_trim_to_fn().ok_or(CudaError::NotSupported)?;
+        unsafe { trim(self.inner, min_bytes_to_keep).to_result() }
+    }
+}
+
+pub(crate) fn device_default_mem_pool(device: Device) -> CudaResult<MemPool> {
+    let get = device_get_default_mem_pool_fn().ok_or(CudaError::NotSupported)?;
+    let mut pool: CUmemoryPool = std::ptr::null_mut();
+    unsafe { get(&mut pool, device.into_inner()).to_result()? };
+    Ok(MemPool { inner: pool })
+}
+
+pub(crate) fn device_mem_pool(device: Device) -> CudaResult<MemPool> {
+    let get = device_get_mem_pool_fn().ok_or(CudaError::NotSupported)?;
+    let mut pool: CUmemoryPool = std::ptr::null_mut();
+    unsafe { get(&mut pool, device.into_inner()).to_result()? };
+    Ok(MemPool { inner: pool })
+}
+
+pub(crate) fn device_set_mem_pool(device: Device, pool: &MemPool) -> CudaResult<()> {
+    let set = device_set_mem_pool_fn().ok_or(CudaError::NotSupported)?;
+    unsafe { set(device.into_inner(), pool.inner).to_result() }
+}
+
+// CUmemoryPool, which is newer than the bindings.
+type CUmemoryPool = *mut c_void;
+
+// CUmemPool_attribute::CU_MEMPOOL_ATTR_RELEASE_THRESHOLD
+const CU_MEMPOOL_ATTR_RELEASE_THRESHOLD: u32 = 4;
+
+type DeviceGetMemPoolFn = unsafe extern "C" fn(*mut CUmemoryPool, CUdevice) -> CUresult;
+type DeviceSetMemPoolFn = unsafe extern "C" fn(CUdevice, CUmemoryPool) -> CUresult;
+type PoolAttributeFn = unsafe extern "C" fn(CUmemoryPool, u32, *mut c_void) -> CUresult;
+type PoolTrimToFn = unsafe extern "C" fn(CUmemoryPool, usize) -> CUresult;
+
+driver_fn!(device_get_default_mem_pool_fn: DeviceGetMemPoolFn = b"cuDeviceGetDefaultMemPool\0");
+driver_fn!(device_get_mem_pool_fn: DeviceGetMemPoolFn = b"cuDeviceGetMemPool\0");
+driver_fn!(device_set_mem_pool_fn: DeviceSetMemPoolFn = b"cuDeviceSetMemPool\0");
+driver_fn!(pool_get_attribute_fn: PoolAttributeFn = b"cuMemPoolGetAttribute\0");
+driver_fn!(pool_set_attribute_fn: PoolAttributeFn = b"cuMemPoolSetAttribute\0");
+driver_fn!(pool_trim_to_fn: PoolTrimToFn = b"cuMemPoolTrimTo\0");
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::device::DeviceAttribute;
+
+    #[test]
+    fn test_default_mem_pool() {
+        let _context = crate::quick_init().unwrap();
+        let device = Device::get_device(0).unwrap();
+        let pool = match device.default_mem_pool() {
+            Ok(pool) => pool,
+            Err(CudaError::NotSupported) | Err(CudaError::InvalidValue) => return,
+            Err(e) => panic!("{}", e),
+        };
+        assert_ne!(
+            0,
+            device
+                .get_attribute(DeviceAttribute::MemoryPoolsSupported)
+                .unwrap()
+        );
+        assert_eq!(pool, device.mem_pool().unwrap());
+
+        pool.set_release_threshold(1 << 20).unwrap();
+        assert_eq!(1 << 20, pool.release_threshold().unwrap());
+        pool.trim_to(0).unwrap();
+        device.set_mem_pool(&pool).unwrap();
+        pool.set_release_threshold(0).unwrap();
+    }
+}
@@ -0,0 +1,166 @@
+use crate::error::{CudaResult, ToResult};
+use crate::memory::DevicePointer;
+use crate::stream::Stream;
+
+macro_rules! memset_2d {
+    (
+        $name:ident,
+        $async_name:ident,
+        $value:ty,
+        $bits:expr,
+        $example_width:expr,
+        $sync_fn:ident,
+        $async_fn:ident
+    ) => {
+        #[doc = concat!(
+            "Unsafe wrapper around the `", stringify!($sync_fn), "` function, which sets each ",
+            $bits, "-bit value in a pitched 2D region of device memory to `value`."
+        )]
+        ///
+        /// `pitch` is the distance in bytes between the starts of consecutive rows, as returned by
+        /// [`cuda_malloc_pitched`](fn.cuda_malloc_pitched.html). `width` is the number of values to
+        /// set in each row and `height` is the number of rows. The padding bytes at the end of each
+        /// row are left untouched.
+        ///
+        /// # Errors
+        ///
+        /// If the memset fails, returns the CUDA error value.
+        ///
+        /// # Safety
+        ///
+        /// The caller must ensure that `ptr` points to an allocation at least `height` rows of
+        /// `pitch` bytes long, that `width` values fit within `pitch`, and that `ptr` and `pitch`
+        /// are suitably aligned for the value size.
+        ///
+        /// # Examples
+        ///
+        /// ```
+        /// # let _context = rustacuda::quick_init().unwrap();
+        /// use rustacuda::memory::*;
+        /// unsafe {
+        ///     let (image, pitch) = cuda_malloc_pitched::<u32>(640, 480).unwrap();
+        #[doc = concat!("    ", stringify!($name), "(image, pitch, 0, ", $example_width, ", 480).unwrap();")]
+        ///     cuda_free(image).unwrap();
+        /// }
+        /// ```
+        pub unsafe fn $name<T>(
+            mut ptr: DevicePointer<T>,
+            pitch: usize,
+            value: $value,
+            width: usize,
+            height: usize,
+        ) -> CudaResult<()> {
+            driver_call!(cuda_driver_sys::$sync_fn(
+                ptr.as_raw_mut() as u64,
+                pitch,
+                value,
+                width,
+                height
+            ))
+                .to_result()
+        }
+
+        #[doc = concat!(
+            "Unsafe wrapper around the `", stringify!($async_fn), "` function, which asynchronously ",
+            "sets each ", $bits, "-bit value in a pitched 2D region of device memory to `value`."
+        )]
+        ///
+        #[doc = concat!("See [`", stringify!($name), "`](fn.", stringify!($name), ".html) for the meaning of the parameters.")]
+        ///
+        /// # Errors
+        ///
+        /// If the memset fails, returns the CUDA error value. Errors may also be returned from
+        /// previous asynchronous work.
+        ///
+        /// # Safety
+        ///
+        #[doc = concat!("In addition to the requirements of [`", stringify!($name), "`](fn.", stringify!($name), ".html),")]
+        /// the caller must ensure that the memory is not freed or accessed by other work until the
+        /// stream has completed the memset.
+        #[track_caller]
+        pub unsafe fn $async_name<T>(
+            mut ptr: DevicePointer<T>,
+            pitch: usize,
+            value: $value,
+            width: usize,
+            height: usize,
+            stream: &Stream,
+        ) -> CudaResult<()> {
+            driver_call!(cuda_driver_sys::$async_fn(
+                ptr.as_raw_mut() as u64,
+                pitch,
+                value,
+                width,
+                height,
+                stream.as_inner(),
+            ))
+            .to_result()?;
+            stream.trace(|| format!(
+                concat!("async ", $bits, "-bit 2D memset of {} by {} values"),
+                width, height
+            ));
+            Ok(())
+        }
+    };
+}
+
+memset_2d!(
+    cuda_memset_2d_u8,
+    cuda_memset_2d_u8_async,
+    u8,
+    "8",
+    "640 * 4",
+    cuMemsetD2D8_v2,
+    cuMemsetD2D8Async
+);
+memset_2d!(
+    cuda_memset_2d_u16,
+    cuda_memset_2d_u16_async,
+    u16,
+    "16",
+    "640 * 2",
+    cuMemsetD2D16_v2,
+    cuMemsetD2D16Async
+);
+memset_2d!(
+    cuda_memset_2d_u32,
+    cuda_memset_2d_u32_async,
+    u32,
+    "32",
+    "640",
+    cuMemsetD2D32_v2,
+    cuMemsetD2D32Async
+);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::memory::{cuda_free, cuda_malloc_pitched};
+    use std::os::raw::c_void;
+
+    #[test]
+    fn test_memset_2d_skips_padding() {
+        let _context = crate::quick_init().unwrap();
+        unsafe {
+            let (image, pitch) = cuda_malloc_pitched::<u32>(3, 2).unwrap();
+            cuda_memset_2d_u8(image, pitch, 0, pitch, 2).unwrap();
+            cuda_memset_2d_u32(image, pitch, 0xdead_beef, 3, 2).unwrap();
+
+            let mut host = vec![0u8; pitch * 2];
+            driver_call!(cuda_driver_sys::cuMemcpyDtoH_v2(
+                host.as_mut_ptr() as *mut c_void,
+                image.as_raw() as u64,
+                pitch * 2,
+            ))
+            .to_result()
+            .unwrap();
+            for row in host.chunks(pitch) {
+                assert!(row[..12]
+                    .chunks(4)
+                    .all(|v| v == 0xdead_beefu32.to_ne_bytes()));
+                assert!(row[12..].iter().all(|&b| b == 0));
+            }
+            cuda_free(image).unwrap();
+        }
+    }
+}
@@ -17,10 +17,11 @@
 //! Unified memory is a memory allocation which can be read from and written to by both the host
 //! and the device. When the host (or device) attempts to access a page of unified memory, it is
 //! seamlessly transferred from host RAM to device RAM or vice versa. The programmer may also
-//! choose to explicitly prefetch data to one side or another (though this is not currently exposed
-//! through RustaCUDA). RustaCUDA exposes unified memory through the
-//! [`UnifiedBox`](struct.UnifiedBox.html) and [`UnifiedBuffer`](struct.UnifiedBuffer.html)
-//! structures, and pointers to unified memory are represented by
+//! choose to explicitly prefetch data to one side or another, using the `prefetch_to` functions on
+//! [`UnifiedBox`](struct.UnifiedBox.html) and [`UnifiedBuffer`](struct.UnifiedBuffer.html).
+//! RustaCUDA exposes unified memory through the
+//! [`UnifiedBox`](struct.UnifiedBox.html), [`UnifiedBuffer`](struct.UnifiedBuffer.html) and
+//! [`UnifiedVec`](struct.UnifiedVec.html) structures, and pointers to unified memory are represented by
 //! [`UnifiedPointer`](struct.UnifiedPointer.html). Since unified memory is accessible to the host,
 //! slices in unified memory are represented by normal Rust slices.
 //!
@@ -38,7 +39,8 @@
 //! the driver copies the data into page-locked host memory then into the normal memory space. This
 //! extra copy can be eliminated if the data is loaded or generated directly into page-locked
 //! memory. RustaCUDA exposes page-locked memory through the
-//! [`LockedBuffer`](struct.LockedBuffer.html) struct.
+//! [`LockedBuffer`](struct.LockedBuffer.html) and growable [`LockedVec`](struct.LockedVec.html)
+//! structs.
 //!
 //! For example, if the programmer needs to read an array of bytes from disk and transfer it to the
 //! device, it would be best to create a `LockedBuffer`, load the bytes directly into the
@@ -49,6 +51,15 @@
 //! system (including other processes) as physical RAM is tied up.  Therefore, page-locked memory
 //! should be used sparingly.
 //!
+//! # Thread Safety
+//!
+//! The owning memory types (`DeviceBox`, `DeviceBuffer`, `UnifiedBox`, `UnifiedBuffer`,
+//! `UnifiedVec`, `LockedBuffer`, `LockedVec` and the types built on them) own their allocations
+//! exclusively, just like `Box` and `Vec`, so they are `Send` and `Sync` whenever their element
+//! type is. The driver's memory
+//! functions can be called from any thread, but dropping or copying one of these on another thread
+//! requires a context to be current there first, as with any other CUDA call.
+//!
 //! # FFI Information
 //!
 //! The internal representations of `DevicePointer<T>` and `UnifiedPointer<T>` are guaranteed to be
@@ -66,14 +77,31 @@
 //! ensure that the memory allocation is safely cleaned up.
 
 pub mod array;
+pub mod texture;
 
 mod device;
+#[cfg(feature = "image")]
+mod image;
 mod locked;
+mod locked_vec;
 mod malloc;
+#[cfg(feature = "cuda-11-2")]
+mod mem_pool;
+mod memset;
+mod staging_pool;
 mod unified;
+mod unified_vec;
 
 pub use self::device::*;
+#[cfg(feature = "image")]
+pub use self::image::*;
 pub use self::locked::*;
+pub use self::locked_vec::*;
 pub use self::malloc::*;
+#[cfg(feature = "cuda-11-2")]
+pub use self::mem_pool::*;
+pub use self::memset::*;
+pub use self::staging_pool::*;
 pub use self::unified::*;
+pub use self::unified_vec::*;
 pub use rustacuda_core::{DeviceCopy, DevicePointer, UnifiedPointer};
@@ -0,0 +1,354 @@
+use super::DeviceCopy;
+use crate::error::*;
+use crate::memory::{HostAllocFlags, LockedBuffer};
+use crate::sync::lock_ignoring_poison;
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+/// A pool of page-locked staging buffers which are recycled instead of freed.
+///
+/// Allocating and freeing page-locked memory is very slow, since the OS must pin and unpin the
+/// pages. Code which uploads data every frame or every batch can instead take a buffer from a
+/// `StagingPool`, fill it, copy it to the device, and drop it; the buffer goes back to the pool and
+/// is reused by the next request of the same or smaller size.
+///
+/// Buffers are allocated in power-of-two sizes so that requests of similar sizes share buffers,
+/// and cached buffers are kept in a free list per size, so taking a buffer from the pool doesn't
+/// need to search every cached buffer. A request is a *hit* if a cached buffer was large enough
+/// and a *miss* if one had to be allocated; [`stats`](#method.stats) reports both so the pool's
+/// effectiveness can be monitored.
+///
+/// # Examples
+///
+/// ```
+/// # let _context = rustacuda::quick_init().unwrap();
+/// use rustacuda::memory::*;
+/// use rustacuda::stream::{Stream, StreamFlags};
+///
+/// let stream = Stream::new(StreamFlags::NON_BLOCKING, None).unwrap();
+/// let pool = StagingPool::new();
+/// let mut device = DeviceBuffer::from_slice(&[0u32; 100]).unwrap();
+/// for frame in 0..3u32 {
+///     let staging = pool.get_from_slice(&[frame; 100]).unwrap();
+///     unsafe { device.async_copy_from(&*staging, &stream).unwrap() };
+///     stream.synchronize().unwrap();
+/// }
+/// assert_eq!(1, pool.cached_buffers());
+/// ```
+#[derive(Debug)]
+pub struct StagingPool<T: DeviceCopy> {
+    flags: HostAllocFlags,
+    max_cached_per_class: usize,
+    // A Mutex rather than a RefCell, so that the pool is Sync whenever LockedBuffer is Send.
+    // Indexed by the base-two logarithm of the capacity of the buffers.
+    free: Mutex<Vec<Vec<LockedBuffer<T>>>>,
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+}
+
+/// Usage statistics of a [`StagingPool`](struct.StagingPool.html).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct StagingPoolStats {
+    /// The number of requests served from a cached buffer.
+    pub hits: usize,
+    /// The number of requests which allocated a new buffer.
+    pub misses: usize,
+    /// The number of buffers currently cached in the pool.
+    pub cached_buffers: usize,
+    /// The total capacity, in elements, of the buffers currently cached in the pool.
+    pub cached_elements: usize,
+}
+impl StagingPoolStats {
+    /// Returns the fraction of requests which were served from a cached buffer, or zero if there
+    /// have been no requests.
+    pub fn hit_rate(&self) -> f64 {
+        let requests = self.hits + self.misses;
+        if requests == 0 {
+            0.0
+        } else {
+            self.hits as f64 / requests as f64
+        }
+    }
+}
+
+impl<T: DeviceCopy> StagingPool<T> {
+    /// Create a new, empty pool. This does not allocate.
+    pub fn new() -> Self {
+        StagingPool::new_with_flags(HostAllocFlags::empty())
+    }
+
+    /// Create a new, empty pool whose buffers are allocated according to `flags`. For buffers
+    /// which the host only writes to, `HostAllocFlags::WRITE_COMBINED` can speed up uploads.
+    pub fn new_with_flags(flags: HostAllocFlags) -> Self {
+        StagingPool {
+            flags,
+            max_cached_per_class: usize::MAX,
+            free: Mutex::new(Vec::new()),
+            hits: AtomicUsize::new(0),
+            misses: AtomicUsize::new(0),
+        }
+    }
+
+    /// Limit the number of cached buffers of each power-of-two size. Buffers returned to the pool
+    /// when it already caches that many buffers of the same size are freed instead.
+    pub fn max_cached_per_class(mut self, max: usize) -> Self {
+        self.max_cached_per_class = max;
+        self
+    }
+
+    /// Take a buffer of `len` elements from the pool, allocating one if no cached buffer is large
+    /// enough, without initializing the contents.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidMemoryAllocation` if `len` is too large. If the allocation fails, returns
+    /// the error from CUDA.
+    ///
+    /// # Safety
+    ///
+    /// The contents of the buffer are either uninitialized or left over from a previous user of
+    /// the pool. The caller must ensure that the contents are initialized before reading them.
+    pub unsafe fn get_uninitialized(&self, len: usize) -> CudaResult<StagingBuffer<'_, T>> {
+        let capacity = len
+            .checked_next_power_of_two()
+            .ok_or(CudaError::InvalidMemoryAllocation)?;
+        let class = capacity.trailing_zeros() as usize;
+        // Take the smallest cached buffer which is large enough.
+        let cached = lock_ignoring_poison(&self.free)
+            .iter_mut()
+            .skip(class)
+            .find_map(Vec::pop);
+        let buffer = match cached {
+            Some(buffer) => {
+                let _ = self.hits.fetch_add(1, Ordering::Relaxed);
+                buffer
+            }
+            None => {
+                let _ = self.misses.fetch_add(1, Ordering::Relaxed);
+                LockedBuffer::uninitialized_with_flags(capacity, self.flags)?
+            }
+        };
+        Ok(StagingBuffer {
+            buffer: Some(buffer),
+            len,
+            pool: self,
+        })
+    }
+
+    /// Returns the number of buffers currently cached in the pool.
+    pub fn cached_buffers(&self) -> usize {
+        lock_ignoring_poison(&self.free).iter().map(Vec::len).sum()
+    }
+
+    /// Returns the pool's usage statistics.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # let _context = rustacuda::quick_init().unwrap();
+    /// use rustacuda::memory::*;
+    ///
+    /// let pool = StagingPool::new().max_cached_per_class(16);
+    /// for request in 0..10u8 {
+    ///     let buffer = pool.get(&request, 1000).unwrap();
+    ///     // Copy `buffer` to the device and process the request here.
+    /// }
+    /// let stats = pool.stats();
+    /// assert_eq!(1, stats.misses);
+    /// assert_eq!(9, stats.hits);
+    /// ```
+    pub fn stats(&self) -> StagingPoolStats {
+        let free = lock_ignoring_poison(&self.free);
+        StagingPoolStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            cached_buffers: free.iter().map(Vec::len).sum(),
+            cached_elements: free.iter().flatten().map(|buf| buf.len()).sum(),
+        }
+    }
+
+    /// Free every buffer cached in the pool. Buffers currently in use are returned to the pool
+    /// when they are dropped, as usual. The hit and miss counts are not reset.
+    ///
+    /// # Errors
+    ///
+    /// If freeing a buffer fails, returns the error from CUDA. The remaining buffers are still
+    /// freed.
+    pub fn clear(&self) -> CudaResult<()> {
+        let buffers = std::mem::take(&mut *lock_ignoring_poison(&self.free));
+        let mut result = Ok(());
+        for buffer in buffers.into_iter().flatten() {
+            if let Err((e, buffer)) = LockedBuffer::drop(buffer) {
+                result = Err(e);
+                std::mem::forget(buffer);
+            }
+        }
+        result
+    }
+
+    fn recycle(&self, buffer: LockedBuffer<T>) {
+        let class = buffer.len().trailing_zeros() as usize;
+        let mut free = lock_ignoring_poison(&self.free);
+        if free.len() <= class {
+            free.resize_with(class + 1, Vec::new);
+        }
+        if free[class].len() < self.max_cached_per_class {
+            free[class].push(buffer);
+        }
+    }
+}
+impl<T: DeviceCopy + Clone> StagingPool<T> {
+    /// Take a buffer of `len` elements from the pool, initialized with clones of `value`.
+    ///
+    /// # Errors
+    ///
+    /// If the allocation fails, returns the error from CUDA.
+    pub fn get(&self, value: &T, len: usize) -> CudaResult<StagingBuffer<'_, T>> {
+        unsafe {
+            let mut buffer = self.get_uninitialized(len)?;
+            for x in buffer.iter_mut() {
+                std::ptr::write(x, value.clone());
+            }
+            Ok(buffer)
+        }
+    }
+
+    /// Take a buffer of the same size as `slice` from the pool, initialized with a clone of the
+    /// data in `slice`.
+    ///
+    /// # Errors
+    ///
+    /// If the allocation fails, returns the error from CUDA.
+    pub fn get_from_slice(&self, slice: &[T]) -> CudaResult<StagingBuffer<'_, T>> {
+        unsafe {
+            let mut buffer = self.get_uninitialized(slice.len())?;
+            for (x, value) in buffer.iter_mut().zip(slice) {
+                std::ptr::write(x, value.clone());
+            }
+            Ok(buffer)
+        }
+    }
+}
+impl<T: DeviceCopy> Default for StagingPool<T> {
+    fn default() -> Self {
+        StagingPool::new()
+    }
+}
+
+/// A page-locked buffer borrowed from a [`StagingPool`](struct.StagingPool.html).
+///
+/// This dereferences to a slice of the requested length, and can be used as the source or
+/// destination of asynchronous copies. The underlying buffer may be larger. When dropped, the
+/// buffer is returned to the pool, so any asynchronous copy using it must complete first.
+#[derive(Debug)]
+pub struct StagingBuffer<'a, T: DeviceCopy> {
+    buffer: Option<LockedBuffer<T>>,
+    len: usize,
+    pool: &'a StagingPool<T>,
+}
+impl<'a, T: DeviceCopy> StagingBuffer<'a, T> {
+    /// Detach the buffer from the pool, returning the whole underlying `LockedBuffer`, whose
+    /// capacity is at least the requested length rounded up to a power of two. It will not be
+    /// returned to the pool.
+    pub fn into_inner(mut self) -> LockedBuffer<T> {
+        self.buffer
+            .take()
+            .expect("staging buffer is always present")
+    }
+}
+impl<'a, T: DeviceCopy> AsRef<[T]> for StagingBuffer<'a, T> {
+    fn as_ref(&self) -> &[T] {
+        self
+    }
+}
+impl<'a, T: DeviceCopy> AsMut<[T]> for StagingBuffer<'a, T> {
+    fn as_mut(&mut self) -> &mut [T] {
+        self
+    }
+}
+impl<'a, T: DeviceCopy> Deref for StagingBuffer<'a, T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        &self
+            .buffer
+            .as_ref()
+            .expect("staging buffer is always present")[..self.len]
+    }
+}
+impl<'a, T: DeviceCopy> DerefMut for StagingBuffer<'a, T> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        &mut self
+            .buffer
+            .as_mut()
+            .expect("staging buffer is always present")[..self.len]
+    }
+}
+impl<'a, T: DeviceCopy> Drop for StagingBuffer<'a, T> {
+    fn drop(&mut self) {
+        if let Some(buffer) = self.buffer.take() {
+            self.pool.recycle(buffer);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_buffers_are_recycled() {
+        let _context = crate::quick_init().unwrap();
+        let pool = StagingPool::new();
+        let ptr = {
+            let buffer = pool.get(&1u32, 100).unwrap();
+            assert_eq!(100, buffer.len());
+            buffer.as_ptr()
+        };
+        assert_eq!(1, pool.cached_buffers());
+
+        // A smaller request reuses the cached 128-element buffer.
+        let buffer = pool.get_from_slice(&[7u32; 60]).unwrap();
+        assert_eq!(ptr, buffer.as_ptr());
+        assert_eq!(&[7u32; 60][..], &buffer[..]);
+        assert_eq!(0, pool.cached_buffers());
+
+        // A concurrent request allocates a new buffer.
+        let other = pool.get(&0u32, 10).unwrap();
+        assert_ne!(ptr, other.as_ptr());
+        drop(buffer);
+        drop(other);
+        assert_eq!(2, pool.cached_buffers());
+
+        let stats = pool.stats();
+        assert_eq!(1, stats.hits);
+        assert_eq!(2, stats.misses);
+        assert_eq!(128 + 16, stats.cached_elements);
+        assert!((stats.hit_rate() - 1.0 / 3.0).abs() < 1e-9);
+
+        pool.clear().unwrap();
+        assert_eq!(0, pool.cached_buffers());
+    }
+
+    #[test]
+    fn test_max_cached_per_class() {
+        let _context = crate::quick_init().unwrap();
+        let pool = StagingPool::new().max_cached_per_class(1);
+        let first = pool.get(&0u8, 10).unwrap();
+        let second = pool.get(&0u8, 10).unwrap();
+        drop(first);
+        drop(second);
+        assert_eq!(1, pool.stats().cached_buffers);
+        assert_eq!(0.0, StagingPoolStats::default().hit_rate());
+    }
+
+    #[test]
+    fn test_into_inner_detaches() {
+        let _context = crate::quick_init().unwrap();
+        let pool = StagingPool::new_with_flags(HostAllocFlags::PORTABLE);
+        let buffer = pool.get(&0u64, 5).unwrap().into_inner();
+        assert_eq!(8, buffer.len());
+        assert_eq!(0, pool.cached_buffers());
+    }
+}
@@ -0,0 +1,310 @@
+//! Texture and surface objects for reading and writing CUDA Arrays from kernels.
+//!
+//! A texture or surface object is a 64-bit handle which kernels receive as a plain
+//! `cudaTextureObject_t` or `cudaSurfaceObject_t` argument. The easiest way to create one is with
+//! [`ArrayObject::as_texture`](../array/struct.ArrayObject.html#method.as_texture) or
+//! [`ArrayObject::as_surface`](../array/struct.ArrayObject.html#method.as_surface), and the handle
+//! from `as_raw` can then be passed to `launch!` like any other argument.
+//!
+//! Detailed documentation about texture and surface objects can be found in the
+//! [CUDA Driver API](https://docs.nvidia.com/cuda/cuda-driver-api/group__CUDA__TEXOBJECT.html)
+
+use std::marker::PhantomData;
+use std::mem;
+use std::os::raw::c_uint;
+
+use cuda_driver_sys::{
+    CUaddress_mode, CUaddress_mode_enum, CUfilter_mode, CUfilter_mode_enum, CUresourcetype_enum,
+    CUsurfObject, CUtexObject, CUDA_RESOURCE_DESC, CUDA_TEXTURE_DESC,
+};
+
+use crate::error::*;
+use crate::memory::array::ArrayObject;
+
+/// Describes how a texture handles coordinates outside of the texture.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AddressMode {
+    /// Coordinates wrap around to the other side of the texture
+    Wrap,
+    /// Coordinates are clamped to the edge of the texture
+    Clamp,
+    /// Coordinates are mirrored at the edge of the texture
+    Mirror,
+    /// Reads outside the texture return the border color
+    Border,
+}
+
+impl AddressMode {
+    /// Converts AddressMode to the CUDA Driver API enum
+    pub fn to_raw(self) -> CUaddress_mode {
+        match self {
+            AddressMode::Wrap => CUaddress_mode_enum::CU_TR_ADDRESS_MODE_WRAP,
+            AddressMode::Clamp => CUaddress_mode_enum::CU_TR_ADDRESS_MODE_CLAMP,
+            AddressMode::Mirror => CUaddress_mode_enum::CU_TR_ADDRESS_MODE_MIRROR,
+            AddressMode::Border => CUaddress_mode_enum::CU_TR_ADDRESS_MODE_BORDER,
+        }
+    }
+}
+
+/// Describes how a texture is sampled between texels.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FilterMode {
+    /// Reads return the nearest texel
+    Point,
+    /// Reads linearly interpolate between neighboring texels. Only valid for floating-point
+    /// reads.
+    Linear,
+}
+
+impl FilterMode {
+    /// Converts FilterMode to the CUDA Driver API enum
+    pub fn to_raw(self) -> CUfilter_mode {
+        match self {
+            FilterMode::Point => CUfilter_mode_enum::CU_TR_FILTER_MODE_POINT,
+            FilterMode::Linear => CUfilter_mode_enum::CU_TR_FILTER_MODE_LINEAR,
+        }
+    }
+}
+
+bitflags! {
+    /// Flags which modify how a texture is read.
+    #[derive(Default)]
+    pub struct TextureFlags: c_uint {
+        /// Integer data is returned as integers rather than being promoted to normalized floats.
+        const READ_AS_INTEGER = cuda_driver_sys::CU_TRSF_READ_AS_INTEGER;
+
+        /// Texture coordinates are normalized to the range [0, 1) rather than being in texels.
+        const NORMALIZED_COORDINATES = cuda_driver_sys::CU_TRSF_NORMALIZED_COORDINATES;
+
+        /// 8-bit data is converted from sRGB to linear color space when read.
+        const SRGB = cuda_driver_sys::CU_TRSF_SRGB;
+    }
+}
+
+/// Describes how a texture object samples its underlying array.
+#[derive(Clone, Copy, Debug)]
+pub struct TextureDesc {
+    desc: CUDA_TEXTURE_DESC,
+}
+
+impl TextureDesc {
+    /// Creates a texture descriptor with the given address mode in every dimension, the given
+    /// filter mode, and no flags.
+    pub fn new(address_mode: AddressMode, filter_mode: FilterMode) -> Self {
+        // All-zero is a valid starting point for this plain C struct.
+        let mut desc: CUDA_TEXTURE_DESC = unsafe { mem::zeroed() };
+        desc.addressMode = [address_mode.to_raw(); 3];
+        desc.filterMode = filter_mode.to_raw();
+        desc.mipmapFilterMode = filter_mode.to_raw();
+        TextureDesc { desc }
+    }
+
+    /// Constructs a TextureDesc from a CUDA Driver API texture descriptor.
+    pub fn from_raw(desc: CUDA_TEXTURE_DESC) -> Self {
+        TextureDesc { desc }
+    }
+
+    /// Sets the address mode used for each of the three texture dimensions.
+    pub fn set_address_modes(&mut self, modes: [AddressMode; 3]) {
+        self.desc.addressMode = [modes[0].to_raw(), modes[1].to_raw(), modes[2].to_raw()];
+    }
+
+    /// Sets the filter mode
+    pub fn set_filter_mode(&mut self, mode: FilterMode) {
+        self.desc.filterMode = mode.to_raw();
+    }
+
+    /// Returns the flags of the TextureDesc
+    pub fn flags(&self) -> TextureFlags {
+        TextureFlags::from_bits_truncate(self.desc.flags)
+    }
+
+    /// Sets the flags of the TextureDesc
+    pub fn set_flags(&mut self, flags: TextureFlags) {
+        self.desc.flags = flags.bits();
+    }
+
+    /// Sets the color returned for reads outside the texture when using `AddressMode::Border`.
+    pub fn set_border_color(&mut self, color: [f32; 4]) {
+        self.desc.borderColor = color;
+    }
+}
+
+impl Default for TextureDesc {
+    /// Point sampling with clamped coordinates, which reads texels exactly as stored.
+    fn default() -> Self {
+        TextureDesc::new(AddressMode::Clamp, FilterMode::Point)
+    }
+}
+
+fn array_resource(array: &ArrayObject) -> CUDA_RESOURCE_DESC {
+    // All-zero is a valid starting point for this plain C struct.
+    let mut desc: CUDA_RESOURCE_DESC = unsafe { mem::zeroed() };
+    desc.resType = CUresourcetype_enum::CU_RESOURCE_TYPE_ARRAY;
+    desc.res.array.hArray = array.as_raw();
+    desc
+}
+
+/// A texture object reading from a CUDA Array.
+///
+/// The texture borrows the array, so the array can't be destroyed while the texture exists.
+#[derive(Debug)]
+pub struct TextureObject<'a> {
+    handle: CUtexObject,
+    array: PhantomData<&'a ArrayObject>,
+}
+
+impl<'a> TextureObject<'a> {
+    /// Creates a texture object which samples `array` as described by `desc`.
+    ///
+    /// # Errors
+    ///
+    /// If the texture object cannot be created, returns the CUDA error value.
+    pub fn new(array: &'a ArrayObject, desc: &TextureDesc) -> CudaResult<Self> {
+        let resource = array_resource(array);
+        let mut handle: CUtexObject = 0;
+        unsafe {
+            driver_call!(cuda_driver_sys::cuTexObjectCreate(
+                &mut handle,
+                &resource as *const _,
+                &desc.desc as *const _,
+                std::ptr::null(),
+            ))
+            .to_result()?;
+        }
+        Ok(TextureObject {
+            handle,
+            array: PhantomData,
+        })
+    }
+
+    /// Returns the raw texture object handle, which can be passed to a kernel as a
+    /// `cudaTextureObject_t`.
+    pub fn as_raw(&self) -> CUtexObject {
+        self.handle
+    }
+
+    /// Try to destroy a `TextureObject`. Can fail - if it does, returns the CUDA error and the
+    /// un-destroyed texture object
+    pub fn drop(texture: TextureObject<'a>) -> DropResult<TextureObject<'a>> {
+        match unsafe { driver_call!(cuda_driver_sys::cuTexObjectDestroy(texture.handle)) }
+            .to_result()
+        {
+            Ok(()) => {
+                mem::forget(texture);
+                Ok(())
+            }
+            Err(e) => Err((e, texture)),
+        }
+    }
+}
+
+impl<'a> Drop for TextureObject<'a> {
+    fn drop(&mut self) {
+        unsafe { driver_call!(cuda_driver_sys::cuTexObjectDestroy(self.handle)) }
+            .to_result()
+            .expect_in_drop("Failed to destroy CUDA texture object")
+    }
+}
+
+/// A surface object reading from and writing to a CUDA Array.
+///
+/// The surface borrows the array, so the array can't be destroyed while the surface exists.
+#[derive(Debug)]
+pub struct SurfaceObject<'a> {
+    handle: CUsurfObject,
+    array: PhantomData<&'a ArrayObject>,
+}
+
+impl<'a> SurfaceObject<'a> {
+    /// Creates a surface object for `array`.
+    ///
+    /// # Errors
+    ///
+    /// If the surface object cannot be created, returns the CUDA error value. In particular, the
+    /// array must have been created with `ArrayObjectFlags::SURFACE_LDST`.
+    pub fn new(array: &'a ArrayObject) -> CudaResult<Self> {
+        let resource = array_resource(array);
+        let mut handle: CUsurfObject = 0;
+        unsafe {
+            driver_call!(cuda_driver_sys::cuSurfObjectCreate(
+                &mut handle,
+                &resource as *const _
+            ))
+            .to_result()?;
+        }
+        Ok(SurfaceObject {
+            handle,
+            array: PhantomData,
+        })
+    }
+
+    /// Returns the raw surface object handle, which can be passed to a kernel as a
+    /// `cudaSurfaceObject_t`.
+    pub fn as_raw(&self) -> CUsurfObject {
+        self.handle
+    }
+
+    /// Try to destroy a `SurfaceObject`. Can fail - if it does, returns the CUDA error and the
+    /// un-destroyed surface object
+    pub fn drop(surface: SurfaceObject<'a>) -> DropResult<SurfaceObject<'a>> {
+        match unsafe { driver_call!(cuda_driver_sys::cuSurfObjectDestroy(surface.handle)) }
+            .to_result()
+        {
+            Ok(()) => {
+                mem::forget(surface);
+                Ok(())
+            }
+            Err(e) => Err((e, surface)),
+        }
+    }
+}
+
+impl<'a> Drop for SurfaceObject<'a> {
+    fn drop(&mut self) {
+        unsafe { driver_call!(cuda_driver_sys::cuSurfObjectDestroy(self.handle)) }
+            .to_result()
+            .expect_in_drop("Failed to destroy CUDA surface object")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::memory::array::{ArrayDescriptor, ArrayFormat, ArrayObjectFlags};
+
+    #[test]
+    fn texture_from_array() {
+        let _context = crate::quick_init().unwrap();
+
+        let array = ArrayObject::new_2d([64, 32], ArrayFormat::Float, 1).unwrap();
+        let mut desc = TextureDesc::new(AddressMode::Wrap, FilterMode::Linear);
+        desc.set_flags(TextureFlags::NORMALIZED_COORDINATES);
+        let texture = array.as_texture(&desc).unwrap();
+        assert_ne!(0, texture.as_raw());
+        TextureObject::drop(texture).unwrap();
+    }
+
+    #[test]
+    fn surface_from_array() {
+        let _context = crate::quick_init().unwrap();
+
+        let descriptor = ArrayDescriptor::new(
+            [64, 32, 0],
+            ArrayFormat::UnsignedInt8,
+            4,
+            ArrayObjectFlags::SURFACE_LDST,
+        );
+        let array = ArrayObject::from_descriptor(&descriptor).unwrap();
+        let surface = array.as_surface().unwrap();
+        SurfaceObject::drop(surface).unwrap();
+    }
+
+    #[test]
+    fn surface_requires_ldst_flag() {
+        let _context = crate::quick_init().unwrap();
+
+        let array = ArrayObject::new_2d([64, 32], ArrayFormat::UnsignedInt8, 4).unwrap();
+        assert!(array.as_surface().is_err());
+    }
+}
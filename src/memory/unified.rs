@@ -1,16 +1,202 @@
 use super::DeviceCopy;
+use crate::context::CurrentContext;
+use crate::device::Device;
 use crate::error::*;
-use crate::memory::malloc::{cuda_free_unified, cuda_malloc_unified};
+use crate::memory::malloc::{
+    cuda_free_unified, cuda_malloc_unified, cuda_malloc_unified_with_flags,
+};
 use crate::memory::UnifiedPointer;
+use crate::stream::Stream;
+use crate::sync::lock_ignoring_poison;
+use cuda_driver_sys::{CUcontext, CUmem_advise};
 use std::borrow::{Borrow, BorrowMut};
 use std::cmp::Ordering;
 use std::convert::{AsMut, AsRef};
 use std::fmt::{self, Display, Pointer};
 use std::hash::{Hash, Hasher};
-use std::mem;
+use std::iter::FusedIterator;
+use std::marker::PhantomData;
+use std::mem::{self, size_of};
 use std::ops::{Deref, DerefMut};
 use std::ptr;
-use std::slice;
+use std::slice::{self, ChunksMut};
+use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
+use std::sync::{Mutex, MutexGuard};
+
+bitflags! {
+    /// Bit flags controlling which streams and processors can initially access a unified
+    /// allocation.
+    ///
+    /// Exactly one of these flags must be given when allocating unified memory.
+    pub struct MemAttachFlags: u32 {
+        /// The memory is accessible from any stream on any device. This is the default.
+        const GLOBAL = 0x1;
+
+        /// The memory is initially only accessible from the host, until it is attached to a
+        /// stream. On devices without concurrent managed access, such as Jetson-class devices,
+        /// the host may not touch globally-attached memory while any kernel is running; host
+        /// attachment keeps buffers that kernels don't use accessible to the host.
+        const HOST = 0x2;
+    }
+}
+
+/// Destination to prefetch unified memory to.
+///
+/// See [`UnifiedBuffer::prefetch_to`](struct.UnifiedBuffer.html#method.prefetch_to).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PrefetchTarget {
+    /// Prefetch to the memory of the given device.
+    Device(Device),
+    /// Prefetch to host memory.
+    Cpu,
+}
+impl From<Device> for PrefetchTarget {
+    fn from(device: Device) -> PrefetchTarget {
+        PrefetchTarget::Device(device)
+    }
+}
+
+// Corresponds to the CU_DEVICE_CPU define, which bindgen doesn't generate.
+const CU_DEVICE_CPU: cuda_driver_sys::CUdevice = -1;
+
+unsafe fn prefetch<T>(
+    ptr: UnifiedPointer<T>,
+    count: usize,
+    target: PrefetchTarget,
+    stream: &Stream,
+) -> CudaResult<()> {
+    let size = count * size_of::<T>();
+    if ptr.is_null() || size == 0 {
+        return Ok(());
+    }
+    driver_call!(cuda_driver_sys::cuMemPrefetchAsync(
+        ptr.as_raw() as u64,
+        size,
+        target_device(target),
+        stream.as_inner()
+    ))
+    .to_result()
+}
+
+unsafe fn advise<T>(
+    ptr: UnifiedPointer<T>,
+    count: usize,
+    advice: CUmem_advise,
+    target: PrefetchTarget,
+) -> CudaResult<()> {
+    let size = count * size_of::<T>();
+    if ptr.is_null() || size == 0 {
+        return Ok(());
+    }
+    driver_call!(cuda_driver_sys::cuMemAdvise(
+        ptr.as_raw() as u64,
+        size,
+        advice,
+        target_device(target)
+    ))
+    .to_result()
+}
+
+/// Memory usage advice for unified memory, applied to every unified allocation made in a context
+/// with [`CurrentContext::set_unified_advice`](../context/struct.CurrentContext.html#method.set_unified_advice).
+///
+/// The advice has the same meaning as the hints of
+/// [`UnifiedBufferBuilder`](struct.UnifiedBufferBuilder.html). The default gives no advice.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct UnifiedAdvice {
+    /// Advise the driver that the memory will mostly be read and only occasionally written.
+    pub read_mostly: bool,
+    /// Advise the driver to keep the pages of the memory in this location where possible.
+    pub preferred_location: Option<PrefetchTarget>,
+    /// Advise the driver that these devices will access the memory.
+    pub accessed_by: Vec<Device>,
+}
+
+unsafe fn apply_advice<T>(
+    ptr: UnifiedPointer<T>,
+    count: usize,
+    advice: &UnifiedAdvice,
+) -> CudaResult<()> {
+    if advice.read_mostly {
+        advise(
+            ptr,
+            count,
+            CUmem_advise::CU_MEM_ADVISE_SET_READ_MOSTLY,
+            // The device is ignored for this advice.
+            PrefetchTarget::Cpu,
+        )?;
+    }
+    if let Some(location) = advice.preferred_location {
+        advise(
+            ptr,
+            count,
+            CUmem_advise::CU_MEM_ADVISE_SET_PREFERRED_LOCATION,
+            location,
+        )?;
+    }
+    for &device in &advice.accessed_by {
+        advise(
+            ptr,
+            count,
+            CUmem_advise::CU_MEM_ADVISE_SET_ACCESSED_BY,
+            device.into(),
+        )?;
+    }
+    Ok(())
+}
+
+static HAS_DEFAULT_ADVICE: AtomicBool = AtomicBool::new(false);
+
+/// The advice set with `CurrentContext::set_unified_advice`, by context handle.
+static DEFAULT_ADVICE: Mutex<Vec<(usize, UnifiedAdvice)>> = Mutex::new(Vec::new());
+
+fn default_advice() -> MutexGuard<'static, Vec<(usize, UnifiedAdvice)>> {
+    lock_ignoring_poison(&DEFAULT_ADVICE)
+}
+
+/// Apply `advice` to every unified allocation made in `ctx` from now on.
+pub(crate) fn set_default_advice(ctx: CUcontext, advice: UnifiedAdvice) {
+    let mut defaults = default_advice();
+    defaults.retain(|(handle, _)| *handle != ctx as usize);
+    if advice != UnifiedAdvice::default() {
+        defaults.push((ctx as usize, advice));
+    }
+    HAS_DEFAULT_ADVICE.store(!defaults.is_empty(), AtomicOrdering::Release);
+}
+
+/// Returns the advice applied to unified allocations made in `ctx`.
+pub(crate) fn get_default_advice(ctx: CUcontext) -> UnifiedAdvice {
+    default_advice()
+        .iter()
+        .find(|(handle, _)| *handle == ctx as usize)
+        .map(|(_, advice)| advice.clone())
+        .unwrap_or_default()
+}
+
+/// Forgets the advice for `ctx`, which has just been destroyed.
+pub(crate) fn forget_default_advice(ctx: CUcontext) {
+    set_default_advice(ctx, UnifiedAdvice::default());
+}
+
+/// Applies the advice set for the current context, if any, to a new unified allocation.
+pub(crate) unsafe fn apply_default_advice<T>(
+    ptr: UnifiedPointer<T>,
+    count: usize,
+) -> CudaResult<()> {
+    if !HAS_DEFAULT_ADVICE.load(AtomicOrdering::Acquire) {
+        return Ok(());
+    }
+    let mut ctx: CUcontext = ptr::null_mut();
+    driver_call!(cuda_driver_sys::cuCtxGetCurrent(&mut ctx)).to_result()?;
+    apply_advice(ptr, count, &get_default_advice(ctx))
+}
+
+fn target_device(target: PrefetchTarget) -> cuda_driver_sys::CUdevice {
+    match target {
+        PrefetchTarget::Device(device) => device.into_inner(),
+        PrefetchTarget::Cpu => CU_DEVICE_CPU,
+    }
+}
 
 /// A pointer type for heap-allocation in CUDA unified memory.
 ///
@@ -21,6 +207,9 @@ use std::slice;
 pub struct UnifiedBox<T: DeviceCopy> {
     ptr: UnifiedPointer<T>,
 }
+// See "Thread Safety" in the `memory` module documentation.
+unsafe impl<T: DeviceCopy + Send> Send for UnifiedBox<T> {}
+unsafe impl<T: DeviceCopy + Sync> Sync for UnifiedBox<T> {}
 impl<T: DeviceCopy> UnifiedBox<T> {
     /// Allocate unified memory and place val into it.
     ///
@@ -179,6 +368,36 @@ impl<T: DeviceCopy> UnifiedBox<T> {
         self.ptr
     }
 
+    /// Asynchronously migrate the contents of this box to the given device or to the host.
+    ///
+    /// Prefetching moves the pages backing the box before they are accessed, avoiding the
+    /// page faults that would otherwise migrate them on first access. The prefetch is enqueued
+    /// in `stream`, so it is ordered with respect to kernels launched in that stream. This is
+    /// only a performance hint; the contents remain accessible from anywhere at any time.
+    ///
+    /// # Errors
+    ///
+    /// If the prefetch fails, returns the error from CUDA. Prefetching to a device requires that
+    /// the device supports concurrent managed access.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # let _context = rustacuda::quick_init().unwrap();
+    /// use rustacuda::context::CurrentContext;
+    /// use rustacuda::memory::*;
+    /// use rustacuda::stream::{Stream, StreamFlags};
+    /// let stream = Stream::new(StreamFlags::NON_BLOCKING, None).unwrap();
+    /// let x = UnifiedBox::new(5u64).unwrap();
+    /// let device = CurrentContext::get_device().unwrap();
+    /// x.prefetch_to(device.into(), &stream).unwrap();
+    /// x.prefetch_to(PrefetchTarget::Cpu, &stream).unwrap();
+    /// stream.synchronize().unwrap();
+    /// ```
+    pub fn prefetch_to(&self, target: PrefetchTarget, stream: &Stream) -> CudaResult<()> {
+        unsafe { prefetch(self.ptr, 1, target, stream) }
+    }
+
     /// Consumes and leaks the UnifiedBox, returning a mutable reference, &'a mut T. Note that the type T
     /// must outlive the chosen lifetime 'a. If the type has only static references, or none at all,
     /// this may be chosen to be 'static.
@@ -240,7 +459,7 @@ impl<T: DeviceCopy> Drop for UnifiedBox<T> {
             let ptr = mem::replace(&mut self.ptr, UnifiedPointer::null());
             // No choice but to panic if this fails.
             unsafe {
-                cuda_free_unified(ptr).expect("Failed to deallocate CUDA Unified memory.");
+                cuda_free_unified(ptr).expect_in_drop("Failed to deallocate CUDA Unified memory.");
             }
         }
     }
@@ -330,6 +549,9 @@ pub struct UnifiedBuffer<T: DeviceCopy> {
     buf: UnifiedPointer<T>,
     capacity: usize,
 }
+// See "Thread Safety" in the `memory` module documentation.
+unsafe impl<T: DeviceCopy + Send> Send for UnifiedBuffer<T> {}
+unsafe impl<T: DeviceCopy + Sync> Sync for UnifiedBuffer<T> {}
 impl<T: DeviceCopy + Clone> UnifiedBuffer<T> {
     /// Allocate a new unified buffer large enough to hold `size` `T`'s and initialized with
     /// clones of `value`.
@@ -348,8 +570,28 @@ impl<T: DeviceCopy + Clone> UnifiedBuffer<T> {
     /// buffer[0] = 1;
     /// ```
     pub fn new(value: &T, size: usize) -> CudaResult<Self> {
+        UnifiedBuffer::new_with_flags(value, size, MemAttachFlags::GLOBAL)
+    }
+
+    /// Allocate a new unified buffer large enough to hold `size` `T`'s, attached according to
+    /// `flags`, and initialized with clones of `value`.
+    ///
+    /// # Errors
+    ///
+    /// If the allocation fails, returns the error from CUDA. If `size` is large enough that
+    /// `size * mem::sizeof::<T>()` overflows usize, then returns InvalidMemoryAllocation.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # let _context = rustacuda::quick_init().unwrap();
+    /// use rustacuda::memory::*;
+    /// let mut buffer = UnifiedBuffer::new_with_flags(&0u64, 5, MemAttachFlags::HOST).unwrap();
+    /// buffer[0] = 1;
+    /// ```
+    pub fn new_with_flags(value: &T, size: usize, flags: MemAttachFlags) -> CudaResult<Self> {
         unsafe {
-            let mut uninit = UnifiedBuffer::uninitialized(size)?;
+            let mut uninit = UnifiedBuffer::uninitialized_with_flags(size, flags)?;
             for x in 0..size {
                 *uninit.get_unchecked_mut(x) = value.clone();
             }
@@ -384,6 +626,13 @@ impl<T: DeviceCopy + Clone> UnifiedBuffer<T> {
     }
 }
 impl<T: DeviceCopy> UnifiedBuffer<T> {
+    /// Returns a builder for a unified buffer which applies memory usage hints at allocation time.
+    ///
+    /// See [`UnifiedBufferBuilder`](struct.UnifiedBufferBuilder.html) for details.
+    pub fn builder<'a>() -> UnifiedBufferBuilder<'a, T> {
+        UnifiedBufferBuilder::new()
+    }
+
     /// Allocate a new unified buffer large enough to hold `size` `T`'s, but without
     /// initializing the contents.
     ///
@@ -408,8 +657,36 @@ impl<T: DeviceCopy> UnifiedBuffer<T> {
     /// }
     /// ```
     pub unsafe fn uninitialized(size: usize) -> CudaResult<Self> {
+        UnifiedBuffer::uninitialized_with_flags(size, MemAttachFlags::GLOBAL)
+    }
+
+    /// Allocate a new unified buffer large enough to hold `size` `T`'s, attached according to
+    /// `flags`, but without initializing the contents.
+    ///
+    /// # Errors
+    ///
+    /// If the allocation fails, returns the error from CUDA. If `size` is large enough that
+    /// `size * mem::sizeof::<T>()` overflows usize, then returns InvalidMemoryAllocation.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that the contents of the buffer are initialized before reading from
+    /// the buffer.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # let _context = rustacuda::quick_init().unwrap();
+    /// use rustacuda::memory::*;
+    /// let mut buffer =
+    ///     unsafe { UnifiedBuffer::uninitialized_with_flags(5, MemAttachFlags::HOST).unwrap() };
+    /// for i in buffer.iter_mut() {
+    ///     *i = 0u64;
+    /// }
+    /// ```
+    pub unsafe fn uninitialized_with_flags(size: usize, flags: MemAttachFlags) -> CudaResult<Self> {
         let ptr = if size > 0 && mem::size_of::<T>() > 0 {
-            cuda_malloc_unified(size)?
+            cuda_malloc_unified_with_flags(size, flags)?
         } else {
             UnifiedPointer::wrap(ptr::NonNull::dangling().as_ptr() as *mut T)
         };
@@ -465,6 +742,94 @@ impl<T: DeviceCopy> UnifiedBuffer<T> {
         self.buf
     }
 
+    /// Returns an iterator over `chunk_size` elements of the buffer at a time, as
+    /// `(UnifiedPointer<T>, len)` pairs that can be passed directly to kernels.
+    ///
+    /// When the buffer length is not evenly divided by the chunk size, the last chunk will be the
+    /// remainder. This is useful for partitioning one buffer across several launches, such as one
+    /// launch per stream.
+    ///
+    /// The same caveats as [`as_unified_ptr`](#method.as_unified_ptr) apply to the returned
+    /// pointers.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `chunk_size` is 0.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # let _context = rustacuda::quick_init().unwrap();
+    /// use rustacuda::memory::*;
+    /// let mut buffer = UnifiedBuffer::new(&0u64, 10).unwrap();
+    /// let lengths: Vec<usize> = buffer.unified_chunks(4).map(|(_ptr, len)| len).collect();
+    /// assert_eq!(vec![4, 4, 2], lengths);
+    /// ```
+    pub fn unified_chunks(&mut self, chunk_size: usize) -> UnifiedChunks<'_, T> {
+        UnifiedChunks(self.as_mut_slice().chunks_mut(chunk_size))
+    }
+
+    /// Divides the buffer into two `(UnifiedPointer<T>, len)` pairs at an index.
+    ///
+    /// The first will contain all indices from `[0, mid)` (excluding the index `mid` itself) and
+    /// the second will contain all indices from `[mid, len)` (excluding the index `len` itself).
+    ///
+    /// The same caveats as [`as_unified_ptr`](#method.as_unified_ptr) apply to the returned
+    /// pointers.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `mid > len`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # let _context = rustacuda::quick_init().unwrap();
+    /// use rustacuda::memory::*;
+    /// let mut buffer = UnifiedBuffer::new(&0u64, 10).unwrap();
+    /// let ((_left, left_len), (_right, right_len)) = buffer.split_at_unified(3);
+    /// assert_eq!((3, 7), (left_len, right_len));
+    /// ```
+    pub fn split_at_unified(
+        &mut self,
+        mid: usize,
+    ) -> ((UnifiedPointer<T>, usize), (UnifiedPointer<T>, usize)) {
+        let (left, right) = self.as_mut_slice().split_at_mut(mid);
+        (to_unified_chunk(left), to_unified_chunk(right))
+    }
+
+    /// Asynchronously migrate the contents of this buffer to the given device or to the host.
+    ///
+    /// Prefetching moves the pages backing the buffer before they are accessed, avoiding the
+    /// page-fault storms that would otherwise migrate them on first access, such as when switching
+    /// between preprocessing on the host and kernels on the device. The prefetch is enqueued in
+    /// `stream`, so it is ordered with respect to kernels launched in that stream. This is only a
+    /// performance hint; the contents remain accessible from anywhere at any time.
+    ///
+    /// # Errors
+    ///
+    /// If the prefetch fails, returns the error from CUDA. Prefetching to a device requires that
+    /// the device supports concurrent managed access.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # let _context = rustacuda::quick_init().unwrap();
+    /// use rustacuda::context::CurrentContext;
+    /// use rustacuda::memory::*;
+    /// use rustacuda::stream::{Stream, StreamFlags};
+    /// let stream = Stream::new(StreamFlags::NON_BLOCKING, None).unwrap();
+    /// let buffer = UnifiedBuffer::new(&0u64, 1024).unwrap();
+    /// let device = CurrentContext::get_device().unwrap();
+    /// buffer.prefetch_to(device.into(), &stream).unwrap();
+    /// // ... launch kernels using the buffer in `stream` ...
+    /// buffer.prefetch_to(PrefetchTarget::Cpu, &stream).unwrap();
+    /// stream.synchronize().unwrap();
+    /// ```
+    pub fn prefetch_to(&self, target: PrefetchTarget, stream: &Stream) -> CudaResult<()> {
+        unsafe { prefetch(self.buf, self.capacity, target, stream) }
+    }
+
     /// Creates a `UnifiedBuffer<T>` directly from the raw components of another unified buffer.
     ///
     /// # Safety
@@ -585,13 +950,185 @@ impl<T: DeviceCopy> Drop for UnifiedBuffer<T> {
             // No choice but to panic if this fails.
             unsafe {
                 let ptr = mem::replace(&mut self.buf, UnifiedPointer::null());
-                cuda_free_unified(ptr).expect("Failed to deallocate CUDA unified memory.");
+                cuda_free_unified(ptr).expect_in_drop("Failed to deallocate CUDA unified memory.");
             }
         }
         self.capacity = 0;
     }
 }
 
+/// Builder for a [`UnifiedBuffer`](struct.UnifiedBuffer.html) which applies memory usage hints
+/// when the buffer is allocated.
+///
+/// The hints are applied in the order the driver expects: the buffer is allocated and initialized
+/// on the host, then the `read_mostly`, `preferred_location` and `accessed_by` advice is applied,
+/// and finally the contents are prefetched, so that the data migrates at most once. All hints
+/// are only performance hints; the buffer remains accessible from anywhere.
+///
+/// This struct is created by [`UnifiedBuffer::builder`](struct.UnifiedBuffer.html#method.builder).
+///
+/// # Examples
+///
+/// ```
+/// # let _context = rustacuda::quick_init().unwrap();
+/// use rustacuda::context::CurrentContext;
+/// use rustacuda::memory::*;
+/// use rustacuda::stream::{Stream, StreamFlags};
+/// let stream = Stream::new(StreamFlags::NON_BLOCKING, None).unwrap();
+/// let device = CurrentContext::get_device().unwrap();
+/// let lookup_table = UnifiedBuffer::builder()
+///     .read_mostly()
+///     .preferred_location(device)
+///     .prefetch(&stream)
+///     .build(&1.0f32, 1024)
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct UnifiedBufferBuilder<'a, T: DeviceCopy> {
+    flags: MemAttachFlags,
+    advice: UnifiedAdvice,
+    prefetch: Option<&'a Stream>,
+    element: PhantomData<T>,
+}
+impl<'a, T: DeviceCopy> UnifiedBufferBuilder<'a, T> {
+    fn new() -> Self {
+        UnifiedBufferBuilder {
+            flags: MemAttachFlags::GLOBAL,
+            advice: UnifiedAdvice::default(),
+            prefetch: None,
+            element: PhantomData,
+        }
+    }
+
+    /// Attach the buffer according to `flags` rather than globally.
+    pub fn flags(mut self, flags: MemAttachFlags) -> Self {
+        self.flags = flags;
+        self
+    }
+
+    /// Advise the driver that the buffer will mostly be read and only occasionally written, so
+    /// that it can keep read-only copies on every processor that accesses it.
+    pub fn read_mostly(mut self) -> Self {
+        self.advice.read_mostly = true;
+        self
+    }
+
+    /// Advise the driver to keep the pages of the buffer in the memory of `location` where
+    /// possible.
+    pub fn preferred_location<L: Into<PrefetchTarget>>(mut self, location: L) -> Self {
+        self.advice.preferred_location = Some(location.into());
+        self
+    }
+
+    /// Advise the driver that `device` will access the buffer, so that it keeps the buffer mapped
+    /// in that device's page tables. May be called more than once.
+    pub fn accessed_by(mut self, device: Device) -> Self {
+        self.advice.accessed_by.push(device);
+        self
+    }
+
+    /// Prefetch the initialized buffer in `stream`, to the preferred location if one was given or
+    /// to the current device otherwise.
+    ///
+    /// The prefetch is asynchronous; `build` returns without waiting for it to complete.
+    pub fn prefetch(mut self, stream: &'a Stream) -> Self {
+        self.prefetch = Some(stream);
+        self
+    }
+
+    /// Allocate the buffer with room for `size` `T`'s, initialize it with clones of `value` and
+    /// apply the hints.
+    ///
+    /// # Errors
+    ///
+    /// If the allocation or any of the hints fail, returns the error from CUDA. If `size` is
+    /// large enough that `size * mem::sizeof::<T>()` overflows usize, then returns
+    /// InvalidMemoryAllocation.
+    pub fn build(self, value: &T, size: usize) -> CudaResult<UnifiedBuffer<T>>
+    where
+        T: Clone,
+    {
+        let buffer = UnifiedBuffer::new_with_flags(value, size, self.flags)?;
+        self.apply(buffer)
+    }
+
+    /// Allocate the buffer, initialize it with a clone of the data in `slice` and apply the
+    /// hints.
+    ///
+    /// # Errors
+    ///
+    /// If the allocation or any of the hints fail, returns the error from CUDA.
+    pub fn build_from_slice(self, slice: &[T]) -> CudaResult<UnifiedBuffer<T>>
+    where
+        T: Clone,
+    {
+        let mut buffer =
+            unsafe { UnifiedBuffer::uninitialized_with_flags(slice.len(), self.flags)? };
+        buffer.clone_from_slice(slice);
+        self.apply(buffer)
+    }
+
+    fn apply(self, buffer: UnifiedBuffer<T>) -> CudaResult<UnifiedBuffer<T>> {
+        unsafe {
+            apply_advice(buffer.buf, buffer.capacity, &self.advice)?;
+            if let Some(stream) = self.prefetch {
+                let target = match self.advice.preferred_location {
+                    Some(location) => location,
+                    None => CurrentContext::get_device()?.into(),
+                };
+                prefetch(buffer.buf, buffer.capacity, target, stream)?;
+            }
+        }
+        Ok(buffer)
+    }
+}
+
+fn to_unified_chunk<T>(slice: &mut [T]) -> (UnifiedPointer<T>, usize) {
+    unsafe { (UnifiedPointer::wrap(slice.as_mut_ptr()), slice.len()) }
+}
+
+/// An iterator over a [`UnifiedBuffer`](struct.UnifiedBuffer.html) in (non-overlapping) chunks
+/// (`chunk_size` elements at a time), yielding a `(UnifiedPointer<T>, len)` pair for each chunk.
+///
+/// When the buffer len is not evenly divided by the chunk size, the last chunk of the iteration
+/// will be the remainder.
+///
+/// This struct is created by the `unified_chunks` method on `UnifiedBuffer`.
+#[derive(Debug)]
+pub struct UnifiedChunks<'a, T: 'a>(ChunksMut<'a, T>);
+impl<'a, T> Iterator for UnifiedChunks<'a, T> {
+    type Item = (UnifiedPointer<T>, usize);
+
+    fn next(&mut self) -> Option<(UnifiedPointer<T>, usize)> {
+        self.0.next().map(to_unified_chunk)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+
+    fn count(self) -> usize {
+        self.0.len()
+    }
+
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        self.0.nth(n).map(to_unified_chunk)
+    }
+
+    #[inline]
+    fn last(self) -> Option<Self::Item> {
+        self.0.last().map(to_unified_chunk)
+    }
+}
+impl<'a, T> DoubleEndedIterator for UnifiedChunks<'a, T> {
+    #[inline]
+    fn next_back(&mut self) -> Option<(UnifiedPointer<T>, usize)> {
+        self.0.next_back().map(to_unified_chunk)
+    }
+}
+impl<'a, T> ExactSizeIterator for UnifiedChunks<'a, T> {}
+impl<'a, T> FusedIterator for UnifiedChunks<'a, T> {}
+
 #[cfg(test)]
 mod test_unified_box {
     use super::*;
@@ -657,12 +1194,119 @@ mod test_unified_box {
 #[cfg(test)]
 mod test_unified_buffer {
     use super::*;
+    use crate::context::CurrentContext;
+    use crate::stream::StreamFlags;
     use std::mem;
 
     #[derive(Clone, Debug)]
     struct ZeroSizedType;
     unsafe impl DeviceCopy for ZeroSizedType {}
 
+    #[test]
+    fn test_builder_hints() {
+        let _context = crate::quick_init().unwrap();
+        let stream = Stream::new(StreamFlags::NON_BLOCKING, None).unwrap();
+        let device = CurrentContext::get_device().unwrap();
+        let mut builder = UnifiedBuffer::builder().read_mostly().accessed_by(device);
+        if device.concurrent_managed_access().unwrap() {
+            builder = builder.preferred_location(device).prefetch(&stream);
+        }
+        let buffer = builder.build_from_slice(&[1u32, 2, 3, 4]).unwrap();
+        stream.synchronize().unwrap();
+        assert_eq!(&[1, 2, 3, 4], buffer.as_slice());
+    }
+
+    #[test]
+    fn test_context_advice() {
+        use cuda_driver_sys::CUmem_range_attribute;
+        use std::mem::size_of_val;
+        use std::os::raw::c_void;
+
+        let _context = crate::quick_init().unwrap();
+        let advice = UnifiedAdvice {
+            read_mostly: true,
+            ..UnifiedAdvice::default()
+        };
+        CurrentContext::set_unified_advice(advice.clone()).unwrap();
+        assert_eq!(advice, CurrentContext::get_unified_advice().unwrap());
+
+        let mut buffer = UnifiedBuffer::new(&0u32, 1024).unwrap();
+        let bytes = size_of_val(buffer.as_slice());
+        let mut read_mostly = 0i32;
+        unsafe {
+            driver_call!(cuda_driver_sys::cuMemRangeGetAttribute(
+                &mut read_mostly as *mut i32 as *mut c_void,
+                size_of_val(&read_mostly),
+                CUmem_range_attribute::CU_MEM_RANGE_ATTRIBUTE_READ_MOSTLY,
+                buffer.as_unified_ptr().as_raw_mut() as u64,
+                bytes,
+            ))
+            .to_result()
+            .unwrap();
+        }
+        assert_eq!(1, read_mostly);
+
+        CurrentContext::set_unified_advice(UnifiedAdvice::default()).unwrap();
+        assert_eq!(
+            UnifiedAdvice::default(),
+            CurrentContext::get_unified_advice().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_host_attached_buffer() {
+        let _context = crate::quick_init().unwrap();
+        let mut buffer = UnifiedBuffer::new_with_flags(&1u32, 8, MemAttachFlags::HOST).unwrap();
+        buffer[3] = 5;
+        assert_eq!(12, buffer.iter().sum::<u32>());
+    }
+
+    #[test]
+    fn test_unified_chunks() {
+        let _context = crate::quick_init().unwrap();
+        let mut buffer = UnifiedBuffer::from_slice(&[0u32, 1, 2, 3, 4, 5, 6]).unwrap();
+        let base = buffer.as_unified_ptr();
+        let chunks: Vec<_> = buffer.unified_chunks(3).collect();
+        assert_eq!(3, chunks.len());
+        for (i, &(ptr, len)) in chunks.iter().enumerate() {
+            assert_eq!(unsafe { base.add(i * 3) }, ptr);
+            assert_eq!(if i == 2 { 1 } else { 3 }, len);
+        }
+        let (ptr, len) = buffer.unified_chunks(3).next_back().unwrap();
+        assert_eq!((6, 1), (unsafe { *ptr.as_raw() }, len));
+    }
+
+    #[test]
+    fn test_split_at_unified() {
+        let _context = crate::quick_init().unwrap();
+        let mut buffer = UnifiedBuffer::from_slice(&[0u32, 1, 2, 3, 4]).unwrap();
+        let ((left, left_len), (right, right_len)) = buffer.split_at_unified(2);
+        assert_eq!((2, 3), (left_len, right_len));
+        assert_eq!(unsafe { left.add(2) }, right);
+        assert_eq!(2, unsafe { *right.as_raw() });
+    }
+
+    #[test]
+    fn test_prefetch() {
+        let _context = crate::quick_init().unwrap();
+        let stream = Stream::new(StreamFlags::NON_BLOCKING, None).unwrap();
+        let mut buffer = UnifiedBuffer::new(&1u64, 1024).unwrap();
+        let device = CurrentContext::get_device().unwrap();
+        buffer.prefetch_to(device.into(), &stream).unwrap();
+        buffer.prefetch_to(PrefetchTarget::Cpu, &stream).unwrap();
+        stream.synchronize().unwrap();
+        buffer[0] = 2;
+        assert_eq!(2, buffer[0]);
+    }
+
+    #[test]
+    fn test_prefetch_zero_length() {
+        let _context = crate::quick_init().unwrap();
+        let stream = Stream::new(StreamFlags::NON_BLOCKING, None).unwrap();
+        let buffer = UnifiedBuffer::new(&0u64, 0).unwrap();
+        buffer.prefetch_to(PrefetchTarget::Cpu, &stream).unwrap();
+    }
+
     #[test]
     fn test_new() {
         let _context = crate::quick_init().unwrap();
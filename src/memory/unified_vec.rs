@@ -0,0 +1,391 @@
+use super::DeviceCopy;
+use crate::error::*;
+use crate::memory::malloc::{cuda_free_unified, cuda_malloc_unified};
+use crate::memory::UnifiedPointer;
+use std::mem::{self, size_of};
+use std::ops::{Bound, Deref, DerefMut, RangeBounds};
+use std::ptr;
+use std::slice;
+use std::vec;
+
+/// Growable vector in unified memory.
+///
+/// A `UnifiedVec` behaves like `std::vec::Vec`, except that the elements are stored in unified
+/// memory and can therefore be handed directly to kernels through
+/// [`as_unified_ptr`](#method.as_unified_ptr). Since allocating unified memory can fail, the
+/// operations that may grow the vector return a `CudaResult`.
+///
+/// Growing the vector moves its contents to a new allocation, which invalidates any
+/// `UnifiedPointer` previously taken from it. The caller must ensure that no kernel is using the
+/// vector while it is being grown.
+///
+/// See the [`module-level documentation`](../memory/index.html) for more details on unified memory.
+#[derive(Debug)]
+pub struct UnifiedVec<T: DeviceCopy> {
+    buf: UnifiedPointer<T>,
+    len: usize,
+    capacity: usize,
+}
+// See "Thread Safety" in the `memory` module documentation.
+unsafe impl<T: DeviceCopy + Send> Send for UnifiedVec<T> {}
+unsafe impl<T: DeviceCopy + Sync> Sync for UnifiedVec<T> {}
+impl<T: DeviceCopy> UnifiedVec<T> {
+    /// Create a new, empty `UnifiedVec`. This does not allocate.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # let _context = rustacuda::quick_init().unwrap();
+    /// use rustacuda::memory::*;
+    /// let mut vec = UnifiedVec::new();
+    /// vec.push(1u64).unwrap();
+    /// ```
+    pub fn new() -> Self {
+        UnifiedVec {
+            buf: unsafe { UnifiedPointer::wrap(ptr::NonNull::dangling().as_ptr()) },
+            len: 0,
+            capacity: if size_of::<T>() == 0 { usize::MAX } else { 0 },
+        }
+    }
+
+    /// Create a new, empty `UnifiedVec` with room for at least `capacity` `T`'s.
+    ///
+    /// # Errors
+    ///
+    /// If the allocation fails, returns the error from CUDA. If `capacity` is large enough that
+    /// `capacity * mem::sizeof::<T>()` overflows usize, then returns InvalidMemoryAllocation.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # let _context = rustacuda::quick_init().unwrap();
+    /// use rustacuda::memory::*;
+    /// let vec = UnifiedVec::<u64>::with_capacity(10).unwrap();
+    /// assert!(vec.capacity() >= 10);
+    /// ```
+    pub fn with_capacity(capacity: usize) -> CudaResult<Self> {
+        let mut vec = UnifiedVec::new();
+        vec.reserve(capacity)?;
+        Ok(vec)
+    }
+
+    /// Returns the number of elements the vector can hold without reallocating.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Reserve capacity for at least `additional` more elements.
+    ///
+    /// # Errors
+    ///
+    /// If the allocation fails, returns the error from CUDA. If the new capacity would overflow,
+    /// returns InvalidMemoryAllocation.
+    pub fn reserve(&mut self, additional: usize) -> CudaResult<()> {
+        let required = self
+            .len
+            .checked_add(additional)
+            .ok_or(CudaError::InvalidMemoryAllocation)?;
+        if required <= self.capacity {
+            return Ok(());
+        }
+        let new_capacity = required.max(self.capacity * 2).max(4);
+
+        unsafe {
+            let mut new_buf = cuda_malloc_unified::<T>(new_capacity)?;
+            ptr::copy_nonoverlapping(self.buf.as_raw(), new_buf.as_raw_mut(), self.len);
+            if self.capacity > 0 {
+                let old_buf = mem::replace(&mut self.buf, new_buf);
+                self.capacity = new_capacity;
+                cuda_free_unified(old_buf)?;
+            } else {
+                self.buf = new_buf;
+                self.capacity = new_capacity;
+            }
+        }
+        Ok(())
+    }
+
+    /// Append `value` to the end of the vector, growing it if necessary.
+    ///
+    /// # Errors
+    ///
+    /// If the vector must grow and the allocation fails, returns the error from CUDA.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # let _context = rustacuda::quick_init().unwrap();
+    /// use rustacuda::memory::*;
+    /// let mut vec = UnifiedVec::new();
+    /// vec.push(1u64).unwrap();
+    /// vec.push(2u64).unwrap();
+    /// assert_eq!(&[1, 2], vec.as_slice());
+    /// ```
+    pub fn push(&mut self, value: T) -> CudaResult<()> {
+        if self.len == self.capacity {
+            self.reserve(1)?;
+        }
+        unsafe {
+            ptr::write(self.buf.as_raw_mut().add(self.len), value);
+        }
+        self.len += 1;
+        Ok(())
+    }
+
+    /// Remove the last element of the vector and return it, or `None` if the vector is empty.
+    pub fn pop(&mut self) -> Option<T> {
+        if self.len == 0 {
+            None
+        } else {
+            self.len -= 1;
+            unsafe { Some(ptr::read(self.buf.as_raw().add(self.len))) }
+        }
+    }
+
+    /// Append every element of `iter` to the vector.
+    ///
+    /// # Errors
+    ///
+    /// If the vector must grow and the allocation fails, returns the error from CUDA. The elements
+    /// appended before the failure are kept.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # let _context = rustacuda::quick_init().unwrap();
+    /// use rustacuda::memory::*;
+    /// let mut vec = UnifiedVec::new();
+    /// vec.extend((0..5u32).map(|x| x * x)).unwrap();
+    /// assert_eq!(&[0, 1, 4, 9, 16], vec.as_slice());
+    /// ```
+    pub fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) -> CudaResult<()> {
+        let iter = iter.into_iter();
+        self.reserve(iter.size_hint().0)?;
+        for value in iter {
+            self.push(value)?;
+        }
+        Ok(())
+    }
+
+    /// Remove the elements in `range` from the vector, returning them as an iterator.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is out of bounds or its start is greater than its end.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # let _context = rustacuda::quick_init().unwrap();
+    /// use rustacuda::memory::*;
+    /// let mut vec = UnifiedVec::new();
+    /// vec.extend(0..6u32).unwrap();
+    /// let drained: Vec<u32> = vec.drain(1..3).collect();
+    /// assert_eq!(vec![1, 2], drained);
+    /// assert_eq!(&[0, 3, 4, 5], vec.as_slice());
+    /// ```
+    pub fn drain<R: RangeBounds<usize>>(&mut self, range: R) -> vec::IntoIter<T> {
+        let start = match range.start_bound() {
+            Bound::Included(&n) => n,
+            Bound::Excluded(&n) => n.checked_add(1).expect("range start index out of range"),
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&n) => n.checked_add(1).expect("range end index out of range"),
+            Bound::Excluded(&n) => n,
+            Bound::Unbounded => self.len,
+        };
+        assert!(start <= end, "drain start is greater than drain end");
+        assert!(end <= self.len, "drain range is out of bounds");
+
+        unsafe {
+            let base = self.buf.as_raw_mut();
+            let drained = slice::from_raw_parts(base.add(start), end - start)
+                .iter()
+                .map(|value| ptr::read(value))
+                .collect::<Vec<T>>();
+            ptr::copy(base.add(end), base.add(start), self.len - end);
+            self.len -= end - start;
+            drained.into_iter()
+        }
+    }
+
+    /// Shorten the vector to `len` elements. Has no effect if the vector is already shorter.
+    pub fn truncate(&mut self, len: usize) {
+        self.len = self.len.min(len);
+    }
+
+    /// Remove all elements from the vector, keeping its allocation.
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    /// Extracts a slice containing the entire vector.
+    pub fn as_slice(&self) -> &[T] {
+        self
+    }
+
+    /// Extracts a mutable slice of the entire vector.
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        self
+    }
+
+    /// Returns a `UnifiedPointer<T>` to the vector's elements.
+    ///
+    /// The caller must ensure that the vector outlives the returned pointer, and that the vector is
+    /// not grown while the pointer is in use, or it will end up pointing to garbage.
+    pub fn as_unified_ptr(&mut self) -> UnifiedPointer<T> {
+        self.buf
+    }
+
+    /// Destroy a `UnifiedVec`, returning an error.
+    ///
+    /// Deallocating unified memory can return errors from previous asynchronous work. This function
+    /// destroys the given vector and returns the error and the un-destroyed vector on failure.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # let _context = rustacuda::quick_init().unwrap();
+    /// use rustacuda::memory::*;
+    /// let mut x = UnifiedVec::new();
+    /// x.push(10u32).unwrap();
+    /// match UnifiedVec::drop(x) {
+    ///     Ok(()) => println!("Successfully destroyed"),
+    ///     Err((e, vec)) => {
+    ///         println!("Failed to destroy vector: {:?}", e);
+    ///         // Do something with vec
+    ///     },
+    /// }
+    /// ```
+    pub fn drop(mut vec: UnifiedVec<T>) -> DropResult<UnifiedVec<T>> {
+        if vec.capacity == 0 || size_of::<T>() == 0 {
+            return Ok(());
+        }
+
+        let (len, capacity) = (vec.len, vec.capacity);
+        let ptr = mem::replace(&mut vec.buf, UnifiedPointer::null());
+        vec.capacity = 0;
+        unsafe {
+            match cuda_free_unified(ptr) {
+                Ok(()) => Ok(()),
+                Err(e) => Err((
+                    e,
+                    UnifiedVec {
+                        buf: ptr,
+                        len,
+                        capacity,
+                    },
+                )),
+            }
+        }
+    }
+}
+impl<T: DeviceCopy> Default for UnifiedVec<T> {
+    fn default() -> Self {
+        UnifiedVec::new()
+    }
+}
+impl<T: DeviceCopy> AsRef<[T]> for UnifiedVec<T> {
+    fn as_ref(&self) -> &[T] {
+        self
+    }
+}
+impl<T: DeviceCopy> AsMut<[T]> for UnifiedVec<T> {
+    fn as_mut(&mut self) -> &mut [T] {
+        self
+    }
+}
+impl<T: DeviceCopy> Deref for UnifiedVec<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        unsafe { slice::from_raw_parts(self.buf.as_raw(), self.len) }
+    }
+}
+impl<T: DeviceCopy> DerefMut for UnifiedVec<T> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        unsafe { slice::from_raw_parts_mut(self.buf.as_raw_mut(), self.len) }
+    }
+}
+impl<T: DeviceCopy> Drop for UnifiedVec<T> {
+    fn drop(&mut self) {
+        if self.capacity > 0 && size_of::<T>() > 0 {
+            // No choice but to panic if this fails.
+            unsafe {
+                let ptr = mem::replace(&mut self.buf, UnifiedPointer::null());
+                cuda_free_unified(ptr).expect_in_drop("Failed to deallocate CUDA unified memory.");
+            }
+        }
+        self.capacity = 0;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    struct ZeroSizedType;
+    unsafe impl DeviceCopy for ZeroSizedType {}
+
+    #[test]
+    fn test_push_pop() {
+        let _context = crate::quick_init().unwrap();
+        let mut vec = UnifiedVec::new();
+        for i in 0..100u64 {
+            vec.push(i).unwrap();
+        }
+        assert_eq!(100, vec.len());
+        assert_eq!(Some(99), vec.pop());
+        assert_eq!(4950 - 99, vec.iter().sum::<u64>());
+    }
+
+    #[test]
+    fn test_extend_and_drain() {
+        let _context = crate::quick_init().unwrap();
+        let mut vec = UnifiedVec::with_capacity(2).unwrap();
+        vec.extend(0..10u32).unwrap();
+        let drained: Vec<u32> = vec.drain(..5).collect();
+        assert_eq!(vec![0, 1, 2, 3, 4], drained);
+        assert_eq!(&[5, 6, 7, 8, 9], vec.as_slice());
+        let drained: Vec<u32> = vec.drain(3..=4).collect();
+        assert_eq!(vec![8, 9], drained);
+        assert_eq!(&[5, 6, 7], vec.as_slice());
+    }
+
+    #[test]
+    #[should_panic(expected = "range end index out of range")]
+    fn test_drain_to_max_inclusive() {
+        let _context = crate::quick_init().unwrap();
+        let mut vec = UnifiedVec::with_capacity(2).unwrap();
+        vec.extend(0..2u32).unwrap();
+        let _ = vec.drain(0..=usize::MAX);
+    }
+
+    #[test]
+    fn test_empty_does_not_allocate() {
+        let _context = crate::quick_init().unwrap();
+        let mut vec = UnifiedVec::<u64>::new();
+        assert_eq!(0, vec.capacity());
+        assert_eq!(None, vec.pop());
+        UnifiedVec::drop(vec).unwrap();
+    }
+
+    #[test]
+    fn test_zero_sized_type() {
+        let _context = crate::quick_init().unwrap();
+        let mut vec = UnifiedVec::new();
+        vec.push(ZeroSizedType).unwrap();
+        vec.push(ZeroSizedType).unwrap();
+        assert_eq!(2, vec.len());
+        assert_eq!(Some(ZeroSizedType), vec.pop());
+    }
+
+    #[test]
+    fn test_with_capacity_overflow() {
+        let _context = crate::quick_init().unwrap();
+        let err = UnifiedVec::<u64>::with_capacity(usize::MAX - 1).unwrap_err();
+        assert_eq!(CudaError::InvalidMemoryAllocation, err);
+    }
+}
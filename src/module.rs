@@ -1,20 +1,39 @@
 //! Functions and types for working with CUDA modules.
 
-use crate::error::{CudaResult, DropResult, ToResult};
+use crate::context::ContextToken;
+use crate::error::{CudaError, CudaResult, DropExpect, DropResult, ToResult};
 use crate::function::Function;
-use crate::memory::{CopyDestination, DeviceCopy, DevicePointer};
-use std::ffi::{c_void, CStr};
+use crate::memory::array::ArrayObject;
+use crate::memory::texture::{AddressMode, FilterMode, TextureFlags};
+use crate::memory::{CopyDestination, DeviceCopy, DevicePointer, DeviceSlice};
+use crate::sync::lock_ignoring_poison;
+use crate::teardown::{self, Resource};
+use cuda_driver_sys::CUcontext;
+use std::error::Error;
+use std::ffi::{c_void, CStr, CString};
 use std::fmt;
 use std::marker::PhantomData;
-use std::mem;
+use std::mem::{self, size_of};
+use std::ops::{Deref, DerefMut};
 use std::ptr;
+use std::sync::{Mutex, MutexGuard};
+#[cfg(feature = "nvrtc")]
+use {crate::context::CurrentContext, crate::jit_cache, std::path::Path};
 
 /// A compiled CUDA module, loaded into a context.
 #[derive(Debug)]
 pub struct Module {
     inner: cuda_driver_sys::CUmodule,
+    context: Option<ContextToken>,
 }
+// Modules are immutable once loaded, and can be used and unloaded from any thread.
+unsafe impl Send for Module {}
+unsafe impl Sync for Module {}
 impl Module {
+    pub(crate) fn context(&self) -> Option<ContextToken> {
+        self.context
+    }
+
     /// Load a module from the given file name into the current context.
     ///
     /// The given file should be either a cubin file, a ptx file, or a fatbin file such as
@@ -39,12 +58,14 @@ impl Module {
         unsafe {
             let mut module = Module {
                 inner: ptr::null_mut(),
+                context: ContextToken::current(),
             };
-            cuda_driver_sys::cuModuleLoad(
+            driver_call!(cuda_driver_sys::cuModuleLoad(
                 &mut module.inner as *mut cuda_driver_sys::CUmodule,
                 filename.as_ptr(),
-            )
+            ))
             .to_result()?;
+            teardown::created(Resource::Module);
             Ok(module)
         }
     }
@@ -76,21 +97,88 @@ impl Module {
         unsafe {
             let mut module = Module {
                 inner: ptr::null_mut(),
+                context: ContextToken::current(),
             };
-            cuda_driver_sys::cuModuleLoadData(
+            driver_call!(cuda_driver_sys::cuModuleLoadData(
                 &mut module.inner as *mut cuda_driver_sys::CUmodule,
                 image.as_ptr() as *const c_void,
-            )
+            ))
             .to_result()?;
+            teardown::created(Resource::Module);
             Ok(module)
         }
     }
 
+    /// Compile CUDA C++ source with NVRTC and load the resulting PTX into the current context.
+    ///
+    /// The source is compiled for the compute capability of the current context's device. `name`
+    /// is the file name used in compiler diagnostics.
+    ///
+    /// If `cache_dir` is given, the compiled PTX is stored there keyed by a hash of the source, the
+    /// NVRTC version and the compiler options, and later calls with the same source load the cached
+    /// PTX instead of invoking the compiler again. The cache is best-effort; failing to read or
+    /// write it is not an error, and an entry which fails to load is compiled again and replaced.
+    /// See the [`jit_cache`](../jit_cache/index.html) module for choosing the directory
+    /// and limiting its size.
+    ///
+    /// This function is only available with the `nvrtc` feature enabled.
+    ///
+    /// # Errors
+    ///
+    /// If compilation fails, returns `CompileError::Compilation` with the compiler's log. If
+    /// loading the module fails, returns the error from CUDA.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rustacuda::*;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # let _ctx = quick_init()?;
+    /// use rustacuda::module::Module;
+    /// use std::ffi::CString;
+    ///
+    /// let source = CString::new(r#"extern "C" __global__ void zero(float* x) { x[threadIdx.x] = 0.0f; }"#)?;
+    /// let name = CString::new("zero.cu")?;
+    /// let module = Module::load_from_nvrtc_program(&source, &name, None)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "nvrtc")]
+    pub fn load_from_nvrtc_program(
+        source: &CStr,
+        name: &CStr,
+        cache_dir: Option<&Path>,
+    ) -> Result<Module, CompileError> {
+        let capability = CurrentContext::get_device()?.compute_capability()?;
+        let arch = format!(
+            "--gpu-architecture=compute_{}{}",
+            capability.major, capability.minor
+        );
+        let (major, minor) = crate::nvrtc::version()?;
+        let key = format!("nvrtc {}.{} {}", major, minor, arch);
+
+        if let Some(ptx) = cache_dir.and_then(|dir| jit_cache::load(dir, source, &key)) {
+            // A corrupt or truncated entry fails to load; fall through to replace it.
+            if let Ok(module) = Module::load_from_string(&ptx) {
+                return Ok(module);
+            }
+        }
+
+        let arch_option = CString::new(arch.as_str()).map_err(|_| CudaError::InvalidValue)?;
+        let ptx = crate::nvrtc::compile_ptx(source, name, &[&arch_option])?;
+        if let Some(dir) = cache_dir {
+            jit_cache::store(dir, source, &key, &ptx);
+        }
+        Ok(Module::load_from_string(&ptx)?)
+    }
+
     /// Get a reference to a global symbol, which can then be copied to/from.
     ///
-    /// # Panics:
+    /// # Errors
     ///
-    /// This function panics if the size of the symbol is not the same as the `mem::sizeof<T>()`.
+    /// Returns `SymbolError::SymbolNotFound` if the module has no global with the given name, and
+    /// `InvalidValue` if the size of the symbol is not the same as `mem::size_of::<T>()`.
     ///
     /// # Examples
     ///
@@ -113,28 +201,192 @@ impl Module {
     /// # Ok(())
     /// # }
     /// ```
-    pub fn get_global<'a, T: DeviceCopy>(&'a self, name: &CStr) -> CudaResult<Symbol<'a, T>> {
+    pub fn get_global<'a, T: DeviceCopy>(
+        &'a self,
+        name: &CStr,
+    ) -> Result<Symbol<'a, T>, SymbolError> {
         unsafe {
             let mut ptr: DevicePointer<T> = DevicePointer::null();
             let mut size: usize = 0;
 
-            cuda_driver_sys::cuModuleGetGlobal_v2(
-                &mut ptr as *mut DevicePointer<T> as *mut cuda_driver_sys::CUdeviceptr,
-                &mut size as *mut usize,
-                self.inner,
-                name.as_ptr(),
-            )
-            .to_result()?;
-            assert_eq!(size, mem::size_of::<T>());
+            symbol_result(
+                name,
+                driver_call!(cuda_driver_sys::cuModuleGetGlobal_v2(
+                    &mut ptr as *mut DevicePointer<T> as *mut cuda_driver_sys::CUdeviceptr,
+                    &mut size as *mut usize,
+                    self.inner,
+                    name.as_ptr(),
+                ))
+                .to_result(),
+            )?;
+            if size != mem::size_of::<T>() {
+                return Err(CudaError::InvalidValue.into());
+            }
             Ok(Symbol {
                 ptr,
+                context: self.context,
                 module: PhantomData,
             })
         }
     }
 
+    /// Get a reference to a global array in this module, which can be used like a device slice.
+    ///
+    /// The length of the array is computed from the size of the global symbol, so the symbol must
+    /// be a whole number of `T`s. Because the array dereferences to a
+    /// [`DeviceSlice`](../memory/struct.DeviceSlice.html), lookup tables and other static data can
+    /// be read and written with the same copy functions as a `DeviceBuffer`.
+    ///
+    /// The array borrows the module mutably, so that there is only ever one mutable view of the
+    /// global at a time.
+    ///
+    /// # Errors
+    ///
+    /// Returns `SymbolError::SymbolNotFound` if the module has no global with the given name, and
+    /// `InvalidValue` if `T` is zero-sized or the size of the symbol is not a multiple of
+    /// `mem::size_of::<T>()`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rustacuda::*;
+    /// # use rustacuda::memory::CopyDestination;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # let _ctx = quick_init()?;
+    /// use rustacuda::module::Module;
+    /// use std::ffi::CString;
+    ///
+    /// let ptx = CString::new(include_str!("../resources/add.ptx"))?;
+    /// let mut module = Module::load_from_string(&ptx)?;
+    /// let name = CString::new("lookup_table")?;
+    /// let mut table = module.get_global_array::<u32>(&name)?;
+    /// assert_eq!(4, table.len());
+    /// table.copy_from(&[5, 6, 7, 8])?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_global_array<'a, T: DeviceCopy>(
+        &'a mut self,
+        name: &CStr,
+    ) -> Result<GlobalArray<'a, T>, SymbolError> {
+        unsafe {
+            let mut ptr: DevicePointer<T> = DevicePointer::null();
+            let mut size: usize = 0;
+
+            symbol_result(
+                name,
+                driver_call!(cuda_driver_sys::cuModuleGetGlobal_v2(
+                    &mut ptr as *mut DevicePointer<T> as *mut cuda_driver_sys::CUdeviceptr,
+                    &mut size as *mut usize,
+                    self.inner,
+                    name.as_ptr(),
+                ))
+                .to_result(),
+            )?;
+            let len = size
+                .checked_div(size_of::<T>())
+                .ok_or(CudaError::InvalidValue)?;
+            if len * size_of::<T>() != size {
+                return Err(CudaError::InvalidValue.into());
+            }
+            Ok(GlobalArray {
+                ptr,
+                len,
+                context: self.context,
+                module: PhantomData,
+            })
+        }
+    }
+
+    /// Get a module-scope texture reference, as used by older kernels which declare
+    /// `texture<...>` variables instead of taking texture objects as arguments.
+    ///
+    /// New code should prefer [texture objects](../memory/texture/index.html).
+    ///
+    /// # Errors
+    ///
+    /// If the module has no texture reference with the given name, returns
+    /// `SymbolError::SymbolNotFound`. If the lookup fails for any other reason, returns the CUDA
+    /// error value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rustacuda::*;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # let _ctx = quick_init()?;
+    /// use rustacuda::memory::array::{ArrayFormat, ArrayObject};
+    /// use rustacuda::memory::texture::{AddressMode, FilterMode};
+    /// use rustacuda::module::Module;
+    /// use std::ffi::CString;
+    ///
+    /// let ptx = CString::new(include_str!("../resources/texref.ptx"))?;
+    /// let module = Module::load_from_string(&ptx)?;
+    /// let array = ArrayObject::new_2d([64, 64], ArrayFormat::Float, 1)?;
+    /// let mut texref = module.get_texref(&CString::new("legacy_texture")?)?;
+    /// texref.set_array(&array)?;
+    /// texref.set_address_mode(0, AddressMode::Wrap)?;
+    /// texref.set_filter_mode(FilterMode::Linear)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_texref<'a>(&'a self, name: &CStr) -> Result<TextureReference<'a>, SymbolError> {
+        let mut inner: cuda_driver_sys::CUtexref = ptr::null_mut();
+        unsafe {
+            symbol_result(
+                name,
+                driver_call!(cuda_driver_sys::cuModuleGetTexRef(
+                    &mut inner,
+                    self.inner,
+                    name.as_ptr(),
+                ))
+                .to_result(),
+            )?;
+        }
+        Ok(TextureReference {
+            inner,
+            module: PhantomData,
+        })
+    }
+
+    /// Get a module-scope surface reference, as used by older kernels which declare
+    /// `surface<...>` variables instead of taking surface objects as arguments.
+    ///
+    /// # Errors
+    ///
+    /// If the module has no surface reference with the given name, returns
+    /// `SymbolError::SymbolNotFound`. If the lookup fails for any other reason, returns the CUDA
+    /// error value.
+    pub fn get_surfref<'a>(&'a self, name: &CStr) -> Result<SurfaceReference<'a>, SymbolError> {
+        let mut inner: cuda_driver_sys::CUsurfref = ptr::null_mut();
+        unsafe {
+            symbol_result(
+                name,
+                driver_call!(cuda_driver_sys::cuModuleGetSurfRef(
+                    &mut inner,
+                    self.inner,
+                    name.as_ptr(),
+                ))
+                .to_result(),
+            )?;
+        }
+        Ok(SurfaceReference {
+            inner,
+            module: PhantomData,
+        })
+    }
+
     /// Get a reference to a kernel function which can then be launched.
     ///
+    /// # Errors
+    ///
+    /// If the module has no function with the given name, returns `SymbolError::SymbolNotFound`,
+    /// which names the missing function. Use [`try_get_function`](#method.try_get_function) if
+    /// the function is optional. If the lookup fails for any other reason, returns the CUDA error
+    /// value.
+    ///
     /// # Examples
     ///
     /// ```
@@ -152,20 +404,58 @@ impl Module {
     /// # Ok(())
     /// # }
     /// ```
-    pub fn get_function<'a>(&'a self, name: &CStr) -> CudaResult<Function<'a>> {
+    pub fn get_function<'a>(&'a self, name: &CStr) -> Result<Function<'a>, SymbolError> {
         unsafe {
             let mut func: cuda_driver_sys::CUfunction = ptr::null_mut();
 
-            cuda_driver_sys::cuModuleGetFunction(
-                &mut func as *mut cuda_driver_sys::CUfunction,
-                self.inner,
-                name.as_ptr(),
-            )
-            .to_result()?;
+            symbol_result(
+                name,
+                driver_call!(cuda_driver_sys::cuModuleGetFunction(
+                    &mut func as *mut cuda_driver_sys::CUfunction,
+                    self.inner,
+                    name.as_ptr(),
+                ))
+                .to_result(),
+            )?;
             Ok(Function::new(func, self))
         }
     }
 
+    /// Get a reference to a kernel function in this module, or `None` if the module has no
+    /// function with the given name.
+    ///
+    /// This is useful for optional kernels, such as specializations which only some builds of a
+    /// module contain.
+    ///
+    /// # Errors
+    ///
+    /// If the lookup fails for any other reason, returns the CUDA error value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rustacuda::*;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # let _ctx = quick_init()?;
+    /// use rustacuda::module::Module;
+    /// use std::ffi::CString;
+    ///
+    /// let ptx = CString::new(include_str!("../resources/add.ptx"))?;
+    /// let module = Module::load_from_string(&ptx)?;
+    /// assert!(module.try_get_function(&CString::new("sum")?)?.is_some());
+    /// assert!(module.try_get_function(&CString::new("smu")?)?.is_none());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn try_get_function<'a>(&'a self, name: &CStr) -> CudaResult<Option<Function<'a>>> {
+        match self.get_function(name) {
+            Ok(function) => Ok(Some(function)),
+            Err(SymbolError::SymbolNotFound { .. }) => Ok(None),
+            Err(SymbolError::Cuda(e)) => Err(e),
+        }
+    }
+
     /// Destroy a `Module`, returning an error.
     ///
     /// Destroying a module can return errors from previous asynchronous work. This function
@@ -200,12 +490,19 @@ impl Module {
 
         unsafe {
             let inner = mem::replace(&mut module.inner, ptr::null_mut());
-            match cuda_driver_sys::cuModuleUnload(inner).to_result() {
+            match driver_call!(cuda_driver_sys::cuModuleUnload(inner)).to_result() {
                 Ok(()) => {
+                    teardown::destroyed(Resource::Module);
                     mem::forget(module);
                     Ok(())
                 }
-                Err(e) => Err((e, Module { inner })),
+                Err(e) => Err((
+                    e,
+                    Module {
+                        inner,
+                        context: module.context,
+                    },
+                )),
             }
         }
     }
@@ -218,17 +515,156 @@ impl Drop for Module {
         unsafe {
             // No choice but to panic if this fails...
             let module = mem::replace(&mut self.inner, ptr::null_mut());
-            cuda_driver_sys::cuModuleUnload(module)
+            driver_call!(cuda_driver_sys::cuModuleUnload(module))
                 .to_result()
-                .expect("Failed to unload CUDA module");
+                .expect_in_drop("Failed to unload CUDA module");
+            teardown::destroyed(Resource::Module);
+        }
+    }
+}
+
+/// The modules embedded in RustaCUDA which have been loaded, as (context, PTX, module) triples.
+/// Each is loaded into a context the first time it is needed there, and stays loaded until the
+/// context is destroyed.
+static BUILTIN_MODULES: Mutex<Vec<(usize, usize, Module)>> = Mutex::new(Vec::new());
+
+fn builtin_modules() -> MutexGuard<'static, Vec<(usize, usize, Module)>> {
+    lock_ignoring_poison(&BUILTIN_MODULES)
+}
+
+/// Calls `f` with the kernel `name` from `ptx`, one of the modules embedded in RustaCUDA, loading
+/// the module into the current context if it hasn't been already.
+///
+/// Returns an error if the module can't be loaded or doesn't contain `name`; errors from `f` are
+/// returned inside the `Ok`.
+pub(crate) fn with_builtin_function<R>(
+    ptx: &'static str,
+    name: &str,
+    f: impl FnOnce(&Function<'_>) -> R,
+) -> CudaResult<R> {
+    let ctx = crate::context::current_handle()? as usize;
+    let mut modules = builtin_modules();
+    let index = match modules
+        .iter()
+        .position(|(handle, image, _)| *handle == ctx && *image == ptx.as_ptr() as usize)
+    {
+        Some(index) => index,
+        None => {
+            let image = CString::new(ptx).map_err(|_| CudaError::InvalidPtx)?;
+            let module = Module::load_from_string(&image)?;
+            // Built-in modules are unloaded along with their context rather than dropped, so
+            // they aren't counted as live resources.
+            teardown::destroyed(Resource::Module);
+            modules.push((ctx, ptx.as_ptr() as usize, module));
+            modules.len() - 1
+        }
+    };
+    let name = CString::new(name).map_err(|_| CudaError::InvalidValue)?;
+    let function = modules[index].2.get_function(&name)?;
+    Ok(f(&function))
+}
+
+/// Forgets the built-in modules loaded into `ctx`, which has just been destroyed along with them.
+pub(crate) fn forget_builtin_modules(ctx: CUcontext) {
+    let mut modules = builtin_modules();
+    let mut index = 0;
+    while index < modules.len() {
+        if modules[index].0 == ctx as usize {
+            mem::forget(modules.swap_remove(index).2);
+        } else {
+            index += 1;
         }
     }
 }
 
+/// The error returned when a function, global, texture reference or surface reference can't be
+/// looked up in a [`Module`](struct.Module.html).
+///
+/// It converts into the `CudaError` it stands for, so it can be propagated with `?` from functions
+/// returning a `CudaResult`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SymbolError {
+    /// The module has no symbol of the requested kind with this name.
+    SymbolNotFound {
+        /// The name which was looked up.
+        name: CString,
+    },
+    /// The lookup failed for another reason.
+    Cuda(CudaError),
+}
+impl From<CudaError> for SymbolError {
+    fn from(e: CudaError) -> Self {
+        SymbolError::Cuda(e)
+    }
+}
+impl From<SymbolError> for CudaError {
+    fn from(e: SymbolError) -> Self {
+        match e {
+            SymbolError::SymbolNotFound { .. } => CudaError::SymbolNotFound,
+            SymbolError::Cuda(e) => e,
+        }
+    }
+}
+impl fmt::Display for SymbolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SymbolError::SymbolNotFound { name } => {
+                write!(f, "The module has no symbol named {:?}", name)
+            }
+            SymbolError::Cuda(e) => e.fmt(f),
+        }
+    }
+}
+impl Error for SymbolError {}
+
+/// The error returned when compiling a module with
+/// [`Module::load_from_nvrtc_program`](struct.Module.html#method.load_from_nvrtc_program) fails.
+///
+/// It converts into the `CudaError` it stands for, so it can be propagated with `?` from functions
+/// returning a `CudaResult`.
+#[cfg(feature = "nvrtc")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CompileError {
+    /// NVRTC rejected the source.
+    Compilation {
+        /// The compiler's log, with the errors and warnings it reported.
+        log: String,
+    },
+    /// Compiling or loading the module failed for another reason.
+    Cuda(CudaError),
+}
+#[cfg(feature = "nvrtc")]
+impl From<CudaError> for CompileError {
+    fn from(e: CudaError) -> Self {
+        CompileError::Cuda(e)
+    }
+}
+#[cfg(feature = "nvrtc")]
+impl From<CompileError> for CudaError {
+    fn from(e: CompileError) -> Self {
+        match e {
+            CompileError::Compilation { .. } => CudaError::InvalidSouce,
+            CompileError::Cuda(e) => e,
+        }
+    }
+}
+#[cfg(feature = "nvrtc")]
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CompileError::Compilation { log } => write!(f, "Compilation failed:\n{}", log),
+            CompileError::Cuda(e) => e.fmt(f),
+        }
+    }
+}
+#[cfg(feature = "nvrtc")]
+impl Error for CompileError {}
+
 /// Handle to a symbol defined within a CUDA module.
 #[derive(Debug)]
 pub struct Symbol<'a, T: DeviceCopy> {
     ptr: DevicePointer<T>,
+    context: Option<ContextToken>,
     module: PhantomData<&'a Module>,
 }
 impl<'a, T: DeviceCopy> crate::private::Sealed for Symbol<'a, T> {}
@@ -239,14 +675,15 @@ impl<'a, T: DeviceCopy> fmt::Pointer for Symbol<'a, T> {
 }
 impl<'a, T: DeviceCopy> CopyDestination<T> for Symbol<'a, T> {
     fn copy_from(&mut self, val: &T) -> CudaResult<()> {
+        ContextToken::check(self.context)?;
         let size = mem::size_of::<T>();
         if size != 0 {
             unsafe {
-                cuda_driver_sys::cuMemcpyHtoD_v2(
+                driver_call!(cuda_driver_sys::cuMemcpyHtoD_v2(
                     self.ptr.as_raw_mut() as u64,
                     val as *const T as *const c_void,
                     size,
-                )
+                ))
                 .to_result()?
             }
         }
@@ -254,14 +691,15 @@ impl<'a, T: DeviceCopy> CopyDestination<T> for Symbol<'a, T> {
     }
 
     fn copy_to(&self, val: &mut T) -> CudaResult<()> {
+        ContextToken::check(self.context)?;
         let size = mem::size_of::<T>();
         if size != 0 {
             unsafe {
-                cuda_driver_sys::cuMemcpyDtoH_v2(
+                driver_call!(cuda_driver_sys::cuMemcpyDtoH_v2(
                     val as *const T as *mut c_void,
                     self.ptr.as_raw() as u64,
                     size,
-                )
+                ))
                 .to_result()?
             }
         }
@@ -269,6 +707,184 @@ impl<'a, T: DeviceCopy> CopyDestination<T> for Symbol<'a, T> {
     }
 }
 
+/// Handle to an array defined within a CUDA module.
+///
+/// This dereferences to a [`DeviceSlice`](../memory/struct.DeviceSlice.html) covering the whole
+/// array. Copies to and from the whole array check that the module's context is still alive,
+/// like those of a [`Symbol`](struct.Symbol.html).
+#[derive(Debug)]
+pub struct GlobalArray<'a, T: DeviceCopy> {
+    ptr: DevicePointer<T>,
+    len: usize,
+    context: Option<ContextToken>,
+    module: PhantomData<&'a mut Module>,
+}
+impl<'a, T: DeviceCopy> crate::private::Sealed for GlobalArray<'a, T> {}
+impl<'a, T: DeviceCopy, I: AsRef<[T]> + AsMut<[T]> + ?Sized> CopyDestination<I>
+    for GlobalArray<'a, T>
+{
+    fn copy_from(&mut self, val: &I) -> CudaResult<()> {
+        ContextToken::check(self.context)?;
+        (**self).copy_from(val)
+    }
+
+    fn copy_to(&self, val: &mut I) -> CudaResult<()> {
+        ContextToken::check(self.context)?;
+        (**self).copy_to(val)
+    }
+}
+impl<'a, T: DeviceCopy> Deref for GlobalArray<'a, T> {
+    type Target = DeviceSlice<T>;
+
+    fn deref(&self) -> &DeviceSlice<T> {
+        unsafe { DeviceSlice::from_raw_parts(self.ptr, self.len) }
+    }
+}
+impl<'a, T: DeviceCopy> DerefMut for GlobalArray<'a, T> {
+    fn deref_mut(&mut self) -> &mut DeviceSlice<T> {
+        unsafe { DeviceSlice::from_raw_parts_mut(self.ptr, self.len) }
+    }
+}
+
+/// Handle to a texture reference defined within a CUDA module.
+///
+/// The settings made through this handle apply to every kernel in the module which reads from
+/// the texture reference. A bound array must stay alive as long as kernels may read from it.
+#[derive(Debug)]
+pub struct TextureReference<'a> {
+    inner: cuda_driver_sys::CUtexref,
+    module: PhantomData<&'a Module>,
+}
+impl<'a> TextureReference<'a> {
+    /// Bind `array` to the texture reference. The texture reference takes its format and number
+    /// of channels from the array.
+    ///
+    /// # Errors
+    ///
+    /// If the array cannot be bound, returns the CUDA error value.
+    pub fn set_array(&mut self, array: &ArrayObject) -> CudaResult<()> {
+        unsafe {
+            driver_call!(cuda_driver_sys::cuTexRefSetArray(
+                self.inner,
+                array.as_raw(),
+                cuda_driver_sys::CU_TRSA_OVERRIDE_FORMAT
+            ))
+            .to_result()
+        }
+    }
+
+    /// Set the address mode of the texture reference in dimension `dim`, where 0 is the x
+    /// dimension, 1 is y and 2 is z.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidValue` if `dim` is greater than 2. Otherwise, if the address mode cannot be
+    /// set, returns the CUDA error value.
+    pub fn set_address_mode(&mut self, dim: u32, mode: AddressMode) -> CudaResult<()> {
+        if dim > 2 {
+            return Err(CudaError::InvalidValue);
+        }
+        unsafe {
+            driver_call!(cuda_driver_sys::cuTexRefSetAddressMode(
+                self.inner,
+                dim as i32,
+                mode.to_raw()
+            ))
+            .to_result()
+        }
+    }
+
+    /// Set the filter mode of the texture reference.
+    ///
+    /// # Errors
+    ///
+    /// If the filter mode cannot be set, returns the CUDA error value.
+    pub fn set_filter_mode(&mut self, mode: FilterMode) -> CudaResult<()> {
+        unsafe {
+            driver_call!(cuda_driver_sys::cuTexRefSetFilterMode(
+                self.inner,
+                mode.to_raw()
+            ))
+            .to_result()
+        }
+    }
+
+    /// Set the flags of the texture reference, such as normalized coordinates.
+    ///
+    /// # Errors
+    ///
+    /// If the flags cannot be set, returns the CUDA error value.
+    pub fn set_flags(&mut self, flags: TextureFlags) -> CudaResult<()> {
+        unsafe {
+            driver_call!(cuda_driver_sys::cuTexRefSetFlags(self.inner, flags.bits())).to_result()
+        }
+    }
+
+    /// Set the color returned for reads outside the texture when using `AddressMode::Border`.
+    ///
+    /// # Errors
+    ///
+    /// If the border color cannot be set, returns the CUDA error value.
+    pub fn set_border_color(&mut self, mut color: [f32; 4]) -> CudaResult<()> {
+        unsafe {
+            driver_call!(cuda_driver_sys::cuTexRefSetBorderColor(
+                self.inner,
+                color.as_mut_ptr()
+            ))
+            .to_result()
+        }
+    }
+
+    /// Returns the raw texture reference handle, for use with driver API functions that
+    /// RustaCUDA does not wrap.
+    pub fn as_raw(&self) -> cuda_driver_sys::CUtexref {
+        self.inner
+    }
+}
+
+/// Handle to a surface reference defined within a CUDA module.
+///
+/// A bound array must stay alive as long as kernels may access it.
+#[derive(Debug)]
+pub struct SurfaceReference<'a> {
+    inner: cuda_driver_sys::CUsurfref,
+    module: PhantomData<&'a Module>,
+}
+impl<'a> SurfaceReference<'a> {
+    /// Bind `array` to the surface reference.
+    ///
+    /// # Errors
+    ///
+    /// If the array cannot be bound, returns the CUDA error value. In particular, the array must
+    /// have been created with `ArrayObjectFlags::SURFACE_LDST`.
+    pub fn set_array(&mut self, array: &ArrayObject) -> CudaResult<()> {
+        unsafe {
+            driver_call!(cuda_driver_sys::cuSurfRefSetArray(
+                self.inner,
+                array.as_raw(),
+                0
+            ))
+            .to_result()
+        }
+    }
+
+    /// Returns the raw surface reference handle, for use with driver API functions that
+    /// RustaCUDA does not wrap.
+    pub fn as_raw(&self) -> cuda_driver_sys::CUsurfref {
+        self.inner
+    }
+}
+
+// Replace the driver's generic NotFound for a missing symbol with an error naming it.
+fn symbol_result(name: &CStr, result: CudaResult<()>) -> Result<(), SymbolError> {
+    match result {
+        Err(CudaError::NotFound) => Err(SymbolError::SymbolNotFound {
+            name: name.to_owned(),
+        }),
+        result => result.map_err(SymbolError::Cuda),
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -286,6 +902,66 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn test_symbol_not_found() -> Result<(), Box<dyn Error>> {
+        let _context = quick_init();
+
+        let ptx = CString::new(include_str!("../resources/add.ptx"))?;
+        let module = Module::load_from_string(&ptx)?;
+        let missing = CString::new("smu")?;
+        let not_found = SymbolError::SymbolNotFound {
+            name: missing.clone(),
+        };
+        assert_eq!(
+            Some(&not_found),
+            module.get_function(&missing).err().as_ref()
+        );
+        assert_eq!(Some(not_found), module.get_global::<u32>(&missing).err());
+        assert!(module.try_get_function(&missing)?.is_none());
+        assert!(module.try_get_function(&CString::new("sum")?)?.is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn test_texture_and_surface_references() -> Result<(), Box<dyn Error>> {
+        use crate::memory::array::{ArrayDescriptor, ArrayFormat, ArrayObjectFlags};
+
+        let _context = quick_init();
+        let ptx = CString::new(include_str!("../resources/texref.ptx"))?;
+        let module = Module::load_from_string(&ptx)?;
+
+        let array = ArrayObject::new_2d([16, 16], ArrayFormat::Float, 1)?;
+        let mut texref = module.get_texref(&CString::new("legacy_texture")?)?;
+        texref.set_array(&array)?;
+        texref.set_address_mode(1, AddressMode::Border)?;
+        assert_eq!(
+            Err(CudaError::InvalidValue),
+            texref.set_address_mode(3, AddressMode::Wrap)
+        );
+        texref.set_filter_mode(FilterMode::Point)?;
+        texref.set_flags(TextureFlags::NORMALIZED_COORDINATES)?;
+        texref.set_border_color([0.0; 4])?;
+
+        let descriptor = ArrayDescriptor::new(
+            [16, 16, 0],
+            ArrayFormat::Float,
+            1,
+            ArrayObjectFlags::SURFACE_LDST,
+        );
+        let surface_array = ArrayObject::from_descriptor(&descriptor)?;
+        let mut surfref = module.get_surfref(&CString::new("legacy_surface")?)?;
+        surfref.set_array(&surface_array)?;
+
+        let missing = CString::new("missing")?;
+        assert_eq!(
+            Some(SymbolError::SymbolNotFound {
+                name: missing.clone()
+            }),
+            module.get_texref(&missing).err()
+        );
+        Ok(())
+    }
+
     #[test]
     fn test_load_from_memory() -> Result<(), Box<dyn Error>> {
         let _context = quick_init();
@@ -328,4 +1004,100 @@ mod test {
         assert_eq!(100, constant_copy);
         Ok(())
     }
+
+    #[test]
+    fn test_global_array() -> Result<(), Box<dyn Error>> {
+        let _context = quick_init()?;
+
+        let ptx = CString::new(include_str!("../resources/add.ptx"))?;
+        let mut module = Module::load_from_string(&ptx)?;
+
+        let table_name = CString::new("lookup_table")?;
+        let mut table = module.get_global_array::<u32>(&table_name)?;
+        let mut host = [0u32; 4];
+        table.copy_to(&mut host[..])?;
+        assert_eq!([1, 2, 3, 4], host);
+
+        table[1..3].copy_from(&[20, 30][..])?;
+        table.copy_to(&mut host[..])?;
+        assert_eq!([1, 20, 30, 4], host);
+
+        // 16 bytes can't be viewed as an array of 12-byte elements.
+        assert_eq!(
+            Some(SymbolError::Cuda(CudaError::InvalidValue)),
+            module.get_global_array::<[u32; 3]>(&table_name).err()
+        );
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    fn test_symbol_detects_destroyed_context() -> Result<(), Box<dyn Error>> {
+        let context = quick_init()?;
+
+        let ptx = CString::new(include_str!("../resources/add.ptx"))?;
+        let module = Module::load_from_string(&ptx)?;
+        let constant_name = CString::new("my_constant")?;
+        let symbol = module.get_global::<u32>(&constant_name)?;
+
+        drop(context);
+        let mut constant_copy = 0u32;
+        assert_eq!(
+            Err(CudaError::ContextIsDestroyed),
+            symbol.copy_to(&mut constant_copy)
+        );
+        // The module can't be unloaded from a destroyed context.
+        mem::forget(module);
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    fn test_global_array_detects_destroyed_context() -> Result<(), Box<dyn Error>> {
+        let context = quick_init()?;
+
+        let ptx = CString::new(include_str!("../resources/add.ptx"))?;
+        let mut module = Module::load_from_string(&ptx)?;
+        let table_name = CString::new("lookup_table")?;
+        let table = module.get_global_array::<u32>(&table_name)?;
+
+        drop(context);
+        let mut host = [0u32; 4];
+        assert_eq!(
+            Err(CudaError::ContextIsDestroyed),
+            table.copy_to(&mut host[..])
+        );
+        // The module can't be unloaded from a destroyed context.
+        mem::forget(module);
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "nvrtc")]
+    fn test_load_from_nvrtc_program() -> Result<(), Box<dyn Error>> {
+        let _context = quick_init();
+
+        let source = CString::new(
+            r#"extern "C" __global__ void scale(float* x, float a) { x[threadIdx.x] *= a; }"#,
+        )?;
+        let name = CString::new("scale.cu")?;
+        let module = Module::load_from_nvrtc_program(&source, &name, None)?;
+        let function_name = CString::new("scale")?;
+        let _function = module.get_function(&function_name)?;
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "nvrtc")]
+    fn test_nvrtc_compilation_log() -> Result<(), Box<dyn Error>> {
+        let _context = quick_init();
+
+        let source = CString::new(r#"extern "C" __global__ void broken() { undeclared = 1; }"#)?;
+        let name = CString::new("broken.cu")?;
+        match Module::load_from_nvrtc_program(&source, &name, None) {
+            Err(CompileError::Compilation { log }) => assert!(log.contains("undeclared")),
+            other => panic!("expected a compilation error, got {:?}", other.map(|_| ())),
+        }
+        Ok(())
+    }
 }
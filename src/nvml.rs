@@ -0,0 +1,307 @@
+//! Device telemetry from NVML, the NVIDIA Management Library.
+//!
+//! This module is only available with the `nvml` feature enabled.
+//!
+//! NVML reports what `nvidia-smi` shows: utilization, memory usage, temperature, power draw and
+//! the reasons clocks are being throttled. It numbers devices differently from CUDA, which
+//! respects `CUDA_VISIBLE_DEVICES` and may order devices differently, so matching the two by index
+//! silently picks the wrong GPU. [`NvmlDevice::from_device`](struct.NvmlDevice.html#method.from_device)
+//! matches them by UUID instead.
+//!
+//! RustaCUDA doesn't link against NVML. On Linux, `libnvidia-ml.so.1`, which is installed with the
+//! driver, is loaded and initialized the first time it is needed, and is never unloaded. If it
+//! can't be loaded, or on other platforms, every function returns `NotSupported`.
+//!
+//! NVML errors are reported as the closest `CudaError`: queries the device doesn't support return
+//! `NotSupported` and queries which need more privileges return `NotPermitted`.
+//!
+//! # Examples
+//!
+//! ```
+//! # use rustacuda::*;
+//! # use std::error::Error;
+//! # fn main() -> Result<(), Box<dyn Error>> {
+//! # init(CudaFlags::empty())?;
+//! use rustacuda::device::Device;
+//! use rustacuda::nvml::NvmlDevice;
+//!
+//! let device = Device::get_device(0)?;
+//! if let Ok(nvml) = NvmlDevice::from_device(device) {
+//!     let memory = nvml.memory_info()?;
+//!     println!("{} of {} bytes used", memory.used, memory.total);
+//!     println!("{}°C, {} mW", nvml.temperature()?, nvml.power_usage()?);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::device::Device;
+use crate::error::{CudaError, CudaResult};
+use std::ffi::CString;
+use std::os::raw::{c_char, c_void};
+use std::sync::OnceLock;
+
+bitflags! {
+    /// The reasons the clocks of a device are currently being held below their maximum, as
+    /// returned by [`NvmlDevice::throttle_reasons`](struct.NvmlDevice.html#method.throttle_reasons).
+    pub struct ThrottleReasons: u64 {
+        /// Nothing is running on the device, so its clocks are lowered to save power.
+        const GPU_IDLE = 0x1;
+        /// The clocks are limited by the application clocks setting.
+        const APPLICATIONS_CLOCKS_SETTING = 0x2;
+        /// The software power management algorithm is holding the power draw below the power
+        /// limit.
+        const SW_POWER_CAP = 0x4;
+        /// The hardware is slowing the clocks, because of high temperature, an external power
+        /// brake or a power supply problem.
+        const HW_SLOWDOWN = 0x8;
+        /// The clocks are matched to the other devices in a sync boost group.
+        const SYNC_BOOST = 0x10;
+        /// The software thermal management algorithm is slowing the clocks to hold the
+        /// temperature below its limit.
+        const SW_THERMAL_SLOWDOWN = 0x20;
+        /// The hardware is slowing the clocks because the device is too hot.
+        const HW_THERMAL_SLOWDOWN = 0x40;
+        /// The hardware is slowing the clocks because of an external power brake signal.
+        const HW_POWER_BRAKE_SLOWDOWN = 0x80;
+        /// The clocks are limited by the display clock setting.
+        const DISPLAY_CLOCK_SETTING = 0x100;
+    }
+}
+
+/// Percentage of time over the last sample period that the device was busy.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Utilization {
+    /// Percentage of time one or more kernels were running
+    pub gpu: u32,
+    /// Percentage of time device memory was being read or written
+    pub memory: u32,
+}
+
+/// Device memory usage, in bytes, across all processes using the device.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MemoryInfo {
+    /// Total installed memory
+    pub total: u64,
+    /// Memory not allocated by any process
+    pub free: u64,
+    /// Memory allocated by processes or reserved by the driver
+    pub used: u64,
+}
+
+/// NVML handle for a CUDA device, for querying its telemetry.
+///
+/// See the [module-level documentation](index.html) for more details.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NvmlDevice {
+    handle: NvmlDeviceHandle,
+    device: Device,
+}
+// NVML is thread-safe, and device handles are valid until NVML is shut down, which RustaCUDA
+// never does.
+unsafe impl Send for NvmlDevice {}
+unsafe impl Sync for NvmlDevice {}
+impl NvmlDevice {
+    /// Returns the NVML handle for `device`, found by its UUID. For a MIG instance, this is the
+    /// handle of the instance, for which NVML doesn't support some queries, such as utilization.
+    ///
+    /// # Errors
+    ///
+    /// Returns `NotSupported` if NVML can't be loaded, and `NotFound` if NVML doesn't know the
+    /// device.
+    pub fn from_device(device: Device) -> CudaResult<Self> {
+        let library = library()?;
+        let uuid = match device.mig_uuid() {
+            Ok(Some(uuid)) => uuid,
+            Ok(None) | Err(CudaError::NotSupported) => device.uuid()?,
+            Err(e) => return Err(e),
+        };
+        let uuid = CString::new(uuid.to_string()).expect("UUIDs don't contain nul bytes");
+        let mut handle: NvmlDeviceHandle = std::ptr::null_mut();
+        unsafe { to_result((library.get_handle_by_uuid)(uuid.as_ptr(), &mut handle))? };
+        Ok(NvmlDevice { handle, device })
+    }
+
+    /// Returns the CUDA device this handle belongs to.
+    pub fn device(&self) -> Device {
+        self.device
+    }
+
+    /// Returns how busy the device was over the last sample period, which is between 1/6 second
+    /// and 1 second depending on the device.
+    pub fn utilization(&self) -> CudaResult<Utilization> {
+        let mut utilization = Utilization { gpu: 0, memory: 0 };
+        unsafe {
+            to_result((library()?.get_utilization_rates)(
+                self.handle,
+                &mut utilization,
+            ))?
+        };
+        Ok(utilization)
+    }
+
+    /// Returns the memory usage of the device, including memory allocated by other processes.
+    pub fn memory_info(&self) -> CudaResult<MemoryInfo> {
+        let mut memory = MemoryInfo {
+            total: 0,
+            free: 0,
+            used: 0,
+        };
+        unsafe { to_result((library()?.get_memory_info)(self.handle, &mut memory))? };
+        Ok(memory)
+    }
+
+    /// Returns the temperature of the GPU die, in degrees Celsius.
+    pub fn temperature(&self) -> CudaResult<u32> {
+        let mut temperature = 0u32;
+        unsafe {
+            to_result((library()?.get_temperature)(
+                self.handle,
+                NVML_TEMPERATURE_GPU,
+                &mut temperature,
+            ))?
+        };
+        Ok(temperature)
+    }
+
+    /// Returns the power draw of the whole board, in milliwatts.
+    pub fn power_usage(&self) -> CudaResult<u32> {
+        let mut power = 0u32;
+        unsafe { to_result((library()?.get_power_usage)(self.handle, &mut power))? };
+        Ok(power)
+    }
+
+    /// Returns the reasons the clocks of the device are currently being throttled, or an empty
+    /// set if they are not.
+    pub fn throttle_reasons(&self) -> CudaResult<ThrottleReasons> {
+        let mut reasons = 0u64;
+        unsafe { to_result((library()?.get_throttle_reasons)(self.handle, &mut reasons))? };
+        Ok(ThrottleReasons::from_bits_truncate(reasons))
+    }
+}
+
+type NvmlDeviceHandle = *mut c_void;
+type NvmlReturn = i32;
+
+const NVML_TEMPERATURE_GPU: u32 = 0;
+
+type InitFn = unsafe extern "C" fn() -> NvmlReturn;
+type GetHandleByUuidFn = unsafe extern "C" fn(*const c_char, *mut NvmlDeviceHandle) -> NvmlReturn;
+type GetUtilizationRatesFn = unsafe extern "C" fn(NvmlDeviceHandle, *mut Utilization) -> NvmlReturn;
+type GetMemoryInfoFn = unsafe extern "C" fn(NvmlDeviceHandle, *mut MemoryInfo) -> NvmlReturn;
+type GetTemperatureFn = unsafe extern "C" fn(NvmlDeviceHandle, u32, *mut u32) -> NvmlReturn;
+type GetPowerUsageFn = unsafe extern "C" fn(NvmlDeviceHandle, *mut u32) -> NvmlReturn;
+type GetThrottleReasonsFn = unsafe extern "C" fn(NvmlDeviceHandle, *mut u64) -> NvmlReturn;
+
+/// The NVML functions RustaCUDA uses, from a library which has been initialized.
+struct Library {
+    get_handle_by_uuid: GetHandleByUuidFn,
+    get_utilization_rates: GetUtilizationRatesFn,
+    get_memory_info: GetMemoryInfoFn,
+    get_temperature: GetTemperatureFn,
+    get_power_usage: GetPowerUsageFn,
+    get_throttle_reasons: GetThrottleReasonsFn,
+}
+
+fn library() -> CudaResult<&'static Library> {
+    static LIBRARY: OnceLock<CudaResult<Library>> = OnceLock::new();
+    LIBRARY.get_or_init(load).as_ref().map_err(|e| *e)
+}
+
+#[cfg(target_os = "linux")]
+fn load() -> CudaResult<Library> {
+    unsafe {
+        let library = libc::dlopen(
+            b"libnvidia-ml.so.1\0".as_ptr() as *const c_char,
+            libc::RTLD_LAZY | libc::RTLD_LOCAL,
+        );
+        if library.is_null() {
+            return Err(CudaError::NotSupported);
+        }
+        // The library is deliberately never closed, so the symbols stay valid.
+        let symbol = |name: &[u8]| {
+            let symbol = libc::dlsym(library, name.as_ptr() as *const c_char);
+            if symbol.is_null() {
+                Err(CudaError::NotSupported)
+            } else {
+                Ok(symbol)
+            }
+        };
+        let init = std::mem::transmute::<*mut c_void, InitFn>(symbol(b"nvmlInit_v2\0")?);
+        let library = Library {
+            get_handle_by_uuid: std::mem::transmute::<*mut c_void, GetHandleByUuidFn>(symbol(
+                b"nvmlDeviceGetHandleByUUID\0",
+            )?),
+            get_utilization_rates: std::mem::transmute::<*mut c_void, GetUtilizationRatesFn>(
+                symbol(b"nvmlDeviceGetUtilizationRates\0")?,
+            ),
+            get_memory_info: std::mem::transmute::<*mut c_void, GetMemoryInfoFn>(symbol(
+                b"nvmlDeviceGetMemoryInfo\0",
+            )?),
+            get_temperature: std::mem::transmute::<*mut c_void, GetTemperatureFn>(symbol(
+                b"nvmlDeviceGetTemperature\0",
+            )?),
+            get_power_usage: std::mem::transmute::<*mut c_void, GetPowerUsageFn>(symbol(
+                b"nvmlDeviceGetPowerUsage\0",
+            )?),
+            get_throttle_reasons: std::mem::transmute::<*mut c_void, GetThrottleReasonsFn>(symbol(
+                b"nvmlDeviceGetCurrentClocksThrottleReasons\0",
+            )?),
+        };
+        to_result(init())?;
+        Ok(library)
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn load() -> CudaResult<Library> {
+    Err(CudaError::NotSupported)
+}
+
+/// Converts an `nvmlReturn_t` to the closest `CudaError`.
+fn to_result(result: NvmlReturn) -> CudaResult<()> {
+    match result {
+        0 => Ok(()),
+        1 => Err(CudaError::NotInitialized),
+        2 => Err(CudaError::InvalidValue),
+        3 => Err(CudaError::NotSupported),
+        4 => Err(CudaError::NotPermitted),
+        6 => Err(CudaError::NotFound),
+        9 => Err(CudaError::NoDevice),
+        _ => Err(CudaError::UnknownError),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_nvml_device() {
+        let _context = crate::quick_init().unwrap();
+        let device = Device::get_device(0).unwrap();
+        let nvml = match NvmlDevice::from_device(device) {
+            Ok(nvml) => nvml,
+            Err(CudaError::NotSupported) => return,
+            Err(e) => panic!("{}", e),
+        };
+        assert_eq!(device, nvml.device());
+
+        let memory = nvml.memory_info().unwrap();
+        assert!(memory.used <= memory.total);
+        if let Ok(utilization) = nvml.utilization() {
+            assert!(utilization.gpu <= 100 && utilization.memory <= 100);
+        }
+        let _ = nvml.throttle_reasons().unwrap();
+    }
+
+    #[test]
+    fn test_to_result() {
+        assert_eq!(Ok(()), to_result(0));
+        assert_eq!(Err(CudaError::NotSupported), to_result(3));
+        assert_eq!(Err(CudaError::NotPermitted), to_result(4));
+        assert_eq!(Err(CudaError::UnknownError), to_result(999));
+    }
+}
@@ -0,0 +1,143 @@
+//! Minimal bindings to the NVRTC runtime compilation library.
+//!
+//! Only the handful of entry points needed to compile CUDA C++ source to PTX are bound here. This
+//! module is only compiled when the `nvrtc` feature is enabled, since it requires linking against
+//! `libnvrtc`.
+
+use crate::error::{CudaError, CudaResult};
+use crate::module::CompileError;
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int, c_void};
+use std::ptr;
+
+#[allow(non_camel_case_types)]
+type nvrtcProgram = *mut c_void;
+#[allow(non_camel_case_types)]
+type nvrtcResult = c_int;
+
+const NVRTC_SUCCESS: nvrtcResult = 0;
+const NVRTC_ERROR_OUT_OF_MEMORY: nvrtcResult = 1;
+const NVRTC_ERROR_INVALID_INPUT: nvrtcResult = 3;
+const NVRTC_ERROR_INVALID_OPTION: nvrtcResult = 5;
+const NVRTC_ERROR_COMPILATION: nvrtcResult = 6;
+
+#[link(name = "nvrtc")]
+extern "C" {
+    fn nvrtcVersion(major: *mut c_int, minor: *mut c_int) -> nvrtcResult;
+    fn nvrtcCreateProgram(
+        prog: *mut nvrtcProgram,
+        src: *const c_char,
+        name: *const c_char,
+        num_headers: c_int,
+        headers: *const *const c_char,
+        include_names: *const *const c_char,
+    ) -> nvrtcResult;
+    fn nvrtcDestroyProgram(prog: *mut nvrtcProgram) -> nvrtcResult;
+    fn nvrtcCompileProgram(
+        prog: nvrtcProgram,
+        num_options: c_int,
+        options: *const *const c_char,
+    ) -> nvrtcResult;
+    fn nvrtcGetPTXSize(prog: nvrtcProgram, ptx_size: *mut usize) -> nvrtcResult;
+    fn nvrtcGetPTX(prog: nvrtcProgram, ptx: *mut c_char) -> nvrtcResult;
+    fn nvrtcGetProgramLogSize(prog: nvrtcProgram, log_size: *mut usize) -> nvrtcResult;
+    fn nvrtcGetProgramLog(prog: nvrtcProgram, log: *mut c_char) -> nvrtcResult;
+}
+
+fn to_result(result: nvrtcResult) -> CudaResult<()> {
+    match result {
+        NVRTC_SUCCESS => Ok(()),
+        NVRTC_ERROR_OUT_OF_MEMORY => Err(CudaError::OutOfMemory),
+        NVRTC_ERROR_INVALID_INPUT | NVRTC_ERROR_INVALID_OPTION => Err(CudaError::InvalidValue),
+        NVRTC_ERROR_COMPILATION => Err(CudaError::InvalidSouce),
+        _ => Err(CudaError::UnknownError),
+    }
+}
+
+/// Returns the major and minor version of the NVRTC library.
+pub(crate) fn version() -> CudaResult<(i32, i32)> {
+    let mut major = 0;
+    let mut minor = 0;
+    unsafe {
+        to_result(nvrtcVersion(
+            &mut major as *mut c_int,
+            &mut minor as *mut c_int,
+        ))?
+    };
+    Ok((major, minor))
+}
+
+/// Owns an NVRTC program handle and destroys it when dropped.
+struct Program {
+    inner: nvrtcProgram,
+}
+impl Drop for Program {
+    fn drop(&mut self) {
+        unsafe {
+            // Nothing useful can be done if destroying the program fails.
+            let _ = nvrtcDestroyProgram(&mut self.inner as *mut nvrtcProgram);
+        }
+    }
+}
+
+/// Compile the CUDA C++ source in `source` to PTX, passing `options` to the compiler.
+///
+/// `name` is the file name used in compiler diagnostics. Compilation errors are reported as
+/// `CompileError::Compilation`, with the compiler's log.
+pub(crate) fn compile_ptx(
+    source: &CStr,
+    name: &CStr,
+    options: &[&CStr],
+) -> Result<CString, CompileError> {
+    unsafe {
+        let mut program = Program {
+            inner: ptr::null_mut(),
+        };
+        to_result(nvrtcCreateProgram(
+            &mut program.inner as *mut nvrtcProgram,
+            source.as_ptr(),
+            name.as_ptr(),
+            0,
+            ptr::null(),
+            ptr::null(),
+        ))?;
+
+        let options: Vec<*const c_char> = options.iter().map(|o| o.as_ptr()).collect();
+        let compiled = nvrtcCompileProgram(program.inner, options.len() as c_int, options.as_ptr());
+        if compiled == NVRTC_ERROR_COMPILATION {
+            return Err(CompileError::Compilation {
+                log: program_log(&program)?,
+            });
+        }
+        to_result(compiled)?;
+
+        let mut size = 0usize;
+        to_result(nvrtcGetPTXSize(program.inner, &mut size as *mut usize))?;
+        let mut ptx = vec![0u8; size];
+        to_result(nvrtcGetPTX(program.inner, ptx.as_mut_ptr() as *mut c_char))?;
+
+        // The reported size includes the trailing nul byte.
+        ptx.truncate(ptx.iter().position(|&b| b == 0).unwrap_or(ptx.len()));
+        Ok(CString::new(ptx).map_err(|_| CudaError::InvalidPtx)?)
+    }
+}
+
+/// Returns the log of the last compilation of `program`.
+fn program_log(program: &Program) -> CudaResult<String> {
+    unsafe {
+        let mut size = 0usize;
+        to_result(nvrtcGetProgramLogSize(
+            program.inner,
+            &mut size as *mut usize,
+        ))?;
+        let mut log = vec![0u8; size];
+        to_result(nvrtcGetProgramLog(
+            program.inner,
+            log.as_mut_ptr() as *mut c_char,
+        ))?;
+
+        // The reported size includes the trailing nul byte.
+        log.truncate(log.iter().position(|&b| b == 0).unwrap_or(log.len()));
+        Ok(String::from_utf8_lossy(&log).into_owned())
+    }
+}
@@ -0,0 +1,58 @@
+//! Best-effort naming of CUDA objects for profilers through NVTX.
+//!
+//! RustaCUDA doesn't link against the NVTX library. Instead, on Linux it is loaded at runtime the
+//! first time a name is registered; if it isn't installed, names are silently ignored.
+
+use cuda_driver_sys::CUstream;
+use std::ffi::CStr;
+
+#[cfg(target_os = "linux")]
+mod imp {
+    use cuda_driver_sys::CUstream;
+    use std::ffi::CStr;
+    use std::os::raw::c_char;
+    use std::sync::OnceLock;
+
+    type NameStreamFn = unsafe extern "C" fn(CUstream, *const c_char);
+
+    fn name_stream_fn() -> Option<NameStreamFn> {
+        static NAME_STREAM: OnceLock<Option<NameStreamFn>> = OnceLock::new();
+        *NAME_STREAM.get_or_init(|| unsafe {
+            let library = libc::dlopen(
+                b"libnvToolsExt.so.1\0".as_ptr() as *const c_char,
+                libc::RTLD_LAZY | libc::RTLD_LOCAL,
+            );
+            if library.is_null() {
+                return None;
+            }
+            // The library is deliberately never closed, so the symbol stays valid.
+            let symbol = libc::dlsym(library, b"nvtxNameCuStreamA\0".as_ptr() as *const c_char);
+            if symbol.is_null() {
+                None
+            } else {
+                Some(std::mem::transmute::<*mut libc::c_void, NameStreamFn>(
+                    symbol,
+                ))
+            }
+        })
+    }
+
+    pub fn name_stream(stream: CUstream, name: &CStr) {
+        if let Some(name_stream) = name_stream_fn() {
+            unsafe { name_stream(stream, name.as_ptr()) }
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod imp {
+    use cuda_driver_sys::CUstream;
+    use std::ffi::CStr;
+
+    pub fn name_stream(_stream: CUstream, _name: &CStr) {}
+}
+
+/// Registers `name` for `stream` with NVTX, if the NVTX library is available.
+pub(crate) fn name_stream(stream: CUstream, name: &CStr) {
+    imp::name_stream(stream, name)
+}
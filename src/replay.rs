@@ -0,0 +1,489 @@
+//! Recording and replaying sequences of kernel launches.
+//!
+//! Nondeterministic GPU pipelines are hard to debug because the failing sequence of launches is
+//! rarely reproducible. A [`LaunchRecorder`](struct.LaunchRecorder.html) launches kernels like the
+//! [`launch!`](../macro.launch.html) macro does, but also records the kernel name, launch
+//! configuration and the raw bytes of every argument into a [`ReplayLog`](struct.ReplayLog.html).
+//! Checksums of device buffers can be recorded between launches to find the first launch whose
+//! output differs between two runs.
+//!
+//! A `ReplayLog` can be written out as text with `to_string` and read back with `parse`, and
+//! [`replay`](fn.replay.html) launches the recorded sequence again.
+//!
+//! Arguments are recorded by value, so device pointers are recorded as addresses. Replaying a log
+//! is therefore only meaningful while the buffers it refers to are still allocated at the same
+//! addresses, typically later in the same process after the inputs have been reset.
+//!
+//! # Examples
+//!
+//! ```
+//! # use rustacuda::*;
+//! # use std::error::Error;
+//! # fn main() -> Result<(), Box<dyn Error>> {
+//! # let _ctx = quick_init()?;
+//! use rustacuda::function::LaunchConfig;
+//! use rustacuda::memory::*;
+//! use rustacuda::module::Module;
+//! use rustacuda::replay::{replay, KernelArg, LaunchRecorder, ReplayLog};
+//! use rustacuda::stream::{Stream, StreamFlags};
+//! use std::ffi::CString;
+//!
+//! let ptx = CString::new(include_str!("../resources/add.ptx"))?;
+//! let module = Module::load_from_string(&ptx)?;
+//! let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+//! let mut x = DeviceBuffer::from_slice(&[1.0f32; 10])?;
+//! let mut y = DeviceBuffer::from_slice(&[2.0f32; 10])?;
+//! let mut out = DeviceBuffer::from_slice(&[0.0f32; 10])?;
+//!
+//! let mut recorder = LaunchRecorder::new();
+//! unsafe {
+//!     recorder.launch(&module, "sum", &LaunchConfig::new(1, 10), &stream, &[
+//!         KernelArg::new(&x.as_device_ptr()),
+//!         KernelArg::new(&y.as_device_ptr()),
+//!         KernelArg::new(&out.as_device_ptr()),
+//!         KernelArg::new(&(out.len() as i32)),
+//!     ])?;
+//! }
+//! stream.synchronize()?;
+//! recorder.checksum("out", &out)?;
+//!
+//! let log: ReplayLog = recorder.into_log().to_string().parse()?;
+//! unsafe { replay(&log, &module, &stream)? };
+//! stream.synchronize()?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::{CudaError, CudaResult, ToResult};
+use crate::function::{BlockSize, GridSize, LaunchAttribute, LaunchConfig, MemSyncDomain};
+use crate::memory::{DeviceCopy, DeviceSlice};
+use crate::module::Module;
+use crate::stream::Stream;
+use std::ffi::{c_void, CString};
+use std::fmt;
+use std::mem::size_of;
+use std::slice;
+use std::str::FromStr;
+
+/// The raw bytes of a single kernel argument.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KernelArg {
+    bytes: Vec<u8>,
+}
+impl KernelArg {
+    /// Capture the bytes of `value` as a kernel argument.
+    pub fn new<T: DeviceCopy>(value: &T) -> KernelArg {
+        let bytes =
+            unsafe { slice::from_raw_parts(value as *const T as *const u8, size_of::<T>()) };
+        KernelArg {
+            bytes: bytes.to_vec(),
+        }
+    }
+
+    /// Returns the raw bytes of this argument.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+/// A single entry in a [`ReplayLog`](struct.ReplayLog.html).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReplayEntry {
+    /// A kernel launch.
+    Launch {
+        /// Name of the kernel function in its module
+        kernel: String,
+        /// Launch configuration
+        config: LaunchConfig,
+        /// Arguments, in order
+        args: Vec<KernelArg>,
+    },
+    /// A checksum of the contents of a device buffer, taken after all preceding launches had
+    /// completed.
+    Checksum {
+        /// Label given when the checksum was recorded
+        label: String,
+        /// 64-bit FNV-1a hash of the buffer's bytes
+        value: u64,
+    },
+}
+
+/// A recorded sequence of kernel launches and buffer checksums.
+///
+/// The text form (produced by `Display` and read by `FromStr`) has one entry per line:
+///
+/// ```text
+/// launch <kernel> <gx>,<gy>,<gz> <bx>,<by>,<bz> <shared> [attrs=<attribute>;...] <hex arg>...
+/// checksum <label> <hex value>
+/// ```
+///
+/// `gx`, `gy` and `gz` are the grid dimensions, `bx`, `by` and `bz` the block dimensions and
+/// `shared` the dynamic shared memory size in bytes. The `attrs=` word is only present if the
+/// launch has attributes. Each attribute is written as `cluster:<x>,<y>,<z>`,
+/// `serialization:<0 or 1>` or `domain:<0 for Default or 1 for Remote>`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReplayLog {
+    entries: Vec<ReplayEntry>,
+}
+impl ReplayLog {
+    /// Returns the entries of this log, in the order they were recorded.
+    pub fn entries(&self) -> &[ReplayEntry] {
+        &self.entries
+    }
+}
+impl fmt::Display for ReplayLog {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for entry in &self.entries {
+            match entry {
+                ReplayEntry::Launch {
+                    kernel,
+                    config,
+                    args,
+                } => {
+                    let (g, b) = (&config.grid, &config.block);
+                    write!(
+                        f,
+                        "launch {} {},{},{} {},{},{} {}",
+                        kernel, g.x, g.y, g.z, b.x, b.y, b.z, config.shared_mem_bytes
+                    )?;
+                    for (i, attribute) in config.attributes.iter().enumerate() {
+                        f.write_str(if i == 0 { " attrs=" } else { ";" })?;
+                        match attribute {
+                            LaunchAttribute::ClusterDimension(x, y, z) => {
+                                write!(f, "cluster:{},{},{}", x, y, z)?
+                            }
+                            LaunchAttribute::ProgrammaticStreamSerialization(enabled) => {
+                                write!(f, "serialization:{}", u8::from(*enabled))?
+                            }
+                            LaunchAttribute::MemSyncDomain(domain) => {
+                                write!(f, "domain:{}", *domain as u32)?
+                            }
+                        }
+                    }
+                    for arg in args {
+                        f.write_str(" ")?;
+                        for byte in &arg.bytes {
+                            write!(f, "{:02x}", byte)?;
+                        }
+                    }
+                    writeln!(f)?;
+                }
+                ReplayEntry::Checksum { label, value } => {
+                    writeln!(f, "checksum {} {:016x}", label, value)?
+                }
+            }
+        }
+        Ok(())
+    }
+}
+impl FromStr for ReplayLog {
+    type Err = CudaError;
+
+    /// Parse a log from its text form. Returns `InvalidValue` if the text is malformed.
+    fn from_str(s: &str) -> CudaResult<ReplayLog> {
+        fn dims(s: Option<&str>) -> CudaResult<(u32, u32, u32)> {
+            let mut parts = s
+                .ok_or(CudaError::InvalidValue)?
+                .split(',')
+                .map(u32::from_str);
+            match (parts.next(), parts.next(), parts.next(), parts.next()) {
+                (Some(Ok(x)), Some(Ok(y)), Some(Ok(z)), None) => Ok((x, y, z)),
+                _ => Err(CudaError::InvalidValue),
+            }
+        }
+        fn hex_bytes(s: &str) -> CudaResult<Vec<u8>> {
+            s.as_bytes()
+                .chunks(2)
+                .map(|digits| {
+                    std::str::from_utf8(digits)
+                        .ok()
+                        .filter(|digits| digits.len() == 2)
+                        .and_then(|digits| u8::from_str_radix(digits, 16).ok())
+                        .ok_or(CudaError::InvalidValue)
+                })
+                .collect()
+        }
+
+        fn attribute(s: &str) -> CudaResult<LaunchAttribute> {
+            match s.split_once(':') {
+                Some(("cluster", d)) => {
+                    let (x, y, z) = dims(Some(d))?;
+                    Ok(LaunchAttribute::ClusterDimension(x, y, z))
+                }
+                Some(("serialization", "0")) => {
+                    Ok(LaunchAttribute::ProgrammaticStreamSerialization(false))
+                }
+                Some(("serialization", "1")) => {
+                    Ok(LaunchAttribute::ProgrammaticStreamSerialization(true))
+                }
+                Some(("domain", "0")) => Ok(LaunchAttribute::MemSyncDomain(MemSyncDomain::Default)),
+                Some(("domain", "1")) => Ok(LaunchAttribute::MemSyncDomain(MemSyncDomain::Remote)),
+                _ => Err(CudaError::InvalidValue),
+            }
+        }
+
+        let mut entries = Vec::new();
+        for line in s.lines().filter(|line| !line.trim().is_empty()) {
+            let mut words = line.split_whitespace().peekable();
+            let entry = match words.next() {
+                Some("launch") => {
+                    let kernel = words.next().ok_or(CudaError::InvalidValue)?.to_string();
+                    let grid = dims(words.next())?;
+                    let block = dims(words.next())?;
+                    let shared_mem_bytes = words
+                        .next()
+                        .and_then(|w| w.parse().ok())
+                        .ok_or(CudaError::InvalidValue)?;
+                    let mut config =
+                        LaunchConfig::new(GridSize::from(grid), BlockSize::from(block))
+                            .with_shared_mem_bytes(shared_mem_bytes);
+                    if let Some(attributes) =
+                        words.peek().copied().and_then(|w| w.strip_prefix("attrs="))
+                    {
+                        for s in attributes.split(';') {
+                            config = config.with_attribute(attribute(s)?);
+                        }
+                        let _ = words.next();
+                    }
+                    let args = words
+                        .map(|w| hex_bytes(w).map(|bytes| KernelArg { bytes }))
+                        .collect::<CudaResult<_>>()?;
+                    ReplayEntry::Launch {
+                        kernel,
+                        config,
+                        args,
+                    }
+                }
+                Some("checksum") => {
+                    let label = words.next().ok_or(CudaError::InvalidValue)?.to_string();
+                    let value = words
+                        .next()
+                        .and_then(|w| u64::from_str_radix(w, 16).ok())
+                        .ok_or(CudaError::InvalidValue)?;
+                    if words.next().is_some() {
+                        return Err(CudaError::InvalidValue);
+                    }
+                    ReplayEntry::Checksum { label, value }
+                }
+                _ => return Err(CudaError::InvalidValue),
+            };
+            entries.push(entry);
+        }
+        Ok(ReplayLog { entries })
+    }
+}
+
+/// Launches kernels while recording them into a [`ReplayLog`](struct.ReplayLog.html).
+///
+/// See the [module-level documentation](index.html) for an example.
+#[derive(Debug, Default)]
+pub struct LaunchRecorder {
+    log: ReplayLog,
+}
+impl LaunchRecorder {
+    /// Create a recorder with an empty log.
+    pub fn new() -> LaunchRecorder {
+        LaunchRecorder::default()
+    }
+
+    /// Record a launch of the kernel `kernel` from `module`, then launch it on `stream`.
+    ///
+    /// # Errors
+    ///
+    /// If `kernel` contains whitespace or nul bytes, returns `InvalidValue` without recording or
+    /// launching anything. If the kernel can't be found or the launch fails, returns the error
+    /// from CUDA; the launch is still recorded.
+    ///
+    /// # Safety
+    ///
+    /// This has the same safety requirements as the [`launch!`](../macro.launch.html) macro.
+    pub unsafe fn launch(
+        &mut self,
+        module: &Module,
+        kernel: &str,
+        config: &LaunchConfig,
+        stream: &Stream,
+        args: &[KernelArg],
+    ) -> CudaResult<()> {
+        if kernel.is_empty() || kernel.contains(char::is_whitespace) {
+            return Err(CudaError::InvalidValue);
+        }
+        let name = CString::new(kernel).map_err(|_| CudaError::InvalidValue)?;
+        self.log.entries.push(ReplayEntry::Launch {
+            kernel: kernel.to_string(),
+            config: config.clone(),
+            args: args.to_vec(),
+        });
+        launch_raw(module, &name, config, stream, args)
+    }
+
+    /// Copy `buffer` to the host and record a checksum of its contents under `label`, returning
+    /// the checksum.
+    ///
+    /// The copy is synchronous with respect to the host, so the caller should synchronize any
+    /// streams writing to the buffer first.
+    ///
+    /// # Errors
+    ///
+    /// If `label` is empty or contains whitespace, returns `InvalidValue`. If the copy fails,
+    /// returns the error from CUDA.
+    pub fn checksum<T: DeviceCopy>(
+        &mut self,
+        label: &str,
+        buffer: &DeviceSlice<T>,
+    ) -> CudaResult<u64> {
+        if label.is_empty() || label.contains(char::is_whitespace) {
+            return Err(CudaError::InvalidValue);
+        }
+        let mut bytes = vec![0u8; buffer.len() * size_of::<T>()];
+        if !bytes.is_empty() {
+            unsafe {
+                driver_call!(cuda_driver_sys::cuMemcpyDtoH_v2(
+                    bytes.as_mut_ptr() as *mut c_void,
+                    buffer.as_ptr() as u64,
+                    bytes.len(),
+                ))
+                .to_result()?;
+            }
+        }
+
+        let mut value: u64 = 0xcbf2_9ce4_8422_2325;
+        for byte in &bytes {
+            value ^= u64::from(*byte);
+            value = value.wrapping_mul(0x0100_0000_01b3);
+        }
+        self.log.entries.push(ReplayEntry::Checksum {
+            label: label.to_string(),
+            value,
+        });
+        Ok(value)
+    }
+
+    /// Returns the log recorded so far.
+    pub fn log(&self) -> &ReplayLog {
+        &self.log
+    }
+
+    /// Consume the recorder, returning the recorded log.
+    pub fn into_log(self) -> ReplayLog {
+        self.log
+    }
+}
+
+unsafe fn launch_raw(
+    module: &Module,
+    name: &CString,
+    config: &LaunchConfig,
+    stream: &Stream,
+    args: &[KernelArg],
+) -> CudaResult<()> {
+    let function = module.get_function(name)?;
+    let arg_ptrs: Vec<*mut c_void> = args
+        .iter()
+        .map(|arg| arg.bytes.as_ptr() as *mut c_void)
+        .collect();
+    stream.launch_with_config(&function, config, &arg_ptrs)
+}
+
+/// Launch every kernel recorded in `log` on `stream`, in order, looking the kernels up in `module`.
+///
+/// Checksum entries are skipped; to compare checksums, record the replayed sequence with a new
+/// [`LaunchRecorder`](struct.LaunchRecorder.html) instead.
+///
+/// # Errors
+///
+/// If a kernel can't be found or a launch fails, returns the error from CUDA without launching
+/// the remaining entries.
+///
+/// # Safety
+///
+/// This has the same safety requirements as the [`launch!`](../macro.launch.html) macro. In
+/// addition, any device pointers recorded in the log must still point to live allocations of the
+/// appropriate size.
+pub unsafe fn replay(log: &ReplayLog, module: &Module, stream: &Stream) -> CudaResult<()> {
+    for entry in &log.entries {
+        if let ReplayEntry::Launch {
+            kernel,
+            config,
+            args,
+        } = entry
+        {
+            let name = CString::new(kernel.as_str()).map_err(|_| CudaError::InvalidValue)?;
+            launch_raw(module, &name, config, stream, args)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_log_round_trip() {
+        let log = ReplayLog {
+            entries: vec![
+                ReplayEntry::Launch {
+                    kernel: "sum".to_string(),
+                    config: LaunchConfig::new((2, 3), 64).with_shared_mem_bytes(128),
+                    args: vec![KernelArg::new(&0x1234u64), KernelArg::new(&7i32)],
+                },
+                ReplayEntry::Checksum {
+                    label: "out".to_string(),
+                    value: 0xdead_beef,
+                },
+            ],
+        };
+        let text = log.to_string();
+        assert_eq!(
+            "launch sum 2,3,1 64,1,1 128 3412000000000000 07000000\nchecksum out 00000000deadbeef\n",
+            text
+        );
+        assert_eq!(log, text.parse().unwrap());
+    }
+
+    #[test]
+    fn test_attributes_round_trip() {
+        let config = LaunchConfig::new((4, 2), 64)
+            .with_attribute(LaunchAttribute::ClusterDimension(2, 1, 1))
+            .with_attribute(LaunchAttribute::ProgrammaticStreamSerialization(true))
+            .with_attribute(LaunchAttribute::MemSyncDomain(MemSyncDomain::Remote));
+        let log = ReplayLog {
+            entries: vec![ReplayEntry::Launch {
+                kernel: "sum".to_string(),
+                config,
+                args: vec![KernelArg::new(&7i32)],
+            }],
+        };
+        let text = log.to_string();
+        assert_eq!(
+            "launch sum 4,2,1 64,1,1 0 attrs=cluster:2,1,1;serialization:1;domain:1 07000000\n",
+            text
+        );
+        assert_eq!(log, text.parse().unwrap());
+        assert_eq!(
+            CudaError::InvalidValue,
+            "launch sum 1,1,1 1,1,1 0 attrs=cluster:2,1"
+                .parse::<ReplayLog>()
+                .unwrap_err()
+        );
+    }
+
+    #[test]
+    fn test_parse_malformed() {
+        assert_eq!(
+            CudaError::InvalidValue,
+            "launch sum 1,1 1,1,1 0".parse::<ReplayLog>().unwrap_err()
+        );
+        assert_eq!(
+            CudaError::InvalidValue,
+            "launch sum 1,1,1 1,1,1 0 abc"
+                .parse::<ReplayLog>()
+                .unwrap_err()
+        );
+        assert_eq!(
+            CudaError::InvalidValue,
+            "frobnicate".parse::<ReplayLog>().unwrap_err()
+        );
+    }
+}
@@ -10,14 +10,31 @@
 //! are not currently supported by RustaCUDA. Finally, the host can wait for all work scheduled in
 //! a stream to be completed.
 
-use crate::error::{CudaResult, DropResult, ToResult};
+use crate::context::{CurrentContext, UnownedContext};
+use crate::device::DeviceAttribute;
+use crate::error::{CudaError, CudaResult, DropExpect, DropResult, ToResult};
 use crate::event::Event;
-use crate::function::{BlockSize, Function, GridSize};
-use cuda_driver_sys::{cudaError_enum, CUstream};
-use std::ffi::c_void;
-use std::mem;
-use std::panic;
+use crate::function::{
+    BlockSize, Function, GridSize, LaunchAttribute, LaunchConfig, MemSyncDomain,
+};
+use crate::graph::Graph;
+use crate::memory::{DeviceCopy, DevicePointer, DeviceSlice};
+use crate::sync::lock_ignoring_poison;
+use crate::teardown::{self, Resource};
+use crate::trace::{self, StreamId};
+use cuda_driver_sys::{
+    cudaError_enum, CUdeviceptr, CUfunction, CUresult, CUstream, CUstreamCaptureMode,
+};
+use std::error::Error;
+use std::ffi::{c_void, CString};
+use std::fmt;
+use std::marker::PhantomData;
+use std::mem::{self, size_of, ManuallyDrop};
+use std::ops::Deref;
+use std::panic::{self, Location};
 use std::ptr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
 
 bitflags! {
     /// Bit flags for configuring a CUDA Stream.
@@ -33,7 +50,8 @@ bitflags! {
         /// regardless of the flag. However, for legacy reasons, CUDA has a notion of a NULL stream,
         /// which is used as the default when no other stream is provided. Work on other streams
         /// may not be executed concurrently with work on the NULL stream unless this flag is set.
-        /// Since RustaCUDA does not provide access to the NULL stream, this flag has no effect in
+        /// RustaCUDA only exposes the NULL stream through
+        /// [`Stream::default_`](struct.Stream.html#method.default_), so this flag has no effect in
         /// most circumstances. However, it is recommended to use it anyway, as some other crate
         /// in this binary may be using the NULL stream directly.
         const NON_BLOCKING = 0x01;
@@ -50,13 +68,254 @@ bitflags! {
     }
 }
 
+bitflags! {
+    /// Bit flags for writing a value to memory from a stream with
+    /// [`Stream::write_value_32`](struct.Stream.html#method.write_value_32) and
+    /// [`Stream::write_value_64`](struct.Stream.html#method.write_value_64).
+    pub struct StreamWriteValueFlags: u32 {
+        /// No flags set. The write is ordered after all previous memory accesses of the stream.
+        const DEFAULT = 0x0;
+
+        /// Allow the write to be reordered with previous memory accesses of the stream, like a
+        /// relaxed write. This is only useful when the write doesn't signal that other data is
+        /// ready.
+        const NO_MEMORY_BARRIER = 0x1;
+    }
+}
+
+/// The condition which a stream waits for with
+/// [`Stream::wait_value_32`](struct.Stream.html#method.wait_value_32) and
+/// [`Stream::wait_value_64`](struct.Stream.html#method.wait_value_64).
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum WaitCondition {
+    /// Wait until `(*addr - value) as i32 >= 0` (or `i64`), a comparison which tolerates the
+    /// counter wrapping around.
+    GreaterOrEqual = 0,
+    /// Wait until `*addr == value`.
+    Equal = 1,
+    /// Wait until `*addr & value != 0`.
+    And = 2,
+    /// Wait until `!(*addr | value) != 0`. Requires
+    /// [`DeviceAttribute::CanUseStreamWaitValueNor`](../device/enum.DeviceAttribute.html#variant.CanUseStreamWaitValueNor).
+    Nor = 3,
+}
+
+/// Priority of a CUDA Stream, for use with [`StreamBuilder`](struct.StreamBuilder.html).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum StreamPriority {
+    /// The lowest priority supported by the current context. This is also the default priority.
+    Low,
+    /// The highest priority supported by the current context.
+    High,
+    /// A specific priority value. Lower numbers represent greater priorities; values outside the
+    /// range supported by the context are clamped.
+    Value(i32),
+}
+
+/// How the L2 cache treats the lines accessed within an
+/// [`AccessPolicyWindow`](struct.AccessPolicyWindow.html).
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AccessProperty {
+    /// Normal cache persistence.
+    Normal = 0,
+    /// The lines are less likely to persist in the cache.
+    Streaming = 1,
+    /// The lines are more likely to persist in the cache, using the set-aside portion of L2
+    /// configured with
+    /// [`ResourceLimit::PersistingL2CacheSize`](../context/enum.ResourceLimit.html#variant.PersistingL2CacheSize).
+    Persisting = 2,
+}
+
+/// A window of device memory with an L2 cache access policy, which kernels launched into a stream
+/// follow once it is set with
+/// [`Stream::set_access_policy_window`](struct.Stream.html#method.set_access_policy_window).
+///
+/// A fraction `hit_ratio` of the accesses to the window, chosen at random, get the `hit_prop`
+/// property and the rest get `miss_prop`. The window is only a hint to the cache; the memory it
+/// covers may be freed while it is set.
+// Laid out as CUaccessPolicyWindow.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AccessPolicyWindow {
+    base_ptr: CUdeviceptr,
+    num_bytes: usize,
+    /// The fraction of accesses to the window which get `hit_prop`, between 0 and 1.
+    pub hit_ratio: f32,
+    /// The property for accesses which hit.
+    pub hit_prop: AccessProperty,
+    /// The property for accesses which miss.
+    pub miss_prop: AccessProperty,
+}
+impl AccessPolicyWindow {
+    /// Create a window covering `slice` in which every access is
+    /// [`Persisting`](enum.AccessProperty.html#variant.Persisting).
+    ///
+    /// The window may be larger than the set-aside portion of L2, in which case lower the
+    /// `hit_ratio` so that the persisting lines fit and don't evict each other.
+    pub fn persisting<T: DeviceCopy>(slice: &DeviceSlice<T>) -> Self {
+        AccessPolicyWindow {
+            base_ptr: slice.as_ptr() as CUdeviceptr,
+            num_bytes: slice.len() * size_of::<T>(),
+            hit_ratio: 1.0,
+            hit_prop: AccessProperty::Persisting,
+            miss_prop: AccessProperty::Streaming,
+        }
+    }
+
+    /// Returns the address of the start of the window.
+    pub fn base_ptr(&self) -> DevicePointer<u8> {
+        unsafe { DevicePointer::wrap(self.base_ptr as *mut u8) }
+    }
+
+    /// Returns the size of the window in bytes. A window of zero bytes disables the policy.
+    pub fn num_bytes(&self) -> usize {
+        self.num_bytes
+    }
+}
+
+/// Builder for creating a [`Stream`](struct.Stream.html) with named options instead of
+/// positional arguments.
+///
+/// # Examples
+///
+/// ```
+/// # use rustacuda::*;
+/// # use std::error::Error;
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// # let _ctx = quick_init()?;
+/// use rustacuda::stream::{StreamBuilder, StreamPriority};
+///
+/// let stream = StreamBuilder::new()
+///     .non_blocking()
+///     .priority(StreamPriority::High)
+///     .name("h2d-copy")
+///     .build()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct StreamBuilder {
+    flags: StreamFlags,
+    priority: Option<StreamPriority>,
+    strict_priority: bool,
+    name: Option<String>,
+}
+impl StreamBuilder {
+    /// Create a builder for a stream with default flags and priority.
+    pub fn new() -> Self {
+        StreamBuilder {
+            flags: StreamFlags::DEFAULT,
+            priority: None,
+            strict_priority: false,
+            name: None,
+        }
+    }
+
+    /// Set the flags of the stream, replacing any flags set previously.
+    pub fn flags(mut self, flags: StreamFlags) -> Self {
+        self.flags = flags;
+        self
+    }
+
+    /// Do not synchronize the stream with the NULL stream. See `StreamFlags::NON_BLOCKING`.
+    pub fn non_blocking(mut self) -> Self {
+        self.flags |= StreamFlags::NON_BLOCKING;
+        self
+    }
+
+    /// Set the priority of the stream.
+    ///
+    /// A `StreamPriority::Value` outside the range supported by the context (see
+    /// [`Stream::priority_range`](struct.Stream.html#method.priority_range)) is clamped to the
+    /// range, and a warning is logged if the `log` crate is enabled. Use
+    /// [`strict_priority`](#method.strict_priority) to reject such values instead.
+    pub fn priority(mut self, priority: StreamPriority) -> Self {
+        self.priority = Some(priority);
+        self
+    }
+
+    /// Make [`build`](#method.build) fail with `InvalidValue` if the priority value is outside the
+    /// range supported by the context, instead of clamping it.
+    pub fn strict_priority(mut self) -> Self {
+        self.strict_priority = true;
+        self
+    }
+
+    /// Set a name for the stream, which is shown by profilers such as Nsight Systems.
+    ///
+    /// The name is registered with NVTX when the stream is built, if the NVTX library
+    /// (`libnvToolsExt`) can be loaded. Otherwise it is ignored. NVTX is currently only loaded on
+    /// Linux.
+    pub fn name<S: Into<String>>(mut self, name: S) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Create the stream in the current context.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidValue` if the name contains a nul byte or, with
+    /// [`strict_priority`](#method.strict_priority), if the priority is out of range. If querying
+    /// the priority range or creating the stream fails, returns the error from CUDA.
+    pub fn build(self) -> CudaResult<Stream> {
+        let name = match self.name {
+            Some(name) => Some(CString::new(name).map_err(|_| CudaError::InvalidValue)?),
+            None => None,
+        };
+        let priority = match self.priority {
+            None => None,
+            Some(StreamPriority::Value(value)) => {
+                let range = CurrentContext::get_stream_priority_range()?;
+                if self.strict_priority && !range.contains(value) {
+                    return Err(CudaError::InvalidValue);
+                }
+                #[cfg(feature = "log")]
+                if !range.contains(value) {
+                    log::warn!(
+                        "Stream priority {} is outside the supported range [{}, {}] and was \
+                         clamped to {}",
+                        value,
+                        range.greatest,
+                        range.least,
+                        range.clamp(value)
+                    );
+                }
+                Some(range.clamp(value))
+            }
+            Some(StreamPriority::Low) => Some(CurrentContext::get_stream_priority_range()?.least),
+            Some(StreamPriority::High) => {
+                Some(CurrentContext::get_stream_priority_range()?.greatest)
+            }
+        };
+
+        let stream = Stream::new(self.flags, priority)?;
+        if let Some(name) = name {
+            crate::nvtx::name_stream(stream.inner, &name);
+        }
+        Ok(stream)
+    }
+}
+impl Default for StreamBuilder {
+    fn default() -> Self {
+        StreamBuilder::new()
+    }
+}
+
 /// A stream of work for the device to perform.
 ///
 /// See the module-level documentation for more information.
 #[derive(Debug)]
 pub struct Stream {
     inner: CUstream,
+    id: StreamId,
 }
+// Work can be submitted to a stream from any number of threads at once; the driver orders it by
+// submission.
+unsafe impl Send for Stream {}
+unsafe impl Sync for Stream {}
 impl Stream {
     /// Create a new stream with the given flags and optional priority.
     ///
@@ -87,17 +346,108 @@ impl Stream {
         unsafe {
             let mut stream = Stream {
                 inner: ptr::null_mut(),
+                id: StreamId::unique(),
             };
-            cuda_driver_sys::cuStreamCreateWithPriority(
+            driver_call!(cuda_driver_sys::cuStreamCreateWithPriority(
                 &mut stream.inner as *mut CUstream,
                 flags.bits(),
                 priority.unwrap_or(0),
-            )
+            ))
             .to_result()?;
+            teardown::created(Resource::Stream);
             Ok(stream)
         }
     }
 
+    /// Returns a handle to the legacy default stream (the NULL stream) of the current context.
+    ///
+    /// This is the stream which libraries and runtime API code use when they aren't given one,
+    /// such as cuBLAS before `cublasSetStream` is called. Work on the legacy default stream waits
+    /// for all work on blocking streams in the context, and work on blocking streams waits for it;
+    /// streams created with `StreamFlags::NON_BLOCKING` don't synchronize with it.
+    ///
+    /// The handle is not owned: dropping it does nothing, and it stays valid as long as the
+    /// current context does. Synchronizing, waiting on events, launching and copying work as for
+    /// any other stream, but the default stream can't be captured into a graph. The handle
+    /// refers to whichever context is current when it is used.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rustacuda::*;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # let _ctx = quick_init()?;
+    /// use rustacuda::event::{Event, EventFlags};
+    /// use rustacuda::stream::{Stream, StreamFlags, StreamWaitEventFlags};
+    ///
+    /// // Make work on our stream wait for work a library submitted to the default stream.
+    /// let library_done = Event::new(EventFlags::DISABLE_TIMING)?;
+    /// library_done.record(&Stream::default_())?;
+    /// let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+    /// stream.wait_event(library_done, StreamWaitEventFlags::DEFAULT)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn default_() -> Stream {
+        Stream {
+            inner: CU_STREAM_LEGACY as CUstream,
+            id: StreamId::LEGACY,
+        }
+    }
+
+    /// Returns a handle to the per-thread default stream of the current context.
+    ///
+    /// Each host thread has its own per-thread default stream, which is used by runtime API code
+    /// compiled with `--default-stream per-thread`. It doesn't synchronize with other streams,
+    /// except for the [legacy default stream](#method.default_).
+    ///
+    /// Like `default_`, the handle is not owned: dropping it does nothing. It refers to the
+    /// stream of whichever thread uses it, not the thread which created the handle.
+    pub fn per_thread() -> Stream {
+        Stream {
+            inner: CU_STREAM_PER_THREAD as CUstream,
+            id: StreamId::PER_THREAD,
+        }
+    }
+
+    /// Returns `true` if this is a handle to the legacy or per-thread default stream, which is
+    /// not owned by this value.
+    pub fn is_default(&self) -> bool {
+        let inner = self.inner as usize;
+        inner == 0 || inner == CU_STREAM_LEGACY || inner == CU_STREAM_PER_THREAD
+    }
+
+    /// Returns the range of stream priorities supported by the current context, as
+    /// `(least, greatest)`.
+    ///
+    /// Lower numbers represent greater priorities, so numerically `greatest <= least`; for
+    /// example, `(0, -5)`. Priorities outside the range are clamped when a stream is created. If
+    /// the device doesn't support stream priorities, both are zero.
+    ///
+    /// # Errors
+    ///
+    /// If there is no current context or the query fails, returns the CUDA error value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rustacuda::*;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # let _ctx = quick_init()?;
+    /// use rustacuda::stream::{Stream, StreamFlags};
+    ///
+    /// let (_least, greatest) = Stream::priority_range()?;
+    /// let urgent = Stream::new(StreamFlags::NON_BLOCKING, Some(greatest))?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn priority_range() -> CudaResult<(i32, i32)> {
+        let range = CurrentContext::get_stream_priority_range()?;
+        Ok((range.least, range.greatest))
+    }
+
     /// Return the flags which were used to create this stream.
     ///
     /// # Examples
@@ -117,7 +467,11 @@ impl Stream {
     pub fn get_flags(&self) -> CudaResult<StreamFlags> {
         unsafe {
             let mut bits = 0u32;
-            cuda_driver_sys::cuStreamGetFlags(self.inner, &mut bits as *mut u32).to_result()?;
+            driver_call!(cuda_driver_sys::cuStreamGetFlags(
+                self.inner,
+                &mut bits as *mut u32
+            ))
+            .to_result()?;
             Ok(StreamFlags::from_bits_truncate(bits))
         }
     }
@@ -145,8 +499,11 @@ impl Stream {
     pub fn get_priority(&self) -> CudaResult<i32> {
         unsafe {
             let mut priority = 0i32;
-            cuda_driver_sys::cuStreamGetPriority(self.inner, &mut priority as *mut i32)
-                .to_result()?;
+            driver_call!(cuda_driver_sys::cuStreamGetPriority(
+                self.inner,
+                &mut priority as *mut i32
+            ))
+            .to_result()?;
             Ok(priority)
         }
     }
@@ -186,13 +543,14 @@ impl Stream {
     where
         T: FnOnce(CudaResult<()>) + Send,
     {
+        let wrapper: cuda_driver_sys::CUstreamCallback = Some(callback_wrapper::<T>);
         unsafe {
-            cuda_driver_sys::cuStreamAddCallback(
+            driver_call!(cuda_driver_sys::cuStreamAddCallback(
                 self.inner,
-                Some(callback_wrapper::<T>),
+                wrapper,
                 Box::into_raw(callback) as *mut c_void,
                 0,
-            )
+            ))
             .to_result()
         }
     }
@@ -220,7 +578,7 @@ impl Stream {
     /// # }
     /// ```
     pub fn synchronize(&self) -> CudaResult<()> {
-        unsafe { cuda_driver_sys::cuStreamSynchronize(self.inner).to_result() }
+        unsafe { driver_call!(cuda_driver_sys::cuStreamSynchronize(self.inner)).to_result() }
     }
 
     /// Make the stream wait on an event.
@@ -255,13 +613,326 @@ impl Stream {
     /// ```
     pub fn wait_event(&self, event: Event, flags: StreamWaitEventFlags) -> CudaResult<()> {
         unsafe {
-            cuda_driver_sys::cuStreamWaitEvent(self.inner, event.as_inner(), flags.bits())
-                .to_result()
+            driver_call!(cuda_driver_sys::cuStreamWaitEvent(
+                self.inner,
+                event.as_inner(),
+                flags.bits()
+            ))
+            .to_result()
+        }
+    }
+
+    /// Make all future work submitted to the stream wait until the 32-bit value at `addr`
+    /// satisfies `condition` with respect to `value`.
+    ///
+    /// The value may be written by a kernel in another stream, by the host through mapped memory,
+    /// or by another device such as a network adapter using GPUDirect RDMA. Nothing else in the
+    /// stream runs until the condition holds, so a condition which never becomes true hangs the
+    /// stream.
+    ///
+    /// # Errors
+    ///
+    /// Returns `NotSupported` if the device of the current context doesn't support stream memory
+    /// operations ([`DeviceAttribute::CanUseStreamMemOps`](../device/enum.DeviceAttribute.html#variant.CanUseStreamMemOps)),
+    /// or `condition` is `Nor` and the device doesn't support it. Otherwise, returns the CUDA
+    /// error value.
+    ///
+    /// # Safety
+    ///
+    /// `addr` must point to device memory, or host memory mapped into the device's address space,
+    /// which remains valid until the wait has completed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rustacuda::*;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # let _ctx = quick_init()?;
+    /// use rustacuda::error::CudaError;
+    /// use rustacuda::memory::DeviceBox;
+    /// use rustacuda::stream::{Stream, StreamFlags, StreamWriteValueFlags, WaitCondition};
+    ///
+    /// let producer = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+    /// let consumer = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+    /// let mut flag = DeviceBox::new(&0u32)?;
+    /// unsafe {
+    ///     match consumer.wait_value_32(flag.as_device_ptr(), 1, WaitCondition::Equal) {
+    ///         Err(CudaError::NotSupported) => return Ok(()),
+    ///         result => result?,
+    ///     }
+    ///     // Work submitted to `consumer` now waits for `producer` to set the flag.
+    ///     producer.write_value_32(flag.as_device_ptr(), 1, StreamWriteValueFlags::DEFAULT)?;
+    /// }
+    /// consumer.synchronize()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub unsafe fn wait_value_32(
+        &self,
+        addr: DevicePointer<u32>,
+        value: u32,
+        condition: WaitCondition,
+    ) -> CudaResult<()> {
+        check_mem_ops(DeviceAttribute::CanUseStreamMemOps, condition)?;
+        driver_call!(cuda_driver_sys::cuStreamWaitValue32(
+            self.inner,
+            addr.as_raw() as CUdeviceptr,
+            value,
+            condition as u32
+        ))
+        .to_result()
+    }
+
+    /// Make all future work submitted to the stream wait until the 64-bit value at `addr`
+    /// satisfies `condition` with respect to `value`. See
+    /// [`wait_value_32`](#method.wait_value_32).
+    ///
+    /// # Errors
+    ///
+    /// Returns `NotSupported` if the device of the current context doesn't support 64-bit stream
+    /// memory operations ([`DeviceAttribute::CanUse64BitStreamMemOps`](../device/enum.DeviceAttribute.html#variant.CanUse64BitStreamMemOps)),
+    /// or `condition` is `Nor` and the device doesn't support it. Otherwise, returns the CUDA
+    /// error value.
+    ///
+    /// # Safety
+    ///
+    /// `addr` must point to device memory, or host memory mapped into the device's address space,
+    /// which remains valid until the wait has completed.
+    pub unsafe fn wait_value_64(
+        &self,
+        addr: DevicePointer<u64>,
+        value: u64,
+        condition: WaitCondition,
+    ) -> CudaResult<()> {
+        check_mem_ops(DeviceAttribute::CanUse64BitStreamMemOps, condition)?;
+        driver_call!(cuda_driver_sys::cuStreamWaitValue64(
+            self.inner,
+            addr.as_raw() as CUdeviceptr,
+            value,
+            condition as u32
+        ))
+        .to_result()
+    }
+
+    /// Write a 32-bit value to `addr` once all previous work in the stream has completed, for
+    /// example to release another stream waiting in [`wait_value_32`](#method.wait_value_32).
+    ///
+    /// # Errors
+    ///
+    /// Returns `NotSupported` if the device of the current context doesn't support stream memory
+    /// operations. Otherwise, returns the CUDA error value.
+    ///
+    /// # Safety
+    ///
+    /// `addr` must point to device memory, or host memory mapped into the device's address space,
+    /// which remains valid until the write has completed.
+    pub unsafe fn write_value_32(
+        &self,
+        addr: DevicePointer<u32>,
+        value: u32,
+        flags: StreamWriteValueFlags,
+    ) -> CudaResult<()> {
+        check_mem_ops(DeviceAttribute::CanUseStreamMemOps, WaitCondition::Equal)?;
+        driver_call!(cuda_driver_sys::cuStreamWriteValue32(
+            self.inner,
+            addr.as_raw() as CUdeviceptr,
+            value,
+            flags.bits()
+        ))
+        .to_result()
+    }
+
+    /// Write a 64-bit value to `addr` once all previous work in the stream has completed. See
+    /// [`write_value_32`](#method.write_value_32).
+    ///
+    /// # Errors
+    ///
+    /// Returns `NotSupported` if the device of the current context doesn't support 64-bit stream
+    /// memory operations. Otherwise, returns the CUDA error value.
+    ///
+    /// # Safety
+    ///
+    /// `addr` must point to device memory, or host memory mapped into the device's address space,
+    /// which remains valid until the write has completed.
+    pub unsafe fn write_value_64(
+        &self,
+        addr: DevicePointer<u64>,
+        value: u64,
+        flags: StreamWriteValueFlags,
+    ) -> CudaResult<()> {
+        check_mem_ops(
+            DeviceAttribute::CanUse64BitStreamMemOps,
+            WaitCondition::Equal,
+        )?;
+        driver_call!(cuda_driver_sys::cuStreamWriteValue64(
+            self.inner,
+            addr.as_raw() as CUdeviceptr,
+            value,
+            flags.bits()
+        ))
+        .to_result()
+    }
+
+    /// Returns the context this stream belongs to.
+    ///
+    /// Kernels launched on a stream must have been loaded into the stream's context. In debug
+    /// builds, launching a kernel from a different context returns `ContextMismatch`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rustacuda::*;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # let _ctx = quick_init()?;
+    /// use rustacuda::stream::{Stream, StreamFlags};
+    ///
+    /// let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+    /// let version = stream.context()?.get_api_version()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn context(&self) -> CudaResult<UnownedContext> {
+        let mut ctx = ptr::null_mut();
+        unsafe {
+            driver_call!(cuda_driver_sys::cuStreamGetCtx(self.inner, &mut ctx)).to_result()?;
         }
+        Ok(UnownedContext::from_inner(ctx))
+    }
+
+    /// Begin capturing the work submitted to this stream into a [`Graph`](../graph/struct.Graph.html)
+    /// instead of executing it.
+    ///
+    /// Until [`end_capture`](#method.end_capture) is called, kernel launches and asynchronous
+    /// copies submitted to the stream are recorded rather than run. Synchronous operations which
+    /// would wait for the captured work, such as `synchronize`, are not allowed during capture and
+    /// invalidate it.
+    ///
+    /// # Errors
+    ///
+    /// If the stream can't be captured, such as when it is already being captured, returns the
+    /// CUDA error value.
+    pub fn begin_capture(&self) -> CudaResult<()> {
+        unsafe {
+            driver_call!(cuda_driver_sys::cuStreamBeginCapture_v2(
+                self.inner,
+                CUstreamCaptureMode::CU_STREAM_CAPTURE_MODE_GLOBAL
+            ))
+            .to_result()
+        }
+    }
+
+    /// End the capture started by [`begin_capture`](#method.begin_capture), returning the graph of
+    /// the captured work.
+    ///
+    /// # Errors
+    ///
+    /// If the stream is not being captured or the capture was invalidated, returns the CUDA error
+    /// value.
+    pub fn end_capture(&self) -> CudaResult<Graph> {
+        let mut graph = ptr::null_mut();
+        unsafe {
+            driver_call!(cuda_driver_sys::cuStreamEndCapture(self.inner, &mut graph))
+                .to_result()?;
+        }
+        Ok(Graph::from_inner(graph))
+    }
+
+    /// Set the L2 cache access policy for kernels launched into this stream after this call.
+    ///
+    /// This is only effective on devices of compute capability 8.0 or higher, and requires a
+    /// non-zero [`ResourceLimit::PersistingL2CacheSize`](../context/enum.ResourceLimit.html#variant.PersistingL2CacheSize)
+    /// for `Persisting` accesses to be kept.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidValue` if `hit_ratio` is not between 0 and 1, and `NotSupported` if the
+    /// driver is older than CUDA 11.0. If the window is larger than the device allows, or the
+    /// attribute can't be set, returns the CUDA error value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rustacuda::*;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # let _ctx = quick_init()?;
+    /// use rustacuda::context::{CurrentContext, ResourceLimit};
+    /// use rustacuda::memory::DeviceBuffer;
+    /// use rustacuda::stream::{AccessPolicyWindow, Stream, StreamFlags};
+    ///
+    /// let table = DeviceBuffer::from_slice(&[0u32; 1024])?;
+    /// let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+    /// if CurrentContext::set_resource_limit(ResourceLimit::PersistingL2CacheSize, 4096).is_ok() {
+    ///     stream.set_access_policy_window(&AccessPolicyWindow::persisting(&table))?;
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_access_policy_window(&self, window: &AccessPolicyWindow) -> CudaResult<()> {
+        if !(0.0..=1.0).contains(&window.hit_ratio) {
+            return Err(CudaError::InvalidValue);
+        }
+        let set = stream_set_attribute_fn().ok_or(CudaError::NotSupported)?;
+        let value = StreamAttrValue {
+            window: *window,
+            reserved: [0; STREAM_ATTR_VALUE_RESERVED],
+        };
+        unsafe { set(self.inner, CU_STREAM_ATTRIBUTE_ACCESS_POLICY_WINDOW, &value).to_result() }
+    }
+
+    /// Returns the L2 cache access policy of this stream. Streams start with an empty window.
+    ///
+    /// # Errors
+    ///
+    /// Returns `NotSupported` if the driver is older than CUDA 11.0. If the attribute can't be
+    /// read, returns the CUDA error value.
+    pub fn get_access_policy_window(&self) -> CudaResult<AccessPolicyWindow> {
+        let get = stream_get_attribute_fn().ok_or(CudaError::NotSupported)?;
+        let mut value = StreamAttrValue {
+            window: AccessPolicyWindow {
+                base_ptr: 0,
+                num_bytes: 0,
+                hit_ratio: 0.0,
+                hit_prop: AccessProperty::Normal,
+                miss_prop: AccessProperty::Normal,
+            },
+            reserved: [0; STREAM_ATTR_VALUE_RESERVED],
+        };
+        unsafe {
+            get(
+                self.inner,
+                CU_STREAM_ATTRIBUTE_ACCESS_POLICY_WINDOW,
+                &mut value,
+            )
+            .to_result()?;
+        }
+        Ok(value.window)
+    }
+
+    /// Remove the L2 cache access policy of this stream, by setting an empty window.
+    ///
+    /// Lines which already persist in L2 stay there until they are evicted by normal accesses;
+    /// see [`CurrentContext::reset_persisting_l2_cache`](../context/struct.CurrentContext.html#method.reset_persisting_l2_cache).
+    ///
+    /// # Errors
+    ///
+    /// Returns `NotSupported` if the driver is older than CUDA 11.0. If the attribute can't be
+    /// set, returns the CUDA error value.
+    pub fn reset_access_policy_window(&self) -> CudaResult<()> {
+        self.set_access_policy_window(&AccessPolicyWindow {
+            base_ptr: 0,
+            num_bytes: 0,
+            hit_ratio: 0.0,
+            hit_prop: AccessProperty::Normal,
+            miss_prop: AccessProperty::Normal,
+        })
     }
 
     // Hidden implementation detail function. Highly unsafe. Use the `launch!` macro instead.
     #[doc(hidden)]
+    #[track_caller]
     pub unsafe fn launch<G, B>(
         &self,
         func: &Function,
@@ -276,8 +947,10 @@ impl Stream {
     {
         let grid_size: GridSize = grid_size.into();
         let block_size: BlockSize = block_size.into();
+        func.check_context()?;
+        func.check_stream(self)?;
 
-        cuda_driver_sys::cuLaunchKernel(
+        driver_call!(cuda_driver_sys::cuLaunchKernel(
             func.to_inner(),
             grid_size.x,
             grid_size.y,
@@ -289,8 +962,137 @@ impl Stream {
             self.inner,
             args.as_ptr() as *mut _,
             ptr::null_mut(),
+        ))
+        .to_result()?;
+        self.trace(|| {
+            format!(
+                "kernel launch <<<{:?}, {:?}, {}>>>",
+                grid_size, block_size, shared_mem_bytes
+            )
+        });
+        Ok(())
+    }
+
+    // Hidden implementation detail function. Highly unsafe. Use the `launch!` macro instead.
+    #[doc(hidden)]
+    #[track_caller]
+    pub unsafe fn launch_with_config(
+        &self,
+        func: &Function,
+        config: &LaunchConfig,
+        args: &[*mut c_void],
+    ) -> CudaResult<()> {
+        if config.attributes.is_empty() {
+            return self.launch(
+                func,
+                config.grid.clone(),
+                config.block.clone(),
+                config.shared_mem_bytes,
+                args,
+            );
+        }
+        let launch_ex = launch_kernel_ex_fn().ok_or(CudaError::NotSupported)?;
+        func.check_context()?;
+        func.check_stream(self)?;
+
+        let mut attributes = config
+            .attributes
+            .iter()
+            .map(RawLaunchAttribute::new)
+            .collect::<CudaResult<Vec<_>>>()?;
+        let raw = RawLaunchConfig {
+            grid_dim: [config.grid.x, config.grid.y, config.grid.z],
+            block_dim: [config.block.x, config.block.y, config.block.z],
+            shared_mem_bytes: config.shared_mem_bytes,
+            stream: self.inner,
+            attributes: attributes.as_mut_ptr(),
+            num_attributes: attributes.len() as u32,
+        };
+        launch_ex(
+            &raw,
+            func.to_inner(),
+            args.as_ptr() as *mut _,
+            ptr::null_mut(),
         )
-        .to_result()
+        .to_result()?;
+        self.trace(|| {
+            format!(
+                "kernel launch <<<{:?}, {:?}, {}>>> with {:?}",
+                config.grid, config.block, config.shared_mem_bytes, config.attributes
+            )
+        });
+        Ok(())
+    }
+
+    /// Returns and clears the first asynchronous error recorded on this stream, along with the
+    /// operation which caused it and where that operation was enqueued.
+    ///
+    /// Faults are only recorded with the `stream-trace` feature enabled, which adds a host callback
+    /// after every kernel launch, asynchronous copy, asynchronous memset and graph launch; without
+    /// it, this always returns `None`. Faults are recorded per `Stream` value, so a fault on a
+    /// stream enqueued through a [`BorrowedStream`](struct.BorrowedStream.html) is only reported
+    /// by that `BorrowedStream`, unless it borrows one of the default streams. See also
+    /// [`synchronize_traced`](#method.synchronize_traced).
+    pub fn take_fault(&self) -> Option<StreamFault> {
+        trace::take_fault(self.id)
+    }
+
+    /// Wait until the stream's tasks are completed, like [`synchronize`](#method.synchronize),
+    /// but report an error together with the operation which caused it, if it is known.
+    ///
+    /// # Errors
+    ///
+    /// If synchronizing fails, returns the error along with the fault recorded by
+    /// [`take_fault`](#method.take_fault). With the `stream-trace` feature disabled, or for errors
+    /// not caused by a traced launch, the operation and location are `None`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rustacuda::*;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # let _ctx = quick_init()?;
+    /// use rustacuda::stream::{Stream, StreamFlags};
+    ///
+    /// let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+    /// // Launch kernels here.
+    /// if let Err(fault) = stream.synchronize_traced() {
+    ///     // eg. "an illegal memory access was encountered (in kernel launch <<<...>>> at src/main.rs:12:5)"
+    ///     eprintln!("{}", fault);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn synchronize_traced(&self) -> Result<(), StreamFault> {
+        self.synchronize().map_err(|error| {
+            let fault = self.take_fault();
+            StreamFault {
+                error,
+                operation: fault.as_ref().and_then(|fault| fault.operation.clone()),
+                location: fault.and_then(|fault| fault.location),
+            }
+        })
+    }
+
+    // Wrap a handle without taking ownership of it, giving it the id of the default stream it
+    // refers to, if any.
+    fn wrap(handle: CUstream) -> Stream {
+        let id = match handle as usize {
+            0 | CU_STREAM_LEGACY => StreamId::LEGACY,
+            CU_STREAM_PER_THREAD => StreamId::PER_THREAD,
+            _ => StreamId::unique(),
+        };
+        Stream { inner: handle, id }
+    }
+
+    // Records that `operation` was enqueued on this stream from the caller's location, for
+    // `take_fault`. The description is only built when the `stream-trace` feature is enabled.
+    #[track_caller]
+    pub(crate) fn trace<F: FnOnce() -> String>(&self, operation: F) {
+        if trace::enabled() {
+            trace::enqueued(self, self.id, operation(), Location::caller());
+        }
     }
 
     // Get the inner `CUstream` from the `Stream`.
@@ -301,6 +1103,86 @@ impl Stream {
         self.inner
     }
 
+    /// Returns the raw CUDA stream handle, for passing to other CUDA libraries such as cuBLAS
+    /// (`cublasSetStream`) or to driver API functions that RustaCUDA does not wrap.
+    ///
+    /// The stream is still owned by this `Stream`, so the handle must not be destroyed, and must
+    /// not be used after this `Stream` is dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rustacuda::*;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # let _ctx = quick_init()?;
+    /// use rustacuda::stream::{Stream, StreamFlags};
+    ///
+    /// let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+    /// let handle = stream.as_raw();
+    /// // Pass `handle` to a library which submits work to it here.
+    /// # let _ = handle;
+    /// stream.synchronize()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn as_raw(&self) -> CUstream {
+        self.inner
+    }
+
+    /// Constructs a `Stream` from a raw CUDA stream handle, taking ownership of it.
+    ///
+    /// After calling this function, the stream is owned by the `Stream`, and the `Stream`
+    /// destructor will destroy it. This function may accept any handle produced by
+    /// `cuStreamCreate` or `cuStreamCreateWithPriority`, such as one taken from
+    /// `Stream::into_raw`. Streams which belong to someone else, such as one created by another
+    /// library, must not be wrapped this way unless the `Stream` is later converted back with
+    /// `into_raw` instead of being dropped. The special handles for the default streams are never
+    /// destroyed; see [`default_`](#method.default_).
+    ///
+    /// # Safety
+    ///
+    /// This function is unsafe because improper use may lead to use-after-free problems. For
+    /// example, the stream may be destroyed twice if this function is called twice on the same
+    /// handle, and the program may crash if the handle is not a valid CUDA stream.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rustacuda::*;
+    /// # use std::error::Error;
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # let _ctx = quick_init()?;
+    /// use rustacuda::stream::{Stream, StreamFlags};
+    ///
+    /// let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+    /// let handle = stream.into_raw();
+    /// let stream = unsafe { Stream::from_raw(handle) };
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub unsafe fn from_raw(handle: CUstream) -> Self {
+        let stream = Stream::wrap(handle);
+        if !stream.is_default() {
+            teardown::created(Resource::Stream);
+        }
+        stream
+    }
+
+    /// Consumes the `Stream`, returning the raw CUDA stream handle.
+    ///
+    /// After calling this function, the caller is responsible for destroying the stream with
+    /// `cuStreamDestroy`. The easiest way to do so is to create a new `Stream` using the
+    /// `Stream::from_raw` function.
+    pub fn into_raw(self) -> CUstream {
+        let handle = self.inner;
+        if !self.is_default() {
+            teardown::destroyed(Resource::Stream);
+        }
+        mem::forget(self);
+        handle
+    }
+
     /// Destroy a `Stream`, returning an error.
     ///
     /// Destroying a stream can return errors from previous asynchronous work. This function
@@ -327,37 +1209,287 @@ impl Stream {
     /// # }
     /// ```
     pub fn drop(mut stream: Stream) -> DropResult<Stream> {
-        if stream.inner.is_null() {
+        if stream.is_default() {
+            mem::forget(stream);
             return Ok(());
         }
 
         unsafe {
             let inner = mem::replace(&mut stream.inner, ptr::null_mut());
-            match cuda_driver_sys::cuStreamDestroy_v2(inner).to_result() {
+            match driver_call!(cuda_driver_sys::cuStreamDestroy_v2(inner)).to_result() {
                 Ok(()) => {
+                    teardown::destroyed(Resource::Stream);
+                    trace::forget(stream.id);
                     mem::forget(stream);
                     Ok(())
                 }
-                Err(e) => Err((e, Stream { inner })),
+                Err(e) => Err((e, Stream { inner, ..stream })),
             }
         }
     }
 }
 impl Drop for Stream {
     fn drop(&mut self) {
-        if self.inner.is_null() {
+        if self.is_default() {
             return;
         }
 
         unsafe {
             let inner = mem::replace(&mut self.inner, ptr::null_mut());
             // No choice but to panic here.
-            cuda_driver_sys::cuStreamDestroy_v2(inner)
+            driver_call!(cuda_driver_sys::cuStreamDestroy_v2(inner))
                 .to_result()
-                .expect("Failed to destroy CUDA stream.");
+                .expect_in_drop("Failed to destroy CUDA stream.");
+            teardown::destroyed(Resource::Stream);
+            trace::forget(self.id);
+        }
+    }
+}
+
+/// A fixed set of streams created up front and shared between tasks.
+///
+/// Creating a stream per task is slow, and a program which does so from many threads can easily
+/// end up with thousands of streams. A `StreamPool` creates its streams once, and hands them out
+/// either round-robin with [`next_stream`](#method.next_stream), for independent work which only
+/// needs to be spread over the streams, or exclusively with [`checkout`](#method.checkout), for a
+/// task which needs a stream of its own until it is done. Round-robin handout doesn't skip
+/// checked-out streams, so pools should generally use one style or the other.
+///
+/// # Examples
+///
+/// ```
+/// # use rustacuda::*;
+/// # use std::error::Error;
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// # let _ctx = quick_init()?;
+/// use rustacuda::memory::{AsyncCopyDestination, DeviceBuffer};
+/// use rustacuda::stream::StreamPool;
+///
+/// let pool = StreamPool::new(4)?;
+/// let mut buffers = Vec::new();
+/// for _ in 0..8 {
+///     let mut buffer = DeviceBuffer::from_slice(&[0u32; 16])?;
+///     unsafe { buffer.async_copy_from(&[1u32; 16][..], pool.next_stream())? };
+///     buffers.push(buffer);
+/// }
+/// pool.synchronize()?;
+///
+/// let stream = pool.checkout().expect("no free stream");
+/// stream.synchronize()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct StreamPool {
+    streams: Vec<Stream>,
+    next: AtomicUsize,
+    // Indices of the streams which are not checked out. A Mutex rather than a RefCell, so that the
+    // pool is Sync whenever Stream is Send.
+    free: Mutex<Vec<usize>>,
+}
+impl StreamPool {
+    /// Create a pool of `count` non-blocking streams with default priority in the current
+    /// context.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidValue` if `count` is zero. If creating a stream fails, returns the error
+    /// from CUDA.
+    pub fn new(count: usize) -> CudaResult<Self> {
+        StreamPool::from_builder(count, StreamBuilder::new().non_blocking())
+    }
+
+    /// Create a pool of `count` streams configured by `builder`, such as a pool of high-priority
+    /// streams.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidValue` if `count` is zero. If building a stream fails, returns the error
+    /// from [`StreamBuilder::build`](struct.StreamBuilder.html#method.build).
+    pub fn from_builder(count: usize, builder: StreamBuilder) -> CudaResult<Self> {
+        if count == 0 {
+            return Err(CudaError::InvalidValue);
+        }
+        let streams = (0..count)
+            .map(|_| builder.clone().build())
+            .collect::<CudaResult<Vec<_>>>()?;
+        Ok(StreamPool {
+            streams,
+            next: AtomicUsize::new(0),
+            free: Mutex::new((0..count).rev().collect()),
+        })
+    }
+
+    /// Returns the number of streams in the pool.
+    pub fn len(&self) -> usize {
+        self.streams.len()
+    }
+
+    /// Returns `true` if the pool has no streams. Pools always have at least one stream, so this
+    /// is always `false`.
+    pub fn is_empty(&self) -> bool {
+        self.streams.is_empty()
+    }
+
+    pub(crate) fn streams(&self) -> &[Stream] {
+        &self.streams
+    }
+
+    /// Returns the next stream of the pool in round-robin order.
+    pub fn next_stream(&self) -> &Stream {
+        let index = self.next.fetch_add(1, Ordering::Relaxed) % self.streams.len();
+        &self.streams[index]
+    }
+
+    /// Take a stream for exclusive use until the returned guard is dropped, or `None` if every
+    /// stream is checked out.
+    ///
+    /// Work submitted to the stream keeps running after the guard is dropped; the next task to
+    /// check the stream out just queues its work behind it.
+    pub fn checkout(&self) -> Option<PooledStream<'_>> {
+        let index = lock_ignoring_poison(&self.free).pop()?;
+        Some(PooledStream { pool: self, index })
+    }
+
+    /// Returns the number of streams which are not checked out.
+    pub fn available(&self) -> usize {
+        lock_ignoring_poison(&self.free).len()
+    }
+
+    /// Wait for all work submitted to every stream of the pool to complete.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error from synchronizing a stream. The remaining streams are still
+    /// synchronized.
+    pub fn synchronize(&self) -> CudaResult<()> {
+        let mut result = Ok(());
+        for stream in &self.streams {
+            if let (Ok(()), Err(e)) = (&result, stream.synchronize()) {
+                result = Err(e);
+            }
+        }
+        result
+    }
+}
+
+/// A stream checked out of a [`StreamPool`](struct.StreamPool.html) for exclusive use.
+///
+/// This dereferences to the `Stream`. When dropped, the stream is returned to the pool.
+#[derive(Debug)]
+pub struct PooledStream<'a> {
+    pool: &'a StreamPool,
+    index: usize,
+}
+impl Deref for PooledStream<'_> {
+    type Target = Stream;
+
+    fn deref(&self) -> &Stream {
+        &self.pool.streams[self.index]
+    }
+}
+impl Drop for PooledStream<'_> {
+    fn drop(&mut self) {
+        lock_ignoring_poison(&self.pool.free).push(self.index);
+    }
+}
+
+/// An asynchronous error reported by a stream, with the operation which caused it if it was
+/// recorded. See [`Stream::synchronize_traced`](struct.Stream.html#method.synchronize_traced).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StreamFault {
+    /// The error reported by the driver.
+    pub error: CudaError,
+    /// A description of the operation which caused the error, such as the launch configuration
+    /// of a kernel.
+    pub operation: Option<String>,
+    /// Where the operation was submitted from.
+    pub location: Option<&'static Location<'static>>,
+}
+impl fmt::Display for StreamFault {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.error)?;
+        match (&self.operation, self.location) {
+            (Some(operation), Some(location)) => write!(f, " (in {} at {})", operation, location),
+            (Some(operation), None) => write!(f, " (in {})", operation),
+            (None, Some(location)) => write!(f, " (at {})", location),
+            (None, None) => Ok(()),
+        }
+    }
+}
+impl Error for StreamFault {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// A stream created outside of RustaCUDA, such as by a framework which RustaCUDA code is embedded
+/// in, borrowed for the lifetime `'a`.
+///
+/// This dereferences to a [`Stream`](struct.Stream.html), so it can be used with `launch!`,
+/// asynchronous copies and events like any other stream. Dropping it does nothing; the stream
+/// stays owned by whoever created it.
+///
+/// # Examples
+///
+/// ```
+/// # use rustacuda::*;
+/// # use std::error::Error;
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// # let _ctx = quick_init()?;
+/// # let owner = stream::Stream::new(stream::StreamFlags::NON_BLOCKING, None)?;
+/// # let raw_stream = owner.as_raw();
+/// use rustacuda::memory::{AsyncCopyDestination, DeviceBox};
+/// use rustacuda::stream::BorrowedStream;
+///
+/// // `raw_stream` is a `CUstream` handed to us by the host application.
+/// let stream = unsafe { BorrowedStream::from_raw(raw_stream) };
+/// let mut x = DeviceBox::new(&0u32)?;
+/// unsafe { x.async_copy_from(&5u32, &stream)? };
+/// stream.synchronize()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct BorrowedStream<'a> {
+    stream: ManuallyDrop<Stream>,
+    _marker: PhantomData<&'a ()>,
+}
+impl<'a> BorrowedStream<'a> {
+    /// Borrows a stream from its raw CUDA stream handle.
+    ///
+    /// # Safety
+    ///
+    /// `handle` must be a valid CUDA stream, or one of the special handles for the default
+    /// streams, which stays alive for the lifetime `'a`.
+    pub unsafe fn from_raw(handle: CUstream) -> Self {
+        BorrowedStream {
+            stream: ManuallyDrop::new(Stream::wrap(handle)),
+            _marker: PhantomData,
         }
     }
 }
+impl Deref for BorrowedStream<'_> {
+    type Target = Stream;
+
+    fn deref(&self) -> &Stream {
+        &self.stream
+    }
+}
+
+// Check that the device of the current context supports the stream memory operations that
+// `attribute` covers, and `condition` if it is a wait.
+fn check_mem_ops(attribute: DeviceAttribute, condition: WaitCondition) -> CudaResult<()> {
+    let device = CurrentContext::get_device()?;
+    if device.get_attribute(attribute)? == 0
+        || (condition == WaitCondition::Nor
+            && device.get_attribute(DeviceAttribute::CanUseStreamWaitValueNor)? == 0)
+    {
+        return Err(CudaError::NotSupported);
+    }
+    Ok(())
+}
+
 unsafe extern "C" fn callback_wrapper<T>(
     _stream: CUstream,
     status: cudaError_enum,
@@ -371,3 +1503,84 @@ unsafe extern "C" fn callback_wrapper<T>(
         callback(status.to_result());
     });
 }
+
+// The special handles for the default streams, which the bindings don't define.
+const CU_STREAM_LEGACY: usize = 0x1;
+const CU_STREAM_PER_THREAD: usize = 0x2;
+
+// CUstreamAttrID_enum, from CUDA 11.0
+const CU_STREAM_ATTRIBUTE_ACCESS_POLICY_WINDOW: u32 = 1;
+
+// The padding which makes StreamAttrValue as large as the largest member of CUstreamAttrValue in
+// any driver version.
+const STREAM_ATTR_VALUE_RESERVED: usize = 64 - size_of::<AccessPolicyWindow>();
+
+// CUstreamAttrValue, with only the access policy window member
+#[repr(C)]
+struct StreamAttrValue {
+    window: AccessPolicyWindow,
+    reserved: [u8; STREAM_ATTR_VALUE_RESERVED],
+}
+
+type StreamSetAttributeFn = unsafe extern "C" fn(CUstream, u32, *const StreamAttrValue) -> CUresult;
+type StreamGetAttributeFn = unsafe extern "C" fn(CUstream, u32, *mut StreamAttrValue) -> CUresult;
+
+driver_fn!(stream_set_attribute_fn: StreamSetAttributeFn = b"cuStreamSetAttribute\0");
+driver_fn!(stream_get_attribute_fn: StreamGetAttributeFn = b"cuStreamGetAttribute\0");
+
+// CUlaunchAttributeID_enum, from CUDA 12.0
+const CU_LAUNCH_ATTRIBUTE_CLUSTER_DIMENSION: u32 = 4;
+const CU_LAUNCH_ATTRIBUTE_PROGRAMMATIC_STREAM_SERIALIZATION: u32 = 6;
+const CU_LAUNCH_ATTRIBUTE_MEM_SYNC_DOMAIN: u32 = 10;
+
+// CUlaunchAttribute. The value is a 64-byte union, aligned for the pointers some of its members
+// hold; every member used here is made of 32-bit integers.
+#[repr(C, align(8))]
+struct RawLaunchAttribute {
+    id: u32,
+    pad: u32,
+    value: [u32; 16],
+}
+impl RawLaunchAttribute {
+    fn new(attribute: &LaunchAttribute) -> CudaResult<RawLaunchAttribute> {
+        let mut value = [0; 16];
+        let id = match *attribute {
+            LaunchAttribute::ClusterDimension(x, y, z) => {
+                value[..3].copy_from_slice(&[x, y, z]);
+                CU_LAUNCH_ATTRIBUTE_CLUSTER_DIMENSION
+            }
+            LaunchAttribute::ProgrammaticStreamSerialization(allowed) => {
+                value[0] = allowed as u32;
+                CU_LAUNCH_ATTRIBUTE_PROGRAMMATIC_STREAM_SERIALIZATION
+            }
+            LaunchAttribute::MemSyncDomain(domain) => {
+                if domain == MemSyncDomain::__Nonexhaustive {
+                    return Err(CudaError::InvalidValue);
+                }
+                value[0] = domain as u32;
+                CU_LAUNCH_ATTRIBUTE_MEM_SYNC_DOMAIN
+            }
+        };
+        Ok(RawLaunchAttribute { id, pad: 0, value })
+    }
+}
+
+// CUlaunchConfig
+#[repr(C)]
+struct RawLaunchConfig {
+    grid_dim: [u32; 3],
+    block_dim: [u32; 3],
+    shared_mem_bytes: u32,
+    stream: CUstream,
+    attributes: *mut RawLaunchAttribute,
+    num_attributes: u32,
+}
+
+type LaunchKernelExFn = unsafe extern "C" fn(
+    *const RawLaunchConfig,
+    CUfunction,
+    *mut *mut c_void,
+    *mut *mut c_void,
+) -> CUresult;
+
+driver_fn!(launch_kernel_ex_fn: LaunchKernelExFn = b"cuLaunchKernelEx\0");
@@ -0,0 +1,26 @@
+//! Poison-tolerant locking for the state RustaCUDA shares between threads.
+//!
+//! Every lock in RustaCUDA guards state which is updated without panicking partway through, so the
+//! state is consistent even when a thread panicked while holding the lock (for example, in a
+//! user-provided hook). Poisoning therefore carries no information, and these helpers ignore it
+//! rather than propagating the panic to every later user of the lock.
+
+use std::sync::{Mutex, MutexGuard, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+/// Locks `mutex`, ignoring poisoning.
+pub(crate) fn lock_ignoring_poison<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Locks `lock` for reading, ignoring poisoning.
+pub(crate) fn read_ignoring_poison<T>(lock: &RwLock<T>) -> RwLockReadGuard<'_, T> {
+    lock.read().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Locks `lock` for writing, ignoring poisoning.
+pub(crate) fn write_ignoring_poison<T>(lock: &RwLock<T>) -> RwLockWriteGuard<'_, T> {
+    lock.write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
@@ -0,0 +1,149 @@
+//! Reporting of resources which are still alive when their context is destroyed.
+//!
+//! Destroying a context frees everything in it, so a stream, event, module or allocation which
+//! outlives its context is left dangling and usually makes the driver fail (and RustaCUDA panic)
+//! when it is dropped later. With the `teardown-stats` feature enabled, RustaCUDA counts the
+//! resources it creates in each context and logs a warning listing the ones which are still alive
+//! when a context is destroyed, which points at the teardown-order bug directly.
+//!
+//! Resources are attributed to whichever context is current when they are created and destroyed,
+//! so the counts are only accurate if resources are dropped while their own context is current.
+
+use cuda_driver_sys::CUcontext;
+
+/// The kinds of resources which are counted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(not(feature = "teardown-stats"), allow(dead_code))]
+pub(crate) enum Resource {
+    Stream,
+    Event,
+    Module,
+    Allocation,
+}
+
+#[cfg(feature = "teardown-stats")]
+mod imp {
+    use super::Resource;
+    use crate::sync::lock_ignoring_poison;
+    use cuda_driver_sys::CUcontext;
+    use std::ptr;
+    use std::sync::{Mutex, MutexGuard};
+
+    /// Live resource counts per context handle, indexed by `Resource`.
+    static LIVE: Mutex<Vec<(usize, [usize; 4])>> = Mutex::new(Vec::new());
+
+    fn live() -> MutexGuard<'static, Vec<(usize, [usize; 4])>> {
+        lock_ignoring_poison(&LIVE)
+    }
+
+    fn current_context() -> usize {
+        let mut ctx: CUcontext = ptr::null_mut();
+        let _ = unsafe { cuda_driver_sys::cuCtxGetCurrent(&mut ctx as *mut CUcontext) };
+        ctx as usize
+    }
+
+    pub fn created(resource: Resource) {
+        let ctx = current_context();
+        let mut live = live();
+        match live.iter_mut().find(|(handle, _)| *handle == ctx) {
+            Some((_, counts)) => counts[resource as usize] += 1,
+            None => {
+                let mut counts = [0; 4];
+                counts[resource as usize] = 1;
+                live.push((ctx, counts));
+            }
+        }
+    }
+
+    pub fn destroyed(resource: Resource) {
+        let ctx = current_context();
+        if let Some((_, counts)) = live().iter_mut().find(|(handle, _)| *handle == ctx) {
+            counts[resource as usize] = counts[resource as usize].saturating_sub(1);
+        }
+    }
+
+    pub fn context_destroyed(ctx: CUcontext) {
+        let counts = {
+            let mut live = live();
+            match live.iter().position(|(handle, _)| *handle == ctx as usize) {
+                Some(index) => live.swap_remove(index).1,
+                None => return,
+            }
+        };
+        if counts.iter().any(|&count| count != 0) {
+            log::warn!(
+                "CUDA context {:p} was destroyed with {} streams, {} events, {} modules and {} \
+                 allocations still alive; dropping them will fail",
+                ctx,
+                counts[Resource::Stream as usize],
+                counts[Resource::Event as usize],
+                counts[Resource::Module as usize],
+                counts[Resource::Allocation as usize],
+            );
+        }
+    }
+
+    #[cfg(test)]
+    pub fn live_count(resource: Resource) -> usize {
+        let ctx = current_context();
+        live()
+            .iter()
+            .find(|(handle, _)| *handle == ctx)
+            .map_or(0, |(_, counts)| counts[resource as usize])
+    }
+}
+
+#[cfg(not(feature = "teardown-stats"))]
+mod imp {
+    use super::Resource;
+    use cuda_driver_sys::CUcontext;
+
+    pub fn created(_resource: Resource) {}
+
+    pub fn destroyed(_resource: Resource) {}
+
+    pub fn context_destroyed(_ctx: CUcontext) {}
+}
+
+/// Records that a resource was created in the current context.
+pub(crate) fn created(resource: Resource) {
+    imp::created(resource)
+}
+
+/// Records that a resource in the current context was destroyed.
+pub(crate) fn destroyed(resource: Resource) {
+    imp::destroyed(resource)
+}
+
+/// Reports the resources still alive in `ctx`, which has just been destroyed, and forgets them.
+pub(crate) fn context_destroyed(ctx: CUcontext) {
+    imp::context_destroyed(ctx)
+}
+
+#[cfg(all(test, feature = "teardown-stats"))]
+mod test {
+    use super::*;
+    use crate::event::{Event, EventFlags};
+    use crate::memory::DeviceBuffer;
+    use crate::stream::{Stream, StreamFlags};
+
+    #[test]
+    fn test_live_counts() {
+        let _context = crate::quick_init().unwrap();
+        let streams = imp::live_count(Resource::Stream);
+        let events = imp::live_count(Resource::Event);
+        let allocations = imp::live_count(Resource::Allocation);
+        {
+            let _stream = Stream::new(StreamFlags::NON_BLOCKING, None).unwrap();
+            let event = Event::new(EventFlags::DEFAULT).unwrap();
+            let _buffer = DeviceBuffer::from_slice(&[0u32; 16]).unwrap();
+            assert_eq!(streams + 1, imp::live_count(Resource::Stream));
+            assert_eq!(events + 1, imp::live_count(Resource::Event));
+            assert_eq!(allocations + 1, imp::live_count(Resource::Allocation));
+            Event::drop(event).unwrap();
+            assert_eq!(events, imp::live_count(Resource::Event));
+        }
+        assert_eq!(streams, imp::live_count(Resource::Stream));
+        assert_eq!(allocations, imp::live_count(Resource::Allocation));
+    }
+}
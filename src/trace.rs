@@ -0,0 +1,242 @@
+//! Tracing of asynchronous errors back to the stream operation which caused them.
+//!
+//! Errors from kernels only surface at the next synchronizing call, often far from the launch
+//! which caused them. With the `stream-trace` feature enabled, RustaCUDA enqueues a host callback
+//! after each kernel launch, asynchronous copy, asynchronous memset and graph launch. The driver
+//! passes the callback the stream's error status, so the first callback which sees an error
+//! identifies the operation which failed, and its description and call site are recorded for
+//! [`Stream::take_fault`](../stream/struct.Stream.html#method.take_fault).
+//!
+//! Operations on a stream which is being captured into a graph are not traced, since host
+//! callbacks can't be added to a capturing stream.
+
+use crate::stream::{Stream, StreamFault};
+use std::panic::Location;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Identifies a `Stream` for fault tracking.
+///
+/// Faults are keyed by this rather than by the stream handle, since the driver can hand the handle
+/// of a destroyed stream out again, and a callback still queued on the old stream must not record a
+/// fault against the new one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct StreamId(u64);
+impl StreamId {
+    /// The id shared by all handles to the legacy default stream, which is never destroyed.
+    pub(crate) const LEGACY: StreamId = StreamId(0);
+    /// The id shared by all handles to the per-thread default stream, which is never destroyed.
+    pub(crate) const PER_THREAD: StreamId = StreamId(1);
+
+    /// Returns an id which no other stream has.
+    pub(crate) fn unique() -> StreamId {
+        static NEXT: AtomicU64 = AtomicU64::new(2);
+        StreamId(NEXT.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+#[cfg(feature = "stream-trace")]
+mod imp {
+    use super::StreamId;
+    use crate::error::CudaResult;
+    use crate::stream::{Stream, StreamFault};
+    use crate::sync::lock_ignoring_poison;
+    use cuda_driver_sys::{CUstream, CUstreamCaptureStatus};
+    use std::panic::Location;
+    use std::sync::{Mutex, MutexGuard};
+
+    /// The first fault seen on each stream, by stream id, until it is taken. A callback which
+    /// completes after its stream was dropped leaves a fault which is never taken, but only one per
+    /// stream, and such streams are rare since faults generally leave the context unusable.
+    static FAULTS: Mutex<Vec<(StreamId, StreamFault)>> = Mutex::new(Vec::new());
+
+    fn faults() -> MutexGuard<'static, Vec<(StreamId, StreamFault)>> {
+        lock_ignoring_poison(&FAULTS)
+    }
+
+    fn is_capturing(stream: CUstream) -> bool {
+        let mut status = CUstreamCaptureStatus::CU_STREAM_CAPTURE_STATUS_NONE;
+        let result = unsafe { cuda_driver_sys::cuStreamIsCapturing(stream, &mut status) };
+        result != cuda_driver_sys::cudaError_enum::CUDA_SUCCESS
+            || status != CUstreamCaptureStatus::CU_STREAM_CAPTURE_STATUS_NONE
+    }
+
+    fn completed(
+        stream: StreamId,
+        operation: String,
+        location: &'static Location<'static>,
+        status: CudaResult<()>,
+    ) {
+        if let Err(error) = status {
+            let mut faults = faults();
+            if !faults.iter().any(|(id, _)| *id == stream) {
+                faults.push((
+                    stream,
+                    StreamFault {
+                        error,
+                        operation: Some(operation),
+                        location: Some(location),
+                    },
+                ));
+            }
+        }
+    }
+
+    pub fn enqueued(
+        stream: &Stream,
+        id: StreamId,
+        operation: String,
+        location: &'static Location<'static>,
+    ) {
+        if is_capturing(stream.as_inner()) {
+            return;
+        }
+        let callback =
+            Box::new(move |status: CudaResult<()>| completed(id, operation, location, status));
+        // If the callback can't be added, the stream is already broken and the error will be
+        // reported by the next call which uses it.
+        let _ = stream.add_callback(callback);
+    }
+
+    pub fn take_fault(stream: StreamId) -> Option<StreamFault> {
+        let mut faults = faults();
+        let index = faults.iter().position(|(id, _)| *id == stream)?;
+        Some(faults.swap_remove(index).1)
+    }
+
+    pub fn forget(stream: StreamId) {
+        faults().retain(|(id, _)| *id != stream);
+    }
+}
+
+#[cfg(not(feature = "stream-trace"))]
+mod imp {
+    use super::StreamId;
+    use crate::stream::{Stream, StreamFault};
+    use std::panic::Location;
+
+    pub fn enqueued(
+        _stream: &Stream,
+        _id: StreamId,
+        _operation: String,
+        _location: &'static Location<'static>,
+    ) {
+    }
+
+    pub fn take_fault(_stream: StreamId) -> Option<StreamFault> {
+        None
+    }
+
+    pub fn forget(_stream: StreamId) {}
+}
+
+/// Returns `true` if operations are traced, so that callers can skip building descriptions.
+pub(crate) fn enabled() -> bool {
+    cfg!(feature = "stream-trace")
+}
+
+/// Records that `operation` was enqueued on `stream`, whose id is `id`, from `location`.
+pub(crate) fn enqueued(
+    stream: &Stream,
+    id: StreamId,
+    operation: String,
+    location: &'static Location<'static>,
+) {
+    imp::enqueued(stream, id, operation, location)
+}
+
+/// Returns and forgets the first fault recorded on the stream with id `stream`.
+pub(crate) fn take_fault(stream: StreamId) -> Option<StreamFault> {
+    imp::take_fault(stream)
+}
+
+/// Forgets any fault recorded on the stream with id `stream`, which has just been destroyed.
+pub(crate) fn forget(stream: StreamId) {
+    imp::forget(stream)
+}
+
+#[cfg(all(test, feature = "stream-trace"))]
+mod test {
+    use crate::error::CudaError;
+    use crate::memory::{DeviceBuffer, DevicePointer};
+    use crate::module::Module;
+    use crate::stream::{Stream, StreamFault, StreamFlags};
+    use std::ffi::{c_void, CString};
+    use std::mem;
+    use std::panic::Location;
+
+    #[test]
+    fn test_traced_launch() {
+        let _context = crate::quick_init().unwrap();
+        let ptx = CString::new(include_str!("../resources/add.ptx")).unwrap();
+        let module = Module::load_from_string(&ptx).unwrap();
+        let sum = module.get_function(&CString::new("sum").unwrap()).unwrap();
+        let stream = Stream::new(StreamFlags::NON_BLOCKING, None).unwrap();
+
+        let mut x = DeviceBuffer::from_slice(&[1.0f32; 4]).unwrap();
+        let mut y = DeviceBuffer::from_slice(&[2.0f32; 4]).unwrap();
+        let mut out = DeviceBuffer::from_slice(&[0.0f32; 4]).unwrap();
+        let (mut a, mut b, mut c, mut n) = (
+            x.as_device_ptr(),
+            y.as_device_ptr(),
+            out.as_device_ptr(),
+            4i32,
+        );
+        let args = [
+            &mut a as *mut _ as *mut c_void,
+            &mut b as *mut _ as *mut c_void,
+            &mut c as *mut _ as *mut c_void,
+            &mut n as *mut _ as *mut c_void,
+        ];
+        unsafe { stream.launch(&sum, 1, 4, 0, &args).unwrap() };
+        stream.synchronize_traced().unwrap();
+        assert_eq!(None, stream.take_fault());
+
+        let location = Location::caller();
+        let fault = StreamFault {
+            error: CudaError::IllegalAddress,
+            operation: Some("kernel launch".to_string()),
+            location: Some(location),
+        };
+        assert!(fault
+            .to_string()
+            .ends_with(&format!(" (in kernel launch at {})", location)));
+    }
+
+    #[test]
+    fn test_traced_fault() {
+        let _context = crate::quick_init().unwrap();
+        let ptx = CString::new(include_str!("../resources/add.ptx")).unwrap();
+        let module = Module::load_from_string(&ptx).unwrap();
+        let sum = module.get_function(&CString::new("sum").unwrap()).unwrap();
+        let stream = Stream::new(StreamFlags::NON_BLOCKING, None).unwrap();
+
+        // Writing the output through a null pointer faults.
+        let mut x = DeviceBuffer::from_slice(&[1.0f32; 4]).unwrap();
+        let (mut a, mut b, mut c, mut n) = (
+            x.as_device_ptr(),
+            x.as_device_ptr(),
+            DevicePointer::<f32>::null(),
+            4i32,
+        );
+        let args = [
+            &mut a as *mut _ as *mut c_void,
+            &mut b as *mut _ as *mut c_void,
+            &mut c as *mut _ as *mut c_void,
+            &mut n as *mut _ as *mut c_void,
+        ];
+        let line = line!() + 1;
+        unsafe { stream.launch(&sum, 1, 4, 0, &args).unwrap() };
+        let fault = stream.synchronize_traced().unwrap_err();
+        assert_eq!(CudaError::IllegalAddress, fault.error);
+        assert!(fault.operation.unwrap().starts_with("kernel launch <<<"));
+        let location = fault.location.unwrap();
+        assert_eq!(file!(), location.file());
+        assert_eq!(line, location.line());
+        assert_eq!(None, stream.take_fault());
+
+        // The context is unusable after the fault, so releasing its resources would fail.
+        mem::forget(x);
+        mem::forget(stream);
+        mem::forget(module);
+    }
+}
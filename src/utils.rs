@@ -0,0 +1,68 @@
+//! Helpers for benchmarking and testing CUDA code.
+
+use crate::error::CudaResult;
+use crate::memory::{CopyDestination, DeviceBuffer};
+use crate::module;
+use crate::stream::Stream;
+
+/// Prepare the current context for benchmarking, so that one-time costs aren't included in the
+/// first measurement.
+///
+/// The driver initializes much of a context lazily: the first module load, kernel launch,
+/// allocation and copy are all considerably slower than later ones. This loads a module with an
+/// empty kernel embedded in RustaCUDA (once per context), launches the kernel on `stream`, makes
+/// a small allocation and copy, and then blocks until `stream` has finished, so that measuring
+/// can start from an idle stream. It does not compile the benchmarked kernels
+/// themselves; run each of them once before measuring to exclude their JIT compilation, or load
+/// them from a cubin built for the device.
+///
+/// Warming up doesn't make the GPU's clock speed stable. GPUs raise their clocks under load and
+/// lower them when idle or hot, so short benchmarks can vary widely from run to run. For
+/// repeatable results, consider enabling persistence mode (`nvidia-smi -pm 1`) so the driver
+/// isn't reinitialized between runs, and locking the clocks (`nvidia-smi --lock-gpu-clocks`,
+/// which needs administrator rights and a supported GPU) for the duration of the benchmark.
+///
+/// # Errors
+///
+/// If loading the module, launching the kernel, allocating, copying or synchronizing fails,
+/// returns the error from CUDA.
+///
+/// # Examples
+///
+/// ```
+/// # let _context = rustacuda::quick_init().unwrap();
+/// use rustacuda::stream::{Stream, StreamFlags};
+/// use rustacuda::utils;
+///
+/// let stream = Stream::new(StreamFlags::NON_BLOCKING, None).unwrap();
+/// utils::warmup(&stream).unwrap();
+/// // Start measuring here.
+/// ```
+pub fn warmup(stream: &Stream) -> CudaResult<()> {
+    module::with_builtin_function(
+        include_str!("../resources/warmup.ptx"),
+        "warmup",
+        |kernel| unsafe { stream.launch(kernel, 1, 1, 0, &[]) },
+    )??;
+
+    let mut buffer = DeviceBuffer::from_slice(&[0u8; 64])?;
+    buffer.copy_from(&[1u8; 64][..])?;
+    let mut host = [0u8; 64];
+    buffer.copy_to(&mut host[..])?;
+
+    stream.synchronize()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::stream::StreamFlags;
+
+    #[test]
+    fn test_warmup() {
+        let _context = crate::quick_init().unwrap();
+        let stream = Stream::new(StreamFlags::NON_BLOCKING, None).unwrap();
+        warmup(&stream).unwrap();
+        warmup(&stream).unwrap();
+    }
+}
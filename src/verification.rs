@@ -0,0 +1,312 @@
+//! Golden testing of kernels against host implementations.
+//!
+//! This module is only available with the `verification` feature enabled.
+//!
+//! A kernel launched with the `verify` form of the [`launch!`](../macro.launch.html) macro is also
+//! checked against a host closure. Before the launch, the output buffer is copied to the host.
+//! After the launch, the closure is given that copy and computes the expected results into it. The
+//! output buffer is then compared with the expected results element by element within a
+//! tolerance, and any mismatches are reported as a
+//! [`VerificationError`](enum.VerificationError.html).
+//!
+//! Verification synchronizes the stream before and after the launch, so it is meant for tests
+//! rather than production code.
+//!
+//! # Examples
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate rustacuda;
+//! # use rustacuda::*;
+//! # use std::error::Error;
+//! # fn main() -> Result<(), Box<dyn Error>> {
+//! # let _ctx = quick_init()?;
+//! use rustacuda::memory::*;
+//! use rustacuda::module::Module;
+//! use rustacuda::stream::{Stream, StreamFlags};
+//! use std::ffi::CString;
+//!
+//! let ptx = CString::new(include_str!("../resources/add.ptx"))?;
+//! let module = Module::load_from_string(&ptx)?;
+//! let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+//!
+//! let (x, y) = ([1.0f32, 2.0, 3.0], [0.5f32, 0.25, 0.125]);
+//! let mut in_x = DeviceBuffer::from_slice(&x)?;
+//! let mut in_y = DeviceBuffer::from_slice(&y)?;
+//! let mut out = DeviceBuffer::from_slice(&[0.0f32; 3])?;
+//! unsafe {
+//!     launch!(module.sum<<<1, 3, 0, stream>>>(
+//!         in_x.as_device_ptr(),
+//!         in_y.as_device_ptr(),
+//!         out.as_device_ptr(),
+//!         3i32
+//!     ) verify(out, 1e-6, |expected: &mut [f32]| {
+//!         for i in 0..expected.len() {
+//!             expected[i] = x[i] + y[i];
+//!         }
+//!     }))?;
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::{CudaError, CudaResult, ToResult};
+use crate::memory::{DeviceCopy, DeviceSlice};
+use crate::stream::Stream;
+use std::error::Error;
+use std::fmt;
+use std::mem::size_of;
+use std::os::raw::c_void;
+
+/// Element types which can be compared within a tolerance.
+pub trait Approx: DeviceCopy + Copy + fmt::Debug {
+    /// Returns `true` if `self` and `other` differ by at most `tolerance`.
+    fn approx_eq(&self, other: &Self, tolerance: f64) -> bool;
+}
+
+macro_rules! impl_approx_float {
+    ($($t:ty)*) => {
+        $(
+            impl Approx for $t {
+                fn approx_eq(&self, other: &Self, tolerance: f64) -> bool {
+                    // NaN is never equal to itself, but a kernel which computes NaN where the host
+                    // does is correct.
+                    (self.is_nan() && other.is_nan())
+                        || f64::from(*self - *other).abs() <= tolerance
+                        || self == other
+                }
+            }
+        )*
+    };
+}
+impl_approx_float!(f32 f64);
+
+macro_rules! impl_approx_int {
+    ($($t:ty)*) => {
+        $(
+            impl Approx for $t {
+                fn approx_eq(&self, other: &Self, tolerance: f64) -> bool {
+                    self == other || (*self as f64 - *other as f64).abs() <= tolerance
+                }
+            }
+        )*
+    };
+}
+impl_approx_int!(i8 i16 i32 i64 isize u8 u16 u32 u64 usize);
+
+/// One element which differs between the device and host results.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Mismatch<T> {
+    /// The index of the element.
+    pub index: usize,
+    /// The value the kernel computed.
+    pub device: T,
+    /// The value the host closure computed.
+    pub host: T,
+}
+
+/// The error returned by a verified launch.
+#[derive(Debug, Clone, PartialEq)]
+pub enum VerificationError<T> {
+    /// Launching the kernel or copying its output failed.
+    Cuda(CudaError),
+    /// The kernel's output differs from the host closure's.
+    Mismatch {
+        /// The number of elements compared.
+        len: usize,
+        /// Every element which differs, in order of index.
+        mismatches: Vec<Mismatch<T>>,
+    },
+}
+impl<T> From<CudaError> for VerificationError<T> {
+    fn from(e: CudaError) -> Self {
+        VerificationError::Cuda(e)
+    }
+}
+impl<T: fmt::Debug> fmt::Display for VerificationError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VerificationError::Cuda(e) => e.fmt(f),
+            VerificationError::Mismatch { len, mismatches } => {
+                write!(
+                    f,
+                    "{} of {} elements differ from the host results",
+                    mismatches.len(),
+                    len
+                )?;
+                // Listing thousands of elements isn't useful; the first few show the pattern.
+                for m in mismatches.iter().take(8) {
+                    write!(
+                        f,
+                        "\n  [{}]: device {:?}, host {:?}",
+                        m.index, m.device, m.host
+                    )?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+impl<T: fmt::Debug> Error for VerificationError<T> {}
+
+/// A host copy of a device buffer, taken before a kernel launch and checked against the buffer
+/// after it.
+///
+/// This is what the `verify` form of [`launch!`](../macro.launch.html) uses; it can also be used
+/// directly to verify kernels launched some other way.
+#[derive(Debug)]
+pub struct HostMirror<T> {
+    data: Vec<T>,
+}
+impl<T: Approx> HostMirror<T> {
+    /// Wait for the work on `stream` to complete, then copy `output` to the host.
+    ///
+    /// # Errors
+    ///
+    /// If synchronizing or copying fails, returns the CUDA error value.
+    pub fn capture(stream: &Stream, output: &DeviceSlice<T>) -> CudaResult<Self> {
+        stream.synchronize()?;
+        let mut data: Vec<T> = Vec::with_capacity(output.len());
+        let size = size_of::<T>() * output.len();
+        unsafe {
+            // Copy into the spare capacity through a raw pointer, since it isn't initialized yet.
+            if size != 0 {
+                driver_call!(cuda_driver_sys::cuMemcpyDtoH_v2(
+                    data.as_mut_ptr() as *mut c_void,
+                    output.as_ptr() as u64,
+                    size,
+                ))
+                .to_result()?;
+            }
+            // Every element was written by the copy.
+            data.set_len(output.len());
+        }
+        Ok(HostMirror { data })
+    }
+
+    /// Wait for the work on `stream` to complete, call `reference` with the captured contents to
+    /// compute the expected results in place, and compare `output` with them.
+    ///
+    /// # Errors
+    ///
+    /// Returns `VerificationError::Mismatch` listing every element of `output` which differs from
+    /// the expected result by more than `tolerance`. If synchronizing or copying fails, or
+    /// `output` has a different length than the captured buffer, returns
+    /// `VerificationError::Cuda`.
+    pub fn verify<F>(
+        mut self,
+        stream: &Stream,
+        output: &DeviceSlice<T>,
+        tolerance: f64,
+        reference: F,
+    ) -> Result<(), VerificationError<T>>
+    where
+        F: FnOnce(&mut [T]),
+    {
+        if output.len() != self.data.len() {
+            return Err(CudaError::InvalidValue.into());
+        }
+        let after = HostMirror::capture(stream, output)?;
+        reference(&mut self.data);
+
+        let mismatches: Vec<_> = after
+            .data
+            .iter()
+            .zip(&self.data)
+            .enumerate()
+            .filter(|(_, (device, host))| !device.approx_eq(host, tolerance))
+            .map(|(index, (&device, &host))| Mismatch {
+                index,
+                device,
+                host,
+            })
+            .collect();
+        if mismatches.is_empty() {
+            Ok(())
+        } else {
+            Err(VerificationError::Mismatch {
+                len: self.data.len(),
+                mismatches,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::launch;
+    use crate::memory::DeviceBuffer;
+    use crate::module::Module;
+    use crate::stream::StreamFlags;
+    use std::ffi::CString;
+
+    #[test]
+    fn test_approx_eq() {
+        assert!(1.0f32.approx_eq(&1.05, 0.1));
+        assert!(!1.0f64.approx_eq(&1.2, 0.1));
+        assert!(f32::NAN.approx_eq(&f32::NAN, 0.0));
+        assert!(f64::INFINITY.approx_eq(&f64::INFINITY, 0.0));
+        assert!(3u32.approx_eq(&3, 0.0));
+        assert!(!3u32.approx_eq(&5, 1.0));
+        assert!((-2i64).approx_eq(&-1, 1.0));
+    }
+
+    #[test]
+    fn test_verified_launch() {
+        let _context = crate::quick_init().unwrap();
+        let ptx = CString::new(include_str!("../resources/add.ptx")).unwrap();
+        let module = Module::load_from_string(&ptx).unwrap();
+        let stream = Stream::new(StreamFlags::NON_BLOCKING, None).unwrap();
+
+        let mut x = DeviceBuffer::from_slice(&[1.0f32; 4]).unwrap();
+        let mut y = DeviceBuffer::from_slice(&[2.0f32; 4]).unwrap();
+        let mut out = DeviceBuffer::from_slice(&[0.0f32; 4]).unwrap();
+        unsafe {
+            launch!(module.sum<<<1, 4, 0, stream>>>(
+                x.as_device_ptr(),
+                y.as_device_ptr(),
+                out.as_device_ptr(),
+                4i32
+            ) verify(out, 0.0, |expected: &mut [f32]| {
+                expected.iter_mut().for_each(|e| *e = 3.0)
+            }))
+            .unwrap();
+
+            // A host closure which disagrees with the kernel is reported element by element.
+            let result = launch!(module.sum<<<1, 2, 0, stream>>>(
+                x.as_device_ptr(),
+                y.as_device_ptr(),
+                out.as_device_ptr(),
+                2i32
+            ) verify(out, 0.5, |expected: &mut [f32]| {
+                expected.copy_from_slice(&[3.0, 3.0, 3.0, 9.0])
+            }));
+            let mismatch = Mismatch {
+                index: 3,
+                device: 3.0,
+                host: 9.0,
+            };
+            assert_eq!(
+                Err(VerificationError::Mismatch {
+                    len: 4,
+                    mismatches: vec![mismatch],
+                }),
+                result
+            );
+        }
+    }
+
+    #[test]
+    fn test_length_mismatch() {
+        let _context = crate::quick_init().unwrap();
+        let stream = Stream::new(StreamFlags::NON_BLOCKING, None).unwrap();
+        let before = DeviceBuffer::from_slice(&[1u32; 4]).unwrap();
+        let after = DeviceBuffer::from_slice(&[1u32; 3]).unwrap();
+        let mirror = HostMirror::capture(&stream, &before).unwrap();
+        assert_eq!(
+            Err(VerificationError::Cuda(CudaError::InvalidValue)),
+            mirror.verify(&stream, &after, 0.0, |_| {})
+        );
+    }
+}
@@ -48,6 +48,66 @@ union TestUnion {
     i: i64,
 }
 
+#[derive(Clone, Copy, KernelStruct)]
+#[repr(C)]
+struct KernelParams {
+    data: rustacuda::memory::DevicePointer<f32>,
+    count: u32,
+    flags: [u8; 3],
+    inner: KernelTuple,
+}
+
+#[derive(Clone, Copy, KernelStruct)]
+#[repr(C)]
+struct KernelTuple(u16, f64);
+
+#[derive(Clone, Copy, KernelStruct)]
+#[repr(C)]
+struct KernelArrays {
+    pointers: [rustacuda::memory::DevicePointer<f32>; 2],
+    tuples: [KernelTuple; 3],
+}
+
+#[test]
+fn test_kernel_struct() {
+    use rustacuda::kernel_header::{c_declaration, KernelField, KernelStruct};
+
+    assert_eq!(
+        KernelField {
+            name: "flags",
+            c_type: "uint8_t",
+            pointers: 0,
+            array_len: 3,
+            offset: 12,
+        },
+        KernelParams::FIELDS[2]
+    );
+    assert_eq!("_1", KernelTuple::FIELDS[1].name);
+    assert_eq!(8, KernelTuple::FIELDS[1].offset);
+
+    let decl = c_declaration::<KernelParams>();
+    assert!(decl.starts_with(
+        "typedef struct KernelParams {\n    float* data;\n    uint32_t count;\n    \
+         uint8_t flags[3];\n    KernelTuple inner;\n} KernelParams;\n"
+    ));
+    assert!(decl.contains("static_assert(sizeof(KernelParams) == 32,"));
+    assert!(decl.contains("static_assert(offsetof(KernelParams, inner) == 16,"));
+}
+
+#[test]
+fn test_kernel_struct_arrays() {
+    use rustacuda::kernel_header::c_declaration;
+
+    // Arrays of pointers and of structs are fine; only arrays of arrays are rejected, at compile
+    // time (see `derive_compile_fail`).
+    let decl = c_declaration::<KernelArrays>();
+    assert!(decl.starts_with(
+        "typedef struct KernelArrays {\n    float* pointers[2];\n    KernelTuple tuples[3];\n} \
+         KernelArrays;\n"
+    ));
+    assert!(decl.contains("static_assert(sizeof(KernelArrays) == 64,"));
+}
+
 #[test]
 fn test_hidden_functions() {
     __verify_ZeroSizedStruct_can_implement_DeviceCopy(&ZeroSizedStruct);
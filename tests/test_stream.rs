@@ -1,9 +1,38 @@
 extern crate rustacuda;
 
+use rustacuda::context::{BorrowedContext, ContextStack, CurrentContext};
+use rustacuda::error::CudaError;
+use rustacuda::event::{Event, EventFlags, EventStatus};
+use rustacuda::memory::{AsyncCopyDestination, DeviceBox};
 use rustacuda::prelude::*;
 use rustacuda::quick_init;
+use rustacuda::stream::{
+    AccessPolicyWindow, AccessProperty, BorrowedStream, StreamBuilder, StreamPool, StreamPriority,
+    StreamWriteValueFlags, WaitCondition,
+};
+use std::ffi::{c_void, CString};
 use std::sync::mpsc::sync_channel;
 
+#[test]
+fn test_stream_builder() {
+    let _ctx = quick_init();
+    let range = CurrentContext::get_stream_priority_range().unwrap();
+    let stream = StreamBuilder::new()
+        .non_blocking()
+        .priority(StreamPriority::High)
+        .name("test-stream")
+        .build()
+        .unwrap();
+    assert_eq!(StreamFlags::NON_BLOCKING, stream.get_flags().unwrap());
+    assert_eq!(range.greatest, stream.get_priority().unwrap());
+
+    let stream = StreamBuilder::new().build().unwrap();
+    assert_eq!(StreamFlags::DEFAULT, stream.get_flags().unwrap());
+    assert_eq!(range.least, stream.get_priority().unwrap());
+
+    assert!(StreamBuilder::new().name("nul\0byte").build().is_err());
+}
+
 #[test]
 fn test_stream_callbacks_execution_order() {
     let _ctx = quick_init();
@@ -60,3 +89,237 @@ fn test_stream_callbacks_status_propagation() {
         .unwrap();
     assert_eq!(Ok(()), status_receiver.recv().unwrap())
 }
+
+#[test]
+fn test_stream_context() {
+    let _ctx = quick_init().unwrap();
+    let stream = Stream::new(StreamFlags::NON_BLOCKING, None).unwrap();
+    let version = CurrentContext::get_current()
+        .unwrap()
+        .get_api_version()
+        .unwrap();
+    assert_eq!(
+        version,
+        stream.context().unwrap().get_api_version().unwrap()
+    );
+}
+
+#[test]
+fn test_stream_drop() {
+    let _ctx = quick_init().unwrap();
+    let stream = Stream::new(StreamFlags::NON_BLOCKING, None).unwrap();
+    let (sender, receiver) = sync_channel(1);
+    stream
+        .add_callback(Box::new(move |status| {
+            let _ = sender.send(status);
+        }))
+        .unwrap();
+
+    // Destroying a stream with pending work succeeds, and the work still completes.
+    if let Err((e, stream)) = Stream::drop(stream) {
+        // Don't panic in the destructor as well.
+        std::mem::forget(stream);
+        panic!("Failed to destroy stream: {:?}", e);
+    }
+    assert_eq!(Ok(()), receiver.recv().unwrap());
+}
+
+#[test]
+#[cfg(debug_assertions)]
+fn test_launch_in_wrong_context() {
+    let ctx = quick_init().unwrap();
+    let ptx = CString::new(include_str!("../resources/add.ptx")).unwrap();
+    let module = Module::load_from_string(&ptx).unwrap();
+    let sum = module.get_function(&CString::new("sum").unwrap()).unwrap();
+
+    let device = Device::get_device(0).unwrap();
+    let other = Context::create_and_push(ContextFlags::SCHED_AUTO, device).unwrap();
+    let stream = Stream::new(StreamFlags::NON_BLOCKING, None).unwrap();
+    let mut n = 0i32;
+    let args = [&mut n as *mut _ as *mut c_void];
+    let result = unsafe { stream.launch(&sum, 1, 1, 0, &args) };
+    assert_eq!(Err(CudaError::ContextMismatch), result);
+
+    drop(stream);
+    ContextStack::pop().unwrap();
+    drop(other);
+    drop(ctx);
+}
+
+#[test]
+fn test_access_policy_window() {
+    let _ctx = quick_init();
+    let stream = Stream::new(StreamFlags::NON_BLOCKING, None).unwrap();
+    let table = DeviceBuffer::from_slice(&[0u32; 256]).unwrap();
+    let mut window = AccessPolicyWindow::persisting(&table);
+    assert_eq!(1024, window.num_bytes());
+    assert_eq!(table.as_ptr() as *const u8, window.base_ptr().as_raw());
+
+    window.hit_ratio = 1.5;
+    assert_eq!(
+        Err(CudaError::InvalidValue),
+        stream.set_access_policy_window(&window)
+    );
+
+    window.hit_ratio = 0.5;
+    match stream.set_access_policy_window(&window) {
+        // Drivers before CUDA 11.0 have no stream attributes.
+        Err(CudaError::NotSupported) => return,
+        result => result.unwrap(),
+    }
+    let current = stream.get_access_policy_window().unwrap();
+    assert_eq!(window.num_bytes(), current.num_bytes());
+    assert_eq!(AccessProperty::Persisting, current.hit_prop);
+
+    stream.reset_access_policy_window().unwrap();
+    assert_eq!(0, stream.get_access_policy_window().unwrap().num_bytes());
+    CurrentContext::reset_persisting_l2_cache().unwrap();
+}
+
+#[test]
+fn test_default_streams() {
+    let _ctx = quick_init();
+    let stream = Stream::new(StreamFlags::NON_BLOCKING, None).unwrap();
+    assert!(!stream.is_default());
+    for default in [Stream::default_(), Stream::per_thread()] {
+        assert!(default.is_default());
+        let mut x = DeviceBox::new(&0u32).unwrap();
+        unsafe { x.async_copy_from(&7u32, &default).unwrap() };
+        default.synchronize().unwrap();
+        let mut host = 0u32;
+        x.copy_to(&mut host).unwrap();
+        assert_eq!(7, host);
+        default.context().unwrap();
+        Stream::drop(default).unwrap();
+    }
+    // Dropping the handles must not have destroyed the default streams.
+    Stream::default_().synchronize().unwrap();
+    Stream::per_thread().synchronize().unwrap();
+}
+
+#[test]
+fn test_stream_pool() {
+    let _ctx = quick_init();
+    assert_eq!(CudaError::InvalidValue, StreamPool::new(0).unwrap_err());
+
+    let pool = StreamPool::new(3).unwrap();
+    assert_eq!(3, pool.len());
+    let first = pool.next_stream() as *const Stream;
+    pool.next_stream();
+    pool.next_stream();
+    assert_eq!(first, pool.next_stream() as *const Stream);
+    assert_eq!(
+        StreamFlags::NON_BLOCKING,
+        pool.next_stream().get_flags().unwrap()
+    );
+
+    let a = pool.checkout().unwrap();
+    let b = pool.checkout().unwrap();
+    let c = pool.checkout().unwrap();
+    assert!(pool.checkout().is_none());
+    assert_eq!(0, pool.available());
+    assert_ne!(&*a as *const Stream, &*b as *const Stream);
+    assert_ne!(&*b as *const Stream, &*c as *const Stream);
+    drop(b);
+    assert_eq!(1, pool.available());
+    let d = pool.checkout().unwrap();
+    d.synchronize().unwrap();
+    drop((a, c, d));
+    assert_eq!(3, pool.available());
+    pool.synchronize().unwrap();
+}
+
+#[test]
+fn test_stream_memops() {
+    let _ctx = quick_init();
+    let producer = Stream::new(StreamFlags::NON_BLOCKING, None).unwrap();
+    let consumer = Stream::new(StreamFlags::NON_BLOCKING, None).unwrap();
+    let mut flag = DeviceBox::new(&0u32).unwrap();
+    let mut wide = DeviceBox::new(&0u64).unwrap();
+    unsafe {
+        match consumer.wait_value_32(flag.as_device_ptr(), 2, WaitCondition::GreaterOrEqual) {
+            Err(CudaError::NotSupported) => return,
+            result => result.unwrap(),
+        }
+        wide.async_copy_from(&5u64, &consumer).unwrap();
+        producer
+            .write_value_32(flag.as_device_ptr(), 2, StreamWriteValueFlags::DEFAULT)
+            .unwrap();
+    }
+    consumer.synchronize().unwrap();
+    let mut host = 0u64;
+    wide.copy_to(&mut host).unwrap();
+    assert_eq!(5, host);
+
+    unsafe {
+        match producer.write_value_64(wide.as_device_ptr(), 7, StreamWriteValueFlags::DEFAULT) {
+            Err(CudaError::NotSupported) => return,
+            result => result.unwrap(),
+        }
+        consumer
+            .wait_value_64(wide.as_device_ptr(), 7, WaitCondition::Equal)
+            .unwrap();
+    }
+    consumer.synchronize().unwrap();
+    wide.copy_to(&mut host).unwrap();
+    assert_eq!(7, host);
+}
+
+#[test]
+fn test_stream_raw_handles() {
+    let _ctx = quick_init();
+    let stream = Stream::new(StreamFlags::NON_BLOCKING, None).unwrap();
+    let handle = stream.as_raw();
+    assert_eq!(handle, stream.into_raw());
+    let stream = unsafe { Stream::from_raw(handle) };
+    assert_eq!(StreamFlags::NON_BLOCKING, stream.get_flags().unwrap());
+    Stream::drop(stream).unwrap();
+
+    let default = unsafe { Stream::from_raw(Stream::default_().into_raw()) };
+    assert!(default.is_default());
+}
+
+#[test]
+fn test_borrowed_handles() {
+    let ctx = quick_init().unwrap();
+    let owner = Stream::new(StreamFlags::NON_BLOCKING, None).unwrap();
+    {
+        let context = unsafe { BorrowedContext::from_raw(ctx.get_unowned().as_raw()) };
+        ContextStack::push(&context).unwrap();
+        let stream = unsafe { BorrowedStream::from_raw(owner.as_raw()) };
+        let mut x = DeviceBox::new(&0u32).unwrap();
+        unsafe { x.async_copy_from(&5u32, &stream).unwrap() };
+        let event = Event::new(EventFlags::DEFAULT).unwrap();
+        event.record(&stream).unwrap();
+        event.synchronize().unwrap();
+        assert_eq!(EventStatus::Ready, event.query().unwrap());
+        ContextStack::pop().unwrap();
+    }
+    // Dropping the borrowed handles must not have destroyed the stream or context.
+    owner.synchronize().unwrap();
+    CurrentContext::synchronize().unwrap();
+}
+
+#[test]
+fn test_priority_clamping() {
+    let _ctx = quick_init();
+    let (least, greatest) = Stream::priority_range().unwrap();
+    assert!(greatest <= least);
+
+    let clamped = StreamBuilder::new()
+        .priority(StreamPriority::Value(greatest - 100))
+        .build()
+        .unwrap();
+    assert_eq!(greatest, clamped.get_priority().unwrap());
+
+    let result = StreamBuilder::new()
+        .priority(StreamPriority::Value(least + 1))
+        .strict_priority()
+        .build();
+    assert_eq!(CudaError::InvalidValue, result.unwrap_err());
+    StreamBuilder::new()
+        .priority(StreamPriority::Value(least))
+        .strict_priority()
+        .build()
+        .unwrap();
+}